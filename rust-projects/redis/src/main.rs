@@ -1,17 +1,66 @@
-use redis::modules::{server::handle_connection, store::Redis};
-use std::{
-    net::TcpListener,
-    sync::{Arc, Mutex},
+use redis::modules::{
+    cluster::Cluster,
+    server::{handle_connection, ServerState},
+    store::Redis,
 };
+use std::{net::TcpListener, process, sync::Arc};
+
+const DEFAULT_PORT: u16 = 6379;
+
+const USAGE: &str = "Usage: redis [--port <port>] [--cluster-config-file <path>]";
+
+struct Options {
+    port: u16,
+    cluster_config_file: Option<String>,
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        port: DEFAULT_PORT,
+        cluster_config_file: None,
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing value for {}", arg))?;
+
+        match arg.as_ref() {
+            "--port" => {
+                options.port = value
+                    .parse()
+                    .map_err(|_| format!("Invalid port '{}'", value))?
+            }
+            "--cluster-config-file" => options.cluster_config_file = Some(value.to_string()),
+            _ => return Err(format!("Unknown option '{}'", arg)),
+        }
+    }
+
+    Ok(options)
+}
 
 fn main() {
-    let listener = TcpListener::bind("127.0.0.1:6379").unwrap();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = parse_options(&args).unwrap_or_else(|err| {
+        eprintln!("{}\n{}", err, USAGE);
+        process::exit(1);
+    });
+
+    let mut state = ServerState::new(Redis::new(), options.port);
+    if let Some(path) = &options.cluster_config_file {
+        let cluster = Cluster::load(path, options.port).unwrap_or_else(|err| {
+            eprintln!("Invalid cluster configuration {}", err);
+            process::exit(1);
+        });
+        state = state.with_cluster(cluster);
+    }
 
-    let redis = Redis::new();
-    let redis = Arc::new(Mutex::new(redis));
+    let listener = TcpListener::bind(("127.0.0.1", options.port)).unwrap();
+    let state = Arc::new(state);
 
     for wrapped_stream in listener.incoming() {
         let stream = wrapped_stream.unwrap();
-        handle_connection(stream, Arc::clone(&redis));
+        handle_connection(stream, Arc::clone(&state));
     }
 }
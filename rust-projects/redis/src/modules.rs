@@ -1,6 +1,18 @@
+pub mod bitmap;
+pub mod clients;
+pub mod cluster;
+pub mod command_table;
 pub mod commands;
+pub mod config;
 pub mod deserialize;
+pub mod hyperloglog;
+pub mod introspection;
+pub mod notifications;
+pub mod outbox;
+pub mod pubsub;
+pub mod serialize;
 pub mod server;
+pub mod slowlog;
 pub mod store;
 pub mod string_array;
 pub mod types;
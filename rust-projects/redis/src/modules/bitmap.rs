@@ -0,0 +1,243 @@
+/// Largest bit offset accepted by `SETBIT`, bitmaps are limited to 512MB like in Redis.
+pub const MAX_BIT_OFFSET: u64 = (512 * 1024 * 1024 * 8) - 1;
+
+/// Unit of the `start` and `end` arguments of `BITCOUNT` and `BITPOS`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeUnit {
+    Byte,
+    Bit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitOperation {
+    And,
+    Or,
+    Xor,
+    Not,
+}
+
+impl BitOperation {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_uppercase().as_ref() {
+            "AND" => Some(BitOperation::And),
+            "OR" => Some(BitOperation::Or),
+            "XOR" => Some(BitOperation::Xor),
+            "NOT" => Some(BitOperation::Not),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the bit at `offset`, bit `0` being the most significant bit of the first byte.
+///
+/// # Example
+///
+/// ```
+/// use redis::modules::bitmap::get_bit;
+///
+/// assert_eq!(get_bit(&[0b0100_0000], 1), 1);
+/// assert_eq!(get_bit(&[0b0100_0000], 2), 0);
+/// assert_eq!(get_bit(&[], 100), 0);
+/// ```
+pub fn get_bit(bytes: &[u8], offset: u64) -> u8 {
+    bytes
+        .get((offset / 8) as usize)
+        .map_or(0, |byte| (byte >> (7 - offset % 8)) & 1)
+}
+
+/// Sets the bit at `offset`, growing the bitmap with zero bytes if needed.
+///
+/// # Returns
+/// The previous value of the bit.
+pub fn set_bit(bytes: &mut Vec<u8>, offset: u64, value: bool) -> u8 {
+    let index = (offset / 8) as usize;
+    let mask = 1 << (7 - offset % 8);
+
+    if bytes.len() <= index {
+        bytes.resize(index + 1, 0);
+    }
+
+    let previous = (bytes[index] & mask != 0) as u8;
+    if value {
+        bytes[index] |= mask;
+    } else {
+        bytes[index] &= !mask;
+    }
+
+    previous
+}
+
+/// Resolves a Redis style inclusive range, where negative positions count from the end.
+///
+/// # Returns
+/// The range clamped to `0..len`, or `None` if it is empty.
+fn resolve_range(start: i64, end: i64, len: i64) -> Option<(i64, i64)> {
+    let clamp = |position: i64| {
+        if position < 0 {
+            (len + position).max(0)
+        } else {
+            position
+        }
+    };
+
+    let (start, end) = (clamp(start), clamp(end).min(len - 1));
+
+    (start <= end && len > 0).then_some((start, end))
+}
+
+fn bit_range(bytes: &[u8], range: Option<(i64, i64)>, unit: RangeUnit) -> Option<(u64, u64)> {
+    let Some((start, end)) = range else {
+        return resolve_range(0, -1, bytes.len() as i64 * 8).map(|(s, e)| (s as u64, e as u64));
+    };
+
+    match unit {
+        RangeUnit::Byte => resolve_range(start, end, bytes.len() as i64)
+            .map(|(start, end)| (start as u64 * 8, end as u64 * 8 + 7)),
+        RangeUnit::Bit => {
+            resolve_range(start, end, bytes.len() as i64 * 8).map(|(s, e)| (s as u64, e as u64))
+        }
+    }
+}
+
+/// Counts the set bits, optionally restricted to an inclusive range.
+///
+/// # Example
+///
+/// ```
+/// use redis::modules::bitmap::{bit_count, RangeUnit};
+///
+/// let bytes = b"foobar";
+/// assert_eq!(bit_count(bytes, None, RangeUnit::Byte), 26);
+/// assert_eq!(bit_count(bytes, Some((1, 1)), RangeUnit::Byte), 6);
+/// assert_eq!(bit_count(bytes, Some((5, 30)), RangeUnit::Bit), 17);
+/// ```
+pub fn bit_count(bytes: &[u8], range: Option<(i64, i64)>, unit: RangeUnit) -> u64 {
+    let Some((start, end)) = bit_range(bytes, range, unit) else {
+        return 0;
+    };
+
+    if start % 8 == 0 && end % 8 == 7 {
+        return bytes[(start / 8) as usize..=(end / 8) as usize]
+            .iter()
+            .map(|byte| byte.count_ones() as u64)
+            .sum();
+    }
+
+    (start..=end)
+        .map(|offset| get_bit(bytes, offset) as u64)
+        .sum()
+}
+
+/// Finds the first bit set to `bit`, optionally restricted to an inclusive range.
+///
+/// Looking for a clear bit in a bitmap without an explicit end considers the bitmap padded with
+/// zeros on the right, so a bitmap full of ones returns the first position past its end.
+///
+/// # Example
+///
+/// ```
+/// use redis::modules::bitmap::{bit_pos, RangeUnit};
+///
+/// assert_eq!(bit_pos(&[0xff, 0xf0, 0x00], 0, None, None, RangeUnit::Byte), 12);
+/// assert_eq!(bit_pos(&[0x00, 0xff, 0xf0], 1, Some(0), None, RangeUnit::Byte), 8);
+/// assert_eq!(bit_pos(&[0xff], 0, None, None, RangeUnit::Byte), 8);
+/// assert_eq!(bit_pos(&[0xff], 0, Some(0), Some(0), RangeUnit::Byte), -1);
+/// assert_eq!(bit_pos(&[], 1, None, None, RangeUnit::Byte), -1);
+/// ```
+pub fn bit_pos(
+    bytes: &[u8],
+    bit: u8,
+    start: Option<i64>,
+    end: Option<i64>,
+    unit: RangeUnit,
+) -> i64 {
+    if bytes.is_empty() {
+        return if bit == 0 { 0 } else { -1 };
+    }
+
+    let range = start.map(|start| (start, end.unwrap_or(-1)));
+    let found = bit_range(bytes, range, unit)
+        .and_then(|(start, end)| (start..=end).find(|offset| get_bit(bytes, *offset) == bit));
+
+    match found {
+        Some(offset) => offset as i64,
+        None if bit == 0 && end.is_none() => bytes.len() as i64 * 8,
+        None => -1,
+    }
+}
+
+/// Combines bitmaps, shorter inputs being padded with zero bytes.
+///
+/// # Example
+///
+/// ```
+/// use redis::modules::bitmap::{bit_op, BitOperation};
+///
+/// assert_eq!(bit_op(BitOperation::And, &[&[0xff, 0x0f], &[0xf0]]), vec![0xf0, 0x00]);
+/// assert_eq!(bit_op(BitOperation::Or, &[&[0xf0], &[0x0f, 0x01]]), vec![0xff, 0x01]);
+/// assert_eq!(bit_op(BitOperation::Not, &[&[0xf0]]), vec![0x0f]);
+/// ```
+pub fn bit_op(operation: BitOperation, sources: &[&[u8]]) -> Vec<u8> {
+    let len = sources.iter().map(|source| source.len()).max().unwrap_or(0);
+    let byte_at = |source: &[u8], index: usize| source.get(index).copied().unwrap_or(0);
+
+    (0..len)
+        .map(|index| {
+            let mut bytes = sources.iter().map(|source| byte_at(source, index));
+            let first = bytes.next().unwrap_or(0);
+
+            match operation {
+                BitOperation::And => bytes.fold(first, |acc, byte| acc & byte),
+                BitOperation::Or => bytes.fold(first, |acc, byte| acc | byte),
+                BitOperation::Xor => bytes.fold(first, |acc, byte| acc ^ byte),
+                BitOperation::Not => !first,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_set_and_get_bits() {
+        let mut bytes = vec![];
+
+        assert_eq!(set_bit(&mut bytes, 7, true), 0);
+        assert_eq!(bytes, vec![0x01]);
+        assert_eq!(set_bit(&mut bytes, 7, true), 1);
+        assert_eq!(set_bit(&mut bytes, 17, true), 0);
+        assert_eq!(bytes, vec![0x01, 0x00, 0x40]);
+        assert_eq!(set_bit(&mut bytes, 7, false), 1);
+        assert_eq!(get_bit(&bytes, 7), 0);
+        assert_eq!(get_bit(&bytes, 17), 1);
+    }
+
+    #[test]
+    fn should_count_bits_with_negative_ranges() {
+        let bytes = b"foobar";
+
+        assert_eq!(bit_count(bytes, Some((-2, -1)), RangeUnit::Byte), 7);
+        assert_eq!(bit_count(bytes, Some((2, 1)), RangeUnit::Byte), 0);
+        assert_eq!(bit_count(bytes, Some((0, 100)), RangeUnit::Byte), 26);
+        assert_eq!(bit_count(&[], None, RangeUnit::Byte), 0);
+    }
+
+    #[test]
+    fn should_find_bits_in_bit_ranges() {
+        let bytes = [0x00, 0xff];
+
+        assert_eq!(bit_pos(&bytes, 1, Some(2), Some(-1), RangeUnit::Bit), 8);
+        assert_eq!(bit_pos(&bytes, 0, Some(8), Some(-1), RangeUnit::Bit), -1);
+        assert_eq!(bit_pos(&bytes, 1, Some(1), None, RangeUnit::Byte), 8);
+    }
+
+    #[test]
+    fn should_xor_bitmaps() {
+        assert_eq!(
+            bit_op(BitOperation::Xor, &[&[0xff], &[0x0f], &[0x01, 0x01]]),
+            vec![0xf1, 0x01]
+        );
+    }
+}
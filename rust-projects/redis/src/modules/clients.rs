@@ -0,0 +1,299 @@
+use std::{
+    collections::BTreeMap,
+    net::{Shutdown, TcpStream},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Instant,
+};
+
+use super::outbox::Outbox;
+
+#[derive(Debug)]
+pub struct Client {
+    pub id: u64,
+    pub addr: String,
+    pub name: Option<String>,
+    pub connected_at: Instant,
+    pub last_interaction: Instant,
+    pub last_command: String,
+    pub monitor: bool,
+    /// Set by `ASKING`, lets the next command run on a slot being imported.
+    pub asking: bool,
+    stream: Option<TcpStream>,
+    outbox: Option<Outbox>,
+}
+
+/// Filters accepted by `CLIENT KILL`. Every filter that is set must match.
+#[derive(Debug, Default, PartialEq)]
+pub struct KillFilter {
+    pub id: Option<u64>,
+    pub addr: Option<String>,
+    /// Client that must not be killed, usually the one issuing the command.
+    pub skip: Option<u64>,
+}
+
+/// Registry of the connected clients, shared by every connection thread.
+#[derive(Debug)]
+pub struct ClientRegistry {
+    clients: Mutex<BTreeMap<u64, Client>>,
+    next_id: AtomicU64,
+}
+
+impl Default for ClientRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ClientRegistry {
+    pub fn new() -> Self {
+        ClientRegistry {
+            clients: Mutex::new(BTreeMap::new()),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Registers a new client and returns its id.
+    ///
+    /// # Arguments
+    /// * `addr` - The peer address of the client.
+    /// * `stream` - A handle to the client socket, written by the client [`Outbox`] and closed on
+    ///   `CLIENT KILL`.
+    pub fn register(&self, addr: String, stream: Option<TcpStream>) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let now = Instant::now();
+        let outbox = stream
+            .as_ref()
+            .and_then(|stream| stream.try_clone().ok())
+            .and_then(|stream| Outbox::spawn(stream).ok());
+
+        self.clients.lock().unwrap().insert(
+            id,
+            Client {
+                id,
+                addr,
+                name: None,
+                connected_at: now,
+                last_interaction: now,
+                last_command: "NULL".to_string(),
+                monitor: false,
+                asking: false,
+                stream,
+                outbox,
+            },
+        );
+
+        id
+    }
+
+    pub fn unregister(&self, id: u64) {
+        self.clients.lock().unwrap().remove(&id);
+    }
+
+    /// Records the last command issued by a client.
+    pub fn touch(&self, id: u64, command: &str) {
+        if let Some(client) = self.clients.lock().unwrap().get_mut(&id) {
+            client.last_interaction = Instant::now();
+            client.last_command = command.to_lowercase();
+        }
+    }
+
+    pub fn set_name(&self, id: u64, name: Option<String>) {
+        if let Some(client) = self.clients.lock().unwrap().get_mut(&id) {
+            client.name = name;
+        }
+    }
+
+    pub fn name(&self, id: u64) -> Option<String> {
+        self.clients
+            .lock()
+            .unwrap()
+            .get(&id)
+            .and_then(|client| client.name.clone())
+    }
+
+    pub fn addr(&self, id: u64) -> Option<String> {
+        self.clients
+            .lock()
+            .unwrap()
+            .get(&id)
+            .map(|client| client.addr.clone())
+    }
+
+    /// Returns the queue of bytes written to the client, if it has a socket.
+    pub fn outbox(&self, id: u64) -> Option<Outbox> {
+        self.clients
+            .lock()
+            .unwrap()
+            .get(&id)
+            .and_then(|client| client.outbox.clone())
+    }
+
+    pub fn len(&self) -> usize {
+        self.clients.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clients.lock().unwrap().is_empty()
+    }
+
+    /// Renders the clients in the `CLIENT LIST` format, one client per line.
+    pub fn list(&self) -> String {
+        let now = Instant::now();
+
+        self.clients
+            .lock()
+            .unwrap()
+            .values()
+            .map(|client| {
+                format!(
+                    "id={} addr={} name={} age={} idle={} flags={} cmd={}\n",
+                    client.id,
+                    client.addr,
+                    client.name.as_deref().unwrap_or(""),
+                    now.duration_since(client.connected_at).as_secs(),
+                    now.duration_since(client.last_interaction).as_secs(),
+                    if client.monitor { "O" } else { "N" },
+                    client.last_command,
+                )
+            })
+            .collect()
+    }
+
+    /// Disconnects every client matching `filter`.
+    ///
+    /// # Returns
+    /// The number of clients killed.
+    pub fn kill(&self, filter: &KillFilter) -> usize {
+        let mut clients = self.clients.lock().unwrap();
+
+        let ids: Vec<u64> = clients
+            .values()
+            .filter(|client| {
+                filter.skip != Some(client.id)
+                    && filter.id.is_none_or(|id| id == client.id)
+                    && filter.addr.as_ref().is_none_or(|addr| *addr == client.addr)
+            })
+            .map(|client| client.id)
+            .collect();
+
+        for id in ids.iter() {
+            if let Some(Client {
+                stream: Some(stream),
+                ..
+            }) = clients.remove(id)
+            {
+                let _ = stream.shutdown(Shutdown::Both);
+            }
+        }
+
+        ids.len()
+    }
+
+    pub fn set_monitor(&self, id: u64) {
+        if let Some(client) = self.clients.lock().unwrap().get_mut(&id) {
+            client.monitor = true;
+        }
+    }
+
+    pub fn set_asking(&self, id: u64, asking: bool) {
+        if let Some(client) = self.clients.lock().unwrap().get_mut(&id) {
+            client.asking = asking;
+        }
+    }
+
+    /// Returns whether the client sent `ASKING`, clearing the flag as it only lasts one command.
+    pub fn take_asking(&self, id: u64) -> bool {
+        self.clients
+            .lock()
+            .unwrap()
+            .get_mut(&id)
+            .is_some_and(|client| std::mem::take(&mut client.asking))
+    }
+
+    pub fn monitor_count(&self) -> usize {
+        self.clients
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|client| client.monitor)
+            .count()
+    }
+
+    /// Queues `line` for every client in `MONITOR` mode, disconnecting the ones that fell too far
+    /// behind.
+    pub fn feed_monitors(&self, line: &str) {
+        let monitors: Vec<Outbox> = self
+            .clients
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|client| client.monitor)
+            .filter_map(|client| client.outbox.clone())
+            .collect();
+
+        for outbox in monitors {
+            outbox.push(line.as_bytes().to_vec());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_register_and_unregister() {
+        let clients = ClientRegistry::new();
+
+        let first = clients.register("127.0.0.1:5000".to_string(), None);
+        let second = clients.register("127.0.0.1:5001".to_string(), None);
+
+        assert_eq!((first, second), (1, 2));
+        assert_eq!(clients.len(), 2);
+
+        clients.unregister(first);
+        assert_eq!(clients.len(), 1);
+        assert_eq!(clients.addr(second), Some("127.0.0.1:5001".to_string()));
+    }
+
+    #[test]
+    fn should_list_clients() {
+        let clients = ClientRegistry::new();
+
+        let id = clients.register("127.0.0.1:5000".to_string(), None);
+        clients.set_name(id, Some("worker".to_string()));
+        clients.touch(id, "GET");
+
+        assert_eq!(
+            clients.list(),
+            "id=1 addr=127.0.0.1:5000 name=worker age=0 idle=0 flags=N cmd=get\n"
+        );
+    }
+
+    #[test]
+    fn should_kill_matching_clients() {
+        let clients = ClientRegistry::new();
+
+        let first = clients.register("127.0.0.1:5000".to_string(), None);
+        let second = clients.register("127.0.0.1:5001".to_string(), None);
+        clients.register("127.0.0.1:5002".to_string(), None);
+
+        let killed = clients.kill(&KillFilter {
+            addr: Some("127.0.0.1:5001".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(killed, 1);
+        assert_eq!(clients.addr(second), None);
+
+        let killed = clients.kill(&KillFilter {
+            skip: Some(first),
+            ..Default::default()
+        });
+        assert_eq!(killed, 1);
+        assert_eq!(clients.len(), 1);
+        assert!(clients.addr(first).is_some());
+    }
+}
@@ -0,0 +1,695 @@
+use std::{collections::BTreeMap, fs};
+
+use super::{
+    serialize::{serialize_array, serialize_bulk_string, serialize_error, serialize_integer},
+    server::ServerState,
+};
+
+pub const CLUSTER_SLOTS: usize = 16384;
+
+pub const CLUSTER_DISABLED: &str = "ERR This instance has cluster support disabled";
+
+/// CRC16 as used by Redis Cluster (XMODEM: polynomial `0x1021`, initial value `0`).
+///
+/// # Example
+///
+/// ```
+/// use redis::modules::cluster::crc16;
+///
+/// assert_eq!(crc16(b"123456789"), 0x31c3);
+/// ```
+pub fn crc16(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0u16, |crc, byte| {
+        (0..8).fold(crc ^ ((*byte as u16) << 8), |crc, _| {
+            if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+/// Returns the hash slot of a key.
+///
+/// If the key contains a non-empty hash tag such as `{user:1000}`, only the tag is hashed, so
+/// related keys can be kept in the same slot.
+///
+/// # Example
+///
+/// ```
+/// use redis::modules::cluster::{crc16, key_hash_slot};
+///
+/// assert_eq!(key_hash_slot("foo"), 12182);
+/// assert_eq!(key_hash_slot("{user1000}.following"), key_hash_slot("{user1000}.followers"));
+/// assert_eq!(key_hash_slot("foo{}{bar}"), crc16(b"foo{}{bar}") & 16383);
+/// ```
+pub fn key_hash_slot(key: &str) -> u16 {
+    let bytes = key.as_bytes();
+
+    let tag = bytes
+        .iter()
+        .position(|byte| *byte == b'{')
+        .and_then(|start| {
+            bytes[start + 1..]
+                .iter()
+                .position(|byte| *byte == b'}')
+                .filter(|len| *len > 0)
+                .map(|len| &bytes[start + 1..start + 1 + len])
+        });
+
+    crc16(tag.unwrap_or(bytes)) & (CLUSTER_SLOTS as u16 - 1)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClusterNode {
+    /// 40 characters hexadecimal node id.
+    pub id: String,
+    pub host: String,
+    pub port: u16,
+    pub config_epoch: u64,
+}
+
+impl ClusterNode {
+    pub fn addr(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+}
+
+/// Static cluster topology loaded from a `nodes.conf` style file.
+///
+/// Slot ownership never changes at runtime. Slots can be marked as migrating to or importing
+/// from another node in the file, which makes the nodes answer with `ASK` redirections.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cluster {
+    nodes: Vec<ClusterNode>,
+    myself: usize,
+    owners: Vec<Option<usize>>,
+    migrating: BTreeMap<u16, usize>,
+    importing: BTreeMap<u16, usize>,
+}
+
+fn parse_slot(slot: &str) -> Result<u16, String> {
+    slot.parse::<u16>()
+        .ok()
+        .filter(|slot| (*slot as usize) < CLUSTER_SLOTS)
+        .ok_or_else(|| format!("Invalid slot '{}'", slot))
+}
+
+impl Cluster {
+    /// Parses a cluster configuration in the format of `CLUSTER NODES`, one node per line:
+    ///
+    /// `<id> <ip:port@cport> <flags> <master> <ping-sent> <pong-recv> <config-epoch> <link-state> <slot> ...`
+    ///
+    /// Slots are either single slots, ranges such as `0-5460`, or migration markers such as
+    /// `[42->-<node id>]` and `[42-<-<node id>]`.
+    ///
+    /// # Arguments
+    /// * `config` - The content of the file.
+    /// * `port` - Port of this server, used to find the local node when no line is flagged `myself`.
+    ///
+    /// # Returns
+    /// * `Ok(Cluster)` if the configuration is valid.
+    /// * `Err(String)` describing the first invalid line otherwise.
+    pub fn parse(config: &str, port: u16) -> Result<Cluster, String> {
+        let lines: Vec<Vec<&str>> = config
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with("vars"))
+            .map(|line| line.split_whitespace().collect())
+            .collect();
+
+        let mut nodes = vec![];
+        let mut myself = None;
+        for fields in lines.iter() {
+            let [id, addr, flags, _, _, _, epoch, _, ..] = fields.as_slice() else {
+                return Err(format!("Invalid node line '{}'", fields.join(" ")));
+            };
+
+            let addr = addr.split(['@', ',']).next().unwrap_or_default();
+            let (host, node_port) = addr
+                .rsplit_once(':')
+                .and_then(|(host, port)| Some((host.to_string(), port.parse::<u16>().ok()?)))
+                .ok_or_else(|| format!("Invalid node address '{}'", addr))?;
+
+            if flags.split(',').any(|flag| flag == "myself") {
+                myself = Some(nodes.len());
+            }
+
+            nodes.push(ClusterNode {
+                id: id.to_string(),
+                host,
+                port: node_port,
+                config_epoch: epoch.parse().unwrap_or(0),
+            });
+        }
+
+        let myself = myself
+            .or_else(|| nodes.iter().position(|node| node.port == port))
+            .ok_or_else(|| format!("No node of the cluster listens on port {}", port))?;
+
+        let mut cluster = Cluster {
+            nodes,
+            myself,
+            owners: vec![None; CLUSTER_SLOTS],
+            migrating: BTreeMap::new(),
+            importing: BTreeMap::new(),
+        };
+
+        for (index, fields) in lines.iter().enumerate() {
+            for slot in fields[8..].iter() {
+                cluster.parse_slots(index, slot)?;
+            }
+        }
+
+        Ok(cluster)
+    }
+
+    fn parse_slots(&mut self, index: usize, slots: &str) -> Result<(), String> {
+        if let Some(marker) = slots.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+            let (slot, target, migrating) = if let Some((slot, target)) = marker.split_once("->-") {
+                (slot, target, true)
+            } else if let Some((slot, source)) = marker.split_once("-<-") {
+                (slot, source, false)
+            } else {
+                return Err(format!("Invalid slot marker '{}'", slots));
+            };
+
+            let other = self
+                .nodes
+                .iter()
+                .position(|node| node.id == target)
+                .ok_or_else(|| format!("Unknown node '{}'", target))?;
+
+            // Like Redis, only the markers of the local node are meaningful.
+            if index == self.myself {
+                let markers = if migrating {
+                    &mut self.migrating
+                } else {
+                    &mut self.importing
+                };
+                markers.insert(parse_slot(slot)?, other);
+            }
+
+            return Ok(());
+        }
+
+        let (start, end) = match slots.split_once('-') {
+            Some((start, end)) => (parse_slot(start)?, parse_slot(end)?),
+            None => (parse_slot(slots)?, parse_slot(slots)?),
+        };
+
+        for slot in start..=end {
+            if let Some(owner) = self.owners[slot as usize] {
+                return Err(format!(
+                    "Slot {} is assigned to {} and {}",
+                    slot, self.nodes[owner].id, self.nodes[index].id
+                ));
+            }
+            self.owners[slot as usize] = Some(index);
+        }
+
+        Ok(())
+    }
+
+    /// Reads and parses a cluster configuration file, see `Cluster::parse`.
+    pub fn load(path: &str, port: u16) -> Result<Cluster, String> {
+        let config = fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+
+        Cluster::parse(&config, port)
+    }
+
+    pub fn myself(&self) -> &ClusterNode {
+        &self.nodes[self.myself]
+    }
+
+    pub fn nodes(&self) -> &[ClusterNode] {
+        &self.nodes
+    }
+
+    pub fn owner(&self, slot: u16) -> Option<&ClusterNode> {
+        self.owners[slot as usize].map(|index| &self.nodes[index])
+    }
+
+    /// Returns the contiguous slot ranges served by each node, sorted by slot.
+    pub fn slot_ranges(&self) -> Vec<(u16, u16, &ClusterNode)> {
+        let mut ranges: Vec<(u16, u16, usize)> = vec![];
+
+        for (slot, owner) in self.owners.iter().enumerate() {
+            let Some(owner) = *owner else {
+                continue;
+            };
+
+            match ranges.last_mut() {
+                Some((_, end, node)) if *node == owner && *end as usize + 1 == slot => {
+                    *end = slot as u16
+                }
+                _ => ranges.push((slot as u16, slot as u16, owner)),
+            }
+        }
+
+        ranges
+            .into_iter()
+            .map(|(start, end, node)| (start, end, &self.nodes[node]))
+            .collect()
+    }
+
+    /// Decides whether a command on `keys` can run on this node.
+    ///
+    /// # Arguments
+    /// * `keys` - The keys of the command, see `CommandSpec::keys`.
+    /// * `asking` - `true` if the client sent `ASKING` right before the command.
+    /// * `exists` - Returns `true` if a key exists on this node.
+    ///
+    /// # Returns
+    /// * `None` if the command can run here.
+    /// * `Some(String)` with the serialized `MOVED`, `ASK`, `CROSSSLOT`, `TRYAGAIN` or
+    ///   `CLUSTERDOWN` error to send back otherwise.
+    pub fn redirect(
+        &self,
+        keys: &[&str],
+        asking: bool,
+        mut exists: impl FnMut(&str) -> bool,
+    ) -> Option<String> {
+        let slot = key_hash_slot(keys.first()?);
+
+        if keys.iter().any(|key| key_hash_slot(key) != slot) {
+            return Some(serialize_error(
+                "CROSSSLOT Keys in request don't hash to the same slot",
+            ));
+        }
+
+        let Some(owner) = self.owners[slot as usize] else {
+            return Some(serialize_error("CLUSTERDOWN Hash slot not served"));
+        };
+
+        if owner != self.myself {
+            if asking && self.importing.contains_key(&slot) {
+                return None;
+            }
+
+            return Some(serialize_error(&format!(
+                "MOVED {} {}",
+                slot,
+                self.nodes[owner].addr()
+            )));
+        }
+
+        let target = self.migrating.get(&slot)?;
+        let missing = keys.iter().filter(|key| !exists(key)).count();
+
+        if missing == keys.len() {
+            Some(serialize_error(&format!(
+                "ASK {} {}",
+                slot,
+                self.nodes[*target].addr()
+            )))
+        } else if missing > 0 {
+            Some(serialize_error(
+                "TRYAGAIN Multiple keys request during rehashing of slot",
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Renders the topology in the format of `CLUSTER NODES`, which is also the file format.
+    pub fn nodes_description(&self) -> String {
+        self.nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| {
+                let mut fields = vec![
+                    node.id.to_string(),
+                    format!("{}@{}", node.addr(), node.port as u32 + 10000),
+                    if index == self.myself {
+                        "myself,master".to_string()
+                    } else {
+                        "master".to_string()
+                    },
+                    "-".to_string(),
+                    "0".to_string(),
+                    "0".to_string(),
+                    node.config_epoch.to_string(),
+                    "connected".to_string(),
+                ];
+
+                fields.extend(
+                    self.slot_ranges()
+                        .iter()
+                        .filter(|(_, _, owner)| owner.id == node.id)
+                        .map(|(start, end, _)| {
+                            if start == end {
+                                start.to_string()
+                            } else {
+                                format!("{}-{}", start, end)
+                            }
+                        }),
+                );
+
+                if index == self.myself {
+                    fields.extend(
+                        self.migrating.iter().map(|(slot, target)| {
+                            format!("[{}->-{}]", slot, self.nodes[*target].id)
+                        }),
+                    );
+                    fields.extend(
+                        self.importing.iter().map(|(slot, source)| {
+                            format!("[{}-<-{}]", slot, self.nodes[*source].id)
+                        }),
+                    );
+                }
+
+                fields.join(" ") + "\n"
+            })
+            .collect()
+    }
+
+    fn info(&self) -> String {
+        let assigned = self.owners.iter().filter(|owner| owner.is_some()).count();
+        let size = (0..self.nodes.len())
+            .filter(|index| self.owners.contains(&Some(*index)))
+            .count();
+        let epoch = self
+            .nodes
+            .iter()
+            .map(|node| node.config_epoch)
+            .max()
+            .unwrap_or(0);
+
+        [
+            (
+                "cluster_state",
+                if assigned == CLUSTER_SLOTS {
+                    "ok"
+                } else {
+                    "fail"
+                }
+                .to_string(),
+            ),
+            ("cluster_slots_assigned", assigned.to_string()),
+            ("cluster_slots_ok", assigned.to_string()),
+            ("cluster_known_nodes", self.nodes.len().to_string()),
+            ("cluster_size", size.to_string()),
+            ("cluster_current_epoch", epoch.to_string()),
+            ("cluster_my_epoch", self.myself().config_epoch.to_string()),
+        ]
+        .iter()
+        .map(|(key, value)| format!("{}:{}\r\n", key, value))
+        .collect()
+    }
+}
+
+fn serialize_node(node: &ClusterNode) -> String {
+    serialize_array(&[
+        serialize_bulk_string(&node.host),
+        serialize_integer(node.port as i64),
+        serialize_bulk_string(&node.id),
+    ])
+}
+
+fn shards(cluster: &Cluster) -> String {
+    let shards: Vec<String> = cluster
+        .nodes()
+        .iter()
+        .map(|node| {
+            let slots: Vec<String> = cluster
+                .slot_ranges()
+                .iter()
+                .filter(|(_, _, owner)| owner.id == node.id)
+                .flat_map(|(start, end, _)| {
+                    [
+                        serialize_integer(*start as i64),
+                        serialize_integer(*end as i64),
+                    ]
+                })
+                .collect();
+
+            let description = serialize_array(&[
+                serialize_bulk_string("id"),
+                serialize_bulk_string(&node.id),
+                serialize_bulk_string("port"),
+                serialize_integer(node.port as i64),
+                serialize_bulk_string("ip"),
+                serialize_bulk_string(&node.host),
+                serialize_bulk_string("endpoint"),
+                serialize_bulk_string(&node.host),
+                serialize_bulk_string("role"),
+                serialize_bulk_string("master"),
+                serialize_bulk_string("replication-offset"),
+                serialize_integer(0),
+                serialize_bulk_string("health"),
+                serialize_bulk_string("online"),
+            ]);
+
+            serialize_array(&[
+                serialize_bulk_string("slots"),
+                serialize_array(&slots),
+                serialize_bulk_string("nodes"),
+                serialize_array(&[description]),
+            ])
+        })
+        .collect();
+
+    serialize_array(&shards)
+}
+
+pub fn cluster(args: &[String], _: u64, state: &ServerState) -> String {
+    let Some(cluster) = &state.cluster else {
+        return serialize_error(CLUSTER_DISABLED);
+    };
+
+    let args: Vec<&str> = args[1..].iter().map(String::as_str).collect();
+    let subcommand = args[0].to_uppercase();
+
+    match (subcommand.as_ref(), &args[1..]) {
+        ("KEYSLOT", [key]) => serialize_integer(key_hash_slot(key) as i64),
+        ("MYID", []) => serialize_bulk_string(&cluster.myself().id),
+        ("INFO", []) => serialize_bulk_string(&cluster.info()),
+        ("NODES", []) => serialize_bulk_string(&cluster.nodes_description()),
+        ("SLOTS", []) => serialize_array(
+            &cluster
+                .slot_ranges()
+                .iter()
+                .map(|(start, end, node)| {
+                    serialize_array(&[
+                        serialize_integer(*start as i64),
+                        serialize_integer(*end as i64),
+                        serialize_node(node),
+                    ])
+                })
+                .collect::<Vec<String>>(),
+        ),
+        ("SHARDS", []) => shards(cluster),
+        _ => serialize_error(&format!(
+            "ERR unknown subcommand or wrong number of arguments for 'cluster|{}' command",
+            args[0].to_lowercase()
+        )),
+    }
+}
+
+/// Lets the next command of the client run on a slot this node is importing.
+pub fn asking(_: &[String], client_id: u64, state: &ServerState) -> String {
+    if state.cluster.is_none() {
+        return serialize_error(CLUSTER_DISABLED);
+    }
+
+    state.clients.set_asking(client_id, true);
+
+    "+OK\r\n".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::modules::{server::process_command, store::Redis, types::RedisDeserializationTypes};
+
+    use super::*;
+
+    const NODE_A: &str = "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+    const NODE_B: &str = "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb";
+    const NODE_C: &str = "cccccccccccccccccccccccccccccccccccccccc";
+
+    fn config() -> String {
+        format!(
+            "{a} 127.0.0.1:7000@17000 master - 0 0 1 connected 0-5460 [1000->-{b}]\n\
+             {b} 127.0.0.1:7001@17001 master - 0 0 2 connected 5461-10922 [5000-<-{a}]\n\
+             {c} 127.0.0.1:7002@17002 master - 0 0 3 connected 10923-16383\n\
+             vars currentEpoch 3 lastVoteEpoch 0\n",
+            a = NODE_A,
+            b = NODE_B,
+            c = NODE_C
+        )
+    }
+
+    /// Finds a key hashing to `slot`, for tests that need a specific slot.
+    fn key_in_slot(slot: u16) -> String {
+        (0..)
+            .map(|i| format!("key:{}", i))
+            .find(|key| key_hash_slot(key) == slot)
+            .unwrap()
+    }
+
+    #[test]
+    fn should_hash_keys_like_redis() {
+        assert_eq!(key_hash_slot("foo"), 12182);
+        assert_eq!(key_hash_slot("bar"), 5061);
+        assert_eq!(key_hash_slot("{foo}bar"), key_hash_slot("foo"));
+        assert_eq!(key_hash_slot("{}foo"), crc16(b"{}foo") & 16383);
+        assert_eq!(key_hash_slot("foo{bar"), crc16(b"foo{bar") & 16383);
+    }
+
+    #[test]
+    fn should_find_myself_by_port() {
+        let cluster = Cluster::parse(&config(), 7001).unwrap();
+
+        assert_eq!(cluster.myself().id, NODE_B);
+        assert_eq!(cluster.owner(0).unwrap().id, NODE_A);
+        assert_eq!(cluster.owner(16383).unwrap().id, NODE_C);
+        assert_eq!(
+            cluster
+                .slot_ranges()
+                .iter()
+                .map(|(start, end, node)| (*start, *end, node.port))
+                .collect::<Vec<_>>(),
+            vec![(0, 5460, 7000), (5461, 10922, 7001), (10923, 16383, 7002)]
+        );
+    }
+
+    #[test]
+    fn should_reject_invalid_configs() {
+        assert!(Cluster::parse(&config(), 6379).is_err());
+        assert!(Cluster::parse(
+            &format!(
+                "{} 127.0.0.1:7000 myself,master - 0 0 1 connected 0-20000",
+                NODE_A
+            ),
+            7000
+        )
+        .is_err());
+        assert!(Cluster::parse(
+            &format!(
+                "{} 127.0.0.1:7000 myself,master - 0 0 1 connected 0-10 5-20",
+                NODE_A
+            ),
+            7000
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn should_redirect_to_owner() {
+        let cluster = Cluster::parse(&config(), 7000).unwrap();
+
+        assert_eq!(cluster.redirect(&["bar"], false, |_| true), None);
+        assert_eq!(
+            cluster.redirect(&["foo"], false, |_| true),
+            Some("-MOVED 12182 127.0.0.1:7002\r\n".to_string())
+        );
+        assert_eq!(
+            cluster.redirect(&["foo", "bar"], false, |_| true),
+            Some("-CROSSSLOT Keys in request don't hash to the same slot\r\n".to_string())
+        );
+        assert_eq!(
+            cluster.redirect(&["{foo}a", "{foo}b"], false, |_| true),
+            Some("-MOVED 12182 127.0.0.1:7002\r\n".to_string())
+        );
+        assert_eq!(cluster.redirect(&[], false, |_| false), None);
+    }
+
+    #[test]
+    fn should_ask_for_migrating_keys() {
+        let source = Cluster::parse(&config(), 7000).unwrap();
+        let key = key_in_slot(1000);
+
+        assert_eq!(source.redirect(&[&key], false, |_| true), None);
+        assert_eq!(
+            source.redirect(&[&key], false, |_| false),
+            Some("-ASK 1000 127.0.0.1:7001\r\n".to_string())
+        );
+
+        let target = Cluster::parse(&config(), 7001).unwrap();
+        let key = key_in_slot(5000);
+        assert_eq!(
+            target.redirect(&[&key], false, |_| false),
+            Some("-MOVED 5000 127.0.0.1:7000\r\n".to_string())
+        );
+        assert_eq!(target.redirect(&[&key], true, |_| false), None);
+    }
+
+    #[test]
+    fn should_describe_nodes_in_config_format() {
+        let cluster = Cluster::parse(&config(), 7000).unwrap();
+        let description = cluster.nodes_description();
+
+        assert_eq!(
+            description.lines().next().unwrap(),
+            format!(
+                "{} 127.0.0.1:7000@17000 myself,master - 0 0 1 connected 0-5460 [1000->-{}]",
+                NODE_A, NODE_B
+            )
+        );
+        assert_eq!(Cluster::parse(&description, 0).unwrap(), cluster);
+    }
+
+    fn execute(state: &ServerState, client_id: u64, args: &[&str]) -> String {
+        let command = RedisDeserializationTypes::Array(Box::new(
+            args.iter()
+                .map(|arg| RedisDeserializationTypes::BulkString((*arg).into()))
+                .collect(),
+        ));
+
+        process_command(&command, client_id, state).to_string()
+    }
+
+    #[test]
+    fn it_should_redirect_commands() {
+        let state = ServerState::new(Redis::new(), 7000)
+            .with_cluster(Cluster::parse(&config(), 7000).unwrap());
+        let client_id = state.clients.register("127.0.0.1:5000".to_string(), None);
+
+        assert_eq!(execute(&state, client_id, &["SET", "bar", "1"]), "+OK\r\n");
+        assert_eq!(
+            execute(&state, client_id, &["GET", "foo"]),
+            "-MOVED 12182 127.0.0.1:7002\r\n"
+        );
+        assert_eq!(
+            execute(&state, client_id, &["CLUSTER", "KEYSLOT", "foo"]),
+            ":12182\r\n"
+        );
+        assert_eq!(
+            execute(&state, client_id, &["CLUSTER", "MYID"]),
+            serialize_bulk_string(NODE_A)
+        );
+        assert!(execute(&state, client_id, &["CLUSTER", "INFO"]).contains("cluster_state:ok"));
+        assert!(execute(&state, client_id, &["CLUSTER", "SLOTS"])
+            .starts_with("*3\r\n*3\r\n:0\r\n:5460\r\n*3\r\n$9\r\n127.0.0.1\r\n:7000\r\n"));
+        assert!(execute(&state, client_id, &["CLUSTER", "SHARDS"])
+            .starts_with("*3\r\n*4\r\n$5\r\nslots\r\n*2\r\n:0\r\n:5460\r\n$5\r\nnodes\r\n"));
+    }
+
+    #[test]
+    fn it_should_only_accept_one_command_after_asking() {
+        let state = ServerState::new(Redis::new(), 7001)
+            .with_cluster(Cluster::parse(&config(), 7001).unwrap());
+        let client_id = state.clients.register("127.0.0.1:5000".to_string(), None);
+        let key = key_in_slot(5000);
+
+        assert_eq!(execute(&state, client_id, &["ASKING"]), "+OK\r\n");
+        assert_eq!(execute(&state, client_id, &["SET", &key, "1"]), "+OK\r\n");
+        assert_eq!(
+            execute(&state, client_id, &["GET", &key]),
+            "-MOVED 5000 127.0.0.1:7000\r\n"
+        );
+    }
+
+    #[test]
+    fn it_should_reject_cluster_commands_when_disabled() {
+        let state = ServerState::new(Redis::new(), 6379);
+
+        assert_eq!(
+            execute(&state, 0, &["CLUSTER", "KEYSLOT", "foo"]),
+            serialize_error(CLUSTER_DISABLED)
+        );
+        assert_eq!(execute(&state, 0, &["GET", "foo"]), "$-1\r\n");
+    }
+}
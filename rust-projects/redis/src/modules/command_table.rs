@@ -0,0 +1,333 @@
+use std::sync::{Arc, Mutex};
+
+use super::{
+    cluster, commands, introspection, pubsub, serialize::serialize_error, server::ServerState,
+    store::Redis,
+    types::{RedisDeserializationTypes, RedisString},
+};
+
+use CommandFlag::{Admin, Readonly, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandFlag {
+    /// The command may modify the keyspace.
+    Write,
+    /// The command only reads the keyspace.
+    Readonly,
+    /// The command inspects or changes the server itself.
+    Admin,
+}
+
+impl CommandFlag {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommandFlag::Write => "write",
+            CommandFlag::Readonly => "readonly",
+            CommandFlag::Admin => "admin",
+        }
+    }
+}
+
+/// Function executing a command. Handlers receive the full argument vector, including the
+/// command name, and can rely on its length matching the command arity.
+#[derive(Clone, Copy)]
+pub enum CommandHandler {
+    /// Commands that only need the keyspace.
+    Store(fn(&[String], &Arc<Mutex<Redis>>) -> String),
+    /// Keyspace commands that take or reply with values, which may be any bytes. They get the
+    /// arguments as sent instead of as text.
+    Value(fn(&[RedisString], &Arc<Mutex<Redis>>) -> RedisString),
+    /// Commands that need the connection issuing them or the server state.
+    Server(fn(&[String], u64, &ServerState) -> String),
+}
+
+impl std::fmt::Debug for CommandHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandHandler::Store(_) => write!(f, "Store"),
+            CommandHandler::Value(_) => write!(f, "Value"),
+            CommandHandler::Server(_) => write!(f, "Server"),
+        }
+    }
+}
+
+/// Static description of a command, used for dispatch and reported by `COMMAND INFO`.
+#[derive(Debug)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    /// Number of arguments including the command name. A negative arity `-N` means at least `N`.
+    pub arity: i64,
+    pub flags: &'static [CommandFlag],
+    /// Position of the first key argument, `0` if the command takes no keys.
+    pub first_key: i64,
+    /// Position of the last key argument, `-1` if the keys run until the last argument.
+    pub last_key: i64,
+    pub step: i64,
+    pub handler: CommandHandler,
+}
+
+impl CommandSpec {
+    pub fn has_flag(&self, flag: CommandFlag) -> bool {
+        self.flags.contains(&flag)
+    }
+
+    /// Returns `true` if `argc` arguments, including the command name, satisfy the arity.
+    pub fn accepts(&self, argc: usize) -> bool {
+        if self.arity >= 0 {
+            argc as i64 == self.arity
+        } else {
+            argc as i64 >= -self.arity
+        }
+    }
+
+    /// Extracts the key arguments from a full argument vector.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use redis::modules::command_table::lookup_command;
+    ///
+    /// let args: Vec<String> = ["DEL", "a", "b"].iter().map(|arg| arg.to_string()).collect();
+    /// assert_eq!(lookup_command("del").unwrap().keys(&args), vec!["a", "b"]);
+    /// ```
+    pub fn keys<'a>(&self, args: &'a [String]) -> Vec<&'a str> {
+        if self.first_key <= 0 || self.step <= 0 {
+            return vec![];
+        }
+
+        let last = if self.last_key < 0 {
+            args.len() as i64 + self.last_key
+        } else {
+            self.last_key
+        };
+
+        (self.first_key..=last)
+            .step_by(self.step as usize)
+            .filter_map(|position| args.get(position as usize))
+            .map(|key| key.as_str())
+            .collect()
+    }
+}
+
+const fn store(
+    name: &'static str,
+    arity: i64,
+    flags: &'static [CommandFlag],
+    (first_key, last_key, step): (i64, i64, i64),
+    handler: fn(&[String], &Arc<Mutex<Redis>>) -> String,
+) -> CommandSpec {
+    CommandSpec {
+        name,
+        arity,
+        flags,
+        first_key,
+        last_key,
+        step,
+        handler: CommandHandler::Store(handler),
+    }
+}
+
+const fn value(
+    name: &'static str,
+    arity: i64,
+    flags: &'static [CommandFlag],
+    (first_key, last_key, step): (i64, i64, i64),
+    handler: fn(&[RedisString], &Arc<Mutex<Redis>>) -> RedisString,
+) -> CommandSpec {
+    CommandSpec {
+        name,
+        arity,
+        flags,
+        first_key,
+        last_key,
+        step,
+        handler: CommandHandler::Value(handler),
+    }
+}
+
+const fn server(
+    name: &'static str,
+    arity: i64,
+    flags: &'static [CommandFlag],
+    handler: fn(&[String], u64, &ServerState) -> String,
+) -> CommandSpec {
+    CommandSpec {
+        name,
+        arity,
+        flags,
+        first_key: 0,
+        last_key: 0,
+        step: 0,
+        handler: CommandHandler::Server(handler),
+    }
+}
+
+const NO_KEYS: (i64, i64, i64) = (0, 0, 0);
+const ONE_KEY: (i64, i64, i64) = (1, 1, 1);
+const ALL_KEYS: (i64, i64, i64) = (1, -1, 1);
+
+pub const COMMAND_TABLE: &[CommandSpec] = &[
+    store("ping", -1, &[], NO_KEYS, commands::ping),
+    store("echo", 2, &[], NO_KEYS, commands::echo),
+    value("set", -3, &[Write], ONE_KEY, commands::set),
+    value("get", 2, &[Readonly], ONE_KEY, commands::get),
+    store("exist", -2, &[Readonly], ALL_KEYS, commands::exist),
+    store("del", -2, &[Write], ALL_KEYS, commands::del),
+    store("incr", 2, &[Write], ONE_KEY, commands::incr_or_decr),
+    store("decr", 2, &[Write], ONE_KEY, commands::incr_or_decr),
+    store("lpush", -3, &[Write], ONE_KEY, commands::push),
+    store("rpush", -3, &[Write], ONE_KEY, commands::push),
+    store("save", 1, &[Admin], NO_KEYS, commands::save),
+    store("load", 1, &[Admin], NO_KEYS, commands::load),
+    store("setbit", 4, &[Write], ONE_KEY, commands::setbit),
+    store("getbit", 3, &[Readonly], ONE_KEY, commands::getbit),
+    store("bitcount", -2, &[Readonly], ONE_KEY, commands::bitcount),
+    store("bitpos", -3, &[Readonly], ONE_KEY, commands::bitpos),
+    store("bitop", -4, &[Write], (2, -1, 1), commands::bitop),
+    store("pfadd", -2, &[Write], ONE_KEY, commands::pfadd),
+    store("pfcount", -2, &[Readonly], ALL_KEYS, commands::pfcount),
+    store("pfmerge", -2, &[Write], ALL_KEYS, commands::pfmerge),
+    server("config", -2, &[Admin], introspection::config),
+    server("info", -1, &[], introspection::info),
+    server("client", -2, &[Admin], introspection::client),
+    server("slowlog", -2, &[Admin], introspection::slowlog),
+    server("monitor", 1, &[Admin], introspection::monitor),
+    server("command", -1, &[], introspection::describe_commands),
+    server("subscribe", -2, &[], pubsub::subscribe_command),
+    server("psubscribe", -2, &[], pubsub::psubscribe_command),
+    server("unsubscribe", -1, &[], pubsub::unsubscribe_command),
+    server("punsubscribe", -1, &[], pubsub::punsubscribe_command),
+    server("publish", 3, &[], pubsub::publish_command),
+    server("cluster", -2, &[], cluster::cluster),
+    server("asking", 1, &[], cluster::asking),
+];
+
+/// Finds a command by name, ignoring case.
+pub fn lookup_command(name: &str) -> Option<&'static CommandSpec> {
+    COMMAND_TABLE
+        .iter()
+        .find(|spec| spec.name.eq_ignore_ascii_case(name))
+}
+
+/// Finds the spec for a deserialized command and validates its arity.
+///
+/// # Arguments
+/// * `command` - A reference to the deserialized Redis command, an array whose first element is the command name.
+///
+/// # Returns
+/// * `Ok((spec, args))` with the command spec and the full argument vector, including the command name.
+/// * `Err(String)` with a serialized Redis error if the command is unknown or has the wrong number of arguments.
+pub fn resolve_command(
+    command: &RedisDeserializationTypes,
+) -> Result<(&'static CommandSpec, Vec<String>), String> {
+    let RedisDeserializationTypes::Array(_) = command else {
+        return Err(serialize_error("ERR Protocol error: expected an array"));
+    };
+
+    let args = command.to_args();
+    let Some(name) = args.first() else {
+        return Err(serialize_error("ERR Protocol error: empty command"));
+    };
+
+    let Some(spec) = lookup_command(name) else {
+        let args_preview: String = args[1..].iter().map(|arg| format!("'{}' ", arg)).collect();
+
+        return Err(serialize_error(&format!(
+            "ERR unknown command '{}', with args beginning with: {}",
+            name, args_preview
+        )));
+    };
+
+    if !spec.accepts(args.len()) {
+        return Err(serialize_error(&format!(
+            "ERR wrong number of arguments for '{}' command",
+            spec.name
+        )));
+    }
+
+    Ok((spec, args))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_command(args: &[&str]) -> RedisDeserializationTypes {
+        RedisDeserializationTypes::Array(Box::new(
+            args.iter()
+                .map(|arg| RedisDeserializationTypes::BulkString((*arg).into()))
+                .collect(),
+        ))
+    }
+
+    #[test]
+    fn should_lookup_command_ignoring_case() {
+        assert_eq!(lookup_command("GET").unwrap().name, "get");
+        assert_eq!(lookup_command("lPuSh").unwrap().arity, -3);
+        assert!(lookup_command("unknown").is_none());
+    }
+
+    #[test]
+    fn should_check_arity() {
+        let get = lookup_command("get").unwrap();
+        assert!(!get.accepts(1));
+        assert!(get.accepts(2));
+        assert!(!get.accepts(3));
+
+        let set = lookup_command("set").unwrap();
+        assert!(!set.accepts(2));
+        assert!(set.accepts(3));
+        assert!(set.accepts(5));
+    }
+
+    #[test]
+    fn should_extract_keys() {
+        let args: Vec<String> = ["SET", "key", "value", "EX", "10"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+
+        assert_eq!(lookup_command("set").unwrap().keys(&args), vec!["key"]);
+        assert!(lookup_command("ping").unwrap().keys(&args).is_empty());
+    }
+
+    #[test]
+    fn should_expose_flags() {
+        assert!(lookup_command("set").unwrap().has_flag(CommandFlag::Write));
+        assert!(lookup_command("get")
+            .unwrap()
+            .has_flag(CommandFlag::Readonly));
+        assert!(lookup_command("client")
+            .unwrap()
+            .has_flag(CommandFlag::Admin));
+        assert!(!lookup_command("get").unwrap().has_flag(CommandFlag::Write));
+    }
+
+    #[test]
+    fn should_resolve_command() {
+        let (spec, args) = resolve_command(&build_command(&["get", "key"])).unwrap();
+
+        assert_eq!(spec.name, "get");
+        assert_eq!(args, vec!["get".to_string(), "key".to_string()]);
+    }
+
+    #[test]
+    fn should_fail_resolve_wrong_arity() {
+        let result = resolve_command(&build_command(&["GET"]));
+
+        assert_eq!(
+            result.unwrap_err(),
+            "-ERR wrong number of arguments for 'get' command\r\n"
+        );
+    }
+
+    #[test]
+    fn should_fail_resolve_unknown_command() {
+        let result = resolve_command(&build_command(&["FOO", "bar", "baz"]));
+
+        assert_eq!(
+            result.unwrap_err(),
+            "-ERR unknown command 'FOO', with args beginning with: 'bar' 'baz' \r\n"
+        );
+    }
+}
@@ -3,13 +3,18 @@ use std::sync::{Arc, Mutex};
 use chrono::{Duration, TimeZone, Utc};
 
 use super::{
+    bitmap::{self, BitOperation, RangeUnit, MAX_BIT_OFFSET},
+    command_table::{resolve_command, CommandHandler},
+    hyperloglog::HyperLogLog,
+    serialize::{serialize_bulk_bytes, serialize_error, serialize_integer, serialize_null},
     store::{Redis, RedisCell},
     string_array::ArrayPlacement,
-    types::RedisDeserializationTypes,
+    types::{RedisDeserializationTypes, RedisString},
 };
 
-const INVALID_COMMAND: &'static str = "-Invalid Command\r\n";
-const OK_COMMAND: &'static str = "+OK\r\n";
+const OK_COMMAND: &str = "+OK\r\n";
+pub const SYNTAX_ERROR: &str = "-ERR syntax error\r\n";
+pub const NOT_AN_INTEGER: &str = "-ERR value is not an integer or out of range\r\n";
 
 /// Performs an arithmetic operation on a value stored in Redis at a given key.
 ///
@@ -25,13 +30,13 @@ const OK_COMMAND: &'static str = "+OK\r\n";
 ///
 /// # Returns
 /// * `Ok(())` if the operation was successful.
-/// * `Err(())` if the operation failed, including if the value could not be parsed as an integer.
+/// * `Err(String)` if the operation failed, including if the value could not be parsed as an integer.
 pub fn arithmetic_command<F>(
     redis: &Arc<Mutex<Redis>>,
     key: &str,
     f: F,
     default: Option<i64>,
-) -> Result<(), ()>
+) -> Result<(), String>
 where
     F: Fn(i64) -> i64,
 {
@@ -39,14 +44,14 @@ where
     let value = match redis.get(key) {
         Some(value) => value
             .value
-            .parse::<i64>()
+            .as_str()
+            .and_then(|number| number.parse::<i64>().ok())
             .map(|number| RedisCell {
-                value: (f(number)).to_string(),
+                value: f(number).to_string().into(),
                 expiry: value.expiry,
-            })
-            .ok(),
+            }),
         None => default.map(|default| RedisCell {
-            value: default.to_string(),
+            value: default.to_string().into(),
             expiry: None,
         }),
     };
@@ -56,184 +61,401 @@ where
             redis.set(key.to_string(), value);
             Ok(())
         }
-        None => Err(()),
+        None => Err("Invalid operation on string".to_string()),
+    }
+}
+
+/// Replies `PONG`, or with the message if one is given. The arity of the table only sets a
+/// minimum, so the upper bound is checked here.
+pub fn ping(args: &[String], _: &Arc<Mutex<Redis>>) -> String {
+    match args {
+        [_] => "+PONG\r\n".to_string(),
+        [_, message] => format!("+{}\r\n", message),
+        _ => serialize_error("ERR wrong number of arguments for 'ping' command"),
+    }
+}
+
+pub fn echo(args: &[String], _: &Arc<Mutex<Redis>>) -> String {
+    format!("+{}\r\n", args[1])
+}
+
+pub fn set(args: &[RedisString], redis: &Arc<Mutex<Redis>>) -> RedisString {
+    let [_, key, value, rest @ ..] = args else {
+        return SYNTAX_ERROR.into();
+    };
+
+    let expiry = match rest {
+        [] => None,
+        [expiry_config, expiry_value] => {
+            let Some(expiry_value) = expiry_value
+                .as_str()
+                .and_then(|expiry_value| expiry_value.parse::<i64>().ok())
+            else {
+                return NOT_AN_INTEGER.into();
+            };
+
+            let expiry = match expiry_config.to_string().to_uppercase().as_ref() {
+                "EX" => Duration::try_seconds(expiry_value)
+                    .and_then(|duration| Utc::now().checked_add_signed(duration)),
+                "PX" => Duration::try_milliseconds(expiry_value)
+                    .and_then(|duration| Utc::now().checked_add_signed(duration)),
+                // `EAXT` is the spelling accepted before `EXAT` was supported.
+                "EXAT" | "EAXT" => Utc.timestamp_opt(expiry_value, 0).single(),
+                "PXAT" => Utc.timestamp_opt(expiry_value / 1000, 0).single(),
+                _ => return SYNTAX_ERROR.into(),
+            };
+
+            match expiry.filter(|_| expiry_value > 0) {
+                Some(expiry) => Some(expiry),
+                None => return serialize_error("ERR invalid expire time in 'set' command").into(),
+            }
+        }
+        _ => return SYNTAX_ERROR.into(),
+    };
+
+    redis.lock().unwrap().set(
+        key.to_string(),
+        RedisCell {
+            value: value.clone(),
+            expiry,
+        },
+    );
+
+    OK_COMMAND.into()
+}
+
+/// Replies with the value as a bulk string, which carries any bytes the value holds, or a null
+/// bulk string for a missing key.
+pub fn get(args: &[RedisString], redis: &Arc<Mutex<Redis>>) -> RedisString {
+    match redis.lock().unwrap().get(&args[1].to_string()) {
+        Some(result) => serialize_bulk_bytes(result.value.as_bytes()).into(),
+        None => serialize_null().into(),
+    }
+}
+
+pub fn exist(args: &[String], redis: &Arc<Mutex<Redis>>) -> String {
+    let count = args[1..]
+        .iter()
+        .fold(0, |acc, k| match redis.lock().unwrap().get(k) {
+            None => acc,
+            Some(_) => acc + 1,
+        });
+
+    format!("+{}\r\n", count)
+}
+
+pub fn del(args: &[String], redis: &Arc<Mutex<Redis>>) -> String {
+    let count = args[1..]
+        .iter()
+        .fold(0, |acc, x| match redis.lock().unwrap().delete(x) {
+            Some(_) => acc + 1,
+            None => acc,
+        });
+
+    format!("+{}\r\n", count)
+}
+
+pub fn incr_or_decr(args: &[String], redis: &Arc<Mutex<Redis>>) -> String {
+    let operation = if args[0].eq_ignore_ascii_case("INCR") {
+        |x| x + 1
+    } else {
+        |x| x - 1
+    };
+
+    match arithmetic_command(redis, &args[1], operation, Some(0)) {
+        Ok(_) => OK_COMMAND.to_string(),
+        Err(err) => format!("-{}\r\n", err),
+    }
+}
+
+pub fn push(args: &[String], redis: &Arc<Mutex<Redis>>) -> String {
+    let left = args[0].eq_ignore_ascii_case("LPUSH");
+    let key = &args[1];
+
+    let result = args[2..].iter().try_fold(0, |_, value| {
+        redis.lock().unwrap().set_list(
+            key.clone(),
+            value.clone(),
+            if left {
+                ArrayPlacement::LEFT
+            } else {
+                ArrayPlacement::RIGHT
+            },
+        )
+    });
+
+    match result {
+        Ok(len) => format!("+{}\r\n", len),
+        Err(err) => format!("-{}\r\n", err),
+    }
+}
+
+pub fn save(_: &[String], redis: &Arc<Mutex<Redis>>) -> String {
+    match redis.lock().unwrap().save() {
+        Ok(_) => OK_COMMAND.to_string(),
+        Err(_) => "-Failed to save\r\n".to_string(),
+    }
+}
+
+pub fn load(_: &[String], redis: &Arc<Mutex<Redis>>) -> String {
+    match redis.lock().unwrap().replace_store() {
+        Ok(_) => OK_COMMAND.to_string(),
+        Err(_) => "-Failed to load\r\n".to_string(),
+    }
+}
+
+/// Returns the raw bytes stored at `key` and its expiry, or an empty value if it does not exist.
+fn get_bytes(redis: &mut Redis, key: &str) -> (Vec<u8>, Option<chrono::DateTime<Utc>>) {
+    redis.get(key).map_or((vec![], None), |cell| {
+        (cell.value.as_bytes().to_vec(), cell.expiry)
+    })
+}
+
+fn parse_range_unit(unit: &str) -> Option<RangeUnit> {
+    match unit.to_uppercase().as_ref() {
+        "BYTE" => Some(RangeUnit::Byte),
+        "BIT" => Some(RangeUnit::Bit),
+        _ => None,
+    }
+}
+
+pub fn setbit(args: &[String], redis: &Arc<Mutex<Redis>>) -> String {
+    let Some(offset) = args[2]
+        .parse::<u64>()
+        .ok()
+        .filter(|offset| *offset <= MAX_BIT_OFFSET)
+    else {
+        return serialize_error("ERR bit offset is not an integer or out of range");
+    };
+
+    let value = match args[3].as_ref() {
+        "0" => false,
+        "1" => true,
+        _ => return serialize_error("ERR bit is not an integer or out of range"),
+    };
+
+    let mut redis = redis.lock().unwrap();
+    let (mut bytes, expiry) = get_bytes(&mut redis, &args[1]);
+    let previous = bitmap::set_bit(&mut bytes, offset, value);
+
+    redis.write(
+        args[1].to_string(),
+        RedisCell {
+            value: bytes.into(),
+            expiry,
+        },
+        "setbit",
+    );
+
+    serialize_integer(previous as i64)
+}
+
+pub fn getbit(args: &[String], redis: &Arc<Mutex<Redis>>) -> String {
+    let Ok(offset) = args[2].parse::<u64>() else {
+        return serialize_error("ERR bit offset is not an integer or out of range");
+    };
+
+    let (bytes, _) = get_bytes(&mut redis.lock().unwrap(), &args[1]);
+
+    serialize_integer(bitmap::get_bit(&bytes, offset) as i64)
+}
+
+pub fn bitcount(args: &[String], redis: &Arc<Mutex<Redis>>) -> String {
+    let (range, unit) = match &args[2..] {
+        [] => (None, RangeUnit::Byte),
+        [start, end, unit @ ..] if unit.len() <= 1 => {
+            let (Ok(start), Ok(end)) = (start.parse::<i64>(), end.parse::<i64>()) else {
+                return NOT_AN_INTEGER.to_string();
+            };
+            let Some(unit) = unit
+                .first()
+                .map_or(Some(RangeUnit::Byte), |unit| parse_range_unit(unit))
+            else {
+                return SYNTAX_ERROR.to_string();
+            };
+
+            (Some((start, end)), unit)
+        }
+        _ => return SYNTAX_ERROR.to_string(),
+    };
+
+    let (bytes, _) = get_bytes(&mut redis.lock().unwrap(), &args[1]);
+
+    serialize_integer(bitmap::bit_count(&bytes, range, unit) as i64)
+}
+
+pub fn bitpos(args: &[String], redis: &Arc<Mutex<Redis>>) -> String {
+    let bit = match args[2].as_ref() {
+        "0" => 0,
+        "1" => 1,
+        _ => return serialize_error("ERR The bit argument must be 1 or 0."),
+    };
+
+    if args.len() > 6 {
+        return SYNTAX_ERROR.to_string();
+    }
+
+    let mut positions = args[3..].iter().take(2).map(|arg| arg.parse::<i64>());
+    let (Ok(start), Ok(end)) = (positions.next().transpose(), positions.next().transpose()) else {
+        return NOT_AN_INTEGER.to_string();
+    };
+    let Some(unit) = args
+        .get(5)
+        .map_or(Some(RangeUnit::Byte), |unit| parse_range_unit(unit))
+    else {
+        return SYNTAX_ERROR.to_string();
+    };
+
+    let (bytes, _) = get_bytes(&mut redis.lock().unwrap(), &args[1]);
+
+    serialize_integer(bitmap::bit_pos(&bytes, bit, start, end, unit))
+}
+
+pub fn bitop(args: &[String], redis: &Arc<Mutex<Redis>>) -> String {
+    let Some(operation) = BitOperation::parse(&args[1]) else {
+        return SYNTAX_ERROR.to_string();
+    };
+
+    let (destination, keys) = (&args[2], &args[3..]);
+    if operation == BitOperation::Not && keys.len() != 1 {
+        return serialize_error("ERR BITOP NOT must be called with a single source key.");
+    }
+
+    let mut redis = redis.lock().unwrap();
+    let sources: Vec<Vec<u8>> = keys
+        .iter()
+        .map(|key| get_bytes(&mut redis, key).0)
+        .collect();
+    let sources: Vec<&[u8]> = sources.iter().map(Vec::as_slice).collect();
+    let result = bitmap::bit_op(operation, &sources);
+    let len = result.len();
+
+    if result.is_empty() {
+        redis.delete(destination);
+    } else {
+        redis.write(
+            destination.to_string(),
+            RedisCell {
+                value: result.into(),
+                expiry: None,
+            },
+            "set",
+        );
+    }
+
+    serialize_integer(len as i64)
+}
+
+/// Reads the HyperLogLog stored at `key`, a missing key being an empty sketch.
+fn get_hyperloglog(redis: &mut Redis, key: &str) -> Result<Option<HyperLogLog>, String> {
+    match redis.get(key) {
+        Some(cell) => HyperLogLog::from_bytes(cell.value.as_bytes().to_vec()).map(Some),
+        None => Ok(None),
+    }
+}
+
+pub fn pfadd(args: &[String], redis: &Arc<Mutex<Redis>>) -> String {
+    let mut redis = redis.lock().unwrap();
+
+    let (mut hll, created) = match get_hyperloglog(&mut redis, &args[1]) {
+        Ok(Some(hll)) => (hll, false),
+        Ok(None) => (HyperLogLog::new(), true),
+        Err(err) => return serialize_error(&err),
+    };
+
+    let mut changed = false;
+    for element in args[2..].iter() {
+        changed |= hll.add(element.as_bytes());
+    }
+
+    if changed || created {
+        let expiry = redis.get(&args[1]).and_then(|cell| cell.expiry);
+        redis.write(
+            args[1].to_string(),
+            RedisCell {
+                value: hll.into_bytes().into(),
+                expiry,
+            },
+            "pfadd",
+        );
+    }
+
+    serialize_integer((changed || created) as i64)
+}
+
+pub fn pfcount(args: &[String], redis: &Arc<Mutex<Redis>>) -> String {
+    let mut redis = redis.lock().unwrap();
+    let mut union = HyperLogLog::new();
+
+    for key in args[1..].iter() {
+        match get_hyperloglog(&mut redis, key) {
+            Ok(Some(hll)) => {
+                union.merge(&hll);
+            }
+            Ok(None) => {}
+            Err(err) => return serialize_error(&err),
+        }
     }
+
+    serialize_integer(union.count() as i64)
 }
 
-/// Executes a given Redis command by deserializing it and applying the corresponding operation on the Redis store.
+pub fn pfmerge(args: &[String], redis: &Arc<Mutex<Redis>>) -> String {
+    let mut redis = redis.lock().unwrap();
+    let mut union = HyperLogLog::new();
+    let mut expiry = None;
+
+    for (position, key) in args[1..].iter().enumerate() {
+        match get_hyperloglog(&mut redis, key) {
+            Ok(Some(hll)) => {
+                union.merge(&hll);
+                if position == 0 {
+                    expiry = redis.get(key).and_then(|cell| cell.expiry);
+                }
+            }
+            Ok(None) => {}
+            Err(err) => return serialize_error(&err),
+        }
+    }
+
+    redis.write(
+        args[1].to_string(),
+        RedisCell {
+            value: union.into_bytes().into(),
+            expiry,
+        },
+        "pfadd",
+    );
+
+    OK_COMMAND.to_string()
+}
+
+/// Executes a given Redis command by looking it up in the command table and applying the corresponding operation on the Redis store.
+///
+/// Only commands that work on the keyspace can be executed this way, server commands such as
+/// `CLIENT` need a connection and go through `server::process_command`.
 ///
 /// # Arguments
 /// * `command` - A reference to the deserialized Redis command to be executed.
 /// * `redis` - An `Arc<Mutex<Redis>>` shared among threads, allowing synchronized access to the Redis store.
 ///
 /// # Returns
-/// A `String` representing the result of the command execution, which could be a success message, error message, or data retrieved from the store.
-pub fn execute_command(command: &RedisDeserializationTypes, redis: Arc<Mutex<Redis>>) -> String {
-    let ret = match command {
-        RedisDeserializationTypes::Array(a) => match a.as_slice() {
-            [RedisDeserializationTypes::BulkString(c), args @ ..] => match c.as_ref() {
-                "PING" => Some("+PONG\r\n".to_string()),
-                "ECHO" => match args {
-                    [RedisDeserializationTypes::BulkString(echo)] => {
-                        Some(format!("+{}\r\n", echo))
-                    }
-                    _ => None,
-                },
-                "SET" => match args {
-                    [RedisDeserializationTypes::BulkString(key), RedisDeserializationTypes::BulkString(value), rest @ ..] =>
-                    {
-                        let expiry = match rest {
-                            [] => None,
-                            [RedisDeserializationTypes::BulkString(expiry_config), RedisDeserializationTypes::BulkString(expiry_value)] => {
-                                Some(match expiry_config.as_ref() {
-                                    "EX" => {
-                                        Utc::now()
-                                            + Duration::seconds(expiry_value.parse().unwrap())
-                                    }
-                                    "PX" => {
-                                        Utc::now()
-                                            + Duration::milliseconds(expiry_value.parse().unwrap())
-                                    }
-                                    "EAXT" => {
-                                        Utc.timestamp_opt(expiry_value.parse().unwrap(), 0).unwrap()
-                                    }
-                                    "PXAT" => Utc
-                                        .timestamp_opt(
-                                            (expiry_value.parse::<i64>().unwrap()) / 1000,
-                                            0,
-                                        )
-                                        .unwrap(),
-                                    _ => return INVALID_COMMAND.to_string(),
-                                })
-                            }
-                            _ => return INVALID_COMMAND.to_string(),
-                        };
-
-                        redis.lock().unwrap().set(
-                            key.to_string(),
-                            RedisCell {
-                                value: value.to_string(),
-                                expiry,
-                            },
-                        );
-
-                        Some(OK_COMMAND.to_string().to_string())
-                    }
-                    _ => None,
-                },
-                "GET" => match args {
-                    [RedisDeserializationTypes::BulkString(key)] => {
-                        match redis.lock().unwrap().get(key) {
-                            Some(result) => Some(format!("+{}\r\n", result.value)),
-                            None => Some(format!("+NONE\r\n")),
-                        }
-                    }
-                    _ => None,
-                },
-                "EXIST" => {
-                    let count = args
-                        .iter()
-                        .filter_map(|x| {
-                            if let RedisDeserializationTypes::BulkString(k) = x {
-                                Some(k)
-                            } else {
-                                None
-                            }
-                        })
-                        .fold(0, |acc, k| match redis.lock().unwrap().get(k) {
-                            None => acc,
-                            Some(_) => acc + 1,
-                        });
-
-                    Some(format!("+{}\r\n", count))
-                }
-                "DEL" => {
-                    let count = args
-                        .iter()
-                        .filter_map(|x| {
-                            if let RedisDeserializationTypes::BulkString(s) = x {
-                                Some(s)
-                            } else {
-                                None
-                            }
-                        })
-                        .fold(0, |acc, x| match redis.lock().unwrap().delete(x) {
-                            Some(_) => acc + 1,
-                            None => acc,
-                        });
-
-                    Some(format!("+{}\r\n", count))
-                }
-                command @ "INCR" | command @ "DECR" => match args {
-                    [RedisDeserializationTypes::BulkString(key)] => {
-                        let operation = if command == "INCR" {
-                            |x| x + 1
-                        } else {
-                            |x| x - 1
-                        };
-                        match arithmetic_command(&redis, key, operation, Some(0)) {
-                            Ok(_) => Some(OK_COMMAND.to_string()),
-                            Err(_) => Some("-Invalid operation on string\r\n".to_string()),
-                        }
-                    }
-                    _ => None,
-                },
-                placement @ "LPUSH" | placement @ "RPUSH" => match args {
-                    [RedisDeserializationTypes::BulkString(key), arr_elements @ ..]
-                        if arr_elements
-                            .iter()
-                            .all(|e| matches!(e, RedisDeserializationTypes::BulkString(_))) =>
-                    {
-                        let result = arr_elements.iter().try_fold(0, |_, e| {
-                            if let RedisDeserializationTypes::BulkString(value) = e {
-                                redis.lock().unwrap().set_list(
-                                    key.clone(),
-                                    value.clone(),
-                                    if placement == "LPUSH" {
-                                        ArrayPlacement::LEFT
-                                    } else {
-                                        ArrayPlacement::RIGHT
-                                    },
-                                )
-                            } else {
-                                Err(INVALID_COMMAND.to_string())
-                            }
-                        });
-
-                        match result {
-                            Ok(len) => Some(format!("+{}\r\n", len)),
-                            Err(err) => Some(format!("-{}\r\n", err)),
-                        }
-                    }
-                    _ => None,
-                },
-                "SAVE" => {
-                    match redis.lock().unwrap().save() {
-                        Ok(_) => Some(OK_COMMAND.to_string()),
-                        Err(_) => Some(format!("-Failed to save\r\n")),       
-                    }
-                }
-                "LOAD" => {
-                    match redis.lock().unwrap().replace_store() {
-                        Ok(_) => Some(OK_COMMAND.to_string()),
-                        Err(_) => Some(format!("-Failed to load\r\n")),   
-                    }
-                }
-                // Mock config, to bypass redis-benchmark request
-                "CONFIG" => Some(
-                    "*2\r\n$4\r\nsave\r\n$23\r\n3600 1 300 100 60 10000\r\n*2\r\n$10\r\nappendonly\r\n$2\r\nno\r\n"
-                        .to_string(),
-                ),
-                _ => None,
-            },
-            _ => None,
+/// A `RedisString` representing the result of the command execution, which could be a success message, error message, or data retrieved from the store.
+pub fn execute_command(
+    command: &RedisDeserializationTypes,
+    redis: Arc<Mutex<Redis>>,
+) -> RedisString {
+    match resolve_command(command) {
+        Ok((spec, args)) => match spec.handler {
+            CommandHandler::Store(handler) => handler(&args, &redis).into(),
+            CommandHandler::Value(handler) => handler(&command.to_raw_args(), &redis),
+            CommandHandler::Server(_) => serialize_error(&format!(
+                "ERR '{}' command is not available without a client connection",
+                spec.name
+            ))
+            .into(),
         },
-        _ => None,
-    };
-
-    match ret {
-        None => INVALID_COMMAND.to_string(),
-        Some(ret) => ret,
+        Err(err) => err.into(),
     }
 }
 
@@ -270,51 +492,48 @@ mod tests {
         key: String,
         value: String,
         expiry: Option<SetExpiryArgs>,
-    ) -> String {
+    ) -> RedisString {
         let mut command = vec![
-            RedisDeserializationTypes::BulkString("SET".to_string()),
-            RedisDeserializationTypes::BulkString(key),
-            RedisDeserializationTypes::BulkString(value),
+            RedisDeserializationTypes::BulkString("SET".into()),
+            RedisDeserializationTypes::BulkString(key.into()),
+            RedisDeserializationTypes::BulkString(value.into()),
         ];
 
-        match expiry {
-            Some(args) => {
-                command.extend([
-                    RedisDeserializationTypes::BulkString(args.config),
-                    RedisDeserializationTypes::BulkString(args.value),
-                ]);
-            }
-            None => {}
-        };
+        if let Some(args) = expiry {
+            command.extend([
+                RedisDeserializationTypes::BulkString(args.config.into()),
+                RedisDeserializationTypes::BulkString(args.value.into()),
+            ]);
+        }
 
         execute_command(&build_command(command), redis)
     }
 
-    fn execute_get(redis: Arc<Mutex<Redis>>, key: String) -> String {
+    fn execute_get(redis: Arc<Mutex<Redis>>, key: String) -> RedisString {
         execute_command(
             &build_command(vec![
-                RedisDeserializationTypes::BulkString("GET".to_string()),
-                RedisDeserializationTypes::BulkString(key),
+                RedisDeserializationTypes::BulkString("GET".into()),
+                RedisDeserializationTypes::BulkString(key.into()),
             ]),
             redis,
         )
     }
 
-    fn execute_exist(redis: Arc<Mutex<Redis>>, keys: Vec<String>) -> String {
-        let mut command = vec![RedisDeserializationTypes::BulkString("EXIST".to_string())];
+    fn execute_exist(redis: Arc<Mutex<Redis>>, keys: Vec<String>) -> RedisString {
+        let mut command = vec![RedisDeserializationTypes::BulkString("EXIST".into())];
         command.extend(
             keys.iter()
-                .map(|x| RedisDeserializationTypes::BulkString(x.to_string())),
+                .map(|x| RedisDeserializationTypes::BulkString(x.to_string().into())),
         );
 
         execute_command(&build_command(command), redis)
     }
 
-    fn execute_del(redis: Arc<Mutex<Redis>>, keys: Vec<String>) -> String {
-        let mut command = vec![RedisDeserializationTypes::BulkString("DEL".to_string())];
+    fn execute_del(redis: Arc<Mutex<Redis>>, keys: Vec<String>) -> RedisString {
+        let mut command = vec![RedisDeserializationTypes::BulkString("DEL".into())];
         command.extend(
             keys.iter()
-                .map(|x| RedisDeserializationTypes::BulkString(x.to_string())),
+                .map(|x| RedisDeserializationTypes::BulkString(x.to_string().into())),
         );
 
         execute_command(&build_command(command), redis)
@@ -325,60 +544,60 @@ mod tests {
         key: String,
         values: Vec<String>,
         placement: ArrayPlacement,
-    ) -> String {
+    ) -> RedisString {
         let mut command = vec![RedisDeserializationTypes::BulkString(
             (if placement == ArrayPlacement::LEFT {
                 "LPUSH"
             } else {
                 "RPUSH"
             })
-            .to_string(),
+            .into(),
         )];
 
-        command.extend([RedisDeserializationTypes::BulkString(key)]);
+        command.extend([RedisDeserializationTypes::BulkString(key.into())]);
 
         command.extend(
             values
                 .iter()
-                .map(|v| RedisDeserializationTypes::BulkString(v.to_string())),
+                .map(|v| RedisDeserializationTypes::BulkString(v.to_string().into())),
         );
 
         execute_command(&build_command(command), redis)
     }
 
-    fn execute_save(redis: Arc<Mutex<Redis>>) -> String {
-        let command = vec![RedisDeserializationTypes::BulkString("SAVE".to_string())];
+    fn execute_save(redis: Arc<Mutex<Redis>>) -> RedisString {
+        let command = vec![RedisDeserializationTypes::BulkString("SAVE".into())];
 
         execute_command(&build_command(command), redis)
     }
 
-    fn execute_load(redis: Arc<Mutex<Redis>>) -> String {
-        let command = vec![RedisDeserializationTypes::BulkString("LOAD".to_string())];
+    fn execute_load(redis: Arc<Mutex<Redis>>) -> RedisString {
+        let command = vec![RedisDeserializationTypes::BulkString("LOAD".into())];
 
         execute_command(&build_command(command), redis)
     }
 
     #[derive(PartialEq)]
     enum ArithmeticCommand {
-        INCR,
+        Incr,
     }
 
     fn execute_incr_or_decr(
         redis: Arc<Mutex<Redis>>,
         key: String,
         command: ArithmeticCommand,
-    ) -> String {
+    ) -> RedisString {
         execute_command(
             &build_command(vec![
                 RedisDeserializationTypes::BulkString(
-                    (if command == ArithmeticCommand::INCR {
+                    (if command == ArithmeticCommand::Incr {
                         "INCR"
                     } else {
                         "DECR"
                     })
-                    .to_string(),
+                    .into(),
                 ),
-                RedisDeserializationTypes::BulkString(key),
+                RedisDeserializationTypes::BulkString(key.into()),
             ]),
             redis,
         )
@@ -389,11 +608,24 @@ mod tests {
         let Setup { redis } = setup();
         let response = execute_command(
             &build_command(vec![RedisDeserializationTypes::BulkString(
-                "PING".to_string(),
+                "PING".into(),
             )]),
             Arc::clone(&redis),
         );
-        assert_eq!(response, "+PONG\r\n")
+        assert_eq!(response, "+PONG\r\n");
+
+        let response = execute_command(
+            &build_command(vec![
+                RedisDeserializationTypes::BulkString("PING".into()),
+                RedisDeserializationTypes::BulkString("a".into()),
+                RedisDeserializationTypes::BulkString("b".into()),
+            ]),
+            Arc::clone(&redis),
+        );
+        assert_eq!(
+            response,
+            "-ERR wrong number of arguments for 'ping' command\r\n"
+        );
     }
 
     #[test]
@@ -401,8 +633,8 @@ mod tests {
         let Setup { redis } = setup();
         let response = execute_command(
             &build_command(vec![
-                RedisDeserializationTypes::BulkString("ECHO".to_string()),
-                RedisDeserializationTypes::BulkString("Hello World".to_string()),
+                RedisDeserializationTypes::BulkString("ECHO".into()),
+                RedisDeserializationTypes::BulkString("Hello World".into()),
             ]),
             Arc::clone(&redis),
         );
@@ -414,11 +646,14 @@ mod tests {
         let Setup { redis } = setup();
         let response = execute_command(
             &build_command(vec![RedisDeserializationTypes::BulkString(
-                "ECHO".to_string(),
+                "ECHO".into(),
             )]),
             Arc::clone(&redis),
         );
-        assert_eq!(response, "-Invalid Command\r\n")
+        assert_eq!(
+            response,
+            "-ERR wrong number of arguments for 'echo' command\r\n"
+        )
     }
 
     #[test]
@@ -426,12 +661,15 @@ mod tests {
         let Setup { redis } = setup();
         let response = execute_command(
             &build_command(vec![
-                RedisDeserializationTypes::BulkString("123".to_string()),
-                RedisDeserializationTypes::BulkString("Hello World".to_string()),
+                RedisDeserializationTypes::BulkString("123".into()),
+                RedisDeserializationTypes::BulkString("Hello World".into()),
             ]),
             Arc::clone(&redis),
         );
-        assert_eq!(response, "-Invalid Command\r\n")
+        assert_eq!(
+            response,
+            "-ERR unknown command '123', with args beginning with: 'Hello World' \r\n"
+        )
     }
 
     #[test]
@@ -448,7 +686,7 @@ mod tests {
         assert_eq!(response, OK_COMMAND.to_string());
         let response = execute_get(Arc::clone(&redis), "Name".to_string());
 
-        assert_eq!(response, "+Felipe\r\n");
+        assert_eq!(response, "$6\r\nFelipe\r\n");
     }
 
     #[test]
@@ -472,7 +710,7 @@ mod tests {
 
         let response = execute_get(redis, "Name".to_string());
 
-        assert_eq!(response, "+Carlos\r\n");
+        assert_eq!(response, "$6\r\nCarlos\r\n");
     }
 
     #[test]
@@ -480,12 +718,15 @@ mod tests {
         let Setup { redis } = setup();
         let response = execute_command(
             &build_command(vec![
-                RedisDeserializationTypes::BulkString("SET".to_string()),
-                RedisDeserializationTypes::BulkString("Name".to_string()),
+                RedisDeserializationTypes::BulkString("SET".into()),
+                RedisDeserializationTypes::BulkString("Name".into()),
             ]),
             Arc::clone(&redis),
         );
-        assert_eq!(response, "-Invalid Command\r\n");
+        assert_eq!(
+            response,
+            "-ERR wrong number of arguments for 'set' command\r\n"
+        );
     }
 
     #[test]
@@ -493,11 +734,14 @@ mod tests {
         let Setup { redis } = setup();
         let response = execute_command(
             &build_command(vec![RedisDeserializationTypes::BulkString(
-                "GET".to_string(),
+                "GET".into(),
             )]),
             Arc::clone(&redis),
         );
-        assert_eq!(response, "-Invalid Command\r\n");
+        assert_eq!(
+            response,
+            "-ERR wrong number of arguments for 'get' command\r\n"
+        );
     }
 
     #[test]
@@ -505,7 +749,7 @@ mod tests {
         let Setup { redis } = setup();
 
         let response = execute_get(redis, "Age".to_string());
-        assert_eq!(response, "+NONE\r\n");
+        assert_eq!(response, "$-1\r\n");
     }
 
     #[test]
@@ -523,12 +767,12 @@ mod tests {
         );
 
         let response = execute_get(Arc::clone(&redis), "Name".to_string());
-        assert_eq!(response, "+Felipe\r\n");
+        assert_eq!(response, "$6\r\nFelipe\r\n");
 
         thread::sleep(Duration::from_secs(2));
 
         let response = execute_get(Arc::clone(&redis), "Name".to_string());
-        assert_eq!(response, "+NONE\r\n");
+        assert_eq!(response, "$-1\r\n");
     }
 
     #[test]
@@ -546,12 +790,12 @@ mod tests {
         );
 
         let response = execute_get(Arc::clone(&redis), "Name".to_string());
-        assert_eq!(response, "+Felipe\r\n");
+        assert_eq!(response, "$6\r\nFelipe\r\n");
 
         thread::sleep(Duration::from_millis(2000));
 
         let response = execute_get(Arc::clone(&redis), "Name".to_string());
-        assert_eq!(response, "+NONE\r\n");
+        assert_eq!(response, "$-1\r\n");
     }
 
     #[test]
@@ -571,12 +815,12 @@ mod tests {
         );
 
         let response = execute_get(Arc::clone(&redis), "Name".to_string());
-        assert_eq!(response, "+Felipe\r\n");
+        assert_eq!(response, "$6\r\nFelipe\r\n");
 
         thread::sleep(Duration::from_secs(2));
 
         let response = execute_get(Arc::clone(&redis), "Name".to_string());
-        assert_eq!(response, "+NONE\r\n");
+        assert_eq!(response, "$-1\r\n");
     }
 
     #[test]
@@ -595,12 +839,12 @@ mod tests {
         );
 
         let response = execute_get(Arc::clone(&redis), "Name".to_string());
-        assert_eq!(response, "+Felipe\r\n");
+        assert_eq!(response, "$6\r\nFelipe\r\n");
 
         thread::sleep(Duration::from_millis(2000));
 
         let response = execute_get(Arc::clone(&redis), "Name".to_string());
-        assert_eq!(response, "+NONE\r\n");
+        assert_eq!(response, "$-1\r\n");
     }
 
     #[test]
@@ -704,7 +948,7 @@ mod tests {
         assert_eq!(response, "+1\r\n");
 
         let response = execute_get(Arc::clone(&redis), "Name".to_string());
-        assert_eq!(response, "+NONE\r\n");
+        assert_eq!(response, "$-1\r\n");
     }
 
     #[test]
@@ -737,11 +981,11 @@ mod tests {
         assert_eq!(response, "+3\r\n");
 
         let response = execute_get(Arc::clone(&redis), "Name".to_string());
-        assert_eq!(response, "+NONE\r\n");
+        assert_eq!(response, "$-1\r\n");
         let response = execute_get(Arc::clone(&redis), "Age".to_string());
-        assert_eq!(response, "+NONE\r\n");
+        assert_eq!(response, "$-1\r\n");
         let response = execute_get(Arc::clone(&redis), "Country".to_string());
-        assert_eq!(response, "+NONE\r\n");
+        assert_eq!(response, "$-1\r\n");
     }
 
     #[test]
@@ -778,11 +1022,11 @@ mod tests {
         assert_eq!(response, "+0\r\n");
 
         let response = execute_get(Arc::clone(&redis), "Name".to_string());
-        assert_eq!(response, "+Felipe\r\n");
+        assert_eq!(response, "$6\r\nFelipe\r\n");
         let response = execute_get(Arc::clone(&redis), "Age".to_string());
-        assert_eq!(response, "+23\r\n");
+        assert_eq!(response, "$2\r\n23\r\n");
         let response = execute_get(Arc::clone(&redis), "Country".to_string());
-        assert_eq!(response, "+UAE\r\n");
+        assert_eq!(response, "$3\r\nUAE\r\n");
     }
 
     #[test]
@@ -792,12 +1036,12 @@ mod tests {
         let response = execute_incr_or_decr(
             Arc::clone(&redis),
             "New".to_string(),
-            ArithmeticCommand::INCR,
+            ArithmeticCommand::Incr,
         );
         assert_eq!(response, OK_COMMAND.to_string().to_string());
 
         let response = execute_get(Arc::clone(&redis), "New".to_string());
-        assert_eq!(response, "+0\r\n".to_string());
+        assert_eq!(response, "$1\r\n0\r\n".to_string());
     }
 
     #[test]
@@ -807,19 +1051,19 @@ mod tests {
         let response = execute_incr_or_decr(
             Arc::clone(&redis),
             "New".to_string(),
-            ArithmeticCommand::INCR,
+            ArithmeticCommand::Incr,
         );
         assert_eq!(response, OK_COMMAND.to_string().to_string());
 
         let response = execute_incr_or_decr(
             Arc::clone(&redis),
             "New".to_string(),
-            ArithmeticCommand::INCR,
+            ArithmeticCommand::Incr,
         );
         assert_eq!(response, OK_COMMAND.to_string().to_string());
 
         let response = execute_get(Arc::clone(&redis), "New".to_string());
-        assert_eq!(response, "+1\r\n".to_string());
+        assert_eq!(response, "$1\r\n1\r\n".to_string());
     }
 
     #[test]
@@ -837,12 +1081,12 @@ mod tests {
         let response = execute_incr_or_decr(
             Arc::clone(&redis),
             "New".to_string(),
-            ArithmeticCommand::INCR,
+            ArithmeticCommand::Incr,
         );
         assert_eq!(response, OK_COMMAND.to_string().to_string());
 
         let response = execute_get(Arc::clone(&redis), "New".to_string());
-        assert_eq!(response, "+13\r\n".to_string());
+        assert_eq!(response, "$2\r\n13\r\n".to_string());
     }
 
     #[test]
@@ -860,12 +1104,12 @@ mod tests {
         let response = execute_incr_or_decr(
             Arc::clone(&redis),
             "New".to_string(),
-            ArithmeticCommand::INCR,
+            ArithmeticCommand::Incr,
         );
         assert_eq!(response, "-Invalid operation on string\r\n".to_string());
 
         let response = execute_get(Arc::clone(&redis), "New".to_string());
-        assert_eq!(response, "+not number\r\n".to_string());
+        assert_eq!(response, "$10\r\nnot number\r\n".to_string());
     }
 
     #[test]
@@ -888,12 +1132,12 @@ mod tests {
         let response = execute_incr_or_decr(
             Arc::clone(&redis),
             "New".to_string(),
-            ArithmeticCommand::INCR,
+            ArithmeticCommand::Incr,
         );
         assert_eq!(response, OK_COMMAND.to_string().to_string());
 
         let response = execute_get(Arc::clone(&redis), "New".to_string());
-        assert_eq!(response, "+11\r\n".to_string());
+        assert_eq!(response, "$2\r\n11\r\n".to_string());
 
         if let Some(value) = redis.lock().unwrap().get("New") {
             assert_eq!(
@@ -924,12 +1168,12 @@ mod tests {
         let response = execute_incr_or_decr(
             Arc::clone(&redis),
             "New".to_string(),
-            ArithmeticCommand::INCR,
+            ArithmeticCommand::Incr,
         );
         assert_eq!(response, "-Invalid operation on string\r\n".to_string());
 
         let response = execute_get(Arc::clone(&redis), "New".to_string());
-        assert_eq!(response, "+not number\r\n".to_string());
+        assert_eq!(response, "$10\r\nnot number\r\n".to_string());
 
         if let Some(value) = redis.lock().unwrap().get("New") {
             assert_eq!(
@@ -959,7 +1203,7 @@ mod tests {
 
         let response = execute_get(redis, "array".to_string());
         assert_eq!(
-            "+[element4,element3,element2,element1]\r\n".to_string(),
+            "$37\r\n[element4,element3,element2,element1]\r\n".to_string(),
             response
         );
     }
@@ -1004,7 +1248,7 @@ mod tests {
 
         let response = execute_get(redis, "array".to_string());
         assert_eq!(
-            "+[element1,element2,element3,element4]\r\n".to_string(),
+            "$37\r\n[element1,element2,element3,element4]\r\n".to_string(),
             response
         );
     }
@@ -1070,15 +1314,224 @@ mod tests {
         execute_load(Arc::clone(&redis));
 
         let response = execute_get(Arc::clone(&redis), "Name".to_string());
-        assert_eq!("+Felipe\r\n", response);
+        assert_eq!("$6\r\nFelipe\r\n", response);
 
         let response = execute_get(Arc::clone(&redis), "array".to_string());
         assert_eq!(
-            "+[element1,element2,element3,element4,element5,element6,element7,element8]\r\n",
+            "$73\r\n[element1,element2,element3,element4,element5,element6,element7,element8]\r\n",
             response
         );
 
         let response = execute_get(Arc::clone(&redis), "Test".to_string());
-        assert_eq!("+NONE\r\n", response);
+        assert_eq!("$-1\r\n", response);
+    }
+
+    #[test]
+    fn it_should_accept_lowercase_commands() {
+        let Setup { redis } = setup();
+
+        let response = execute_command(
+            &build_command(vec![
+                RedisDeserializationTypes::BulkString("set".into()),
+                RedisDeserializationTypes::BulkString("Name".into()),
+                RedisDeserializationTypes::BulkString("Felipe".into()),
+            ]),
+            Arc::clone(&redis),
+        );
+        assert_eq!(response, OK_COMMAND);
+
+        let response = execute_get(redis, "Name".to_string());
+        assert_eq!(response, "$6\r\nFelipe\r\n");
+    }
+
+    #[test]
+    fn it_should_fail_set_invalid_expiry() {
+        let Setup { redis } = setup();
+
+        let response = execute_set(
+            Arc::clone(&redis),
+            "Name".to_string(),
+            "Felipe".to_string(),
+            Some(SetExpiryArgs {
+                config: "EX".to_string(),
+                value: "soon".to_string(),
+            }),
+        );
+        assert_eq!(response, NOT_AN_INTEGER);
+
+        let response = execute_set(
+            Arc::clone(&redis),
+            "Name".to_string(),
+            "Felipe".to_string(),
+            Some(SetExpiryArgs {
+                config: "KEEP".to_string(),
+                value: "10".to_string(),
+            }),
+        );
+        assert_eq!(response, SYNTAX_ERROR);
+
+        for (config, value) in [
+            ("EX", "0"),
+            ("PX", "-1"),
+            ("EXAT", "-5"),
+            ("EAXT", "0"),
+            ("PX", "9223372036854775807"),
+            ("EX", "9223372036854775"),
+        ] {
+            let response = execute_set(
+                Arc::clone(&redis),
+                "Name".to_string(),
+                "Felipe".to_string(),
+                Some(SetExpiryArgs {
+                    config: config.to_string(),
+                    value: value.to_string(),
+                }),
+            );
+            assert_eq!(
+                response,
+                "-ERR invalid expire time in 'set' command\r\n",
+                "{} {}",
+                config,
+                value
+            );
+        }
+
+        let response = execute_get(Arc::clone(&redis), "Name".to_string());
+        assert_eq!(response, "$-1\r\n");
+
+        let response = execute_set(
+            Arc::clone(&redis),
+            "Name".to_string(),
+            "Felipe".to_string(),
+            Some(SetExpiryArgs {
+                config: "EXAT".to_string(),
+                value: "10000000000".to_string(),
+            }),
+        );
+        assert_eq!(response, OK_COMMAND);
+        let response = execute_get(redis, "Name".to_string());
+        assert_eq!(response, "$6\r\nFelipe\r\n");
+    }
+
+    #[test]
+    fn it_should_not_execute_server_commands_without_connection() {
+        let Setup { redis } = setup();
+
+        let response = execute_command(
+            &build_command(vec![RedisDeserializationTypes::BulkString(
+                "MONITOR".into(),
+            )]),
+            redis,
+        );
+        assert_eq!(
+            response,
+            "-ERR 'monitor' command is not available without a client connection\r\n"
+        );
+    }
+
+    fn execute(redis: &Arc<Mutex<Redis>>, args: &[&str]) -> RedisString {
+        execute_command(
+            &build_command(
+                args.iter()
+                    .map(|arg| RedisDeserializationTypes::BulkString((*arg).into()))
+                    .collect(),
+            ),
+            Arc::clone(redis),
+        )
+    }
+
+    #[test]
+    fn should_set_and_count_bits() {
+        let Setup { redis } = setup();
+
+        assert_eq!(execute(&redis, &["SETBIT", "visits", "7", "1"]), ":0\r\n");
+        assert_eq!(execute(&redis, &["SETBIT", "visits", "7", "1"]), ":1\r\n");
+        assert_eq!(execute(&redis, &["SETBIT", "visits", "100", "1"]), ":0\r\n");
+        assert_eq!(execute(&redis, &["GETBIT", "visits", "100"]), ":1\r\n");
+        assert_eq!(execute(&redis, &["GETBIT", "visits", "1000"]), ":0\r\n");
+        assert_eq!(execute(&redis, &["BITCOUNT", "visits"]), ":2\r\n");
+        assert_eq!(execute(&redis, &["BITCOUNT", "visits", "0", "0"]), ":1\r\n");
+        assert_eq!(
+            execute(&redis, &["BITCOUNT", "visits", "8", "-1", "BIT"]),
+            ":1\r\n"
+        );
+        assert_eq!(execute(&redis, &["BITPOS", "visits", "1"]), ":7\r\n");
+        assert_eq!(execute(&redis, &["BITPOS", "visits", "1", "1"]), ":100\r\n");
+        assert_eq!(execute(&redis, &["BITPOS", "missing", "0"]), ":0\r\n");
+
+        assert_eq!(
+            execute(&redis, &["SETBIT", "visits", "-1", "1"]),
+            "-ERR bit offset is not an integer or out of range\r\n"
+        );
+        assert_eq!(
+            execute(&redis, &["SETBIT", "visits", "1", "2"]),
+            "-ERR bit is not an integer or out of range\r\n"
+        );
+        assert_eq!(
+            execute(&redis, &["BITCOUNT", "visits", "0"]),
+            SYNTAX_ERROR.to_string()
+        );
+    }
+
+    #[test]
+    fn should_keep_bitmaps_binary_safe() {
+        let Setup { redis } = setup();
+
+        execute(&redis, &["SETBIT", "bits", "0", "1"]);
+
+        let mut store = redis.lock().unwrap();
+        assert_eq!(store.get("bits").unwrap().value.as_bytes(), &[0x80]);
+        assert_eq!(store.get("bits").unwrap().value.as_str(), None);
+    }
+
+    #[test]
+    fn should_combine_bitmaps() {
+        let Setup { redis } = setup();
+
+        execute(&redis, &["SET", "a", "foof"]);
+        execute(&redis, &["SET", "b", "obao"]);
+
+        assert_eq!(
+            execute(&redis, &["BITOP", "AND", "dest", "a", "b"]),
+            ":4\r\n"
+        );
+        assert_eq!(execute(&redis, &["BITCOUNT", "dest"]), ":14\r\n");
+        assert_eq!(execute(&redis, &["BITOP", "NOT", "dest", "a"]), ":4\r\n");
+        assert_eq!(execute(&redis, &["BITCOUNT", "dest"]), ":12\r\n");
+        assert_eq!(
+            execute(&redis, &["BITOP", "NOT", "dest", "a", "b"]),
+            "-ERR BITOP NOT must be called with a single source key.\r\n"
+        );
+
+        assert_eq!(
+            execute(&redis, &["BITOP", "OR", "dest", "missing"]),
+            ":0\r\n"
+        );
+        assert_eq!(execute(&redis, &["GET", "dest"]), "$-1\r\n");
+    }
+
+    #[test]
+    fn should_count_unique_elements() {
+        let Setup { redis } = setup();
+
+        assert_eq!(execute(&redis, &["PFADD", "hll", "a", "b", "c"]), ":1\r\n");
+        assert_eq!(execute(&redis, &["PFADD", "hll", "a", "b"]), ":0\r\n");
+        assert_eq!(execute(&redis, &["PFCOUNT", "hll"]), ":3\r\n");
+
+        execute(&redis, &["PFADD", "other", "c", "d"]);
+        assert_eq!(execute(&redis, &["PFCOUNT", "hll", "other"]), ":4\r\n");
+        assert_eq!(execute(&redis, &["PFCOUNT", "missing"]), ":0\r\n");
+
+        assert_eq!(
+            execute(&redis, &["PFMERGE", "union", "hll", "other"]),
+            OK_COMMAND
+        );
+        assert_eq!(execute(&redis, &["PFCOUNT", "union"]), ":4\r\n");
+
+        execute(&redis, &["SET", "name", "Felipe"]);
+        assert_eq!(
+            execute(&redis, &["PFADD", "name", "a"]),
+            "-WRONGTYPE Key is not a valid HyperLogLog string value.\r\n"
+        );
     }
 }
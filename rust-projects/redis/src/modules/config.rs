@@ -0,0 +1,173 @@
+use super::notifications::{flags_to_string, parse_flags};
+
+/// Runtime configuration exposed through `CONFIG GET` and `CONFIG SET`.
+#[derive(Debug)]
+pub struct Config {
+    /// Commands slower than this many microseconds are recorded in the slow log.
+    /// A negative value disables the slow log, zero records every command.
+    pub slowlog_log_slower_than: i64,
+    /// Maximum number of entries kept in the slow log.
+    pub slowlog_max_len: usize,
+    /// Keyspace notification classes, see `notifications::parse_flags`.
+    pub notify_keyspace_events: u32,
+    /// Memory limit in bytes before keys are evicted, `0` means no limit.
+    pub maxmemory: usize,
+    save: String,
+    appendonly: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            slowlog_log_slower_than: 10_000,
+            slowlog_max_len: 128,
+            notify_keyspace_events: 0,
+            maxmemory: 0,
+            save: "3600 1 300 100 60 10000".to_string(),
+            appendonly: "no".to_string(),
+        }
+    }
+}
+
+impl Config {
+    fn parameters(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("save", self.save.clone()),
+            ("appendonly", self.appendonly.clone()),
+            (
+                "slowlog-log-slower-than",
+                self.slowlog_log_slower_than.to_string(),
+            ),
+            ("slowlog-max-len", self.slowlog_max_len.to_string()),
+            (
+                "notify-keyspace-events",
+                flags_to_string(self.notify_keyspace_events),
+            ),
+            ("maxmemory", self.maxmemory.to_string()),
+        ]
+    }
+
+    /// Returns every parameter whose name matches the glob `pattern`.
+    ///
+    /// # Arguments
+    /// * `pattern` - A case-insensitive glob supporting `*` and `?`.
+    ///
+    /// # Returns
+    /// The matching `(name, value)` pairs, in declaration order.
+    pub fn get(&self, pattern: &str) -> Vec<(&'static str, String)> {
+        let pattern = pattern.to_lowercase();
+
+        self.parameters()
+            .into_iter()
+            .filter(|(name, _)| glob_match(&pattern, name))
+            .collect()
+    }
+
+    /// Updates a single parameter.
+    ///
+    /// # Returns
+    /// * `Ok(())` if the parameter exists and the value is valid.
+    /// * `Err(String)` with a Redis-style error message otherwise.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let invalid = || format!("ERR Invalid argument '{}' for CONFIG SET '{}'", value, name);
+
+        match name.to_lowercase().as_str() {
+            "save" => self.save = value.to_string(),
+            "appendonly" => match value {
+                "yes" | "no" => self.appendonly = value.to_string(),
+                _ => return Err(invalid()),
+            },
+            "slowlog-log-slower-than" => {
+                self.slowlog_log_slower_than = value.parse().map_err(|_| invalid())?
+            }
+            "slowlog-max-len" => self.slowlog_max_len = value.parse().map_err(|_| invalid())?,
+            "notify-keyspace-events" => {
+                self.notify_keyspace_events = parse_flags(value).map_err(|_| invalid())?
+            }
+            "maxmemory" => self.maxmemory = value.parse().map_err(|_| invalid())?,
+            _ => {
+                return Err(format!(
+                    "ERR Unknown option or number of arguments for CONFIG SET - '{}'",
+                    name
+                ))
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Matches `name` against a glob `pattern` where `*` matches any sequence and `?` any character.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    fn matches(pattern: &[char], name: &[char]) -> bool {
+        match pattern.split_first() {
+            None => name.is_empty(),
+            Some(('*', rest)) => (0..=name.len()).any(|skip| matches(rest, &name[skip..])),
+            Some(('?', rest)) => !name.is_empty() && matches(rest, &name[1..]),
+            Some((c, rest)) => name.first() == Some(c) && matches(rest, &name[1..]),
+        }
+    }
+
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    matches(&pattern, &name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_match_glob() {
+        assert!(glob_match("*", "save"));
+        assert!(glob_match("slowlog-*", "slowlog-max-len"));
+        assert!(glob_match("s?ve", "save"));
+        assert!(!glob_match("slowlog-*", "save"));
+        assert!(!glob_match("sav", "save"));
+    }
+
+    #[test]
+    fn should_get_single_parameter() {
+        let config = Config::default();
+
+        assert_eq!(
+            config.get("SAVE"),
+            vec![("save", "3600 1 300 100 60 10000".to_string())]
+        );
+    }
+
+    #[test]
+    fn should_set_slowlog_threshold() {
+        let mut config = Config::default();
+
+        config.set("slowlog-log-slower-than", "-1").unwrap();
+
+        assert_eq!(config.slowlog_log_slower_than, -1);
+        assert_eq!(
+            config.get("slowlog-log-*"),
+            vec![("slowlog-log-slower-than", "-1".to_string())]
+        );
+    }
+
+    #[test]
+    fn should_set_notify_keyspace_events() {
+        let mut config = Config::default();
+
+        config.set("notify-keyspace-events", "Ex$").unwrap();
+
+        assert_eq!(
+            config.get("notify-keyspace-events"),
+            vec![("notify-keyspace-events", "$xE".to_string())]
+        );
+        assert!(config.set("notify-keyspace-events", "Kw").is_err());
+    }
+
+    #[test]
+    fn should_fail_set_invalid_value() {
+        let mut config = Config::default();
+
+        assert!(config.set("slowlog-max-len", "many").is_err());
+        assert!(config.set("unknown", "1").is_err());
+        assert_eq!(config.slowlog_max_len, 128);
+    }
+}
@@ -1,12 +1,63 @@
 use super::types::RedisDeserializationTypes;
 
-/// Deserialize a flat command from the given string slice.
+/// Position of the first `\r\n` in `command`.
+fn find_crlf(command: &[u8]) -> Option<usize> {
+    command.windows(2).position(|window| window == b"\r\n")
+}
+
+/// Parses the length or integer written between the prefix and `\r\n`.
+fn parse_number<T: std::str::FromStr>(bytes: &[u8]) -> Option<T> {
+    std::str::from_utf8(bytes).ok()?.parse::<T>().ok()
+}
+
+/// Longest bulk string accepted, as the `proto-max-bulk-len` default of Redis.
+const MAX_BULK_LENGTH: usize = 512 * 1024 * 1024;
+
+/// Returns the length of the first frame of `command` once all of it was received, or `None`
+/// while bytes are missing. A frame with an invalid header ends at the first `\r\n`, parsing it
+/// then fails.
+///
+/// # Example
+///
+/// ```
+/// use redis::modules::deserialize::frame_length;
+///
+/// assert_eq!(frame_length(b"*1\r\n$4\r\nPING\r\n*1"), Some(14));
+/// assert_eq!(frame_length(b"*2\r\n$3\r\nGET\r\n$4\r\nNa"), None);
+/// ```
+pub fn frame_length(command: &[u8]) -> Option<usize> {
+    let pos = find_crlf(command)?;
+    let start = pos + 2;
+
+    match command[0] {
+        b'$' => match parse_number::<usize>(&command[1..pos]) {
+            Some(count) if count <= MAX_BULK_LENGTH => {
+                let end = start + count + 2;
+                (command.len() >= end).then_some(end)
+            }
+            _ => Some(start),
+        },
+        b'*' => match parse_number::<u32>(&command[1..pos]) {
+            Some(count) => {
+                let mut end = start;
+                for _ in 0..count {
+                    end += frame_length(&command[end..])?;
+                }
+                Some(end)
+            }
+            None => Some(start),
+        },
+        _ => Some(start),
+    }
+}
+
+/// Deserialize a flat command from the given byte slice.
 ///
 /// Flat commands are prefixed with `+`, `-`, or `:` and terminated by `\r\n`.
 ///
 /// # Arguments
 ///
-/// * `command` - A mutable reference to a string slice containing the command.
+/// * `command` - A mutable reference to a byte slice containing the command.
 ///
 /// # Returns
 ///
@@ -18,23 +69,27 @@ use super::types::RedisDeserializationTypes;
 /// use redis::modules::deserialize::deserialize_flat_command;
 /// use redis::modules::types::RedisDeserializationTypes;
 ///
-/// let mut command = "+OK\r\n";
+/// let mut command: &[u8] = b"+OK\r\n";
 /// let result = deserialize_flat_command(&mut command);
 /// assert_eq!(result, RedisDeserializationTypes::SimpleString("OK".to_string()));
-/// assert_eq!(command, "");
+/// assert_eq!(command, b"");
 /// ```
-pub fn deserialize_flat_command(command: &mut &str) -> RedisDeserializationTypes {
-    if let Some(pos) = command.find("\r\n") {
+pub fn deserialize_flat_command(command: &mut &[u8]) -> RedisDeserializationTypes {
+    if let Some(pos) = find_crlf(command) {
         let simple_str = &command[..pos];
-        let first_char = simple_str.chars().next().expect("Invalid Command");
+        let first_char = *simple_str.first().expect("Invalid Command");
 
         *command = &command[pos + 2..];
 
         match first_char {
-            '+' => RedisDeserializationTypes::SimpleString(simple_str[1..].to_string()),
-            '-' => RedisDeserializationTypes::ErrorMessage(simple_str[1..].to_string()),
-            ':' => RedisDeserializationTypes::Integer(
-                simple_str[1..].parse::<i64>().expect("To be a number"),
+            b'+' => RedisDeserializationTypes::SimpleString(
+                String::from_utf8_lossy(&simple_str[1..]).into_owned(),
+            ),
+            b'-' => RedisDeserializationTypes::ErrorMessage(
+                String::from_utf8_lossy(&simple_str[1..]).into_owned(),
+            ),
+            b':' => RedisDeserializationTypes::Integer(
+                parse_number::<i64>(&simple_str[1..]).expect("To be a number"),
             ),
             _ => panic!("Invalid Command"),
         }
@@ -43,17 +98,18 @@ pub fn deserialize_flat_command(command: &mut &str) -> RedisDeserializationTypes
     }
 }
 
-/// Deserialize a bulk string from the given string slice.
+/// Deserialize a bulk string from the given byte slice.
 ///
-/// Bulk strings are prefixed with `$` followed by the length of the string and terminated by `\r\n`.
+/// Bulk strings are prefixed with `$` followed by the length of the string in bytes and terminated by `\r\n`.
+/// They are binary-safe, the content is kept as is even when it is not valid UTF-8.
 ///
 /// # Arguments
 ///
-/// * `command` - A mutable reference to a string slice containing the command.
+/// * `command` - A mutable reference to a byte slice containing the command.
 ///
 /// # Returns
 ///
-/// An `Option<String>` containing the deserialized bulk string if successful, or `None` if the bulk string is null.
+/// An `Option<RedisDeserializationTypes>` containing the deserialized bulk string if successful, or `None` if the bulk string is null.
 ///
 /// # Example
 ///
@@ -61,23 +117,21 @@ pub fn deserialize_flat_command(command: &mut &str) -> RedisDeserializationTypes
 /// use redis::modules::deserialize::deserialize_bulk_string;
 /// use redis::modules::types::RedisDeserializationTypes;
 ///
-/// let mut command = "$6\r\nfoobar\r\n";
+/// let mut command: &[u8] = b"$6\r\nfoobar\r\n";
 /// let result = deserialize_bulk_string(&mut command);
-/// assert_eq!(result, Some(RedisDeserializationTypes::BulkString("foobar".to_string())));
-/// assert_eq!(command, "");
+/// assert_eq!(result, Some(RedisDeserializationTypes::BulkString("foobar".into())));
+/// assert_eq!(command, b"");
 /// ```
-pub fn deserialize_bulk_string(command: &mut &str) -> Option<RedisDeserializationTypes> {
-    if let Some(pos) = command.find("\r\n") {
+pub fn deserialize_bulk_string(command: &mut &[u8]) -> Option<RedisDeserializationTypes> {
+    if let Some(pos) = find_crlf(command) {
         let start = pos + 2;
 
-        if let Ok(count) = command[1..pos].parse::<usize>() {
-            let string: String = command[start..].chars().take(count).collect();
-            let len = string.len() + 2;
-
-            if command.len() >= (start + len) {
-                *command = &command[start + len..];
+        if let Some(count) = parse_number::<usize>(&command[1..pos]) {
+            if command.len() >= (start + count + 2) {
+                let bytes = command[start..start + count].to_vec();
+                *command = &command[start + count + 2..];
 
-                return Some(RedisDeserializationTypes::BulkString(string));
+                return Some(RedisDeserializationTypes::BulkString(bytes.into()));
             }
         } else {
             *command = &command[start..];
@@ -88,13 +142,13 @@ pub fn deserialize_bulk_string(command: &mut &str) -> Option<RedisDeserializatio
     panic!("Invalid Command")
 }
 
-/// Deserialize an array from the given string slice.
+/// Deserialize an array from the given byte slice.
 ///
 /// Arrays are prefixed with `*` followed by the number of elements in the array and terminated by `\r\n`.
 ///
 /// # Arguments
 ///
-/// * `command` - A mutable reference to a string slice containing the command.
+/// * `command` - A mutable reference to a byte slice containing the command.
 ///
 /// # Returns
 ///
@@ -106,7 +160,7 @@ pub fn deserialize_bulk_string(command: &mut &str) -> Option<RedisDeserializatio
 /// use redis::modules::deserialize::deserialize_array;
 /// use redis::modules::types::RedisDeserializationTypes;
 ///
-/// let mut command = "*2\r\n+OK\r\n:1000\r\n";
+/// let mut command: &[u8] = b"*2\r\n+OK\r\n:1000\r\n";
 /// let result = deserialize_array(&mut command);
 /// assert_eq!(
 ///     result,
@@ -115,12 +169,12 @@ pub fn deserialize_bulk_string(command: &mut &str) -> Option<RedisDeserializatio
 ///         RedisDeserializationTypes::Integer(1000)
 ///     ])
 /// );
-/// assert_eq!(command, "");
+/// assert_eq!(command, b"");
 /// ```
-pub fn deserialize_array(command: &mut &str) -> Option<Vec<RedisDeserializationTypes>> {
-    if let Some(pos) = command.find("\r\n") {
+pub fn deserialize_array(command: &mut &[u8]) -> Option<Vec<RedisDeserializationTypes>> {
+    if let Some(pos) = find_crlf(command) {
         let start = pos + 2;
-        if let Ok(count) = command[1..pos].parse::<u32>() {
+        if let Some(count) = parse_number::<u32>(&command[1..pos]) {
             let mut ret: Vec<RedisDeserializationTypes> = Vec::new();
 
             *command = &command[start..];
@@ -139,7 +193,7 @@ pub fn deserialize_array(command: &mut &str) -> Option<Vec<RedisDeserializationT
     None
 }
 
-/// Deserialize a command from the given string slice.
+/// Deserialize a command from the given byte slice.
 ///
 /// The command can be of various types, indicated by the first character:
 /// - `$`: Bulk string
@@ -148,7 +202,7 @@ pub fn deserialize_array(command: &mut &str) -> Option<Vec<RedisDeserializationT
 ///
 /// # Arguments
 ///
-/// * `command` - A mutable reference to a string slice containing the command.
+/// * `command` - A mutable reference to a byte slice containing the command.
 ///
 /// # Returns
 ///
@@ -160,25 +214,25 @@ pub fn deserialize_array(command: &mut &str) -> Option<Vec<RedisDeserializationT
 /// use redis::modules::deserialize::deserialize;
 /// use redis::modules::types::RedisDeserializationTypes;
 ///
-/// let mut command = "+OK\r\n";
+/// let mut command: &[u8] = b"+OK\r\n";
 /// let result = deserialize(&mut command);
 /// assert_eq!(result, Some(RedisDeserializationTypes::SimpleString("OK".to_string())));
-/// assert_eq!(command, "");
+/// assert_eq!(command, b"");
 /// ```
-pub fn deserialize(command: &mut &str) -> Option<RedisDeserializationTypes> {
-    if let Some(first_char) = command.chars().next() {
+pub fn deserialize(command: &mut &[u8]) -> Option<RedisDeserializationTypes> {
+    if let Some(first_char) = command.first() {
         match first_char {
-            '$' => {
+            b'$' => {
                 if let Some(result) = deserialize_bulk_string(command) {
                     return Some(result);
                 }
             }
-            '*' => {
+            b'*' => {
                 if let Some(result) = deserialize_array(command) {
                     return Some(RedisDeserializationTypes::Array(Box::new(result)));
                 }
             }
-            '+' | ':' | '-' => return Some(deserialize_flat_command(command)),
+            b'+' | b':' | b'-' => return Some(deserialize_flat_command(command)),
             _ => panic!("Invalid command"),
         };
     }
@@ -191,224 +245,249 @@ mod tests {
 
     #[test]
     fn it_should_de_serialize_simple_string() {
-        let mut command = "+OK\r\n";
+        let mut command: &[u8] = b"+OK\r\n";
 
         let result = deserialize_flat_command(&mut command);
         assert_eq!(
             result,
             RedisDeserializationTypes::SimpleString("OK".to_string())
         );
-        assert_eq!(command, "");
+        assert_eq!(command, b"");
     }
 
     #[test]
     fn it_should_de_serialize_error_message() {
-        let mut command = "-Error message\r\n";
+        let mut command: &[u8] = b"-Error message\r\n";
 
         let result = deserialize_flat_command(&mut command);
         assert_eq!(
             result,
             RedisDeserializationTypes::ErrorMessage("Error message".to_string())
         );
-        assert_eq!(command, "");
+        assert_eq!(command, b"");
     }
 
     #[test]
     fn it_should_de_serialize_number_positive() {
-        let mut command = ":+1000\r\n";
+        let mut command: &[u8] = b":+1000\r\n";
 
         let result = deserialize_flat_command(&mut command);
         assert_eq!(result, RedisDeserializationTypes::Integer(1000));
-        assert_eq!(command, "");
+        assert_eq!(command, b"");
     }
 
     #[test]
     fn it_should_de_serialize_number_negative() {
-        let mut command = ":-1000\r\n";
+        let mut command: &[u8] = b":-1000\r\n";
 
         let result = deserialize_flat_command(&mut command);
         assert_eq!(result, RedisDeserializationTypes::Integer(-1000));
-        assert_eq!(command, "");
+        assert_eq!(command, b"");
     }
 
     #[test]
     fn it_should_de_serialize_number_0() {
-        let mut command = ":0\r\n";
+        let mut command: &[u8] = b":0\r\n";
 
         let result = deserialize_flat_command(&mut command);
         assert_eq!(result, RedisDeserializationTypes::Integer(0));
-        assert_eq!(command, "");
+        assert_eq!(command, b"");
     }
 
     #[test]
     #[should_panic(expected = "Invalid Command")]
     fn it_should_de_serialize_number_empty() {
-        let mut command = "";
+        let mut command: &[u8] = b"";
 
         let result = deserialize_flat_command(&mut command);
         assert_eq!(result, RedisDeserializationTypes::Integer(0));
-        assert_eq!(command, "");
+        assert_eq!(command, b"");
     }
 
     #[test]
     fn it_should_de_serialize_return_remaining_command() {
-        let mut command = ":0\r\n$4\r\necho\r\n";
+        let mut command: &[u8] = b":0\r\n$4\r\necho\r\n";
 
         let result = deserialize_flat_command(&mut command);
         assert_eq!(result, RedisDeserializationTypes::Integer(0));
-        assert_eq!(command, "$4\r\necho\r\n");
+        assert_eq!(command, b"$4\r\necho\r\n");
     }
 
     #[test]
     fn it_should_de_serialize_return_remaining_command_2() {
-        let mut command = "+hello\r\n$4\r\necho\r\n+echo\r\n-Error Message\r\n";
+        let mut command: &[u8] = b"+hello\r\n$4\r\necho\r\n+echo\r\n-Error Message\r\n";
 
         let result = deserialize_flat_command(&mut command);
         assert_eq!(
             result,
             RedisDeserializationTypes::SimpleString("hello".to_string())
         );
-        assert_eq!(command, "$4\r\necho\r\n+echo\r\n-Error Message\r\n");
+        assert_eq!(command, b"$4\r\necho\r\n+echo\r\n-Error Message\r\n");
     }
 
     #[test]
     fn it_should_deserialize_bulk_string() {
-        let mut command = "$4\r\nping\r\n";
+        let mut command: &[u8] = b"$4\r\nping\r\n";
 
         let result = deserialize_bulk_string(&mut command);
         assert_eq!(
             result,
-            Some(RedisDeserializationTypes::BulkString("ping".to_string()))
+            Some(RedisDeserializationTypes::BulkString("ping".into()))
         );
-        assert_eq!(command, "");
+        assert_eq!(command, b"");
     }
 
     #[test]
     fn it_should_deserialize_bulk_string_remaining_command() {
-        let mut command = "$4\r\nping\r\n:123\r\n";
+        let mut command: &[u8] = b"$4\r\nping\r\n:123\r\n";
 
         let result = deserialize_bulk_string(&mut command);
         assert_eq!(
             result,
-            Some(RedisDeserializationTypes::BulkString("ping".to_string()))
+            Some(RedisDeserializationTypes::BulkString("ping".into()))
         );
-        assert_eq!(command, ":123\r\n");
+        assert_eq!(command, b":123\r\n");
     }
 
     #[test]
     fn it_should_deserialize_bulk_string_remaining_command_2_with_special_chars() {
-        let mut command = "$18\r\nping \r\nhello world\r\n$7\r\n1234567\r\n:4\r\n";
+        let mut command: &[u8] = b"$18\r\nping \r\nhello world\r\n$7\r\n1234567\r\n:4\r\n";
 
         let result = deserialize_bulk_string(&mut command);
         assert_eq!(
             result,
-            Some(RedisDeserializationTypes::BulkString(
-                "ping \r\nhello world".to_string()
-            ))
+            Some(RedisDeserializationTypes::BulkString("ping \r\nhello world".into()))
         );
-        assert_eq!(command, "$7\r\n1234567\r\n:4\r\n");
+        assert_eq!(command, b"$7\r\n1234567\r\n:4\r\n");
+    }
+
+    #[test]
+    fn it_should_measure_complete_frames() {
+        assert_eq!(frame_length(b""), None);
+        assert_eq!(frame_length(b"+OK\r"), None);
+        assert_eq!(frame_length(b"+OK\r\n+PONG\r\n"), Some(5));
+        assert_eq!(frame_length(b"$4\r\npi"), None);
+        assert_eq!(frame_length(b"$4\r\n\r\n\r\n\r\n"), Some(10));
+        assert_eq!(frame_length(b"$-1\r\n"), Some(5));
+        assert_eq!(frame_length(b"*2\r\n$3\r\nGET\r\n"), None);
+        assert_eq!(frame_length(b"*0\r\n"), Some(4));
+
+        let command = b"*2\r\n*1\r\n:1\r\n$3\r\nfoo\r\n";
+        assert_eq!(frame_length(command), Some(command.len()));
     }
 
     #[test]
     fn it_should_deserialize_bulk_string_null_elements() {
-        let mut command = "$-1\r\n";
+        let mut command: &[u8] = b"$-1\r\n";
         let result = deserialize_bulk_string(&mut command);
         assert_eq!(result, None);
-        assert_eq!(command, "");
+        assert_eq!(command, b"");
+    }
+
+    #[test]
+    fn it_should_deserialize_bulk_string_counting_bytes() {
+        let mut command: &[u8] = b"$4\r\n\xc3\xa9\xff\n\r\n:1\r\n";
+        let result = deserialize_bulk_string(&mut command);
+        assert_eq!(
+            result,
+            Some(RedisDeserializationTypes::BulkString(
+                vec![0xc3, 0xa9, 0xff, b'\n'].into()
+            ))
+        );
+        assert_eq!(command, b":1\r\n");
     }
 
     #[test]
     fn it_should_deserialize_bulk_string_empty() {
-        let mut command = "$0\r\n\r\n";
+        let mut command: &[u8] = b"$0\r\n\r\n";
         let result = deserialize_bulk_string(&mut command);
         assert_eq!(
             result,
-            Some(RedisDeserializationTypes::BulkString("".to_string()))
+            Some(RedisDeserializationTypes::BulkString("".into()))
         );
-        assert_eq!(command, "");
+        assert_eq!(command, b"");
     }
 
     #[test]
     #[should_panic(expected = "Invalid Command")]
     fn it_should_deserialize_bulk_string_incomplete() {
-        let mut command = "$4\r\npin";
+        let mut command: &[u8] = b"$4\r\npin";
         let result = deserialize_bulk_string(&mut command);
         assert_eq!(result, None);
-        assert_eq!(command, "");
+        assert_eq!(command, b"");
     }
 
     #[test]
     fn it_should_deserialize_array_nested_arr() {
-        let mut command =
-            "*5\r\n+echo\r\n:11\r\n$4\r\n1234\r\n*2\r\n$4\r\n1234\r\n:11\r\n$4\r\nlast\r\n";
+        let mut command: &[u8] = b"*5\r\n+echo\r\n:11\r\n$4\r\n1234\r\n*2\r\n$4\r\n1234\r\n:11\r\n$4\r\nlast\r\n";
         let result = deserialize_array(&mut command);
         assert_eq!(
             result,
             Some(Vec::from([
                 RedisDeserializationTypes::SimpleString("echo".to_string()),
                 RedisDeserializationTypes::Integer(11),
-                RedisDeserializationTypes::BulkString("1234".to_string()),
+                RedisDeserializationTypes::BulkString("1234".into()),
                 RedisDeserializationTypes::Array(Box::new(vec![
-                    RedisDeserializationTypes::BulkString("1234".to_string()),
+                    RedisDeserializationTypes::BulkString("1234".into()),
                     RedisDeserializationTypes::Integer(11)
                 ])),
-                RedisDeserializationTypes::BulkString("last".to_string())
+                RedisDeserializationTypes::BulkString("last".into())
             ]))
         );
-        assert_eq!(command, "");
+        assert_eq!(command, b"");
     }
 
     #[test]
     fn it_should_deserialize_array() {
-        let mut command = "*4\r\n+echo\r\n:11\r\n$4\r\n1234\r\n";
+        let mut command: &[u8] = b"*4\r\n+echo\r\n:11\r\n$4\r\n1234\r\n";
         let result = deserialize_array(&mut command);
         assert_eq!(
             result,
             Some(vec![
                 RedisDeserializationTypes::SimpleString("echo".to_string()),
                 RedisDeserializationTypes::Integer(11),
-                RedisDeserializationTypes::BulkString("1234".to_string()),
+                RedisDeserializationTypes::BulkString("1234".into()),
             ])
         );
-        assert_eq!(command, "");
+        assert_eq!(command, b"");
     }
 
     #[test]
     fn it_should_deserialize_array_0_elements() {
-        let mut command = "*0\r\n";
+        let mut command: &[u8] = b"*0\r\n";
         let result = deserialize_array(&mut command);
         assert_eq!(result, Some(Vec::new()));
-        assert_eq!(command, "");
+        assert_eq!(command, b"");
     }
 
     #[test]
     fn it_should_deserialize_array_null() {
-        let mut command = "*-1\r\n";
+        let mut command: &[u8] = b"*-1\r\n";
         let result = deserialize_array(&mut command);
         assert_eq!(result, None);
-        assert_eq!(command, "");
+        assert_eq!(command, b"");
     }
 
     #[test]
     #[should_panic(expected = "Invalid Command")]
     fn it_should_deserialize_array_incomplete() {
-        let mut command = "*2\r\n+echo\r\n:11";
+        let mut command: &[u8] = b"*2\r\n+echo\r\n:11";
         let result = deserialize_array(&mut command);
         assert_eq!(result, None);
-        assert_eq!(command, "");
+        assert_eq!(command, b"");
     }
 
     #[test]
     fn it_should_deserialize_array_with_empty_bulk_string() {
-        let mut command = "*2\r\n$0\r\n\r\n+OK\r\n";
+        let mut command: &[u8] = b"*2\r\n$0\r\n\r\n+OK\r\n";
         let result = deserialize_array(&mut command);
         assert_eq!(
             result,
             Some(vec![
-                RedisDeserializationTypes::BulkString("".to_string()),
+                RedisDeserializationTypes::BulkString("".into()),
                 RedisDeserializationTypes::SimpleString("OK".to_string())
             ])
         );
-        assert_eq!(command, "");
+        assert_eq!(command, b"");
     }
 }
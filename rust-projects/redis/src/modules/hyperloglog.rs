@@ -0,0 +1,322 @@
+//! HyperLogLog cardinality estimation, following the dense representation used by Redis.
+//!
+//! Like Redis, it uses 16384 registers of 6 bits, which gives a standard error of 0.81%, the
+//! MurmurHash64A hash and the estimator described by Otmar Ertl in "New cardinality estimation
+//! algorithms for HyperLogLog sketches".
+
+/// Number of bits of the hash used to select a register.
+const HLL_P: u32 = 14;
+/// Number of bits of the hash used to count leading zeros.
+const HLL_Q: u32 = 64 - HLL_P;
+const HLL_REGISTERS: usize = 1 << HLL_P;
+const HLL_BITS: usize = 6;
+const HLL_REGISTER_MAX: u8 = (1 << HLL_BITS) - 1;
+/// `magic (4) + encoding (1) + unused (3) + cached cardinality (8)`.
+const HLL_HEADER_SIZE: usize = 16;
+const HLL_DENSE_SIZE: usize = HLL_HEADER_SIZE + (HLL_REGISTERS * HLL_BITS).div_ceil(8);
+const HLL_MAGIC: &[u8; 4] = b"HYLL";
+const HLL_DENSE: u8 = 0;
+const HLL_ALPHA_INF: f64 = 0.721_347_520_444_481_7;
+const HLL_HASH_SEED: u64 = 0xadc8_3b19;
+
+pub const INVALID_HLL: &str = "WRONGTYPE Key is not a valid HyperLogLog string value.";
+
+/// MurmurHash64A by Austin Appleby, as used by Redis to hash HyperLogLog elements.
+fn murmur_hash64a(data: &[u8], seed: u64) -> u64 {
+    const M: u64 = 0xc6a4_a793_5bd1_e995;
+    const R: u32 = 47;
+
+    let mut hash = seed ^ (data.len() as u64).wrapping_mul(M);
+    let mut chunks = data.chunks_exact(8);
+
+    for chunk in chunks.by_ref() {
+        let mut k = u64::from_le_bytes(chunk.try_into().unwrap());
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+
+        hash ^= k;
+        hash = hash.wrapping_mul(M);
+    }
+
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        for (i, byte) in tail.iter().enumerate() {
+            hash ^= (*byte as u64) << (8 * i);
+        }
+        hash = hash.wrapping_mul(M);
+    }
+
+    hash ^= hash >> R;
+    hash = hash.wrapping_mul(M);
+    hash ^= hash >> R;
+
+    hash
+}
+
+/// Returns the register selected by `element` and the length of its run of zeros plus one.
+fn pattern(element: &[u8]) -> (usize, u8) {
+    let hash = murmur_hash64a(element, HLL_HASH_SEED);
+    let index = (hash & (HLL_REGISTERS as u64 - 1)) as usize;
+    // Setting the bit past the last counted one bounds the count to `HLL_Q + 1`.
+    let hash = (hash >> HLL_P) | (1 << HLL_Q);
+
+    (index, hash.trailing_zeros() as u8 + 1)
+}
+
+fn sigma(mut x: f64) -> f64 {
+    if x == 1.0 {
+        return f64::INFINITY;
+    }
+
+    let mut y = 1.0;
+    let mut z = x;
+    loop {
+        x *= x;
+        let previous = z;
+        z += x * y;
+        y += y;
+
+        if previous == z {
+            return z;
+        }
+    }
+}
+
+fn tau(mut x: f64) -> f64 {
+    if x == 0.0 || x == 1.0 {
+        return 0.0;
+    }
+
+    let mut y = 1.0;
+    let mut z = 1.0 - x;
+    loop {
+        x = x.sqrt();
+        let previous = z;
+        y *= 0.5;
+        z -= (1.0 - x).powi(2) * y;
+
+        if previous == z {
+            return z / 3.0;
+        }
+    }
+}
+
+/// A HyperLogLog sketch in the dense Redis layout, stored as a plain string value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HyperLogLog {
+    bytes: Vec<u8>,
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HyperLogLog {
+    pub fn new() -> Self {
+        let mut bytes = vec![0; HLL_DENSE_SIZE];
+        bytes[..4].copy_from_slice(HLL_MAGIC);
+        bytes[4] = HLL_DENSE;
+
+        HyperLogLog { bytes }
+    }
+
+    /// Reads a sketch from a string value.
+    ///
+    /// # Returns
+    /// * `Ok(HyperLogLog)` if the value is a dense HyperLogLog.
+    /// * `Err(String)` with the Redis `WRONGTYPE` error otherwise.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, String> {
+        if bytes.len() != HLL_DENSE_SIZE || &bytes[..4] != HLL_MAGIC || bytes[4] != HLL_DENSE {
+            return Err(INVALID_HLL.to_string());
+        }
+
+        Ok(HyperLogLog { bytes })
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    fn register(&self, index: usize) -> u8 {
+        let registers = &self.bytes[HLL_HEADER_SIZE..];
+        let byte = index * HLL_BITS / 8;
+        let shift = index * HLL_BITS % 8;
+
+        let low = registers[byte] as u16;
+        let high = registers.get(byte + 1).copied().unwrap_or(0) as u16;
+
+        (((low | (high << 8)) >> shift) as u8) & HLL_REGISTER_MAX
+    }
+
+    fn set_register(&mut self, index: usize, value: u8) {
+        let registers = &mut self.bytes[HLL_HEADER_SIZE..];
+        let byte = index * HLL_BITS / 8;
+        let shift = index * HLL_BITS % 8;
+        let mask = (HLL_REGISTER_MAX as u16) << shift;
+        let value = (value as u16) << shift;
+
+        registers[byte] = (registers[byte] & !(mask as u8)) | value as u8;
+        if let Some(next) = registers.get_mut(byte + 1) {
+            *next = (*next & !((mask >> 8) as u8)) | (value >> 8) as u8;
+        }
+    }
+
+    /// Flags the cached cardinality in the header as stale, as Redis does on every change.
+    fn invalidate_cache(&mut self) {
+        self.bytes[HLL_HEADER_SIZE - 1] |= 1 << 7;
+    }
+
+    /// Adds an element to the sketch.
+    ///
+    /// # Returns
+    /// `true` if a register changed, which means the estimated cardinality may have changed.
+    pub fn add(&mut self, element: &[u8]) -> bool {
+        let (index, count) = pattern(element);
+
+        if self.register(index) >= count {
+            return false;
+        }
+
+        self.set_register(index, count);
+        self.invalidate_cache();
+
+        true
+    }
+
+    /// Merges another sketch into this one, keeping the maximum of each register.
+    ///
+    /// # Returns
+    /// `true` if a register changed.
+    pub fn merge(&mut self, other: &HyperLogLog) -> bool {
+        let mut changed = false;
+
+        for index in 0..HLL_REGISTERS {
+            let value = other.register(index);
+            if value > self.register(index) {
+                self.set_register(index, value);
+                changed = true;
+            }
+        }
+
+        if changed {
+            self.invalidate_cache();
+        }
+
+        changed
+    }
+
+    /// Estimates the number of distinct elements added to the sketch.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use redis::modules::hyperloglog::HyperLogLog;
+    ///
+    /// let mut hll = HyperLogLog::new();
+    /// for element in ["a", "b", "c", "a"] {
+    ///     hll.add(element.as_bytes());
+    /// }
+    /// assert_eq!(hll.count(), 3);
+    /// ```
+    pub fn count(&self) -> u64 {
+        let mut histogram = [0u32; HLL_Q as usize + 2];
+        for index in 0..HLL_REGISTERS {
+            histogram[self.register(index) as usize] += 1;
+        }
+
+        let m = HLL_REGISTERS as f64;
+        let mut z = m * tau((m - histogram[HLL_Q as usize + 1] as f64) / m);
+        for count in histogram[1..=HLL_Q as usize].iter().rev() {
+            z += *count as f64;
+            z *= 0.5;
+        }
+        z += m * sigma(histogram[0] as f64 / m);
+
+        (HLL_ALPHA_INF * m * m / z).round() as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_store_registers_across_byte_boundaries() {
+        let mut hll = HyperLogLog::new();
+
+        for index in [0, 1, 2, 3, HLL_REGISTERS - 1] {
+            hll.set_register(index, HLL_REGISTER_MAX - index as u8 % 8);
+        }
+
+        for index in [0, 1, 2, 3, HLL_REGISTERS - 1] {
+            assert_eq!(hll.register(index), HLL_REGISTER_MAX - index as u8 % 8);
+        }
+        assert_eq!(hll.register(4), 0);
+    }
+
+    #[test]
+    fn should_estimate_within_error_bounds() {
+        let mut hll = HyperLogLog::new();
+        let mut added = 0;
+
+        for expected in [10, 100, 1_000, 10_000, 100_000, 1_000_000] {
+            while added < expected {
+                hll.add(format!("element:{}", added).as_bytes());
+                added += 1;
+            }
+
+            let estimate = hll.count() as f64;
+            let error = (estimate - expected as f64).abs() / expected as f64;
+
+            // Five standard errors, estimates this far off are practically impossible.
+            assert!(
+                error < 5.0 * 0.0081,
+                "estimated {} for {} elements",
+                estimate,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn should_count_small_sets_exactly() {
+        let mut hll = HyperLogLog::new();
+
+        assert_eq!(hll.count(), 0);
+        assert!(hll.add(b"foo"));
+        assert!(!hll.add(b"foo"));
+        hll.add(b"bar");
+        hll.add(b"zap");
+
+        assert_eq!(hll.count(), 3);
+    }
+
+    #[test]
+    fn should_merge_sketches() {
+        let mut left = HyperLogLog::new();
+        let mut right = HyperLogLog::new();
+
+        for element in 0..5_000 {
+            left.add(format!("{}", element).as_bytes());
+            right.add(format!("{}", element + 2_500).as_bytes());
+        }
+
+        assert!(left.merge(&right));
+        assert!(!left.merge(&right));
+
+        let estimate = left.count() as f64;
+        assert!((estimate - 7_500.0).abs() / 7_500.0 < 5.0 * 0.0081);
+    }
+
+    #[test]
+    fn should_reject_other_strings() {
+        assert_eq!(
+            HyperLogLog::from_bytes(b"foobar".to_vec()),
+            Err(INVALID_HLL.to_string())
+        );
+        assert!(HyperLogLog::from_bytes(HyperLogLog::new().into_bytes()).is_ok());
+    }
+}
@@ -0,0 +1,596 @@
+use std::sync::atomic::Ordering;
+
+use super::{
+    clients::KillFilter,
+    command_table::{lookup_command, CommandSpec, COMMAND_TABLE},
+    commands::SYNTAX_ERROR,
+    serialize::{
+        serialize_array, serialize_bulk_string, serialize_error, serialize_integer, serialize_null,
+        serialize_simple_string,
+    },
+    server::ServerState,
+};
+
+const INFO_SECTIONS: [&str; 7] = [
+    "server",
+    "clients",
+    "memory",
+    "persistence",
+    "stats",
+    "cluster",
+    "keyspace",
+];
+
+fn subcommand_error(command: &str, subcommand: &str) -> String {
+    serialize_error(&format!(
+        "ERR unknown subcommand or wrong number of arguments for '{}|{}' command",
+        command,
+        subcommand.to_lowercase()
+    ))
+}
+
+fn info_section(section: &str, state: &ServerState) -> Option<String> {
+    let fields: Vec<(&str, String)> = match section {
+        "server" => {
+            let uptime = state.started_at.elapsed().as_secs();
+            vec![
+                ("redis_version", env!("CARGO_PKG_VERSION").to_string()),
+                (
+                    "redis_mode",
+                    if state.cluster.is_some() {
+                        "cluster"
+                    } else {
+                        "standalone"
+                    }
+                    .to_string(),
+                ),
+                ("process_id", std::process::id().to_string()),
+                ("tcp_port", state.port.to_string()),
+                ("uptime_in_seconds", uptime.to_string()),
+                ("uptime_in_days", (uptime / 86_400).to_string()),
+            ]
+        }
+        "clients" => vec![
+            ("connected_clients", state.clients.len().to_string()),
+            ("monitor_clients", state.clients.monitor_count().to_string()),
+            ("blocked_clients", "0".to_string()),
+        ],
+        "memory" => vec![(
+            "used_memory",
+            state.redis.lock().unwrap().used_memory().to_string(),
+        )],
+        "persistence" => {
+            let redis = state.redis.lock().unwrap();
+            vec![
+                ("loading", "0".to_string()),
+                ("rdb_changes_since_last_save", redis.dirty().to_string()),
+                (
+                    "rdb_last_save_time",
+                    redis.last_save().timestamp().to_string(),
+                ),
+                ("aof_enabled", "0".to_string()),
+            ]
+        }
+        "stats" => vec![
+            (
+                "total_connections_received",
+                state
+                    .stats
+                    .total_connections_received
+                    .load(Ordering::SeqCst)
+                    .to_string(),
+            ),
+            (
+                "total_commands_processed",
+                state
+                    .stats
+                    .total_commands_processed
+                    .load(Ordering::SeqCst)
+                    .to_string(),
+            ),
+        ],
+        "cluster" => vec![(
+            "cluster_enabled",
+            (state.cluster.is_some() as u8).to_string(),
+        )],
+        "keyspace" => {
+            let redis = state.redis.lock().unwrap();
+            if redis.is_empty() {
+                vec![]
+            } else {
+                vec![(
+                    "db0",
+                    format!("keys={},expires={},avg_ttl=0", redis.len(), redis.expires()),
+                )]
+            }
+        }
+        _ => return None,
+    };
+
+    let mut title = section.to_string();
+    title[..1].make_ascii_uppercase();
+
+    Some(
+        fields
+            .iter()
+            .fold(format!("# {}\r\n", title), |acc, (key, value)| {
+                acc + &format!("{}:{}\r\n", key, value)
+            }),
+    )
+}
+
+/// Builds the `INFO` reply for the requested sections, every section if none is given.
+pub fn info(args: &[String], _: u64, state: &ServerState) -> String {
+    let requested: Vec<String> = args[1..].iter().map(|arg| arg.to_lowercase()).collect();
+    let all = requested.is_empty()
+        || requested
+            .iter()
+            .any(|section| section == "all" || section == "default" || section == "everything");
+
+    let sections: Vec<String> = INFO_SECTIONS
+        .iter()
+        .filter(|section| all || requested.iter().any(|r| r == *section))
+        .filter_map(|section| info_section(section, state))
+        .collect();
+
+    serialize_bulk_string(&sections.join("\r\n"))
+}
+
+pub fn client(args: &[String], client_id: u64, state: &ServerState) -> String {
+    let args: Vec<&str> = args[1..].iter().map(String::as_str).collect();
+
+    match args.as_slice() {
+        [subcommand, rest @ ..] => match (subcommand.to_uppercase().as_str(), rest) {
+            ("LIST", []) => serialize_bulk_string(&state.clients.list()),
+            ("ID", []) => serialize_integer(client_id as i64),
+            ("GETNAME", []) => match state.clients.name(client_id) {
+                Some(name) => serialize_bulk_string(&name),
+                None => serialize_null(),
+            },
+            ("SETNAME", [name]) => {
+                if name.chars().any(|c| c == ' ' || c == '\n') {
+                    return serialize_error(
+                        "ERR Client names cannot contain spaces, newlines or special characters.",
+                    );
+                }
+
+                let name = if name.is_empty() {
+                    None
+                } else {
+                    Some(name.to_string())
+                };
+                state.clients.set_name(client_id, name);
+                serialize_simple_string("OK")
+            }
+            // Old form: `CLIENT KILL addr:port`
+            ("KILL", [addr]) => {
+                let filter = KillFilter {
+                    addr: Some(addr.to_string()),
+                    ..Default::default()
+                };
+
+                match state.clients.kill(&filter) {
+                    0 => serialize_error("ERR No such client"),
+                    _ => serialize_simple_string("OK"),
+                }
+            }
+            // New form: `CLIENT KILL <filter> <value> ...`
+            ("KILL", filters) if !filters.is_empty() && filters.len() % 2 == 0 => {
+                let mut filter = KillFilter {
+                    skip: Some(client_id),
+                    ..Default::default()
+                };
+
+                for pair in filters.chunks(2) {
+                    match (pair[0].to_uppercase().as_str(), pair[1]) {
+                        ("ID", id) => match id.parse() {
+                            Ok(id) => filter.id = Some(id),
+                            Err(_) => {
+                                return serialize_error("ERR client-id should be greater than 0")
+                            }
+                        },
+                        ("ADDR", addr) => filter.addr = Some(addr.to_string()),
+                        ("SKIPME", "yes") => filter.skip = Some(client_id),
+                        ("SKIPME", "no") => filter.skip = None,
+                        _ => return SYNTAX_ERROR.to_string(),
+                    }
+                }
+
+                serialize_integer(state.clients.kill(&filter) as i64)
+            }
+            ("KILL", _) => SYNTAX_ERROR.to_string(),
+            _ => subcommand_error("client", subcommand),
+        },
+        [] => unreachable!("arity is checked by the command table"),
+    }
+}
+
+pub fn slowlog(args: &[String], _: u64, state: &ServerState) -> String {
+    let args: Vec<&str> = args[1..].iter().map(String::as_str).collect();
+
+    match args.as_slice() {
+        [subcommand, rest @ ..] => match (subcommand.to_uppercase().as_str(), rest) {
+            ("GET", count) => {
+                let count = match count {
+                    [] => 10,
+                    [count] => match count.parse::<i64>() {
+                        Ok(-1) => usize::MAX,
+                        Ok(count) if count >= 0 => count as usize,
+                        _ => {
+                            return serialize_error(
+                                "ERR count should be greater than or equal to -1",
+                            )
+                        }
+                    },
+                    _ => return subcommand_error("slowlog", subcommand),
+                };
+
+                let slowlog = state.slowlog.lock().unwrap();
+                let entries: Vec<String> = slowlog
+                    .get(count)
+                    .iter()
+                    .map(|entry| {
+                        let args: Vec<String> = entry
+                            .args
+                            .iter()
+                            .map(|arg| serialize_bulk_string(arg))
+                            .collect();
+
+                        serialize_array(&[
+                            serialize_integer(entry.id as i64),
+                            serialize_integer(entry.timestamp),
+                            serialize_integer(entry.duration_micros as i64),
+                            serialize_array(&args),
+                            serialize_bulk_string(&entry.client_addr),
+                            serialize_bulk_string(&entry.client_name),
+                        ])
+                    })
+                    .collect();
+
+                serialize_array(&entries)
+            }
+            ("LEN", []) => serialize_integer(state.slowlog.lock().unwrap().len() as i64),
+            ("RESET", []) => {
+                state.slowlog.lock().unwrap().reset();
+                serialize_simple_string("OK")
+            }
+            _ => subcommand_error("slowlog", subcommand),
+        },
+        [] => unreachable!("arity is checked by the command table"),
+    }
+}
+
+fn command_info(spec: &CommandSpec) -> String {
+    let flags: Vec<String> = spec
+        .flags
+        .iter()
+        .map(|flag| serialize_bulk_string(flag.as_str()))
+        .collect();
+
+    serialize_array(&[
+        serialize_bulk_string(spec.name),
+        serialize_integer(spec.arity),
+        serialize_array(&flags),
+        serialize_integer(spec.first_key),
+        serialize_integer(spec.last_key),
+        serialize_integer(spec.step),
+    ])
+}
+
+pub fn monitor(_: &[String], client_id: u64, state: &ServerState) -> String {
+    state.clients.set_monitor(client_id);
+    serialize_simple_string("OK")
+}
+
+pub fn describe_commands(args: &[String], _: u64, _: &ServerState) -> String {
+    let args: Vec<&str> = args[1..].iter().map(String::as_str).collect();
+
+    match args.as_slice() {
+        [] => serialize_array(&COMMAND_TABLE.iter().map(command_info).collect::<Vec<_>>()),
+        [subcommand, rest @ ..] => match (subcommand.to_uppercase().as_str(), rest) {
+            ("COUNT", []) => serialize_integer(COMMAND_TABLE.len() as i64),
+            ("INFO", []) => {
+                serialize_array(&COMMAND_TABLE.iter().map(command_info).collect::<Vec<_>>())
+            }
+            ("INFO", names) => serialize_array(
+                &names
+                    .iter()
+                    .map(|name| lookup_command(name).map_or_else(serialize_null, command_info))
+                    .collect::<Vec<_>>(),
+            ),
+            _ => subcommand_error("command", subcommand),
+        },
+    }
+}
+
+pub fn config(args: &[String], _: u64, state: &ServerState) -> String {
+    let args: Vec<&str> = args[1..].iter().map(String::as_str).collect();
+
+    match args.as_slice() {
+        [subcommand, rest @ ..] => match (subcommand.to_uppercase().as_str(), rest) {
+            ("GET", [pattern]) => {
+                let pairs: Vec<String> = state
+                    .config
+                    .lock()
+                    .unwrap()
+                    .get(pattern)
+                    .iter()
+                    .flat_map(|(name, value)| {
+                        [serialize_bulk_string(name), serialize_bulk_string(value)]
+                    })
+                    .collect();
+
+                serialize_array(&pairs)
+            }
+            ("SET", [name, value]) => {
+                let result = state.config.lock().unwrap().set(name, value);
+
+                match result {
+                    Ok(_) => {
+                        state.apply_config();
+                        serialize_simple_string("OK")
+                    }
+                    Err(err) => serialize_error(&err),
+                }
+            }
+            _ => subcommand_error("config", subcommand),
+        },
+        [] => unreachable!("arity is checked by the command table"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::modules::{server::process_command, store::Redis, types::RedisDeserializationTypes};
+
+    use super::*;
+
+    fn build_command(args: &[&str]) -> RedisDeserializationTypes {
+        RedisDeserializationTypes::Array(Box::new(
+            args.iter()
+                .map(|arg| RedisDeserializationTypes::BulkString((*arg).into()))
+                .collect(),
+        ))
+    }
+
+    fn setup() -> (ServerState, u64) {
+        let state = ServerState::new(Redis::new(), 6379);
+        let client_id = state.clients.register("127.0.0.1:5000".to_string(), None);
+
+        (state, client_id)
+    }
+
+    #[test]
+    fn it_should_return_all_info_sections() {
+        let (state, client_id) = setup();
+
+        let response = process_command(&build_command(&["INFO"]), client_id, &state);
+
+        for section in [
+            "# Server",
+            "# Clients",
+            "# Memory",
+            "# Persistence",
+            "# Stats",
+            "# Keyspace",
+        ] {
+            assert!(response.to_string().contains(section), "missing {}", section);
+        }
+        assert!(response.to_string().contains("connected_clients:1\r\n"));
+    }
+
+    #[test]
+    fn it_should_return_requested_info_section() {
+        let (state, client_id) = setup();
+
+        process_command(
+            &build_command(&["SET", "Name", "Felipe"]),
+            client_id,
+            &state,
+        );
+        process_command(
+            &build_command(&["SET", "Age", "23", "EX", "100"]),
+            client_id,
+            &state,
+        );
+
+        let response = process_command(&build_command(&["INFO", "keyspace"]), client_id, &state);
+        let body = "# Keyspace\r\ndb0:keys=2,expires=1,avg_ttl=0\r\n";
+
+        assert_eq!(response, format!("${}\r\n{}\r\n", body.len(), body));
+    }
+
+    #[test]
+    fn it_should_count_commands_and_changes() {
+        let (state, client_id) = setup();
+
+        process_command(
+            &build_command(&["SET", "Name", "Felipe"]),
+            client_id,
+            &state,
+        );
+        process_command(&build_command(&["DEL", "Name"]), client_id, &state);
+
+        let response = process_command(
+            &build_command(&["INFO", "stats", "persistence"]),
+            client_id,
+            &state,
+        );
+
+        assert!(response.to_string().contains("total_commands_processed:2\r\n"));
+        assert!(response.to_string().contains("rdb_changes_since_last_save:2\r\n"));
+    }
+
+    #[test]
+    fn it_should_set_and_get_client_name() {
+        let (state, client_id) = setup();
+
+        let response = process_command(&build_command(&["CLIENT", "GETNAME"]), client_id, &state);
+        assert_eq!(response, "$-1\r\n");
+
+        let response = process_command(
+            &build_command(&["CLIENT", "SETNAME", "worker"]),
+            client_id,
+            &state,
+        );
+        assert_eq!(response, "+OK\r\n");
+
+        let response = process_command(&build_command(&["CLIENT", "GETNAME"]), client_id, &state);
+        assert_eq!(response, "$6\r\nworker\r\n");
+
+        let response = process_command(
+            &build_command(&["CLIENT", "SETNAME", "my worker"]),
+            client_id,
+            &state,
+        );
+        assert!(response.to_string().starts_with("-ERR"));
+    }
+
+    #[test]
+    fn it_should_return_client_id_and_list() {
+        let (state, client_id) = setup();
+
+        let response = process_command(&build_command(&["CLIENT", "ID"]), client_id, &state);
+        assert_eq!(response, format!(":{}\r\n", client_id));
+
+        let response = process_command(&build_command(&["CLIENT", "LIST"]), client_id, &state);
+        assert!(response.to_string().contains("id=1 addr=127.0.0.1:5000 name= "));
+        assert!(response.to_string().contains("cmd=client\n"));
+    }
+
+    #[test]
+    fn it_should_kill_client() {
+        let (state, client_id) = setup();
+        let other = state.clients.register("127.0.0.1:5001".to_string(), None);
+
+        let response = process_command(
+            &build_command(&["CLIENT", "KILL", "127.0.0.1:9999"]),
+            client_id,
+            &state,
+        );
+        assert_eq!(response, "-ERR No such client\r\n");
+
+        let response = process_command(
+            &build_command(&["CLIENT", "KILL", "ID", &other.to_string()]),
+            client_id,
+            &state,
+        );
+        assert_eq!(response, ":1\r\n");
+
+        // The issuing client is skipped by default.
+        let response = process_command(
+            &build_command(&["CLIENT", "KILL", "ADDR", "127.0.0.1:5000"]),
+            client_id,
+            &state,
+        );
+        assert_eq!(response, ":0\r\n");
+        assert_eq!(state.clients.len(), 1);
+    }
+
+    #[test]
+    fn it_should_not_kill_without_filters() {
+        let (state, client_id) = setup();
+        state.clients.register("127.0.0.1:5001".to_string(), None);
+
+        let response = process_command(&build_command(&["CLIENT", "KILL"]), client_id, &state);
+        assert_eq!(response, SYNTAX_ERROR);
+
+        let response = process_command(
+            &build_command(&["CLIENT", "KILL", "ID", "2", "SKIPME"]),
+            client_id,
+            &state,
+        );
+        assert_eq!(response, SYNTAX_ERROR);
+        assert_eq!(state.clients.len(), 2);
+    }
+
+    #[test]
+    fn it_should_record_slow_commands() {
+        let (state, client_id) = setup();
+
+        process_command(
+            &build_command(&["CONFIG", "SET", "slowlog-log-slower-than", "0"]),
+            client_id,
+            &state,
+        );
+        process_command(
+            &build_command(&["SET", "Name", "Felipe"]),
+            client_id,
+            &state,
+        );
+
+        let response = process_command(&build_command(&["SLOWLOG", "GET", "1"]), client_id, &state);
+        assert!(response.to_string().starts_with("*1\r\n*6\r\n:1\r\n"));
+        assert!(response.to_string().contains(
+            "*3\r\n$3\r\nSET\r\n$4\r\nName\r\n$6\r\nFelipe\r\n$14\r\n127.0.0.1:5000\r\n$0\r\n\r\n"
+        ));
+
+        let response = process_command(&build_command(&["SLOWLOG", "LEN"]), client_id, &state);
+        assert_eq!(response, ":3\r\n");
+
+        process_command(
+            &build_command(&["CONFIG", "SET", "slowlog-log-slower-than", "-1"]),
+            client_id,
+            &state,
+        );
+        let response = process_command(&build_command(&["SLOWLOG", "RESET"]), client_id, &state);
+        assert_eq!(response, "+OK\r\n");
+
+        let response = process_command(&build_command(&["SLOWLOG", "GET"]), client_id, &state);
+        assert_eq!(response, "*0\r\n");
+    }
+
+    #[test]
+    fn it_should_describe_commands() {
+        let (state, client_id) = setup();
+
+        let response = process_command(&build_command(&["COMMAND", "COUNT"]), client_id, &state);
+        assert_eq!(response, format!(":{}\r\n", COMMAND_TABLE.len()));
+
+        let response = process_command(
+            &build_command(&["COMMAND", "INFO", "get", "unknown"]),
+            client_id,
+            &state,
+        );
+        assert_eq!(
+            response,
+            "*2\r\n*6\r\n$3\r\nget\r\n:2\r\n*1\r\n$8\r\nreadonly\r\n:1\r\n:1\r\n:1\r\n$-1\r\n"
+        );
+    }
+
+    #[test]
+    fn it_should_get_config() {
+        let (state, client_id) = setup();
+
+        let response = process_command(
+            &build_command(&["CONFIG", "GET", "appendonly"]),
+            client_id,
+            &state,
+        );
+        assert_eq!(response, "*2\r\n$10\r\nappendonly\r\n$2\r\nno\r\n");
+
+        let response = process_command(
+            &build_command(&["CONFIG", "SET", "slowlog-max-len", "x"]),
+            client_id,
+            &state,
+        );
+        assert!(response.to_string().starts_with("-ERR"));
+    }
+
+    #[test]
+    fn it_should_reject_unknown_subcommand() {
+        let (state, client_id) = setup();
+
+        let response = process_command(&build_command(&["CLIENT", "PAUSE"]), client_id, &state);
+        assert_eq!(
+            response,
+            "-ERR unknown subcommand or wrong number of arguments for 'client|pause' command\r\n"
+        );
+
+        let response = process_command(&build_command(&["CLIENT"]), client_id, &state);
+        assert_eq!(
+            response,
+            "-ERR wrong number of arguments for 'client' command\r\n"
+        );
+    }
+}
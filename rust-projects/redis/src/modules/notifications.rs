@@ -0,0 +1,202 @@
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+
+use super::pubsub::PubSub;
+
+/// Publish `__keyspace@<db>__:<key>` messages.
+pub const NOTIFY_KEYSPACE: u32 = 1 << 0;
+/// Publish `__keyevent@<db>__:<event>` messages.
+pub const NOTIFY_KEYEVENT: u32 = 1 << 1;
+/// Generic commands such as `DEL`.
+pub const NOTIFY_GENERIC: u32 = 1 << 2;
+pub const NOTIFY_STRING: u32 = 1 << 3;
+pub const NOTIFY_LIST: u32 = 1 << 4;
+pub const NOTIFY_SET: u32 = 1 << 5;
+pub const NOTIFY_HASH: u32 = 1 << 6;
+pub const NOTIFY_ZSET: u32 = 1 << 7;
+pub const NOTIFY_EXPIRED: u32 = 1 << 8;
+pub const NOTIFY_EVICTED: u32 = 1 << 9;
+pub const NOTIFY_STREAM: u32 = 1 << 10;
+pub const NOTIFY_KEY_MISS: u32 = 1 << 11;
+pub const NOTIFY_NEW: u32 = 1 << 12;
+
+const NOTIFY_ALL: u32 = NOTIFY_GENERIC
+    | NOTIFY_STRING
+    | NOTIFY_LIST
+    | NOTIFY_SET
+    | NOTIFY_HASH
+    | NOTIFY_ZSET
+    | NOTIFY_EXPIRED
+    | NOTIFY_EVICTED
+    | NOTIFY_STREAM;
+
+const FLAG_CHARS: [(char, u32); 13] = [
+    ('g', NOTIFY_GENERIC),
+    ('$', NOTIFY_STRING),
+    ('l', NOTIFY_LIST),
+    ('s', NOTIFY_SET),
+    ('h', NOTIFY_HASH),
+    ('z', NOTIFY_ZSET),
+    ('x', NOTIFY_EXPIRED),
+    ('e', NOTIFY_EVICTED),
+    ('t', NOTIFY_STREAM),
+    ('m', NOTIFY_KEY_MISS),
+    ('n', NOTIFY_NEW),
+    ('K', NOTIFY_KEYSPACE),
+    ('E', NOTIFY_KEYEVENT),
+];
+
+/// Parses a `notify-keyspace-events` value such as `KEA` or `Kx`.
+///
+/// # Returns
+/// * `Ok(u32)` with the flags, `0` disables notifications.
+/// * `Err(String)` if the value contains an unknown class.
+///
+/// # Example
+///
+/// ```
+/// use redis::modules::notifications::{parse_flags, NOTIFY_EXPIRED, NOTIFY_KEYSPACE};
+///
+/// assert_eq!(parse_flags("Kx"), Ok(NOTIFY_KEYSPACE | NOTIFY_EXPIRED));
+/// assert!(parse_flags("Kq").is_err());
+/// ```
+pub fn parse_flags(value: &str) -> Result<u32, String> {
+    value.chars().try_fold(0, |flags, c| match c {
+        'A' => Ok(flags | NOTIFY_ALL),
+        c => FLAG_CHARS
+            .iter()
+            .find(|(flag_char, _)| *flag_char == c)
+            .map(|(_, flag)| flags | flag)
+            .ok_or_else(|| format!("Invalid event class character '{}'", c)),
+    })
+}
+
+/// Renders flags back to their `notify-keyspace-events` form, using `A` when possible.
+pub fn flags_to_string(flags: u32) -> String {
+    let mut rendered = String::new();
+
+    if flags & NOTIFY_ALL == NOTIFY_ALL {
+        rendered.push('A');
+    }
+
+    for (c, flag) in FLAG_CHARS.iter() {
+        if flags & flag != 0 && (flag & NOTIFY_ALL == 0 || flags & NOTIFY_ALL != NOTIFY_ALL) {
+            rendered.push(*c);
+        }
+    }
+
+    rendered
+}
+
+/// A keyspace event recorded by the store, published once the store is unlocked.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub class: u32,
+    pub event: String,
+    pub key: String,
+}
+
+/// Publishes keyspace notifications for the store, according to `notify-keyspace-events`.
+#[derive(Debug)]
+pub struct KeyspaceNotifier {
+    flags: AtomicU32,
+    pubsub: Arc<PubSub>,
+}
+
+impl KeyspaceNotifier {
+    pub fn new(pubsub: Arc<PubSub>) -> Self {
+        KeyspaceNotifier {
+            flags: AtomicU32::new(0),
+            pubsub,
+        }
+    }
+
+    pub fn set_flags(&self, flags: u32) {
+        self.flags.store(flags, Ordering::SeqCst);
+    }
+
+    pub fn flags(&self) -> u32 {
+        self.flags.load(Ordering::SeqCst)
+    }
+
+    /// Returns `true` if events of `class` are published.
+    pub fn is_enabled(&self, class: u32) -> bool {
+        self.flags() & class != 0
+    }
+
+    /// Publishes `event` on `key` if its class is enabled.
+    ///
+    /// # Arguments
+    /// * `class` - The event class, one of the `NOTIFY_*` type flags.
+    /// * `event` - The event name, e.g. `set` or `expired`.
+    /// * `key` - The key that was affected.
+    pub fn notify(&self, class: u32, event: &str, key: &str) {
+        let flags = self.flags();
+
+        if !self.is_enabled(class) {
+            return;
+        }
+
+        if flags & NOTIFY_KEYSPACE != 0 {
+            self.pubsub
+                .publish(&format!("__keyspace@0__:{}", key), event);
+        }
+
+        if flags & NOTIFY_KEYEVENT != 0 {
+            self.pubsub
+                .publish(&format!("__keyevent@0__:{}", event), key);
+        }
+    }
+
+    /// Publishes the events recorded by the store, see [`Redis::take_notifications`].
+    ///
+    /// [`Redis::take_notifications`]: super::store::Redis::take_notifications
+    pub fn publish(&self, notifications: Vec<Notification>) {
+        for notification in notifications {
+            self.notify(
+                notification.class,
+                &notification.event,
+                &notification.key,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::modules::pubsub::tests::Inbox;
+
+    use super::*;
+
+    #[test]
+    fn should_parse_and_render_flags() {
+        assert_eq!(parse_flags(""), Ok(0));
+        assert_eq!(flags_to_string(parse_flags("KEA").unwrap()), "AKE");
+        assert_eq!(flags_to_string(parse_flags("Elg$").unwrap()), "g$lE");
+        assert!(parse_flags("K?").is_err());
+    }
+
+    #[test]
+    fn should_only_notify_enabled_classes() {
+        let pubsub = Arc::new(PubSub::new());
+        let (outbox, inbox) = Inbox::new();
+        pubsub.attach(1, outbox);
+        pubsub.psubscribe(1, &["__key*__:*".to_string()]);
+
+        let notifier = KeyspaceNotifier::new(Arc::clone(&pubsub));
+        notifier.notify(NOTIFY_STRING, "set", "name");
+        assert_eq!(inbox.take(), "");
+
+        notifier.set_flags(parse_flags("Kx").unwrap());
+        notifier.notify(NOTIFY_STRING, "set", "name");
+        assert_eq!(inbox.take(), "");
+
+        notifier.notify(NOTIFY_EXPIRED, "expired", "name");
+        assert_eq!(
+            inbox.take(),
+            "*4\r\n$8\r\npmessage\r\n$10\r\n__key*__:*\r\n$19\r\n__keyspace@0__:name\r\n$7\r\nexpired\r\n"
+        );
+    }
+}
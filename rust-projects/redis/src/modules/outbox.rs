@@ -0,0 +1,125 @@
+use std::{
+    io::Write,
+    net::{Shutdown, TcpStream},
+    sync::{
+        mpsc::{self, Receiver, SyncSender, TrySendError},
+        Arc,
+    },
+    thread,
+};
+
+/// Number of replies and pushed messages a client may have waiting before it is considered too
+/// slow and disconnected.
+pub const OUTBOX_CAPACITY: usize = 1024;
+
+/// Queue of bytes waiting to be written to a client.
+///
+/// A writer thread drains the queue, so the threads producing `MONITOR` lines or published
+/// messages never block on a slow client, let alone while holding a lock. Replies go through
+/// the same queue to keep them ordered with the pushed messages.
+#[derive(Debug, Clone)]
+pub struct Outbox {
+    sender: SyncSender<Vec<u8>>,
+    /// Shut down when the client falls too far behind, `None` when there is no socket.
+    stream: Option<Arc<TcpStream>>,
+}
+
+impl Outbox {
+    /// Creates an outbox without a writer thread, the queued bytes are read from the receiver.
+    pub fn channel(capacity: usize) -> (Outbox, Receiver<Vec<u8>>) {
+        let (sender, receiver) = mpsc::sync_channel(capacity);
+
+        (
+            Outbox {
+                sender,
+                stream: None,
+            },
+            receiver,
+        )
+    }
+
+    /// Creates an outbox written to `stream` by a dedicated thread, which stops once every
+    /// clone of the outbox is dropped or the socket is closed.
+    pub fn spawn(stream: TcpStream) -> std::io::Result<Outbox> {
+        let mut writer = stream.try_clone()?;
+        let (mut outbox, receiver) = Outbox::channel(OUTBOX_CAPACITY);
+        outbox.stream = Some(Arc::new(stream));
+
+        thread::spawn(move || {
+            for bytes in receiver {
+                if writer.write_all(&bytes).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(outbox)
+    }
+
+    /// Queues the reply to a command of the client, waiting for room if needed.
+    ///
+    /// # Returns
+    /// `false` once the client is gone.
+    pub fn send(&self, bytes: Vec<u8>) -> bool {
+        self.sender.send(bytes).is_ok()
+    }
+
+    /// Queues bytes the client did not ask for, such as a published message, without blocking.
+    /// A client whose queue is full is disconnected.
+    ///
+    /// # Returns
+    /// `false` if the bytes were dropped.
+    pub fn push(&self, bytes: Vec<u8>) -> bool {
+        match self.sender.try_send(bytes) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                if let Some(stream) = &self.stream {
+                    let _ = stream.shutdown(Shutdown::Both);
+                }
+                false
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Read, net::TcpListener};
+
+    use super::*;
+
+    #[test]
+    fn should_drop_pushes_when_full() {
+        let (outbox, receiver) = Outbox::channel(1);
+
+        assert!(outbox.push(b"first".to_vec()));
+        assert!(!outbox.push(b"second".to_vec()));
+        assert_eq!(receiver.recv().unwrap(), b"first");
+
+        drop(receiver);
+        assert!(!outbox.send(b"gone".to_vec()));
+    }
+
+    #[test]
+    fn should_write_in_order_and_close_slow_clients() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        let outbox = Outbox::spawn(stream).unwrap();
+
+        assert!(outbox.send(b"+OK\r\n".to_vec()));
+        assert!(outbox.push(b":1\r\n".to_vec()));
+        let mut buffer = [0; 9];
+        client.read_exact(&mut buffer).unwrap();
+        assert_eq!(&buffer, b"+OK\r\n:1\r\n");
+
+        // The client stops reading, the socket buffers and then the queue fill up.
+        let message = vec![b'x'; 1024];
+        while outbox.push(message.clone()) {}
+
+        // Returns once the server closed the connection.
+        let mut received = Vec::new();
+        let _ = client.read_to_end(&mut received);
+    }
+}
@@ -0,0 +1,377 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Mutex,
+};
+
+use super::{
+    config::glob_match,
+    outbox::Outbox,
+    serialize::{serialize_array, serialize_bulk_string, serialize_integer},
+    server::ServerState,
+};
+
+#[derive(Default)]
+struct Subscriptions {
+    channels: BTreeMap<String, BTreeSet<u64>>,
+    patterns: BTreeMap<String, BTreeSet<u64>>,
+    sinks: BTreeMap<u64, Outbox>,
+}
+
+impl Subscriptions {
+    fn count(&self, client_id: u64) -> usize {
+        self.channels
+            .values()
+            .chain(self.patterns.values())
+            .filter(|subscribers| subscribers.contains(&client_id))
+            .count()
+    }
+
+    fn subscribed(map: &BTreeMap<String, BTreeSet<u64>>, client_id: u64) -> Vec<String> {
+        map.iter()
+            .filter(|(_, subscribers)| subscribers.contains(&client_id))
+            .map(|(name, _)| name.to_string())
+            .collect()
+    }
+}
+
+/// Channel and pattern subscriptions, shared by every connection and by the store to publish
+/// keyspace notifications.
+#[derive(Default)]
+pub struct PubSub {
+    subscriptions: Mutex<Subscriptions>,
+}
+
+impl std::fmt::Debug for PubSub {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let subscriptions = self.subscriptions.lock().unwrap();
+
+        f.debug_struct("PubSub")
+            .field("channels", &subscriptions.channels.len())
+            .field("patterns", &subscriptions.patterns.len())
+            .finish()
+    }
+}
+
+fn subscription_reply(kind: &str, name: Option<&str>, count: usize) -> String {
+    serialize_array(&[
+        serialize_bulk_string(kind),
+        name.map_or_else(|| "$-1\r\n".to_string(), serialize_bulk_string),
+        serialize_integer(count as i64),
+    ])
+}
+
+impl PubSub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns `true` if messages for `client_id` already have somewhere to go.
+    pub fn is_attached(&self, client_id: u64) -> bool {
+        self.subscriptions
+            .lock()
+            .unwrap()
+            .sinks
+            .contains_key(&client_id)
+    }
+
+    /// Sets where the messages published to `client_id` are queued, usually its socket.
+    pub fn attach(&self, client_id: u64, sink: Outbox) {
+        self.subscriptions
+            .lock()
+            .unwrap()
+            .sinks
+            .insert(client_id, sink);
+    }
+
+    /// Drops every subscription of a client, called when it disconnects.
+    pub fn detach(&self, client_id: u64) {
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        let Subscriptions {
+            channels,
+            patterns,
+            sinks,
+        } = &mut *subscriptions;
+
+        sinks.remove(&client_id);
+        for map in [channels, patterns] {
+            map.retain(|_, subscribers| {
+                subscribers.remove(&client_id);
+                !subscribers.is_empty()
+            });
+        }
+    }
+
+    fn add(&self, client_id: u64, names: &[String], pattern: bool) -> String {
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+
+        names
+            .iter()
+            .map(|name| {
+                let map = if pattern {
+                    &mut subscriptions.patterns
+                } else {
+                    &mut subscriptions.channels
+                };
+                map.entry(name.to_string()).or_default().insert(client_id);
+
+                subscription_reply(
+                    if pattern { "psubscribe" } else { "subscribe" },
+                    Some(name),
+                    subscriptions.count(client_id),
+                )
+            })
+            .collect()
+    }
+
+    fn remove(&self, client_id: u64, names: &[String], pattern: bool) -> String {
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        let kind = if pattern {
+            "punsubscribe"
+        } else {
+            "unsubscribe"
+        };
+
+        let names = if names.is_empty() {
+            Subscriptions::subscribed(
+                if pattern {
+                    &subscriptions.patterns
+                } else {
+                    &subscriptions.channels
+                },
+                client_id,
+            )
+        } else {
+            names.to_vec()
+        };
+
+        if names.is_empty() {
+            return subscription_reply(kind, None, subscriptions.count(client_id));
+        }
+
+        names
+            .iter()
+            .map(|name| {
+                let map = if pattern {
+                    &mut subscriptions.patterns
+                } else {
+                    &mut subscriptions.channels
+                };
+                if let Some(subscribers) = map.get_mut(name) {
+                    subscribers.remove(&client_id);
+                    if subscribers.is_empty() {
+                        map.remove(name);
+                    }
+                }
+
+                subscription_reply(kind, Some(name), subscriptions.count(client_id))
+            })
+            .collect()
+    }
+
+    /// Subscribes a client to channels.
+    ///
+    /// # Returns
+    /// One serialized `subscribe` confirmation per channel.
+    pub fn subscribe(&self, client_id: u64, channels: &[String]) -> String {
+        self.add(client_id, channels, false)
+    }
+
+    /// Subscribes a client to glob patterns matched against channel names.
+    pub fn psubscribe(&self, client_id: u64, patterns: &[String]) -> String {
+        self.add(client_id, patterns, true)
+    }
+
+    /// Unsubscribes a client from channels, or from all of its channels if none is given.
+    pub fn unsubscribe(&self, client_id: u64, channels: &[String]) -> String {
+        self.remove(client_id, channels, false)
+    }
+
+    /// Unsubscribes a client from patterns, or from all of its patterns if none is given.
+    pub fn punsubscribe(&self, client_id: u64, patterns: &[String]) -> String {
+        self.remove(client_id, patterns, true)
+    }
+
+    /// Sends a message to the subscribers of `channel` and of every pattern matching it.
+    ///
+    /// Messages are queued without blocking, a subscriber that cannot keep up is disconnected.
+    ///
+    /// # Returns
+    /// The number of deliveries, a client subscribed through a channel and a pattern counts twice.
+    pub fn publish(&self, channel: &str, message: &str) -> usize {
+        let subscriptions = self.subscriptions.lock().unwrap();
+        let Subscriptions {
+            channels,
+            patterns,
+            sinks,
+        } = &*subscriptions;
+
+        let mut deliveries: Vec<(u64, String)> = channels
+            .get(channel)
+            .into_iter()
+            .flatten()
+            .map(|client_id| {
+                (
+                    *client_id,
+                    serialize_array(&[
+                        serialize_bulk_string("message"),
+                        serialize_bulk_string(channel),
+                        serialize_bulk_string(message),
+                    ]),
+                )
+            })
+            .collect();
+
+        for (pattern, subscribers) in patterns.iter() {
+            if glob_match(pattern, channel) {
+                deliveries.extend(subscribers.iter().map(|client_id| {
+                    (
+                        *client_id,
+                        serialize_array(&[
+                            serialize_bulk_string("pmessage"),
+                            serialize_bulk_string(pattern),
+                            serialize_bulk_string(channel),
+                            serialize_bulk_string(message),
+                        ]),
+                    )
+                }));
+            }
+        }
+
+        let count = deliveries.len();
+        let deliveries: Vec<(Outbox, String)> = deliveries
+            .into_iter()
+            .filter_map(|(client_id, payload)| Some((sinks.get(&client_id)?.clone(), payload)))
+            .collect();
+        drop(subscriptions);
+
+        for (sink, payload) in deliveries {
+            sink.push(payload.into_bytes());
+        }
+
+        count
+    }
+}
+
+/// Routes published messages to the client socket the first time it subscribes.
+fn attach_client(client_id: u64, state: &ServerState) {
+    if state.pubsub.is_attached(client_id) {
+        return;
+    }
+
+    if let Some(outbox) = state.clients.outbox(client_id) {
+        state.pubsub.attach(client_id, outbox);
+    }
+}
+
+pub fn subscribe_command(args: &[String], client_id: u64, state: &ServerState) -> String {
+    attach_client(client_id, state);
+    state.pubsub.subscribe(client_id, &args[1..])
+}
+
+pub fn psubscribe_command(args: &[String], client_id: u64, state: &ServerState) -> String {
+    attach_client(client_id, state);
+    state.pubsub.psubscribe(client_id, &args[1..])
+}
+
+pub fn unsubscribe_command(args: &[String], client_id: u64, state: &ServerState) -> String {
+    state.pubsub.unsubscribe(client_id, &args[1..])
+}
+
+pub fn punsubscribe_command(args: &[String], client_id: u64, state: &ServerState) -> String {
+    state.pubsub.punsubscribe(client_id, &args[1..])
+}
+
+pub fn publish_command(args: &[String], _: u64, state: &ServerState) -> String {
+    serialize_integer(state.pubsub.publish(&args[1], &args[2]) as i64)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::mpsc::Receiver;
+
+    use super::*;
+
+    /// Receiving end of an outbox standing in for a client socket.
+    pub struct Inbox(Receiver<Vec<u8>>);
+
+    impl Inbox {
+        pub fn new() -> (Outbox, Inbox) {
+            let (outbox, receiver) = Outbox::channel(16);
+            (outbox, Inbox(receiver))
+        }
+
+        pub fn take(&self) -> String {
+            String::from_utf8(self.0.try_iter().flatten().collect()).unwrap()
+        }
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn should_publish_to_channel_subscribers() {
+        let pubsub = PubSub::new();
+        let (outbox, inbox) = Inbox::new();
+        pubsub.attach(1, outbox);
+
+        let reply = pubsub.subscribe(1, &names(&["news", "sport"]));
+        assert_eq!(
+            reply,
+            "*3\r\n$9\r\nsubscribe\r\n$4\r\nnews\r\n:1\r\n*3\r\n$9\r\nsubscribe\r\n$5\r\nsport\r\n:2\r\n"
+        );
+
+        assert_eq!(pubsub.publish("news", "hello"), 1);
+        assert_eq!(pubsub.publish("weather", "sunny"), 0);
+        assert_eq!(
+            inbox.take(),
+            "*3\r\n$7\r\nmessage\r\n$4\r\nnews\r\n$5\r\nhello\r\n"
+        );
+    }
+
+    #[test]
+    fn should_publish_to_pattern_subscribers() {
+        let pubsub = PubSub::new();
+        let (outbox, inbox) = Inbox::new();
+        pubsub.attach(1, outbox);
+
+        pubsub.psubscribe(1, &names(&["news.*"]));
+
+        assert_eq!(pubsub.publish("news.tech", "rust"), 1);
+        assert_eq!(
+            inbox.take(),
+            "*4\r\n$8\r\npmessage\r\n$6\r\nnews.*\r\n$9\r\nnews.tech\r\n$4\r\nrust\r\n"
+        );
+    }
+
+    #[test]
+    fn should_unsubscribe_from_all_channels() {
+        let pubsub = PubSub::new();
+        pubsub.subscribe(1, &names(&["a", "b"]));
+        pubsub.subscribe(2, &names(&["a"]));
+
+        let reply = pubsub.unsubscribe(1, &[]);
+        assert_eq!(
+            reply,
+            "*3\r\n$11\r\nunsubscribe\r\n$1\r\na\r\n:1\r\n*3\r\n$11\r\nunsubscribe\r\n$1\r\nb\r\n:0\r\n"
+        );
+
+        let reply = pubsub.unsubscribe(1, &[]);
+        assert_eq!(reply, "*3\r\n$11\r\nunsubscribe\r\n$-1\r\n:0\r\n");
+
+        assert_eq!(pubsub.publish("a", "still here"), 1);
+    }
+
+    #[test]
+    fn should_detach_client() {
+        let pubsub = PubSub::new();
+        pubsub.attach(1, Inbox::new().0);
+        pubsub.subscribe(1, &names(&["a"]));
+        pubsub.psubscribe(1, &names(&["*"]));
+
+        pubsub.detach(1);
+
+        assert!(!pubsub.is_attached(1));
+        assert_eq!(pubsub.publish("a", "gone"), 0);
+    }
+}
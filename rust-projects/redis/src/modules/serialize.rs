@@ -0,0 +1,143 @@
+/// Serialize a simple string.
+///
+/// Simple strings are prefixed with `+` and terminated by `\r\n`.
+///
+/// # Example
+///
+/// ```
+/// use redis::modules::serialize::serialize_simple_string;
+///
+/// assert_eq!(serialize_simple_string("OK"), "+OK\r\n");
+/// ```
+pub fn serialize_simple_string(value: &str) -> String {
+    format!("+{}\r\n", value)
+}
+
+/// Serialize an error message.
+///
+/// Errors are prefixed with `-` and terminated by `\r\n`.
+///
+/// # Example
+///
+/// ```
+/// use redis::modules::serialize::serialize_error;
+///
+/// assert_eq!(serialize_error("ERR unknown"), "-ERR unknown\r\n");
+/// ```
+pub fn serialize_error(message: &str) -> String {
+    format!("-{}\r\n", message)
+}
+
+/// Serialize an integer.
+///
+/// Integers are prefixed with `:` and terminated by `\r\n`.
+///
+/// # Example
+///
+/// ```
+/// use redis::modules::serialize::serialize_integer;
+///
+/// assert_eq!(serialize_integer(-12), ":-12\r\n");
+/// ```
+pub fn serialize_integer(value: i64) -> String {
+    format!(":{}\r\n", value)
+}
+
+/// Serialize a bulk string.
+///
+/// Bulk strings are prefixed with `$` followed by the byte length of the string, so they can
+/// carry `\r\n` and any other content.
+///
+/// # Example
+///
+/// ```
+/// use redis::modules::serialize::serialize_bulk_string;
+///
+/// assert_eq!(serialize_bulk_string("foo\r\nbar"), "$8\r\nfoo\r\nbar\r\n");
+/// ```
+pub fn serialize_bulk_string(value: &str) -> String {
+    format!("${}\r\n{}\r\n", value.len(), value)
+}
+
+/// Serialize a bulk string from raw bytes, which need not be valid UTF-8.
+///
+/// # Example
+///
+/// ```
+/// use redis::modules::serialize::serialize_bulk_bytes;
+///
+/// assert_eq!(serialize_bulk_bytes(&[0xff, b'\r']), b"$2\r\n\xff\r\r\n");
+/// ```
+pub fn serialize_bulk_bytes(value: &[u8]) -> Vec<u8> {
+    let mut bytes = format!("${}\r\n", value.len()).into_bytes();
+    bytes.extend_from_slice(value);
+    bytes.extend_from_slice(b"\r\n");
+    bytes
+}
+
+/// Serialize a null bulk string.
+///
+/// # Example
+///
+/// ```
+/// use redis::modules::serialize::serialize_null;
+///
+/// assert_eq!(serialize_null(), "$-1\r\n");
+/// ```
+pub fn serialize_null() -> String {
+    "$-1\r\n".to_string()
+}
+
+/// Serialize an array from elements that are already serialized.
+///
+/// # Arguments
+///
+/// * `elements` - The serialized elements, in order.
+///
+/// # Example
+///
+/// ```
+/// use redis::modules::serialize::{serialize_array, serialize_bulk_string, serialize_integer};
+///
+/// let result = serialize_array(&[serialize_bulk_string("get"), serialize_integer(2)]);
+/// assert_eq!(result, "*2\r\n$3\r\nget\r\n:2\r\n");
+/// ```
+pub fn serialize_array(elements: &[String]) -> String {
+    format!("*{}\r\n{}", elements.len(), elements.concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::modules::{deserialize::deserialize, types::RedisDeserializationTypes};
+
+    use super::*;
+
+    #[test]
+    fn it_should_serialize_empty_array() {
+        assert_eq!(serialize_array(&[]), "*0\r\n");
+    }
+
+    #[test]
+    fn it_should_serialize_nested_array() {
+        let result = serialize_array(&[
+            serialize_simple_string("echo"),
+            serialize_array(&[serialize_bulk_string("1234"), serialize_integer(11)]),
+        ]);
+
+        assert_eq!(
+            deserialize(&mut result.as_bytes()),
+            Some(RedisDeserializationTypes::Array(Box::new(vec![
+                RedisDeserializationTypes::SimpleString("echo".to_string()),
+                RedisDeserializationTypes::Array(Box::new(vec![
+                    RedisDeserializationTypes::BulkString("1234".into()),
+                    RedisDeserializationTypes::Integer(11),
+                ])),
+            ])))
+        );
+    }
+
+    #[test]
+    fn it_should_serialize_empty_bulk_string() {
+        assert_eq!(serialize_bulk_string(""), "$0\r\n\r\n");
+    }
+}
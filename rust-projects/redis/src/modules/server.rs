@@ -1,39 +1,407 @@
 use std::{
-    io::{Read, Write},
+    io::Read,
     net::TcpStream,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
     thread,
+    time::Instant,
 };
 
-use super::{commands::execute_command, deserialize::deserialize, store::Redis};
+use chrono::Utc;
 
-pub fn handle_connection(mut stream: TcpStream, redis: Arc<Mutex<Redis>>) {
+use super::{
+    clients::ClientRegistry,
+    cluster::Cluster,
+    command_table::{resolve_command, CommandHandler, CommandSpec},
+    config::Config,
+    deserialize::{deserialize, frame_length},
+    notifications::KeyspaceNotifier,
+    pubsub::PubSub,
+    slowlog::SlowLog,
+    store::Redis,
+    types::{RedisDeserializationTypes, RedisString},
+};
+
+#[derive(Debug, Default)]
+pub struct ServerStats {
+    pub total_connections_received: AtomicU64,
+    pub total_commands_processed: AtomicU64,
+}
+
+/// State shared by every connection thread.
+#[derive(Debug)]
+pub struct ServerState {
+    pub redis: Arc<Mutex<Redis>>,
+    pub clients: ClientRegistry,
+    pub slowlog: Mutex<SlowLog>,
+    pub config: Mutex<Config>,
+    pub pubsub: Arc<PubSub>,
+    pub notifier: Arc<KeyspaceNotifier>,
+    /// Cluster topology, `None` when running standalone.
+    pub cluster: Option<Cluster>,
+    pub stats: ServerStats,
+    pub started_at: Instant,
+    pub port: u16,
+}
+
+impl ServerState {
+    pub fn new(mut redis: Redis, port: u16) -> Self {
+        let pubsub = Arc::new(PubSub::new());
+        let notifier = Arc::new(KeyspaceNotifier::new(Arc::clone(&pubsub)));
+        redis.set_notifier(Arc::clone(&notifier));
+
+        let state = ServerState {
+            redis: Arc::new(Mutex::new(redis)),
+            clients: ClientRegistry::new(),
+            slowlog: Mutex::new(SlowLog::new()),
+            config: Mutex::new(Config::default()),
+            pubsub,
+            notifier,
+            cluster: None,
+            stats: ServerStats::default(),
+            started_at: Instant::now(),
+            port,
+        };
+        state.apply_config();
+
+        state
+    }
+
+    /// Enables cluster mode with a static topology.
+    pub fn with_cluster(mut self, cluster: Cluster) -> Self {
+        self.cluster = Some(cluster);
+        self
+    }
+
+    /// Pushes the configuration that the store depends on, called after `CONFIG SET`.
+    pub fn apply_config(&self) {
+        let config = self.config.lock().unwrap();
+
+        self.notifier.set_flags(config.notify_keyspace_events);
+        self.redis.lock().unwrap().set_maxmemory(config.maxmemory);
+    }
+}
+
+/// Formats a command the way `MONITOR` streams it, e.g. `+1718000000.000123 [0 127.0.0.1:5000] "GET" "key"`.
+fn monitor_line(args: &[String], addr: &str) -> String {
+    let now = Utc::now();
+    let args: Vec<String> = args.iter().map(|arg| format!("{:?}", arg)).collect();
+
+    format!(
+        "+{}.{:06} [0 {}] {}\r\n",
+        now.timestamp(),
+        now.timestamp_subsec_micros(),
+        addr,
+        args.join(" ")
+    )
+}
+
+/// Checks that the keys of a command are served by this node when running in cluster mode.
+///
+/// # Returns
+/// The serialized redirection error, or `None` if the command can run here.
+fn redirect(
+    spec: &CommandSpec,
+    args: &[String],
+    client_id: u64,
+    state: &ServerState,
+) -> Option<String> {
+    let cluster = state.cluster.as_ref()?;
+    let asking = spec.name != "asking" && state.clients.take_asking(client_id);
+
+    cluster.redirect(&spec.keys(args), asking, |key| {
+        state.redis.lock().unwrap().get(key).is_some()
+    })
+}
+
+/// Executes a command on behalf of a client, keeping the server bookkeeping up to date.
+///
+/// Besides running the command, this records the client's last command, feeds the clients in
+/// `MONITOR` mode, updates the command counters and logs the command if it was slow.
+///
+/// # Arguments
+/// * `command` - A reference to the deserialized Redis command to be executed.
+/// * `client_id` - Id of the client issuing the command.
+/// * `state` - The state shared by every connection.
+///
+/// # Returns
+/// The serialized reply to send back to the client.
+pub fn process_command(
+    command: &RedisDeserializationTypes,
+    client_id: u64,
+    state: &ServerState,
+) -> RedisString {
+    let args = command.to_args();
+    let name = args.first().cloned().unwrap_or_default();
+
+    state.clients.touch(client_id, &name);
+
+    let started = Instant::now();
+    let response = match resolve_command(command) {
+        Ok((spec, args)) => match redirect(spec, &args, client_id, state) {
+            Some(redirection) => redirection.into(),
+            None => match spec.handler {
+                CommandHandler::Store(handler) => handler(&args, &state.redis).into(),
+                CommandHandler::Value(handler) => handler(&command.to_raw_args(), &state.redis),
+                CommandHandler::Server(handler) => handler(&args, client_id, state).into(),
+            },
+        },
+        Err(err) => err.into(),
+    };
+    let duration = started.elapsed();
+
+    // Published once the store is unlocked, a slow subscriber must not hold up other writers.
+    let notifications = state.redis.lock().unwrap().take_notifications();
+    state.notifier.publish(notifications);
+
+    state
+        .stats
+        .total_commands_processed
+        .fetch_add(1, Ordering::SeqCst);
+
+    let addr = state.clients.addr(client_id).unwrap_or_default();
+    let (threshold, max_len) = {
+        let config = state.config.lock().unwrap();
+        (config.slowlog_log_slower_than, config.slowlog_max_len)
+    };
+
+    if !name.eq_ignore_ascii_case("MONITOR") {
+        state.clients.feed_monitors(&monitor_line(&args, &addr));
+    }
+
+    state.slowlog.lock().unwrap().record(
+        threshold,
+        max_len,
+        duration,
+        args,
+        addr,
+        state.clients.name(client_id).unwrap_or_default(),
+    );
+
+    response
+}
+
+pub fn handle_connection(mut stream: TcpStream, state: Arc<ServerState>) {
     thread::spawn(move || {
         let mut buffer = [0; 1024];
 
-        // Keep the connection alive
-        loop {
-            match stream.read(&mut buffer) {
-                // Close connection
-                Ok(0) => {
-                    return;
-                }
-                Ok(size) => {
-                    if let Ok(command) = String::from_utf8(Vec::from(&buffer[..size])) {
-                        if let Some(command) = deserialize(&mut command.as_ref()) {
-                            println!("Command: {:?}", command);
+        let addr = stream
+            .peer_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or_default();
+        let client_id = state.clients.register(addr, stream.try_clone().ok());
+        state
+            .stats
+            .total_connections_received
+            .fetch_add(1, Ordering::SeqCst);
+
+        // Replies are written by the outbox thread, after the messages pushed before them.
+        if let Some(outbox) = state.clients.outbox(client_id) {
+            // Bytes received but not parsed yet, a read may end in the middle of a command or
+            // hold several pipelined ones.
+            let mut pending: Vec<u8> = Vec::new();
+
+            // Keep the connection alive
+            'connection: loop {
+                match stream.read(&mut buffer) {
+                    // Close connection
+                    Ok(0) => {
+                        break;
+                    }
+                    Ok(size) => {
+                        pending.extend_from_slice(&buffer[..size]);
+
+                        let mut parsed = 0;
+                        while let Some(length) = frame_length(&pending[parsed..]) {
+                            let mut frame = &pending[parsed..parsed + length];
+                            parsed += length;
 
-                            let response = execute_command(&command, Arc::clone(&redis));
+                            // Values are binary-safe, the request is parsed as bytes.
+                            if let Some(command) = deserialize(&mut frame) {
+                                let response = process_command(&command, client_id, &state);
 
-                            stream.write_all(response.as_bytes()).unwrap();
-                            stream.flush().unwrap();
+                                if !outbox.send(response.into_bytes()) {
+                                    break 'connection;
+                                }
+                            }
                         }
+                        pending.drain(..parsed);
+                    }
+                    Err(_) => {
+                        break;
                     }
-                }
-                Err(_) => {
-                    return;
                 }
             }
         }
+
+        state.pubsub.detach(client_id);
+        state.clients.unregister(client_id);
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        time::Duration,
+    };
+
+    use super::*;
+
+    fn connect(listener: &TcpListener, state: &Arc<ServerState>) -> TcpStream {
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        handle_connection(stream, Arc::clone(state));
+
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        client
+    }
+
+    fn send(stream: &mut TcpStream, command: &str) -> String {
+        stream.write_all(command.as_bytes()).unwrap();
+
+        let mut buffer = [0; 1024];
+        let size = stream.read(&mut buffer).unwrap();
+        String::from_utf8_lossy(&buffer[..size]).to_string()
+    }
+
+    #[test]
+    fn it_should_stream_commands_to_monitor() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let state = Arc::new(ServerState::new(Redis::new(), 0));
+
+        let mut monitor = connect(&listener, &state);
+        let mut client = connect(&listener, &state);
+
+        assert_eq!(send(&mut monitor, "*1\r\n$7\r\nMONITOR\r\n"), "+OK\r\n");
+        assert_eq!(
+            send(
+                &mut client,
+                "*3\r\n$3\r\nSET\r\n$4\r\nName\r\n$6\r\nFelipe\r\n"
+            ),
+            "+OK\r\n"
+        );
+
+        let mut line = String::new();
+        BufReader::new(monitor).read_line(&mut line).unwrap();
+
+        assert!(line.starts_with('+'));
+        assert!(line.ends_with("] \"SET\" \"Name\" \"Felipe\"\r\n"));
+    }
+
+    #[test]
+    fn it_should_keep_binary_values_intact() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let state = Arc::new(ServerState::new(Redis::new(), 0));
+
+        let mut client = connect(&listener, &state);
+
+        client
+            .write_all(b"*3\r\n$3\r\nSET\r\n$3\r\nbin\r\n$4\r\n\xff\r\n\x00\r\n")
+            .unwrap();
+        let mut buffer = [0; 5];
+        client.read_exact(&mut buffer).unwrap();
+        assert_eq!(&buffer, b"+OK\r\n");
+
+        client
+            .write_all(b"*2\r\n$3\r\nGET\r\n$3\r\nbin\r\n")
+            .unwrap();
+        let mut buffer = [0; 10];
+        client.read_exact(&mut buffer).unwrap();
+        assert_eq!(&buffer, b"$4\r\n\xff\r\n\x00\r\n");
+    }
+
+    #[test]
+    fn it_should_buffer_split_and_pipelined_commands() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let state = Arc::new(ServerState::new(Redis::new(), 0));
+
+        let mut client = connect(&listener, &state);
+
+        // A value larger than a read, sent in two writes.
+        let value = "v".repeat(5000);
+        let command = format!(
+            "*3\r\n$3\r\nSET\r\n$3\r\nbig\r\n${}\r\n{}\r\n",
+            value.len(),
+            value
+        );
+        let (head, tail) = command.as_bytes().split_at(2000);
+        client.write_all(head).unwrap();
+        client.flush().unwrap();
+        thread::sleep(Duration::from_millis(50));
+        client.write_all(tail).unwrap();
+        let mut buffer = [0; 5];
+        client.read_exact(&mut buffer).unwrap();
+        assert_eq!(&buffer, b"+OK\r\n");
+
+        client
+            .write_all(b"*2\r\n$3\r\nGET\r\n$3\r\nbig\r\n*2\r\n$3\r\nGET\r\n$4\r\nnone\r\n")
+            .unwrap();
+        let expected = format!("$5000\r\n{}\r\n$-1\r\n", value);
+        let mut buffer = vec![0; expected.len()];
+        client.read_exact(&mut buffer).unwrap();
+        assert_eq!(String::from_utf8(buffer).unwrap(), expected);
+    }
+
+    #[test]
+    fn it_should_close_killed_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let state = Arc::new(ServerState::new(Redis::new(), 0));
+
+        let mut admin = connect(&listener, &state);
+        let mut victim = connect(&listener, &state);
+
+        let victim_id = send(&mut victim, "*2\r\n$6\r\nCLIENT\r\n$2\r\nID\r\n");
+        let victim_id = victim_id.trim_start_matches(':').trim_end();
+
+        let command = format!(
+            "*4\r\n$6\r\nCLIENT\r\n$4\r\nKILL\r\n$2\r\nID\r\n${}\r\n{}\r\n",
+            victim_id.len(),
+            victim_id
+        );
+        assert_eq!(send(&mut admin, &command), ":1\r\n");
+
+        let mut buffer = [0; 16];
+        assert_eq!(victim.read(&mut buffer).unwrap(), 0);
+    }
+
+    #[test]
+    fn it_should_publish_keyspace_notifications() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let state = Arc::new(ServerState::new(Redis::new(), 0));
+
+        let mut subscriber = connect(&listener, &state);
+        let mut client = connect(&listener, &state);
+
+        assert_eq!(
+            send(
+                &mut subscriber,
+                "*2\r\n$9\r\nSUBSCRIBE\r\n$19\r\n__keyspace@0__:Name\r\n"
+            ),
+            "*3\r\n$9\r\nsubscribe\r\n$19\r\n__keyspace@0__:Name\r\n:1\r\n"
+        );
+        assert_eq!(
+            send(
+                &mut client,
+                "*4\r\n$6\r\nCONFIG\r\n$3\r\nSET\r\n$22\r\nnotify-keyspace-events\r\n$3\r\nKg$\r\n"
+            ),
+            "+OK\r\n"
+        );
+        send(
+            &mut client,
+            "*3\r\n$3\r\nSET\r\n$4\r\nName\r\n$6\r\nFelipe\r\n",
+        );
+
+        let mut buffer = [0; 1024];
+        let size = subscriber.read(&mut buffer).unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&buffer[..size]),
+            "*3\r\n$7\r\nmessage\r\n$19\r\n__keyspace@0__:Name\r\n$3\r\nset\r\n"
+        );
+    }
+}
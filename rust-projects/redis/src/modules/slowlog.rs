@@ -0,0 +1,148 @@
+use std::{collections::VecDeque, time::Duration};
+
+use chrono::Utc;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlowLogEntry {
+    pub id: u64,
+    pub timestamp: i64,
+    pub duration_micros: u64,
+    pub args: Vec<String>,
+    pub client_addr: String,
+    pub client_name: String,
+}
+
+/// Bounded log of the commands that took longer than the configured threshold.
+#[derive(Debug, Default)]
+pub struct SlowLog {
+    entries: VecDeque<SlowLogEntry>,
+    next_id: u64,
+}
+
+impl SlowLog {
+    pub fn new() -> Self {
+        SlowLog {
+            entries: VecDeque::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Records a command execution if it was slower than `threshold_micros`.
+    ///
+    /// # Arguments
+    /// * `threshold_micros` - Minimum duration to be logged, negative values disable the log.
+    /// * `max_len` - Maximum number of entries kept, the oldest entries are dropped first.
+    /// * `duration` - How long the command took.
+    /// * `args` - The command name followed by its arguments.
+    /// * `client_addr` - Address of the client that issued the command.
+    /// * `client_name` - Name of the client set with `CLIENT SETNAME`, or empty.
+    ///
+    /// # Returns
+    /// `true` if the command was recorded.
+    pub fn record(
+        &mut self,
+        threshold_micros: i64,
+        max_len: usize,
+        duration: Duration,
+        args: Vec<String>,
+        client_addr: String,
+        client_name: String,
+    ) -> bool {
+        let duration_micros = duration.as_micros() as u64;
+
+        if threshold_micros < 0 || duration_micros < threshold_micros as u64 {
+            return false;
+        }
+
+        self.entries.push_front(SlowLogEntry {
+            id: self.next_id,
+            timestamp: Utc::now().timestamp(),
+            duration_micros,
+            args,
+            client_addr,
+            client_name,
+        });
+        self.next_id += 1;
+        self.entries.truncate(max_len);
+
+        true
+    }
+
+    /// Returns up to `count` entries, newest first.
+    pub fn get(&self, count: usize) -> Vec<&SlowLogEntry> {
+        self.entries.iter().take(count).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn reset(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(
+        slowlog: &mut SlowLog,
+        threshold: i64,
+        max_len: usize,
+        micros: u64,
+        name: &str,
+    ) -> bool {
+        slowlog.record(
+            threshold,
+            max_len,
+            Duration::from_micros(micros),
+            vec![name.to_string()],
+            "127.0.0.1:5000".to_string(),
+            String::new(),
+        )
+    }
+
+    #[test]
+    fn should_only_record_slow_commands() {
+        let mut slowlog = SlowLog::new();
+
+        assert!(!record(&mut slowlog, 100, 10, 99, "GET"));
+        assert!(record(&mut slowlog, 100, 10, 100, "SET"));
+        assert!(!record(&mut slowlog, -1, 10, 1_000_000, "DEL"));
+
+        assert_eq!(slowlog.len(), 1);
+        assert_eq!(slowlog.get(10)[0].args, vec!["SET".to_string()]);
+    }
+
+    #[test]
+    fn should_keep_newest_entries() {
+        let mut slowlog = SlowLog::new();
+
+        for name in ["first", "second", "third"] {
+            record(&mut slowlog, 0, 2, 1, name);
+        }
+
+        let entries = slowlog.get(10);
+        assert_eq!(entries.len(), 2);
+        assert_eq!((entries[0].id, entries[0].args[0].as_str()), (2, "third"));
+        assert_eq!((entries[1].id, entries[1].args[0].as_str()), (1, "second"));
+        assert_eq!(slowlog.get(1).len(), 1);
+    }
+
+    #[test]
+    fn should_reset() {
+        let mut slowlog = SlowLog::new();
+
+        record(&mut slowlog, 0, 10, 1, "GET");
+        slowlog.reset();
+
+        assert!(slowlog.is_empty());
+        record(&mut slowlog, 0, 10, 1, "GET");
+        assert_eq!(slowlog.get(1)[0].id, 1);
+    }
+}
@@ -1,4 +1,5 @@
 use std::io::Read;
+use std::sync::Arc;
 use std::{collections::HashMap, io::Write};
 
 use std::fs::{self, File};
@@ -9,11 +10,18 @@ use chrono::serde::ts_seconds_option;
 use chrono::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use super::string_array::{insert_on_array, ArrayPlacement};
+use super::{
+    notifications::{
+        KeyspaceNotifier, Notification, NOTIFY_EVICTED, NOTIFY_EXPIRED, NOTIFY_GENERIC, NOTIFY_LIST,
+        NOTIFY_STRING,
+    },
+    string_array::{insert_on_array, ArrayPlacement},
+    types::RedisString,
+};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RedisCell {
-    pub value: String,
+    pub value: RedisString,
     #[serde(with = "ts_seconds_option")]
     pub expiry: Option<DateTime<Utc>>,
 }
@@ -21,19 +29,125 @@ pub struct RedisCell {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Redis {
     map: HashMap<String, RedisCell>,
+    #[serde(skip)]
+    dirty: u64,
+    #[serde(skip, default = "Utc::now")]
+    last_save: DateTime<Utc>,
+    /// Memory limit in bytes as reported by `used_memory`, `0` disables eviction.
+    #[serde(skip)]
+    maxmemory: usize,
+    /// Running total of `cell_size` over the map, recomputed on `load`.
+    #[serde(skip)]
+    used_memory: usize,
+    #[serde(skip)]
+    notifier: Option<Arc<KeyspaceNotifier>>,
+    /// Events waiting to be published, see [`Redis::take_notifications`].
+    #[serde(skip)]
+    notifications: Vec<Notification>,
 }
 
 const REDIS_STORE_DIR: &str = "redis_store";
 
+/// Approximate number of bytes used by a key and its value.
+fn cell_size(key: &str, cell: &RedisCell) -> usize {
+    key.len() + cell.value.len() + std::mem::size_of::<RedisCell>()
+}
+
+impl Default for Redis {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Redis {
     pub fn new() -> Self {
         Redis {
             map: HashMap::new(),
+            dirty: 0,
+            last_save: Utc::now(),
+            maxmemory: 0,
+            used_memory: 0,
+            notifier: None,
+            notifications: Vec::new(),
+        }
+    }
+
+    /// Sets which keyspace notifications are recorded, according to the flags of `notifier`.
+    pub fn set_notifier(&mut self, notifier: Arc<KeyspaceNotifier>) {
+        self.notifier = Some(notifier);
+    }
+
+    /// Returns the keyspace events recorded since the last call.
+    ///
+    /// They are published by the caller once the store is unlocked, so that no subscriber is
+    /// written to while holding the store.
+    pub fn take_notifications(&mut self) -> Vec<Notification> {
+        std::mem::take(&mut self.notifications)
+    }
+
+    pub fn set_maxmemory(&mut self, maxmemory: usize) {
+        self.maxmemory = maxmemory;
+    }
+
+    fn notify(&mut self, class: u32, event: &str, key: &str) {
+        if self
+            .notifier
+            .as_ref()
+            .is_some_and(|notifier| notifier.is_enabled(class))
+        {
+            self.notifications.push(Notification {
+                class,
+                event: event.to_string(),
+                key: key.to_string(),
+            });
+        }
+    }
+
+    fn insert(&mut self, key: String, value: RedisCell) -> Option<RedisCell> {
+        self.dirty += 1;
+        self.used_memory += cell_size(&key, &value);
+        let previous = self.map.insert(key.clone(), value);
+        if let Some(previous) = &previous {
+            self.used_memory = self
+                .used_memory
+                .saturating_sub(cell_size(&key, previous));
+        }
+        self.evict_over(&key);
+        previous
+    }
+
+    fn remove(&mut self, key: &str) -> Option<RedisCell> {
+        let removed = self.map.remove(key);
+        if let Some(removed) = &removed {
+            self.dirty += 1;
+            self.used_memory = self.used_memory.saturating_sub(cell_size(key, removed));
+        }
+        removed
+    }
+
+    /// Evicts keys other than `keep` until the store fits in `maxmemory`.
+    fn evict_over(&mut self, keep: &str) {
+        while self.maxmemory > 0 && self.used_memory > self.maxmemory {
+            let Some(key) = self.map.keys().find(|key| *key != keep).cloned() else {
+                return;
+            };
+
+            self.evict(&key);
         }
     }
 
     pub fn set(&mut self, key: String, value: RedisCell) -> Option<RedisCell> {
-        self.map.insert(key, value)
+        self.write(key, value, "set")
+    }
+
+    /// Stores a string value on behalf of a command other than `SET`, e.g. `SETBIT` or `PFADD`.
+    ///
+    /// # Arguments
+    /// * `event` - The name of the keyspace event published for the write.
+    pub fn write(&mut self, key: String, value: RedisCell, event: &str) -> Option<RedisCell> {
+        let previous = self.insert(key.clone(), value);
+        self.notify(NOTIFY_STRING, event, &key);
+        previous
     }
 
     pub fn get(&mut self, key: &str) -> Option<&RedisCell> {
@@ -41,7 +155,8 @@ impl Redis {
             if expiry > Utc::now() {
                 self.map.get(key)
             } else {
-                self.delete(key);
+                self.remove(key);
+                self.notify(NOTIFY_EXPIRED, "expired", key);
                 None
             }
         } else {
@@ -50,7 +165,51 @@ impl Redis {
     }
 
     pub fn delete(&mut self, key: &str) -> Option<RedisCell> {
-        self.map.remove(key)
+        let removed = self.remove(key);
+        if removed.is_some() {
+            self.notify(NOTIFY_GENERIC, "del", key);
+        }
+        removed
+    }
+
+    /// Removes a key to free memory.
+    pub fn evict(&mut self, key: &str) -> Option<RedisCell> {
+        let removed = self.remove(key);
+        if removed.is_some() {
+            self.notify(NOTIFY_EVICTED, "evicted", key);
+        }
+        removed
+    }
+
+    /// Number of keys in the store, including expired keys that were not accessed yet.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Number of keys with an expiry set.
+    pub fn expires(&self) -> usize {
+        self.map
+            .values()
+            .filter(|cell| cell.expiry.is_some())
+            .count()
+    }
+
+    /// Approximate number of bytes used by the keys and values.
+    pub fn used_memory(&self) -> usize {
+        self.used_memory
+    }
+
+    /// Number of writes since the last `save`.
+    pub fn dirty(&self) -> u64 {
+        self.dirty
+    }
+
+    pub fn last_save(&self) -> DateTime<Utc> {
+        self.last_save
     }
 
     pub fn set_list(
@@ -59,36 +218,50 @@ impl Redis {
         value: String,
         placement: ArrayPlacement,
     ) -> Result<u32, String> {
+        let event = if placement == ArrayPlacement::LEFT {
+            "lpush"
+        } else {
+            "rpush"
+        };
+
         let redis_return = self.get(&key);
-        match redis_return {
+        let len = match redis_return {
             Some(cell) => {
-                let (new_value, len) = insert_on_array(cell.value.as_str(), &value, placement)?;
+                let array = cell
+                    .value
+                    .as_str()
+                    .ok_or_else(|| "The string it's not an array".to_string())?;
+                let (new_value, len) = insert_on_array(array, &value, placement)?;
                 let expiry = cell.expiry;
-                self.set(
-                    key,
+                self.insert(
+                    key.clone(),
                     RedisCell {
-                        value: new_value,
+                        value: new_value.into(),
                         expiry,
                     },
                 );
 
-                Ok(len)
+                len
             }
             None => {
-                self.set(
+                self.insert(
                     key.to_string(),
                     RedisCell {
-                        value: format!("[{}]", value),
+                        value: format!("[{}]", value).into(),
                         expiry: None,
                     },
                 );
 
-                Ok(1)
+                1
             }
-        }
+        };
+
+        self.notify(NOTIFY_LIST, event, &key);
+
+        Ok(len)
     }
 
-    pub fn save(&self) -> Result<(), std::io::Error> {
+    pub fn save(&mut self) -> Result<(), std::io::Error> {
         let serialized = serde_json::to_string(&self).unwrap();
 
         fs::create_dir_all(REDIS_STORE_DIR)?;
@@ -97,7 +270,10 @@ impl Redis {
         let path = Path::new(&path);
 
         let mut file = File::create(path)?;
-        file.write(serialized.as_bytes())?;
+        file.write_all(serialized.as_bytes())?;
+
+        self.dirty = 0;
+        self.last_save = Utc::now();
 
         Ok(())
     }
@@ -111,11 +287,21 @@ impl Redis {
         let mut serialized = String::new();
         file.read_to_string(&mut serialized)?;
 
-        Ok(serde_json::from_str::<Redis>(&serialized)?)
+        let mut redis = serde_json::from_str::<Redis>(&serialized)?;
+        redis.used_memory = redis
+            .map
+            .iter()
+            .map(|(key, cell)| cell_size(key, cell))
+            .sum();
+
+        Ok(redis)
     }
 
     pub fn replace_store(&mut self) -> Result<(), std::io::Error> {
-        let redis = Redis::load()?;
+        let mut redis = Redis::load()?;
+        redis.maxmemory = self.maxmemory;
+        redis.notifier = self.notifier.take();
+        redis.notifications = std::mem::take(&mut self.notifications);
         *self = redis;
 
         Ok(())
@@ -127,6 +313,10 @@ pub mod tests {
     use chrono::Duration;
 
     use super::*;
+    use crate::modules::{
+        notifications::parse_flags,
+        pubsub::{tests::Inbox, PubSub},
+    };
 
     #[test]
     fn it_should_succeed_get() {
@@ -134,7 +324,7 @@ pub mod tests {
 
         let key = "Name";
         let value = RedisCell {
-            value: String::from("Felipe"),
+            value: "Felipe".into(),
             expiry: None,
         };
 
@@ -150,15 +340,12 @@ pub mod tests {
         let key_set = "Name";
         let key_get = "Age";
         let value = RedisCell {
-            value: String::from("Felipe"),
+            value: "Felipe".into(),
             expiry: None,
         };
 
         redis.set(key_set.to_string(), value);
-        match redis.get(key_get) {
-            None => assert!(true),
-            _ => assert!(false),
-        }
+        assert!(redis.get(key_get).is_none());
     }
 
     #[test]
@@ -167,13 +354,13 @@ pub mod tests {
 
         let key = "Name";
         let value = RedisCell {
-            value: String::from("Felipe"),
+            value: "Felipe".into(),
             expiry: None,
         };
 
         redis.set(key.to_string(), value);
         let value = RedisCell {
-            value: String::from("Carlos"),
+            value: "Carlos".into(),
             expiry: None,
         };
 
@@ -190,16 +377,13 @@ pub mod tests {
 
         let key = "Name";
         let value = RedisCell {
-            value: String::from("Carlos"),
+            value: "Carlos".into(),
             expiry: Some(Utc::now() - Duration::seconds(10)),
         };
 
         redis.set(key.to_string(), value);
 
-        match redis.get(key) {
-            Some(_) => assert!(false),
-            None => assert!(true),
-        }
+        assert!(redis.get(key).is_none());
     }
 
     #[test]
@@ -208,16 +392,13 @@ pub mod tests {
 
         let key = "Name";
         let value = RedisCell {
-            value: String::from("Carlos"),
+            value: "Carlos".into(),
             expiry: Some(Utc::now() + Duration::hours(1)),
         };
 
         redis.set(key.to_string(), value);
 
-        match redis.get(key) {
-            Some(_) => assert!(true),
-            None => assert!(false),
-        }
+        assert!(redis.get(key).is_some());
     }
 
     #[test]
@@ -255,7 +436,7 @@ pub mod tests {
         redis.set(
             "arr".to_string(),
             RedisCell {
-                value: "first".to_string(),
+                value: "first".into(),
                 expiry: None,
             },
         );
@@ -272,7 +453,7 @@ pub mod tests {
         redis.set(
             "first".to_string(),
             RedisCell {
-                value: "1".to_string(),
+                value: "1".into(),
                 expiry: None,
             },
         );
@@ -280,17 +461,15 @@ pub mod tests {
         redis.set(
             "second".to_string(),
             RedisCell {
-                value: "2".to_string(),
-                expiry: Some(DateTime::from(
-                    Utc.timestamp_opt((10 as i64).pow(10), 0).unwrap(),
-                )),
+                value: "2".into(),
+                expiry: Some(Utc.timestamp_opt(10_i64.pow(10), 0).unwrap()),
             },
         );
 
         redis.set(
             "third".to_string(),
             RedisCell {
-                value: "3".to_string(),
+                value: "3".into(),
                 expiry: None,
             },
         );
@@ -298,8 +477,8 @@ pub mod tests {
         redis.set(
             "fourth".to_string(),
             RedisCell {
-                value: "4".to_string(),
-                expiry: Some(DateTime::from(Utc.timestamp_opt(1_000_000, 0).unwrap())),
+                value: "4".into(),
+                expiry: Some(Utc.timestamp_opt(1_000_000, 0).unwrap()),
             },
         );
 
@@ -313,9 +492,7 @@ pub mod tests {
         let second = redis_deserialized.map.get("second").unwrap();
         assert_eq!("2", second.value);
         assert_eq!(
-            Some(DateTime::from(
-                Utc.timestamp_opt((10 as i64).pow(10), 0).unwrap(),
-            )),
+            Some(Utc.timestamp_opt(10_i64.pow(10), 0).unwrap()),
             second.expiry
         );
 
@@ -326,13 +503,198 @@ pub mod tests {
         let fourth = redis_deserialized.map.get("fourth").unwrap();
         assert_eq!("4", fourth.value);
         assert_eq!(
-            Some(DateTime::from(Utc.timestamp_opt(1_000_000, 0).unwrap())),
+            Some(Utc.timestamp_opt(1_000_000, 0).unwrap()),
             fourth.expiry
         );
 
         assert_eq!(redis_deserialized.map.len(), 4);
     }
 
+    #[test]
+    fn should_track_dirty_keys_and_expires() {
+        let mut redis = Redis::new();
+
+        redis.set(
+            "Name".to_string(),
+            RedisCell {
+                value: "Felipe".into(),
+                expiry: None,
+            },
+        );
+        redis.set(
+            "Session".to_string(),
+            RedisCell {
+                value: "token".into(),
+                expiry: Some(Utc::now() + Duration::hours(1)),
+            },
+        );
+        redis.delete("Missing");
+        redis.delete("Name");
+
+        assert_eq!(redis.len(), 1);
+        assert_eq!(redis.expires(), 1);
+        assert_eq!(redis.dirty(), 3);
+        assert_eq!(
+            redis.used_memory(),
+            "Session".len() + "token".len() + std::mem::size_of::<RedisCell>()
+        );
+
+        // Overwriting a key only counts its new value.
+        redis.set(
+            "Session".to_string(),
+            RedisCell {
+                value: "longer token".into(),
+                expiry: None,
+            },
+        );
+        assert_eq!(
+            redis.used_memory(),
+            "Session".len() + "longer token".len() + std::mem::size_of::<RedisCell>()
+        );
+    }
+
+    fn notified_redis(flags: &str) -> (Redis, Arc<KeyspaceNotifier>, Inbox) {
+        let pubsub = Arc::new(PubSub::new());
+        let (outbox, inbox) = Inbox::new();
+        pubsub.attach(1, outbox);
+        pubsub.psubscribe(1, &["__keyevent@0__:*".to_string()]);
+
+        let notifier = Arc::new(KeyspaceNotifier::new(Arc::clone(&pubsub)));
+        notifier.set_flags(parse_flags(flags).unwrap());
+
+        let mut redis = Redis::new();
+        redis.set_notifier(Arc::clone(&notifier));
+
+        (redis, notifier, inbox)
+    }
+
+    fn keyevent(event: &str, key: &str) -> String {
+        let channel = format!("__keyevent@0__:{}", event);
+
+        format!(
+            "*4\r\n$8\r\npmessage\r\n$16\r\n__keyevent@0__:*\r\n${}\r\n{}\r\n${}\r\n{}\r\n",
+            channel.len(),
+            channel,
+            key.len(),
+            key
+        )
+    }
+
+    #[test]
+    fn should_notify_mutations() {
+        let (mut redis, notifier, inbox) = notified_redis("EA");
+
+        redis.set(
+            "Name".to_string(),
+            RedisCell {
+                value: "Felipe".into(),
+                expiry: None,
+            },
+        );
+        redis
+            .set_list(
+                "Friends".to_string(),
+                "Marcelo".to_string(),
+                ArrayPlacement::LEFT,
+            )
+            .unwrap();
+        redis
+            .set_list(
+                "Friends".to_string(),
+                "Carlos".to_string(),
+                ArrayPlacement::RIGHT,
+            )
+            .unwrap();
+        redis.delete("Name");
+        redis.delete("Missing");
+
+        assert!(inbox.take().is_empty());
+        notifier.publish(redis.take_notifications());
+        assert_eq!(
+            inbox.take(),
+            [
+                keyevent("set", "Name"),
+                keyevent("lpush", "Friends"),
+                keyevent("rpush", "Friends"),
+                keyevent("del", "Name"),
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn should_notify_expired_keys() {
+        let (mut redis, notifier, inbox) = notified_redis("Ex");
+
+        redis.set(
+            "Session".to_string(),
+            RedisCell {
+                value: "token".into(),
+                expiry: Some(Utc::now() - Duration::seconds(1)),
+            },
+        );
+
+        assert!(redis.get("Session").is_none());
+        notifier.publish(redis.take_notifications());
+        assert_eq!(inbox.take(), keyevent("expired", "Session"));
+    }
+
+    #[test]
+    fn should_evict_keys_over_maxmemory() {
+        let (mut redis, notifier, inbox) = notified_redis("Ee");
+        redis.set_maxmemory("Name".len() + "Felipe".len() + std::mem::size_of::<RedisCell>());
+
+        redis.set(
+            "Name".to_string(),
+            RedisCell {
+                value: "Felipe".into(),
+                expiry: None,
+            },
+        );
+        redis.set(
+            "City".to_string(),
+            RedisCell {
+                value: "Recife".into(),
+                expiry: None,
+            },
+        );
+
+        assert!(redis.get("Name").is_none());
+        assert_eq!(redis.get("City").unwrap().value, "Recife");
+        notifier.publish(redis.take_notifications());
+        assert_eq!(inbox.take(), keyevent("evicted", "Name"));
+    }
+
+    #[test]
+    fn should_serialize_and_deserialize_binary_values() {
+        let mut redis = Redis::new();
+
+        redis.set(
+            "bits".to_string(),
+            RedisCell {
+                value: vec![0xff, 0x00, 0x80].into(),
+                expiry: None,
+            },
+        );
+        redis.set(
+            "Name".to_string(),
+            RedisCell {
+                value: "Felipe".into(),
+                expiry: None,
+            },
+        );
+
+        let serialized = serde_json::to_string(&redis).unwrap();
+        assert!(serialized.contains("\"Felipe\""));
+
+        let mut redis_deserialized: Redis = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(
+            redis_deserialized.get("bits").unwrap().value.as_bytes(),
+            &[0xff, 0x00, 0x80]
+        );
+        assert_eq!(redis_deserialized.get("Name").unwrap().value, "Felipe");
+    }
+
     #[test]
     fn should_serialize_and_deserialize_empty() {
         let redis = Redis::new();
@@ -352,7 +714,7 @@ pub mod tests {
         redis.set(
             "Name".to_string(),
             RedisCell {
-                value: "Felipe".to_string(),
+                value: "Felipe".into(),
                 expiry: None,
             },
         );
@@ -360,7 +722,7 @@ pub mod tests {
         redis.set(
             "BirthDate".to_string(),
             RedisCell {
-                value: "02/09/1980".to_string(),
+                value: "02/09/1980".into(),
                 expiry: Some(Utc.timestamp_opt(100_000_000_000, 0).unwrap()),
             },
         );
@@ -389,8 +751,10 @@ pub mod tests {
             )
             .unwrap();
 
+        let used_memory = redis.used_memory();
         redis.save().unwrap();
         let mut redis = Redis::load().unwrap();
+        assert_eq!(redis.used_memory(), used_memory);
 
         let name = redis.get("Name").unwrap();
         assert_eq!("Felipe", name.value);
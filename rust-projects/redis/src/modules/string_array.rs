@@ -20,10 +20,10 @@ pub fn insert_on_array(
         let s = array.trim();
         let mut s: Vec<&str> = s[1..s.len() - 1]
             .split(",")
-            .filter(|v| v.len() > 0)
+            .filter(|v| !v.is_empty())
             .collect();
 
-        if value == "" {
+        if value.is_empty() {
             return Ok((array.to_string(), s.len() as u32));
         }
 
@@ -1,8 +1,195 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
 #[derive(Debug, PartialEq)]
 pub enum RedisDeserializationTypes {
     SimpleString(String),
     ErrorMessage(String),
     Integer(i64),
-    BulkString(String),
+    BulkString(RedisString),
     Array(Box<Vec<RedisDeserializationTypes>>),
 }
+
+impl RedisDeserializationTypes {
+    /// Flattens a value into plain strings, as used for logging a command and its arguments.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use redis::modules::types::RedisDeserializationTypes;
+    ///
+    /// let command = RedisDeserializationTypes::Array(Box::new(vec![
+    ///     RedisDeserializationTypes::BulkString("INCR".into()),
+    ///     RedisDeserializationTypes::Integer(1),
+    /// ]));
+    /// assert_eq!(command.to_args(), vec!["INCR".to_string(), "1".to_string()]);
+    /// ```
+    pub fn to_args(&self) -> Vec<String> {
+        match self {
+            RedisDeserializationTypes::SimpleString(value)
+            | RedisDeserializationTypes::ErrorMessage(value) => vec![value.to_string()],
+            RedisDeserializationTypes::BulkString(value) => vec![value.to_string()],
+            RedisDeserializationTypes::Integer(value) => vec![value.to_string()],
+            RedisDeserializationTypes::Array(values) => {
+                values.iter().flat_map(|value| value.to_args()).collect()
+            }
+        }
+    }
+
+    /// Flattens a value like [`to_args`](Self::to_args), keeping bulk strings as sent instead
+    /// of replacing the bytes that are not valid UTF-8.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use redis::modules::types::RedisDeserializationTypes;
+    ///
+    /// let command = RedisDeserializationTypes::Array(Box::new(vec![
+    ///     RedisDeserializationTypes::BulkString("SET".into()),
+    ///     RedisDeserializationTypes::BulkString(vec![0xff].into()),
+    /// ]));
+    /// assert_eq!(command.to_args()[1], "\u{fffd}");
+    /// assert_eq!(command.to_raw_args()[1].as_bytes(), &[0xff]);
+    /// ```
+    pub fn to_raw_args(&self) -> Vec<RedisString> {
+        match self {
+            RedisDeserializationTypes::BulkString(value) => vec![value.clone()],
+            RedisDeserializationTypes::Array(values) => {
+                values.iter().flat_map(|value| value.to_raw_args()).collect()
+            }
+            value => value.to_args().into_iter().map(RedisString::from).collect(),
+        }
+    }
+}
+
+/// Binary-safe string value stored in the keyspace, also used for the replies sent to clients.
+///
+/// Values are usually UTF-8 text, but commands such as `SETBIT` and `PFADD` store arbitrary bytes,
+/// and clients may `SET` any bytes.
+/// UTF-8 values are persisted as JSON strings and other values as byte arrays.
+///
+/// # Example
+///
+/// ```
+/// use redis::modules::types::RedisString;
+///
+/// let text = RedisString::from("Felipe");
+/// assert_eq!(text, "Felipe");
+/// assert_eq!(text.as_str(), Some("Felipe"));
+///
+/// let bytes = RedisString::from(vec![0xff, 0x00]);
+/// assert_eq!(bytes.as_str(), None);
+/// assert_eq!(bytes.as_bytes(), &[0xff, 0x00]);
+/// ```
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+pub struct RedisString(Vec<u8>);
+
+impl RedisString {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+
+    /// Returns the value as text, or `None` if it is not valid UTF-8.
+    pub fn as_str(&self) -> Option<&str> {
+        std::str::from_utf8(&self.0).ok()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<Vec<u8>> for RedisString {
+    fn from(bytes: Vec<u8>) -> Self {
+        RedisString(bytes)
+    }
+}
+
+impl From<String> for RedisString {
+    fn from(value: String) -> Self {
+        RedisString(value.into_bytes())
+    }
+}
+
+impl From<&str> for RedisString {
+    fn from(value: &str) -> Self {
+        RedisString(value.as_bytes().to_vec())
+    }
+}
+
+impl std::fmt::Display for RedisString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", String::from_utf8_lossy(&self.0))
+    }
+}
+
+impl std::fmt::Debug for RedisString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.as_str() {
+            Some(value) => write!(f, "{:?}", value),
+            None => write!(f, "{:?}", self.0),
+        }
+    }
+}
+
+impl PartialEq<str> for RedisString {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other.as_bytes()
+    }
+}
+
+impl PartialEq<&str> for RedisString {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == other.as_bytes()
+    }
+}
+
+impl PartialEq<String> for RedisString {
+    fn eq(&self, other: &String) -> bool {
+        self.0 == other.as_bytes()
+    }
+}
+
+impl PartialEq<RedisString> for &str {
+    fn eq(&self, other: &RedisString) -> bool {
+        self.as_bytes() == other.0
+    }
+}
+
+impl PartialEq<RedisString> for String {
+    fn eq(&self, other: &RedisString) -> bool {
+        self.as_bytes() == other.0
+    }
+}
+
+impl Serialize for RedisString {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.as_str() {
+            Some(value) => serializer.serialize_str(value),
+            None => self.0.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for RedisString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Text(String),
+            Bytes(Vec<u8>),
+        }
+
+        Ok(match Repr::deserialize(deserializer)? {
+            Repr::Text(value) => value.into(),
+            Repr::Bytes(bytes) => bytes.into(),
+        })
+    }
+}
@@ -0,0 +1,280 @@
+use std::time::Duration;
+use std::vec;
+
+use bytes::Bytes;
+use mini_redis::Frame;
+
+/// A command sent by a client.
+///
+/// `mini_redis::Command` keeps the arguments of most commands private, so the server
+/// parses frames itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Get {
+        key: String,
+    },
+    Set {
+        key: String,
+        value: Bytes,
+        expire: Option<Duration>,
+    },
+    Publish {
+        channel: String,
+        message: Bytes,
+    },
+    Subscribe {
+        channels: Vec<String>,
+    },
+    /// Unsubscribes from every channel when `channels` is empty.
+    Unsubscribe {
+        channels: Vec<String>,
+    },
+    Ping {
+        message: Option<Bytes>,
+    },
+    Unknown {
+        name: String,
+    },
+}
+
+impl Command {
+    /// Reads a command from an array frame.
+    ///
+    /// The error is the message of the error frame to answer with.
+    pub fn from_frame(frame: Frame) -> Result<Command, String> {
+        let mut parse = Parse::new(frame)?;
+
+        let command = match parse.name.as_str() {
+            "get" => Command::Get {
+                key: parse.next_string()?,
+            },
+            "set" => {
+                let key = parse.next_string()?;
+                let value = parse.next_bytes()?;
+                let expire = match parse.try_next_string()? {
+                    None => None,
+                    Some(option) => {
+                        let amount = parse.next_int()?;
+                        let millis = match option.to_lowercase().as_str() {
+                            "ex" => amount.checked_mul(1000),
+                            "px" => Some(amount),
+                            _ => return Err("ERR syntax error".to_string()),
+                        };
+                        // As Redis, keeps the deadline within the range of a signed timestamp.
+                        match millis {
+                            Some(millis) if millis > 0 && millis <= i64::MAX as u64 => {
+                                Some(Duration::from_millis(millis))
+                            }
+                            _ => return Err("ERR invalid expire time in 'set' command".to_string()),
+                        }
+                    }
+                };
+                Command::Set { key, value, expire }
+            }
+            "publish" => Command::Publish {
+                channel: parse.next_string()?,
+                message: parse.next_bytes()?,
+            },
+            "subscribe" => {
+                let channels = parse.rest()?;
+                if channels.is_empty() {
+                    return Err(parse.wrong_arguments());
+                }
+                Command::Subscribe { channels }
+            }
+            "unsubscribe" => Command::Unsubscribe {
+                channels: parse.rest()?,
+            },
+            "ping" => Command::Ping {
+                message: parse.try_next_bytes()?,
+            },
+            _ => return Ok(Command::Unknown { name: parse.name }),
+        };
+
+        parse.finish()?;
+        Ok(command)
+    }
+}
+
+/// Cursor over the arguments of a command.
+struct Parse {
+    name: String,
+    parts: vec::IntoIter<Frame>,
+}
+
+impl Parse {
+    fn new(frame: Frame) -> Result<Parse, String> {
+        let mut parse = match frame {
+            Frame::Array(parts) if !parts.is_empty() => Parse {
+                name: String::new(),
+                parts: parts.into_iter(),
+            },
+            frame => {
+                return Err(format!(
+                    "ERR protocol error; expected a non-empty array, got {:?}",
+                    frame
+                ))
+            }
+        };
+        parse.name = parse.next_string()?.to_lowercase();
+        Ok(parse)
+    }
+
+    fn try_next_bytes(&mut self) -> Result<Option<Bytes>, String> {
+        match self.parts.next() {
+            None => Ok(None),
+            Some(Frame::Bulk(data)) => Ok(Some(data)),
+            Some(Frame::Simple(s)) => Ok(Some(Bytes::from(s))),
+            Some(Frame::Integer(n)) => Ok(Some(Bytes::from(n.to_string()))),
+            Some(frame) => Err(format!(
+                "ERR protocol error; expected a bulk string, got {:?}",
+                frame
+            )),
+        }
+    }
+
+    fn try_next_string(&mut self) -> Result<Option<String>, String> {
+        match self.try_next_bytes()? {
+            None => Ok(None),
+            Some(data) => String::from_utf8(data.to_vec())
+                .map(Some)
+                .map_err(|_| "ERR protocol error; invalid string".to_string()),
+        }
+    }
+
+    fn next_bytes(&mut self) -> Result<Bytes, String> {
+        self.try_next_bytes()?.ok_or_else(|| self.wrong_arguments())
+    }
+
+    fn next_string(&mut self) -> Result<String, String> {
+        self.try_next_string()?
+            .ok_or_else(|| self.wrong_arguments())
+    }
+
+    /// Reads an integer, sent either as an integer frame or as a string.
+    fn next_int(&mut self) -> Result<u64, String> {
+        let data = match self.parts.next() {
+            Some(Frame::Integer(n)) => return Ok(n),
+            Some(Frame::Bulk(data)) => data,
+            Some(Frame::Simple(s)) => Bytes::from(s),
+            Some(frame) => {
+                return Err(format!(
+                    "ERR protocol error; expected an integer, got {:?}",
+                    frame
+                ))
+            }
+            None => return Err(self.wrong_arguments()),
+        };
+
+        std::str::from_utf8(&data)
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| "ERR value is not an integer or out of range".to_string())
+    }
+
+    /// Reads the remaining arguments as strings.
+    fn rest(&mut self) -> Result<Vec<String>, String> {
+        let mut ret = Vec::new();
+        while let Some(s) = self.try_next_string()? {
+            ret.push(s);
+        }
+        Ok(ret)
+    }
+
+    /// Checks that every argument was read.
+    fn finish(&mut self) -> Result<(), String> {
+        match self.parts.next() {
+            None => Ok(()),
+            Some(_) => Err(self.wrong_arguments()),
+        }
+    }
+
+    fn wrong_arguments(&self) -> String {
+        format!("ERR wrong number of arguments for '{}' command", self.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn array(parts: &[&str]) -> Frame {
+        Frame::Array(
+            parts
+                .iter()
+                .map(|part| Frame::Bulk(Bytes::from(part.to_string())))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_from_frame() {
+        assert_eq!(
+            Command::from_frame(array(&["SET", "k", "v", "EX", "10"])),
+            Ok(Command::Set {
+                key: "k".to_string(),
+                value: Bytes::from("v"),
+                expire: Some(Duration::from_secs(10)),
+            })
+        );
+        // The mini-redis client sends the expiry as an integer frame.
+        let mut frame = array(&["set", "k", "v", "px"]);
+        if let Frame::Array(parts) = &mut frame {
+            parts.push(Frame::Integer(1500));
+        }
+        assert!(matches!(
+            Command::from_frame(frame),
+            Ok(Command::Set { expire: Some(expire), .. }) if expire == Duration::from_millis(1500)
+        ));
+        assert_eq!(
+            Command::from_frame(array(&["subscribe", "a", "b"])),
+            Ok(Command::Subscribe {
+                channels: vec!["a".to_string(), "b".to_string()],
+            })
+        );
+        assert_eq!(
+            Command::from_frame(array(&["Ping"])),
+            Ok(Command::Ping { message: None })
+        );
+        assert_eq!(
+            Command::from_frame(array(&["flushall", "now"])),
+            Ok(Command::Unknown {
+                name: "flushall".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_from_frame_errors() {
+        assert_eq!(
+            Command::from_frame(array(&["get"])),
+            Err("ERR wrong number of arguments for 'get' command".to_string())
+        );
+        assert_eq!(
+            Command::from_frame(array(&["get", "a", "b"])),
+            Err("ERR wrong number of arguments for 'get' command".to_string())
+        );
+        assert!(Command::from_frame(array(&["subscribe"])).is_err());
+        assert!(Command::from_frame(array(&["set", "k", "v", "ex", "soon"])).is_err());
+        assert!(Command::from_frame(array(&["set", "k", "v", "xx", "1"])).is_err());
+        for expire in [
+            &["ex", "0"][..],
+            &["px", "0"],
+            &["ex", "18446744073709551615"],
+            &["ex", "9223372036854776"],
+            &["px", "9223372036854775808"],
+        ] {
+            let mut parts = vec!["set", "k", "v"];
+            parts.extend(expire);
+            assert_eq!(
+                Command::from_frame(array(&parts)),
+                Err("ERR invalid expire time in 'set' command".to_string())
+            );
+        }
+        assert!(
+            Command::from_frame(array(&["set", "k", "v", "px", "9223372036854775807"])).is_ok()
+        );
+        assert!(Command::from_frame(array(&[])).is_err());
+        assert!(Command::from_frame(Frame::Simple("get".to_string())).is_err());
+    }
+}
@@ -56,37 +56,134 @@ impl Connection {
     }
 
     pub async fn write_frame(&mut self, frame: &Frame) -> io::Result<()> {
-        match frame {
-            Frame::Simple(val) => {
-                self.stream.write_all(b"+").await?;
-                self.stream.write_all(val.as_bytes()).await?;
-                self.stream.write_all(b"\r\n").await?;
-            }
-            Frame::Error(val) => {
-                self.stream.write_all(b"-").await?;
-                self.stream.write_all(val.as_bytes()).await?;
-                self.stream.write_all(b"\r\n").await?;
-            }
-            Frame::Integer(val) => {
-                self.stream.write_all(b":").await?;
-                self.stream.write_u64(*val).await?;
-            }
-            Frame::Null => {
-                self.stream.write_all(b"$-1\r\n").await?;
-            }
-            Frame::Bulk(val) => {
-                let len = val.len();
+        let mut buf = Vec::new();
+        encode(frame, &mut buf);
+
+        self.stream.write_all(&buf).await?;
+        self.stream.flush().await?;
+
+        Ok(())
+    }
+}
 
-                self.stream.write_all(b"$").await?;
-                self.stream.write_u64(len as u64).await?;
-                self.stream.write_all(val).await?;
-                self.stream.write_all(b"\r\n").await?;
+/// Appends the RESP encoding of `frame` to `dst`. Arrays may be nested.
+fn encode(frame: &Frame, dst: &mut Vec<u8>) {
+    match frame {
+        Frame::Simple(val) => {
+            dst.push(b'+');
+            dst.extend_from_slice(val.as_bytes());
+            dst.extend_from_slice(b"\r\n");
+        }
+        Frame::Error(val) => {
+            dst.push(b'-');
+            dst.extend_from_slice(val.as_bytes());
+            dst.extend_from_slice(b"\r\n");
+        }
+        Frame::Integer(val) => {
+            dst.push(b':');
+            encode_decimal(*val, dst);
+        }
+        Frame::Null => {
+            dst.extend_from_slice(b"$-1\r\n");
+        }
+        Frame::Bulk(val) => {
+            dst.push(b'$');
+            encode_decimal(val.len() as u64, dst);
+            dst.extend_from_slice(val);
+            dst.extend_from_slice(b"\r\n");
+        }
+        Frame::Array(frames) => {
+            dst.push(b'*');
+            encode_decimal(frames.len() as u64, dst);
+            for frame in frames {
+                encode(frame, dst);
             }
-            Frame::Array(_) => unimplemented!(),
         }
+    }
+}
 
-        self.stream.flush().await?;
+/// Writes `val` as ASCII digits followed by `\r\n`.
+fn encode_decimal(val: u64, dst: &mut Vec<u8>) {
+    dst.extend_from_slice(val.to_string().as_bytes());
+    dst.extend_from_slice(b"\r\n");
+}
 
-        Ok(())
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    fn frames() -> Vec<Frame> {
+        vec![
+            Frame::Simple("OK".to_string()),
+            Frame::Error("ERR unknown command".to_string()),
+            Frame::Integer(0),
+            Frame::Integer(u64::MAX),
+            Frame::Null,
+            Frame::Bulk(Bytes::new()),
+            Frame::Bulk(Bytes::from_static(b"binary\r\n\0data")),
+            Frame::Array(vec![]),
+            Frame::Array(vec![
+                Frame::Bulk(Bytes::from_static(b"message")),
+                Frame::Array(vec![Frame::Integer(42), Frame::Null]),
+                Frame::Simple("nested".to_string()),
+            ]),
+        ]
+    }
+
+    /// Returns a connection writing to the other one.
+    async fn pair() -> (Connection, Connection) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+
+        (Connection::new(client), Connection::new(server))
+    }
+
+    #[test]
+    fn test_encode() {
+        let mut buf = Vec::new();
+        encode(&Frame::Integer(1234), &mut buf);
+        assert_eq!(buf, b":1234\r\n");
+
+        buf.clear();
+        encode(&Frame::Bulk(Bytes::from_static(b"hello")), &mut buf);
+        assert_eq!(buf, b"$5\r\nhello\r\n");
+
+        buf.clear();
+        encode(
+            &Frame::Array(vec![
+                Frame::Simple("a".to_string()),
+                Frame::Array(vec![Frame::Integer(1)]),
+            ]),
+            &mut buf,
+        );
+        assert_eq!(buf, b"*2\r\n+a\r\n*1\r\n:1\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_round_trip() {
+        let (mut writer, mut reader) = pair().await;
+
+        for frame in frames() {
+            writer.write_frame(&frame).await.unwrap();
+            let read = reader.read_stream().await.unwrap().unwrap();
+            assert_eq!(format!("{:?}", read), format!("{:?}", frame));
+        }
+
+        // Frames written back to back are read one at a time.
+        for frame in frames() {
+            writer.write_frame(&frame).await.unwrap();
+        }
+        drop(writer);
+        for frame in frames() {
+            let read = reader.read_stream().await.unwrap().unwrap();
+            assert_eq!(format!("{:?}", read), format!("{:?}", frame));
+        }
+        assert!(reader.read_stream().await.unwrap().is_none());
     }
 }
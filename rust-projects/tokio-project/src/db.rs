@@ -0,0 +1,344 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashMap};
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use tokio::sync::{broadcast, Notify};
+use tokio::time::{self, Duration, Instant};
+
+/// Messages a slow subscriber may fall behind before it starts missing some.
+const CHANNEL_CAPACITY: usize = 1024;
+
+type Shard = Mutex<HashMap<String, Entry>>;
+
+struct Entry {
+    data: Bytes,
+    expires_at: Option<Instant>,
+}
+
+impl Entry {
+    fn is_expired(&self, now: Instant) -> bool {
+        self.expires_at.is_some_and(|at| at <= now)
+    }
+}
+
+/// Key-value store sharded by key, and the pub/sub channels. Cloning is cheap.
+///
+/// Expired keys are removed by a background task, see [`DbDropGuard`].
+#[derive(Clone)]
+pub struct Db {
+    shared: Arc<Shared>,
+}
+
+/// Owns the [`Db`] of a server and stops its purge task when dropped.
+pub struct DbDropGuard {
+    db: Db,
+}
+
+struct Shared {
+    shards: Vec<Shard>,
+    /// Deadlines of the keys of every shard, soonest first.
+    ///
+    /// Locked after a shard when both are needed, never the other way around.
+    expirations: Mutex<BTreeSet<(Instant, String)>>,
+    pub_sub: Mutex<HashMap<String, broadcast::Sender<Bytes>>>,
+    /// Wakes up the purge task when the next deadline changes or on shutdown.
+    background_task: Notify,
+    shutdown: AtomicBool,
+}
+
+fn hash<T: Hash>(t: T) -> u64 {
+    let mut s = DefaultHasher::new();
+    t.hash(&mut s);
+    s.finish()
+}
+
+impl DbDropGuard {
+    /// Creates a db and spawns its purge task, which must happen inside a Tokio runtime.
+    pub fn new(num_shards: usize) -> DbDropGuard {
+        DbDropGuard {
+            db: Db::new(num_shards),
+        }
+    }
+
+    pub fn db(&self) -> Db {
+        self.db.clone()
+    }
+}
+
+impl Drop for DbDropGuard {
+    fn drop(&mut self) {
+        self.db.shared.shutdown.store(true, Ordering::SeqCst);
+        self.db.shared.background_task.notify_one();
+    }
+}
+
+impl Db {
+    fn new(num_shards: usize) -> Db {
+        let mut shards = Vec::with_capacity(num_shards);
+
+        for _ in 0..num_shards {
+            shards.push(Mutex::new(HashMap::new()));
+        }
+
+        let shared = Arc::new(Shared {
+            shards,
+            expirations: Mutex::new(BTreeSet::new()),
+            pub_sub: Mutex::new(HashMap::new()),
+            background_task: Notify::new(),
+            shutdown: AtomicBool::new(false),
+        });
+        tokio::spawn(purge_expired_keys(shared.clone()));
+
+        Db { shared }
+    }
+
+    /// Returns the value of `key`, `None` if it is missing or expired.
+    pub fn get(&self, key: &str) -> Option<Bytes> {
+        let shard = self.shared.shard(key).lock().unwrap();
+
+        match shard.get(key) {
+            // Expired but not purged yet.
+            Some(entry) if entry.is_expired(Instant::now()) => None,
+            Some(entry) => Some(entry.data.clone()),
+            None => None,
+        }
+    }
+
+    /// Sets `key` to `value`, replacing any previous value and expiry. A key expiring too far
+    /// in the future for the clock never expires.
+    pub fn set(&self, key: String, value: Bytes, expire: Option<Duration>) {
+        let expires_at = expire.and_then(|expire| Instant::now().checked_add(expire));
+        let mut shard = self.shared.shard(&key).lock().unwrap();
+
+        let previous = shard.insert(
+            key.clone(),
+            Entry {
+                data: value,
+                expires_at,
+            },
+        );
+
+        let previous = previous.and_then(|entry| entry.expires_at);
+        if previous.is_none() && expires_at.is_none() {
+            return;
+        }
+
+        let mut expirations = self.shared.expirations.lock().unwrap();
+        if let Some(when) = previous {
+            expirations.remove(&(when, key.clone()));
+        }
+        if let Some(when) = expires_at {
+            let next = expirations.first().is_none_or(|(first, _)| when < *first);
+            expirations.insert((when, key));
+            if next {
+                self.shared.background_task.notify_one();
+            }
+        }
+    }
+
+    /// Returns a receiver for the messages published to `channel` from now on.
+    pub fn subscribe(&self, channel: String) -> broadcast::Receiver<Bytes> {
+        let mut pub_sub = self.shared.pub_sub.lock().unwrap();
+
+        match pub_sub.get(&channel) {
+            Some(tx) => tx.subscribe(),
+            None => {
+                let (tx, rx) = broadcast::channel(CHANNEL_CAPACITY);
+                pub_sub.insert(channel, tx);
+                rx
+            }
+        }
+    }
+
+    /// Publishes `message` to `channel` and returns the number of subscribers that got it.
+    pub fn publish(&self, channel: &str, message: Bytes) -> usize {
+        let mut pub_sub = self.shared.pub_sub.lock().unwrap();
+
+        let Some(tx) = pub_sub.get(channel) else {
+            return 0;
+        };
+        match tx.send(message) {
+            Ok(receivers) => receivers,
+            Err(_) => {
+                // Every subscriber is gone.
+                pub_sub.remove(channel);
+                0
+            }
+        }
+    }
+}
+
+impl Shared {
+    fn shard(&self, key: &str) -> &Shard {
+        &self.shards[hash(key) as usize % self.shards.len()]
+    }
+
+    /// Removes the expired keys and returns the next deadline, if any.
+    fn purge_expired_keys(&self) -> Option<Instant> {
+        let now = Instant::now();
+
+        let mut due = Vec::new();
+        let next = {
+            let mut expirations = self.expirations.lock().unwrap();
+            while let Some((when, _)) = expirations.first() {
+                if *when > now {
+                    break;
+                }
+                due.push(expirations.pop_first().unwrap());
+            }
+            expirations.first().map(|(when, _)| *when)
+        };
+
+        for (when, key) in due {
+            let mut shard = self.shard(&key).lock().unwrap();
+            // The key may have been set again since its deadline was taken out.
+            if shard
+                .get(&key)
+                .is_some_and(|entry| entry.expires_at == Some(when))
+            {
+                shard.remove(&key);
+            }
+        }
+
+        next
+    }
+}
+
+/// Sleeps until the next deadline, purges the expired keys and starts over, until the
+/// [`DbDropGuard`] is dropped.
+async fn purge_expired_keys(shared: Arc<Shared>) {
+    while !shared.shutdown.load(Ordering::SeqCst) {
+        match shared.purge_expired_keys() {
+            Some(when) => {
+                tokio::select! {
+                    _ = time::sleep_until(when) => {}
+                    _ = shared.background_task.notified() => {}
+                }
+            }
+            None => shared.background_task.notified().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn len(db: &Db) -> usize {
+        let shards = &db.shared.shards;
+        shards.iter().map(|shard| shard.lock().unwrap().len()).sum()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_expiry() {
+        let guard = DbDropGuard::new(4);
+        let db = guard.db();
+        db.set(
+            "a".to_string(),
+            Bytes::from("1"),
+            Some(Duration::from_secs(10)),
+        );
+        db.set("b".to_string(), Bytes::from("2"), None);
+
+        tokio::time::advance(Duration::from_secs(9)).await;
+        assert_eq!(db.get("a"), Some(Bytes::from("1")));
+
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert_eq!(db.get("a"), None);
+        assert_eq!(db.get("b"), Some(Bytes::from("2")));
+
+        // Setting a key again clears its expiry.
+        db.set(
+            "b".to_string(),
+            Bytes::from("3"),
+            Some(Duration::from_secs(1)),
+        );
+        db.set("b".to_string(), Bytes::from("4"), None);
+        tokio::time::advance(Duration::from_secs(2)).await;
+        assert_eq!(db.get("b"), Some(Bytes::from("4")));
+
+        db.set("c".to_string(), Bytes::from("5"), Some(Duration::MAX));
+        assert_eq!(db.get("c"), Some(Bytes::from("5")));
+    }
+
+    #[tokio::test]
+    async fn test_publish() {
+        let guard = DbDropGuard::new(1);
+        let db = guard.db();
+        assert_eq!(db.publish("news", Bytes::from("dropped")), 0);
+
+        let mut rx1 = db.subscribe("news".to_string());
+        let mut rx2 = db.subscribe("news".to_string());
+        assert_eq!(db.publish("news", Bytes::from("hello")), 2);
+        assert_eq!(rx1.recv().await.unwrap(), Bytes::from("hello"));
+        assert_eq!(rx2.recv().await.unwrap(), Bytes::from("hello"));
+
+        drop(rx1);
+        drop(rx2);
+        assert_eq!(db.publish("news", Bytes::from("dropped")), 0);
+        assert!(db.shared.pub_sub.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_purge() {
+        let guard = DbDropGuard::new(4);
+        let db = guard.db();
+        db.set(
+            "late".to_string(),
+            Bytes::from("1"),
+            Some(Duration::from_secs(60)),
+        );
+        db.set("kept".to_string(), Bytes::from("2"), None);
+        // Comes before the deadline the task is sleeping until.
+        db.set(
+            "early".to_string(),
+            Bytes::from("3"),
+            Some(Duration::from_secs(5)),
+        );
+        // Set again with a later deadline, the first one must not purge it.
+        db.set(
+            "moved".to_string(),
+            Bytes::from("4"),
+            Some(Duration::from_secs(5)),
+        );
+        db.set(
+            "moved".to_string(),
+            Bytes::from("5"),
+            Some(Duration::from_secs(30)),
+        );
+        assert_eq!(len(&db), 4);
+
+        time::sleep(Duration::from_secs(6)).await;
+        assert_eq!(len(&db), 3);
+        assert_eq!(db.get("early"), None);
+
+        time::sleep(Duration::from_secs(25)).await;
+        assert_eq!(len(&db), 2);
+
+        time::sleep(Duration::from_secs(30)).await;
+        assert_eq!(len(&db), 1);
+        assert_eq!(db.get("kept"), Some(Bytes::from("2")));
+        assert!(db.shared.expirations.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown() {
+        let guard = DbDropGuard::new(1);
+        let db = guard.db();
+        db.set(
+            "k".to_string(),
+            Bytes::from("v"),
+            Some(Duration::from_secs(60)),
+        );
+        assert_eq!(Arc::strong_count(&db.shared), 3);
+
+        // The purge task lets go of the db once the guard is dropped.
+        drop(guard);
+        while Arc::strong_count(&db.shared) > 1 {
+            tokio::task::yield_now().await;
+        }
+    }
+}
@@ -1,3 +1,8 @@
+pub mod cmd;
+pub mod connection;
+pub mod db;
+pub mod server;
+
 pub fn add(left: usize, right: usize) -> usize {
     left + right
 }
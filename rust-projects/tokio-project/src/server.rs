@@ -0,0 +1,357 @@
+use std::future::Future;
+use std::pin::Pin;
+
+use bytes::Bytes;
+use futures::Stream;
+use mini_redis::{Frame, Result};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio_stream::{StreamExt, StreamMap};
+
+use crate::cmd::Command;
+use crate::connection::Connection;
+use crate::db::{Db, DbDropGuard};
+
+const NUM_SHARDS: usize = 5;
+
+type Messages = Pin<Box<dyn Stream<Item = Bytes> + Send>>;
+
+/// Accepts connections on `listener` and serves each one in its own task, until
+/// `shutdown` completes.
+pub async fn run(listener: TcpListener, shutdown: impl Future) {
+    // Stops the purge task of the db once the server stops.
+    let db_holder = DbDropGuard::new(NUM_SHARDS);
+
+    tokio::select! {
+        _ = accept(&listener, db_holder.db()) => {}
+        _ = shutdown => eprintln!("shutting down"),
+    }
+}
+
+async fn accept(listener: &TcpListener, db: Db) {
+    loop {
+        let (socket, _) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                eprintln!("accept error: {}", e);
+                continue;
+            }
+        };
+        let db = db.clone();
+
+        tokio::spawn(async move {
+            if let Err(e) = process(socket, db).await {
+                eprintln!("connection error: {}", e);
+            }
+        });
+    }
+}
+
+async fn process(socket: TcpStream, db: Db) -> Result<()> {
+    let mut connection = Connection::new(socket);
+
+    while let Some(frame) = connection.read_stream().await? {
+        let response = match Command::from_frame(frame) {
+            Ok(Command::Subscribe { channels }) => {
+                if subscribe(&mut connection, &db, channels).await? {
+                    continue;
+                }
+                return Ok(());
+            }
+            Ok(Command::Unsubscribe { channels }) => {
+                // Not subscribed to anything, there is nothing to leave.
+                let channels = if channels.is_empty() {
+                    vec![Frame::Null]
+                } else {
+                    channels
+                        .into_iter()
+                        .map(|c| Frame::Bulk(c.into()))
+                        .collect()
+                };
+                for channel in channels {
+                    connection
+                        .write_frame(&Frame::Array(vec![
+                            bulk("unsubscribe"),
+                            channel,
+                            Frame::Integer(0),
+                        ]))
+                        .await?;
+                }
+                continue;
+            }
+            Ok(cmd) => apply(cmd, &db),
+            Err(e) => Frame::Error(e),
+        };
+
+        connection.write_frame(&response).await?;
+    }
+
+    Ok(())
+}
+
+/// Runs a command that does not change the mode of the connection.
+fn apply(cmd: Command, db: &Db) -> Frame {
+    match cmd {
+        Command::Get { key } => match db.get(&key) {
+            Some(value) => Frame::Bulk(value),
+            None => Frame::Null,
+        },
+        Command::Set { key, value, expire } => {
+            db.set(key, value, expire);
+            Frame::Simple("OK".to_string())
+        }
+        Command::Publish { channel, message } => {
+            Frame::Integer(db.publish(&channel, message) as u64)
+        }
+        Command::Ping { message: None } => Frame::Simple("PONG".to_string()),
+        Command::Ping {
+            message: Some(message),
+        } => Frame::Bulk(message),
+        Command::Subscribe { .. } | Command::Unsubscribe { .. } => {
+            unreachable!("handled by `process`")
+        }
+        Command::Unknown { name } => Frame::Error(format!("ERR unknown command '{}'", name)),
+    }
+}
+
+/// Serves a connection in subscriber mode, where only (un)subscribing and pinging are
+/// allowed and published messages are pushed to the client.
+///
+/// Returns `Ok(false)` once the client is gone, `Ok(true)` when it unsubscribed from
+/// every channel and is back to normal mode.
+async fn subscribe(connection: &mut Connection, db: &Db, channels: Vec<String>) -> Result<bool> {
+    let mut subscriptions: StreamMap<String, Messages> = StreamMap::new();
+    let mut to_subscribe = channels;
+
+    loop {
+        for channel in to_subscribe.drain(..) {
+            if !subscriptions.contains_key(&channel) {
+                subscriptions.insert(channel.clone(), messages(db.subscribe(channel.clone())));
+            }
+            connection
+                .write_frame(&Frame::Array(vec![
+                    bulk("subscribe"),
+                    Frame::Bulk(Bytes::from(channel)),
+                    Frame::Integer(subscriptions.len() as u64),
+                ]))
+                .await?;
+        }
+
+        tokio::select! {
+            Some((channel, message)) = subscriptions.next() => {
+                connection
+                    .write_frame(&Frame::Array(vec![
+                        bulk("message"),
+                        Frame::Bulk(Bytes::from(channel)),
+                        Frame::Bulk(message),
+                    ]))
+                    .await?;
+            }
+            frame = connection.read_stream() => {
+                let Some(frame) = frame? else {
+                    return Ok(false);
+                };
+
+                match Command::from_frame(frame) {
+                    Ok(Command::Subscribe { channels }) => to_subscribe = channels,
+                    Ok(Command::Unsubscribe { mut channels }) => {
+                        if channels.is_empty() {
+                            channels = subscriptions.keys().cloned().collect();
+                        }
+                        for channel in channels {
+                            subscriptions.remove(&channel);
+                            connection
+                                .write_frame(&Frame::Array(vec![
+                                    bulk("unsubscribe"),
+                                    Frame::Bulk(Bytes::from(channel)),
+                                    Frame::Integer(subscriptions.len() as u64),
+                                ]))
+                                .await?;
+                        }
+                        if subscriptions.is_empty() {
+                            return Ok(true);
+                        }
+                    }
+                    Ok(Command::Ping { message }) => {
+                        connection
+                            .write_frame(&Frame::Array(vec![
+                                bulk("pong"),
+                                Frame::Bulk(message.unwrap_or_default()),
+                            ]))
+                            .await?;
+                    }
+                    Ok(_) => {
+                        connection
+                            .write_frame(&Frame::Error(
+                                "ERR only SUBSCRIBE, UNSUBSCRIBE and PING are allowed in this context"
+                                    .to_string(),
+                            ))
+                            .await?;
+                    }
+                    Err(e) => connection.write_frame(&Frame::Error(e)).await?,
+                }
+            }
+        }
+    }
+}
+
+/// Turns a channel receiver into a stream, skipping over the messages missed when lagging.
+fn messages(rx: broadcast::Receiver<Bytes>) -> Messages {
+    Box::pin(futures::stream::unfold(rx, |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                Ok(message) => return Some((message, rx)),
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }))
+}
+
+fn bulk(s: &'static str) -> Frame {
+    Frame::Bulk(Bytes::from_static(s.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+    use std::time::Duration;
+
+    use mini_redis::client;
+
+    use super::*;
+
+    async fn start() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(run(listener, std::future::pending::<()>()));
+        addr
+    }
+
+    async fn request(connection: &mut Connection, parts: &[&str]) -> Frame {
+        let frame = Frame::Array(
+            parts
+                .iter()
+                .map(|part| Frame::Bulk(Bytes::from(part.to_string())))
+                .collect(),
+        );
+        connection.write_frame(&frame).await.unwrap();
+        connection.read_stream().await.unwrap().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_get_set() {
+        let addr = start().await;
+        let mut client = client::connect(addr).await.unwrap();
+
+        assert_eq!(client.get("hello").await.unwrap(), None);
+        client.set("hello", "world".into()).await.unwrap();
+        assert_eq!(client.get("hello").await.unwrap(), Some("world".into()));
+
+        client
+            .set_expires("short", "lived".into(), Duration::from_millis(50))
+            .await
+            .unwrap();
+        assert_eq!(client.get("short").await.unwrap(), Some("lived".into()));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(client.get("short").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_ping_and_errors() {
+        let addr = start().await;
+        let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+        let pong = request(&mut connection, &["PING"]).await;
+        assert!(matches!(pong, Frame::Simple(s) if s == "PONG"));
+        let echo = request(&mut connection, &["PING", "hi"]).await;
+        assert!(matches!(echo, Frame::Bulk(b) if b == "hi"));
+
+        let unknown = request(&mut connection, &["FLUSHALL"]).await;
+        assert!(matches!(unknown, Frame::Error(e) if e == "ERR unknown command 'flushall'"));
+        let invalid = request(&mut connection, &["GET"]).await;
+        assert!(matches!(invalid, Frame::Error(_)));
+
+        // The connection is still usable after errors.
+        assert!(matches!(
+            request(&mut connection, &["SET", "k", "v"]).await,
+            Frame::Simple(s) if s == "OK"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_pub_sub() {
+        let addr = start().await;
+        let mut publisher = client::connect(addr).await.unwrap();
+        let subscriber = client::connect(addr).await.unwrap();
+
+        assert_eq!(publisher.publish("news", "nobody".into()).await.unwrap(), 0);
+
+        let mut subscriber = subscriber
+            .subscribe(vec!["news".to_string(), "sports".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(publisher.publish("news", "hello".into()).await.unwrap(), 1);
+
+        let message = subscriber.next_message().await.unwrap().unwrap();
+        assert_eq!(message.channel, "news");
+        assert_eq!(message.content, "hello");
+
+        subscriber.unsubscribe(&["news".to_string()]).await.unwrap();
+        assert_eq!(subscriber.get_subscribed(), ["sports".to_string()]);
+        assert_eq!(publisher.publish("news", "missed".into()).await.unwrap(), 0);
+        assert_eq!(publisher.publish("sports", "goal".into()).await.unwrap(), 1);
+        let message = subscriber.next_message().await.unwrap().unwrap();
+        assert_eq!(message.content, "goal");
+    }
+
+    #[tokio::test]
+    async fn test_subscriber_mode() {
+        let addr = start().await;
+        let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+        let subscribed = request(&mut connection, &["SUBSCRIBE", "a"]).await;
+        assert_eq!(
+            format!("{:?}", subscribed),
+            format!(
+                "{:?}",
+                Frame::Array(vec![bulk("subscribe"), bulk("a"), Frame::Integer(1)])
+            )
+        );
+        assert!(matches!(
+            request(&mut connection, &["GET", "k"]).await,
+            Frame::Error(_)
+        ));
+        assert!(matches!(
+            request(&mut connection, &["PING"]).await,
+            Frame::Array(parts) if parts.len() == 2
+        ));
+
+        // Leaving the last channel goes back to normal mode.
+        let unsubscribed = request(&mut connection, &["UNSUBSCRIBE"]).await;
+        assert_eq!(
+            format!("{:?}", unsubscribed),
+            format!(
+                "{:?}",
+                Frame::Array(vec![bulk("unsubscribe"), bulk("a"), Frame::Integer(0)])
+            )
+        );
+        assert!(matches!(
+            request(&mut connection, &["GET", "k"]).await,
+            Frame::Null
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_shutdown() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(run(listener, rx));
+
+        tx.send(()).unwrap();
+        tokio::time::timeout(Duration::from_secs(1), server)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
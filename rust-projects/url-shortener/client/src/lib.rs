@@ -0,0 +1,525 @@
+//! Typed client of the url-shortener API, see `/openapi.json` for the document it follows.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use url_shortener_client::{AddUrlRequest, Client};
+//!
+//! # async fn run() -> Result<(), url_shortener_client::Error> {
+//! let client = Client::new("http://localhost:3000").with_api_key("my-key");
+//! let link = client
+//!     .create_url(&AddUrlRequest {
+//!         url: "https://example.com".to_string(),
+//!         alias: Some("example".to_string()),
+//!         ..AddUrlRequest::default()
+//!     })
+//!     .await?;
+//!
+//! assert_eq!(client.visit(&link.hash).await?.location, "https://example.com/");
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{collections::BTreeMap, fmt};
+
+use chrono::{DateTime, Utc};
+use reqwest::{header, redirect, Method, RequestBuilder, Response, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// Header carrying the API key.
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Body of `POST /urls` and `POST /add_url`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct AddUrlRequest {
+    pub url: String,
+    /// Custom short code, a generated one is used when missing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+    /// Lifetime of the link in seconds, exclusive with `expires_at`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl_seconds: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Number of redirects after which the link is gone.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_clicks: Option<u64>,
+    /// Shows a preview page of the URL instead of redirecting.
+    #[serde(default)]
+    pub interstitial: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Group, or campaign, the link is part of.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+}
+
+/// Body of `PATCH /urls/{code}`, fields left to `None` are unchanged and `Some(None)` removes a
+/// limit, the title or the group.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct PatchUrlRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Lifetime of the link in seconds from now, exclusive with `expires_at`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl_seconds: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<Option<DateTime<Utc>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_clicks: Option<Option<u64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interstitial: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<Option<String>>,
+    /// Replaces every tag of the link.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<Option<String>>,
+}
+
+/// Query of `GET /urls` and `GET /get_all`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct PageRequest {
+    /// `next_cursor` of the previous page.
+    pub cursor: Option<String>,
+    pub limit: Option<usize>,
+    /// `code`, `-code`, `created` or `-created`, by code by default.
+    pub sort: Option<String>,
+    /// Only the links with this tag.
+    pub tag: Option<String>,
+    /// Only the links whose URL or title contains this text, ignoring case.
+    pub q: Option<String>,
+    /// `substring` or `prefix`, where `q` is looked for, `substring` by default.
+    #[serde(rename = "match")]
+    pub match_mode: Option<String>,
+    /// Only the links of this group.
+    pub group: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct UrlMap {
+    pub hash: String,
+    pub original: String,
+    pub short: String,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub max_clicks: Option<u64>,
+    pub clicks: u64,
+    pub owner: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub group: Option<String>,
+    /// Shows a preview page instead of redirecting.
+    #[serde(default)]
+    pub interstitial: bool,
+    /// Title, description and image of the URL, if they could be fetched.
+    #[serde(default)]
+    pub preview: Option<LinkPreview>,
+}
+
+/// What a link leads to, as announced by the target page.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct LinkPreview {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// Absolute URL of an image of the page.
+    pub image: Option<String>,
+}
+
+/// A page of links.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct UrlPage {
+    pub links: Vec<UrlMap>,
+    /// Cursor of the next page, `None` on the last page.
+    pub next_cursor: Option<String>,
+    pub limit: usize,
+}
+
+/// Click counts of a link, broken down by time bucket and by client.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct LinkStats {
+    pub hash: String,
+    pub clicks: u64,
+    /// Clicks per UTC day, `YYYY-MM-DD`.
+    pub daily: BTreeMap<String, u64>,
+    /// Clicks per UTC hour, `YYYY-MM-DDTHH`.
+    pub hourly: BTreeMap<String, u64>,
+    pub referrers: BTreeMap<String, u64>,
+    pub user_agents: BTreeMap<String, u64>,
+    pub countries: BTreeMap<String, u64>,
+}
+
+/// A group of links, with their clicks added up.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct GroupSummary {
+    pub name: String,
+    /// Number of links in the group.
+    pub links: usize,
+    pub clicks: u64,
+}
+
+/// Click counts of the links of a group, added up.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct GroupStats {
+    pub name: String,
+    pub clicks: u64,
+    /// Clicks of each link of the group, by short code.
+    pub links: BTreeMap<String, u64>,
+    /// Clicks per UTC day, `YYYY-MM-DD`.
+    pub daily: BTreeMap<String, u64>,
+    /// Clicks per UTC hour, `YYYY-MM-DDTHH`.
+    pub hourly: BTreeMap<String, u64>,
+    pub referrers: BTreeMap<String, u64>,
+    pub user_agents: BTreeMap<String, u64>,
+    pub countries: BTreeMap<String, u64>,
+}
+
+/// Body of the error responses.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ErrorResponse {
+    /// Machine readable reason, e.g. `unsupported_scheme` or `not_found`.
+    pub code: String,
+    pub message: String,
+}
+
+/// Outcome of a row of a bulk import.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BulkResult {
+    /// Line of the row in the request body, from 1.
+    pub line: usize,
+    /// Short code of the link, if the row was added.
+    pub hash: Option<String>,
+    pub error: Option<ErrorResponse>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BulkResponse {
+    pub succeeded: usize,
+    pub failed: usize,
+    pub results: Vec<BulkResult>,
+}
+
+/// Format of bulk imports and exports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BulkFormat {
+    /// Comma separated values with a header line.
+    Csv,
+    /// One JSON object per line.
+    JsonLines,
+}
+
+impl BulkFormat {
+    fn content_type(&self) -> &'static str {
+        match self {
+            BulkFormat::Csv => "text/csv",
+            BulkFormat::JsonLines => "application/x-ndjson",
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            BulkFormat::Csv => "csv",
+            BulkFormat::JsonLines => "jsonl",
+        }
+    }
+}
+
+/// Where a short link redirects to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redirect {
+    /// 307, as links can be changed after they are created.
+    pub status: u16,
+    pub location: String,
+}
+
+#[derive(Deserialize)]
+struct AddUrlResponse {
+    hashed_url: String,
+}
+
+#[derive(Deserialize)]
+struct HealthResponse {
+    status: String,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// The server answered with an error status.
+    Api { status: u16, error: ErrorResponse },
+    /// The request could not be sent, or the response could not be read.
+    Http(reqwest::Error),
+}
+
+impl Error {
+    /// Status of the error response, `None` if there was no response.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Error::Api { status, .. } => Some(*status),
+            Error::Http(e) => e.status().map(|status| status.as_u16()),
+        }
+    }
+
+    /// Machine readable reason of the error response, e.g. `not_found`.
+    pub fn code(&self) -> Option<&str> {
+        match self {
+            Error::Api { error, .. } => Some(&error.code),
+            Error::Http(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Api { status, error } => {
+                write!(f, "{} {}: {}", status, error.code, error.message)
+            }
+            Error::Http(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Http(e)
+    }
+}
+
+/// Client of a url-shortener server. Clones share the same connections.
+#[derive(Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+}
+
+impl Client {
+    /// Creates a client of the server at `base_url`, e.g. `http://localhost:3000`.
+    pub fn new(base_url: &str) -> Self {
+        let http = reqwest::Client::builder()
+            // Redirects are answers of the API, see `Client::visit`.
+            .redirect(redirect::Policy::none())
+            .build()
+            .expect("the HTTP client configuration is valid");
+
+        Client {
+            http,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: None,
+        }
+    }
+
+    /// Sends `key` with every request, required by every route but the redirects, health checks
+    /// and metrics.
+    pub fn with_api_key(mut self, key: &str) -> Self {
+        self.api_key = Some(key.to_string());
+        self
+    }
+
+    /// URL of the server, without a trailing `/`.
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let request = self
+            .http
+            .request(method, format!("{}{}", self.base_url, path));
+
+        match &self.api_key {
+            Some(key) => request.header(API_KEY_HEADER, key),
+            None => request,
+        }
+    }
+
+    /// Sends a request, turning error statuses into [`Error::Api`].
+    async fn send(&self, request: RequestBuilder) -> Result<Response, Error> {
+        let response = request.send().await?;
+        let status = response.status();
+        if status.is_client_error() || status.is_server_error() {
+            // Requests rejected before reaching the handlers may have a plain text body.
+            let body = response.text().await?;
+            let error = serde_json::from_str(&body).unwrap_or(ErrorResponse {
+                code: "unknown".to_string(),
+                message: body,
+            });
+            return Err(Error::Api {
+                status: status.as_u16(),
+                error,
+            });
+        }
+        Ok(response)
+    }
+
+    async fn json<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, Error> {
+        Ok(self.send(request).await?.json().await?)
+    }
+
+    /// `POST /add_url`, returns the short code.
+    pub async fn add_url(&self, request: &AddUrlRequest) -> Result<String, Error> {
+        let response: AddUrlResponse = self
+            .json(self.request(Method::POST, "/add_url").json(request))
+            .await?;
+        Ok(response.hashed_url)
+    }
+
+    /// `GET /get_all`.
+    pub async fn get_all(&self, page: &PageRequest) -> Result<UrlPage, Error> {
+        self.json(self.request(Method::GET, "/get_all").query(page))
+            .await
+    }
+
+    /// `PUT /update_url`, changes the URL of a link.
+    pub async fn update_url(&self, hash: &str, url: &str) -> Result<UrlMap, Error> {
+        let body = serde_json::json!({ "hash": hash, "url": url });
+        self.json(self.request(Method::PUT, "/update_url").json(&body))
+            .await
+    }
+
+    /// `DELETE /delete_url`, removes every short code of a URL and returns the URL.
+    pub async fn delete_url(&self, url: &str) -> Result<String, Error> {
+        let body = serde_json::json!({ "url": url });
+        self.json(self.request(Method::DELETE, "/delete_url").json(&body))
+            .await
+    }
+
+    /// `GET /urls`.
+    pub async fn list_urls(&self, page: &PageRequest) -> Result<UrlPage, Error> {
+        self.json(self.request(Method::GET, "/urls").query(page))
+            .await
+    }
+
+    /// `POST /urls`.
+    pub async fn create_url(&self, request: &AddUrlRequest) -> Result<UrlMap, Error> {
+        self.json(self.request(Method::POST, "/urls").json(request))
+            .await
+    }
+
+    /// `GET /urls/{code}`.
+    pub async fn get_url(&self, code: &str) -> Result<UrlMap, Error> {
+        self.json(self.request(Method::GET, &format!("/urls/{}", code)))
+            .await
+    }
+
+    /// `PATCH /urls/{code}`.
+    pub async fn patch_url(&self, code: &str, request: &PatchUrlRequest) -> Result<UrlMap, Error> {
+        self.json(
+            self.request(Method::PATCH, &format!("/urls/{}", code))
+                .json(request),
+        )
+        .await
+    }
+
+    /// `DELETE /urls/{code}`.
+    pub async fn delete_code(&self, code: &str) -> Result<(), Error> {
+        self.send(self.request(Method::DELETE, &format!("/urls/{}", code)))
+            .await?;
+        Ok(())
+    }
+
+    /// `POST /urls/bulk`, adds the links of a CSV or JSON lines body.
+    pub async fn bulk_import(
+        &self,
+        format: BulkFormat,
+        body: impl Into<String>,
+    ) -> Result<BulkResponse, Error> {
+        let request = self
+            .request(Method::POST, "/urls/bulk")
+            .header(header::CONTENT_TYPE, format.content_type())
+            .body(body.into());
+        self.json(request).await
+    }
+
+    /// `GET /urls/export`, returns the links of the caller as CSV or JSON lines.
+    pub async fn export(&self, format: BulkFormat) -> Result<String, Error> {
+        let request = self
+            .request(Method::GET, "/urls/export")
+            .query(&[("format", format.name())]);
+        Ok(self.send(request).await?.text().await?)
+    }
+
+    /// `GET /{code}/stats`.
+    pub async fn stats(&self, code: &str) -> Result<LinkStats, Error> {
+        self.json(self.request(Method::GET, &format!("/{}/stats", code)))
+            .await
+    }
+
+    /// `GET /groups`.
+    pub async fn groups(&self) -> Result<Vec<GroupSummary>, Error> {
+        self.json(self.request(Method::GET, "/groups")).await
+    }
+
+    /// `GET /groups/{name}/stats`.
+    pub async fn group_stats(&self, name: &str) -> Result<GroupStats, Error> {
+        self.json(self.request(Method::GET, &format!("/groups/{}/stats", name)))
+            .await
+    }
+
+    /// `GET /{code}`, follows a short link without following the redirect. Interstitial links
+    /// answer with their preview page, a status of 200 and no location.
+    pub async fn visit(&self, code: &str) -> Result<Redirect, Error> {
+        let response = self
+            .send(self.request(Method::GET, &format!("/{}", code)))
+            .await?;
+
+        let location = response
+            .headers()
+            .get(header::LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .unwrap_or_default();
+        Ok(Redirect {
+            status: response.status().as_u16(),
+            location: location.to_string(),
+        })
+    }
+
+    /// `GET /{code}?preview`, returns the preview page of a link without counting a click.
+    pub async fn preview(&self, code: &str) -> Result<String, Error> {
+        let request = self
+            .request(Method::GET, &format!("/{}", code))
+            .query(&[("preview", "")]);
+        Ok(self.send(request).await?.text().await?)
+    }
+
+    /// `GET /healthz`, `true` if the server runs.
+    pub async fn healthz(&self) -> Result<bool, Error> {
+        self.health("/healthz").await
+    }
+
+    /// `GET /readyz`, `true` if the storage backend answers.
+    pub async fn readyz(&self) -> Result<bool, Error> {
+        self.health("/readyz").await
+    }
+
+    async fn health(&self, path: &str) -> Result<bool, Error> {
+        match self
+            .json::<HealthResponse>(self.request(Method::GET, path))
+            .await
+        {
+            Ok(health) => Ok(health.status == "ok"),
+            Err(e) if e.status() == Some(StatusCode::SERVICE_UNAVAILABLE.as_u16()) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// `GET /metrics`, in the Prometheus text format.
+    pub async fn metrics(&self) -> Result<String, Error> {
+        Ok(self
+            .send(self.request(Method::GET, "/metrics"))
+            .await?
+            .text()
+            .await?)
+    }
+
+    /// `GET /openapi.json`.
+    pub async fn openapi(&self) -> Result<serde_json::Value, Error> {
+        self.json(self.request(Method::GET, "/openapi.json")).await
+    }
+}
@@ -1,10 +1,159 @@
-use tokio::net::TcpListener;
-use url_shortener::modules::routes::setup_router;
+use std::{future::IntoFuture, net::SocketAddr, sync::Arc};
+
+use tokio::{net::TcpListener, sync::Notify};
+use tracing_subscriber::EnvFilter;
+use url_shortener::modules::{
+    auth::ApiKeys,
+    cache::{redis_invalidations::RedisInvalidations, LinkCache},
+    config::{Config, Settings},
+    preview::http_fetcher::HttpFetcher,
+    rate_limit::{
+        memory_buckets::MemoryBuckets, redis_buckets::RedisBuckets, RateLimiter, RateLimits,
+        TokenBuckets,
+    },
+    routes::setup_router,
+    storage::{redis_prefix, redis_url, StorageConfig},
+    store::Store,
+    validation::UrlPolicy,
+};
+
+/// Filter of the logged events when `RUST_LOG` is not set.
+const DEFAULT_LOG_FILTER: &str = "url_shortener=info,tower_http=info";
+
+/// Logs to stdout, as JSON lines if `LOG_FORMAT` is `json`, keeping the events allowed by
+/// `RUST_LOG`.
+fn init_tracing(settings: &Settings) {
+    let filter = EnvFilter::try_new(
+        settings
+            .get("RUST_LOG")
+            .unwrap_or_else(|| DEFAULT_LOG_FILTER.to_string()),
+    )
+    .expect("invalid RUST_LOG");
+    let subscriber = tracing_subscriber::fmt().with_env_filter(filter);
+
+    match settings.get("LOG_FORMAT").as_deref() {
+        Some("json") => subscriber.json().init(),
+        _ => subscriber.init(),
+    }
+}
+
+/// Resolves on Ctrl-C or, on Unix, on SIGTERM.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("could not listen for Ctrl-C");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("could not listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
 
 #[tokio::main]
 async fn main() {
-    let app = setup_router().await;
-    let listener = TcpListener::bind("127.0.0.1:3000").await.unwrap();
+    let settings = Settings::load().expect("invalid configuration");
+    let config = Config::from_settings(&settings).expect("invalid configuration");
+    init_tracing(&settings);
+
+    let store = Store::new(config.storage.connect().await.unwrap())
+        .with_code_length(config.code_length)
+        .with_base_url(&config.base_url)
+        .with_analytics(settings.flag("ANALYTICS"));
+
+    let mut policy =
+        UrlPolicy::default().with_block_private(settings.flag("BLOCK_PRIVATE_TARGETS"));
+    if let Some(schemes) = settings.get("ALLOWED_SCHEMES") {
+        policy = policy.with_schemes(schemes.split(','));
+    }
+    if let Some(path) = settings.get("BLOCKLIST_PATH") {
+        policy = policy
+            .load_blocklist(&path)
+            .expect("could not read BLOCKLIST_PATH");
+    }
+    let mut store = store.with_url_policy(policy.clone());
+
+    if !config.preview_timeout.is_zero() {
+        store =
+            store.with_preview_fetcher(Arc::new(HttpFetcher::new(policy, config.preview_timeout)));
+    }
+
+    if config.cache.capacity > 0 {
+        let mut cache = LinkCache::new(&config.cache);
+        // Instances sharing a Redis backend tell each other which links changed.
+        if let StorageConfig::Redis { url, prefix, .. } = &config.storage {
+            let invalidations = RedisInvalidations::connect(url, prefix).await.unwrap();
+            invalidations.listen(cache.clone());
+            cache = cache.with_invalidations(Arc::new(invalidations));
+        }
+        store = store.with_cache(cache);
+    }
+
+    let keys = match (settings.get("API_KEYS"), settings.get("API_KEYS_FILE")) {
+        (Some(spec), _) => ApiKeys::parse(&spec).expect("invalid API_KEYS"),
+        (_, Some(path)) => ApiKeys::load(&path).expect("could not read API_KEYS_FILE"),
+        _ => {
+            tracing::warn!("No API_KEYS or API_KEYS_FILE set, only redirects will be served");
+            ApiKeys::new()
+        }
+    };
+
+    let limits = match settings.get("RATE_LIMITS") {
+        Some(spec) => RateLimits::parse(&spec).expect("invalid RATE_LIMITS"),
+        None => RateLimits::new(),
+    };
+    let buckets: Arc<dyn TokenBuckets> = match settings.get("RATE_LIMIT_BACKEND").as_deref() {
+        Some("redis") => Arc::new(
+            RedisBuckets::connect(&redis_url(&settings), &redis_prefix(&settings))
+                .await
+                .unwrap(),
+        ),
+        _ => Arc::new(MemoryBuckets::new()),
+    };
+    let rate_limiter = RateLimiter::new(buckets, limits)
+        .with_trust_forwarded_for(settings.flag("TRUST_FORWARDED_FOR"));
+
+    let app = setup_router(store, keys, rate_limiter).await;
+    let listener = TcpListener::bind(config.bind_address).await.unwrap();
+    tracing::info!(address = %config.bind_address, "listening");
+
+    // Stops accepting connections on the signal and lets the open ones finish, for up to
+    // `shutdown_timeout`.
+    let shutdown = Arc::new(Notify::new());
+    let mut server = tokio::spawn({
+        let shutdown = Arc::clone(&shutdown);
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(async move { shutdown.notified().await })
+        .into_future()
+    });
+
+    tokio::select! {
+        result = &mut server => {
+            result.unwrap().unwrap();
+            return;
+        }
+        _ = shutdown_signal() => {}
+    }
 
-    axum::serve(listener, app).await.unwrap();
+    tracing::info!("shutting down, draining open connections");
+    shutdown.notify_one();
+    match tokio::time::timeout(config.shutdown_timeout, server).await {
+        Ok(result) => result.unwrap().unwrap(),
+        // Returning drops the runtime, which closes the connections still open.
+        Err(_) => tracing::warn!("shutdown timeout reached, closing the open connections"),
+    }
 }
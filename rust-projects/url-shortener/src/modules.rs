@@ -1,2 +1,15 @@
+pub mod analytics;
+pub mod auth;
+pub mod bulk;
+pub mod cache;
+pub mod config;
+pub mod labels;
+pub mod metrics;
+pub mod openapi;
+pub mod preview;
+pub mod rate_limit;
 pub mod routes;
+pub mod short_code;
+pub mod storage;
 pub mod store;
+pub mod validation;
@@ -0,0 +1,311 @@
+use std::collections::BTreeMap;
+
+use axum::http::{header, HeaderMap};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Headers set by CDNs and load balancers with the country of the client, first match wins.
+const COUNTRY_HEADERS: &[&str] = &["cf-ipcountry", "x-country-code"];
+
+const UNKNOWN: &str = "unknown";
+
+/// A redirect, with what we keep about the client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Click {
+    pub at: DateTime<Utc>,
+    /// Host of the referring page.
+    pub referrer: Option<String>,
+    pub user_agent: Option<String>,
+    /// ISO 3166-1 alpha-2 country code.
+    pub country: Option<String>,
+}
+
+impl Click {
+    /// Reads a click from the headers of a redirect request.
+    pub fn from_headers(headers: &HeaderMap, at: DateTime<Utc>) -> Self {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
+
+        Click {
+            at,
+            referrer: header(header::REFERER.as_str()).and_then(referrer_host),
+            user_agent: header(header::USER_AGENT.as_str()).map(str::to_string),
+            country: COUNTRY_HEADERS
+                .iter()
+                .find_map(|name| header(name))
+                .filter(|country| {
+                    country.len() == 2 && country.chars().all(|c| c.is_ascii_alphabetic())
+                })
+                .map(str::to_uppercase),
+        }
+    }
+
+    /// Names of the counters incremented by this click.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chrono::{TimeZone, Utc};
+    /// use url_shortener::modules::analytics::Click;
+    ///
+    /// let click = Click {
+    ///     at: Utc.with_ymd_and_hms(2024, 6, 1, 13, 45, 0).unwrap(),
+    ///     referrer: Some("news.ycombinator.com".to_string()),
+    ///     user_agent: Some("curl/8.5.0".to_string()),
+    ///     country: None,
+    /// };
+    ///
+    /// assert_eq!(
+    ///     click.counters(),
+    ///     vec![
+    ///         "day:2024-06-01",
+    ///         "hour:2024-06-01T13",
+    ///         "referrer:news.ycombinator.com",
+    ///         "agent:curl",
+    ///         "country:unknown",
+    ///     ]
+    /// );
+    /// ```
+    pub fn counters(&self) -> Vec<String> {
+        vec![
+            format!("day:{}", self.at.format("%Y-%m-%d")),
+            format!("hour:{}", self.at.format("%Y-%m-%dT%H")),
+            format!("referrer:{}", self.referrer.as_deref().unwrap_or("direct")),
+            format!(
+                "agent:{}",
+                user_agent_family(self.user_agent.as_deref().unwrap_or(""))
+            ),
+            format!("country:{}", self.country.as_deref().unwrap_or(UNKNOWN)),
+        ]
+    }
+}
+
+fn referrer_host(referrer: &str) -> Option<String> {
+    let rest = referrer
+        .split_once("://")
+        .map_or(referrer, |(_, rest)| rest);
+    let host = rest.split(['/', '?', '#']).next()?;
+    let host = host.rsplit('@').next()?.to_lowercase();
+
+    (!host.is_empty()).then_some(host)
+}
+
+/// Reduces a `User-Agent` header to a browser or client family.
+///
+/// # Example
+///
+/// ```
+/// use url_shortener::modules::analytics::user_agent_family;
+///
+/// let chrome = "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) \
+///               Chrome/125.0.0.0 Safari/537.36";
+/// assert_eq!(user_agent_family(chrome), "chrome");
+/// assert_eq!(user_agent_family("Googlebot/2.1 (+http://www.google.com/bot.html)"), "bot");
+/// assert_eq!(user_agent_family(""), "unknown");
+/// ```
+pub fn user_agent_family(user_agent: &str) -> &'static str {
+    let user_agent = user_agent.to_lowercase();
+
+    // Order matters, most browsers also claim to be Safari or Mozilla.
+    const FAMILIES: &[(&str, &str)] = &[
+        ("bot", "bot"),
+        ("spider", "bot"),
+        ("crawler", "bot"),
+        ("curl/", "curl"),
+        ("wget/", "wget"),
+        ("edg/", "edge"),
+        ("opr/", "opera"),
+        ("firefox/", "firefox"),
+        ("chrome/", "chrome"),
+        ("crios/", "chrome"),
+        ("safari/", "safari"),
+    ];
+
+    if user_agent.is_empty() {
+        return UNKNOWN;
+    }
+
+    FAMILIES
+        .iter()
+        .find(|(pattern, _)| user_agent.contains(pattern))
+        .map_or("other", |(_, family)| family)
+}
+
+/// Click counts of a link, broken down by time bucket and by client.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq, ToSchema)]
+pub struct LinkStats {
+    pub hash: String,
+    pub clicks: u64,
+    /// Clicks per UTC day, `YYYY-MM-DD`.
+    pub daily: BTreeMap<String, u64>,
+    /// Clicks per UTC hour, `YYYY-MM-DDTHH`.
+    pub hourly: BTreeMap<String, u64>,
+    pub referrers: BTreeMap<String, u64>,
+    pub user_agents: BTreeMap<String, u64>,
+    pub countries: BTreeMap<String, u64>,
+}
+
+impl LinkStats {
+    /// Groups the counters produced by [`Click::counters`].
+    pub fn from_counters(hash: String, clicks: u64, counters: Vec<(String, u64)>) -> Self {
+        let mut stats = LinkStats {
+            hash,
+            clicks,
+            ..LinkStats::default()
+        };
+
+        for (counter, count) in counters {
+            let Some((kind, name)) = counter.split_once(':') else {
+                continue;
+            };
+
+            let breakdown = match kind {
+                "day" => &mut stats.daily,
+                "hour" => &mut stats.hourly,
+                "referrer" => &mut stats.referrers,
+                "agent" => &mut stats.user_agents,
+                "country" => &mut stats.countries,
+                _ => continue,
+            };
+            breakdown.insert(name.to_string(), count);
+        }
+
+        stats
+    }
+}
+
+/// Click counts of the links of a group, added up.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq, ToSchema)]
+pub struct GroupStats {
+    pub name: String,
+    pub clicks: u64,
+    /// Clicks of each link of the group, by short code.
+    pub links: BTreeMap<String, u64>,
+    /// Clicks per UTC day, `YYYY-MM-DD`.
+    pub daily: BTreeMap<String, u64>,
+    /// Clicks per UTC hour, `YYYY-MM-DDTHH`.
+    pub hourly: BTreeMap<String, u64>,
+    pub referrers: BTreeMap<String, u64>,
+    pub user_agents: BTreeMap<String, u64>,
+    pub countries: BTreeMap<String, u64>,
+}
+
+impl GroupStats {
+    pub fn new(name: String) -> Self {
+        GroupStats {
+            name,
+            ..GroupStats::default()
+        }
+    }
+
+    /// Adds the clicks of a link of the group.
+    pub fn add(&mut self, link: LinkStats) {
+        fn merge(total: &mut BTreeMap<String, u64>, breakdown: BTreeMap<String, u64>) {
+            for (name, count) in breakdown {
+                *total.entry(name).or_default() += count;
+            }
+        }
+
+        self.clicks += link.clicks;
+        self.links.insert(link.hash, link.clicks);
+        merge(&mut self.daily, link.daily);
+        merge(&mut self.hourly, link.hourly);
+        merge(&mut self.referrers, link.referrers);
+        merge(&mut self.user_agents, link.user_agents);
+        merge(&mut self.countries, link.countries);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn test_from_headers() {
+        let at = Utc.with_ymd_and_hms(2024, 6, 1, 13, 45, 0).unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::REFERER,
+            HeaderValue::from_static("https://user@Example.com:8080/page?q=1"),
+        );
+        headers.insert("cf-ipcountry", HeaderValue::from_static("fr"));
+
+        assert_eq!(
+            Click::from_headers(&headers, at),
+            Click {
+                at,
+                referrer: Some("example.com:8080".to_string()),
+                user_agent: None,
+                country: Some("FR".to_string()),
+            }
+        );
+
+        headers.insert("cf-ipcountry", HeaderValue::from_static("XX1"));
+        assert_eq!(Click::from_headers(&headers, at).country, None);
+    }
+
+    #[test]
+    fn test_from_counters() {
+        let stats = LinkStats::from_counters(
+            "abc".to_string(),
+            3,
+            vec![
+                ("day:2024-06-01".to_string(), 3),
+                ("hour:2024-06-01T13".to_string(), 2),
+                ("hour:2024-06-01T14".to_string(), 1),
+                ("agent:firefox".to_string(), 3),
+                ("bogus".to_string(), 1),
+            ],
+        );
+
+        assert_eq!(stats.daily, BTreeMap::from([("2024-06-01".to_string(), 3)]));
+        assert_eq!(stats.hourly.len(), 2);
+        assert_eq!(
+            stats.user_agents,
+            BTreeMap::from([("firefox".to_string(), 3)])
+        );
+        assert!(stats.countries.is_empty());
+    }
+
+    #[test]
+    fn test_group_stats() {
+        let mut stats = GroupStats::new("spring".to_string());
+        stats.add(LinkStats::from_counters(
+            "a".to_string(),
+            3,
+            vec![
+                ("day:2024-06-01".to_string(), 3),
+                ("country:FR".to_string(), 3),
+            ],
+        ));
+        stats.add(LinkStats::from_counters(
+            "b".to_string(),
+            2,
+            vec![
+                ("day:2024-06-01".to_string(), 1),
+                ("day:2024-06-02".to_string(), 1),
+                ("country:DE".to_string(), 2),
+            ],
+        ));
+
+        assert_eq!(stats.clicks, 5);
+        assert_eq!(
+            stats.links,
+            BTreeMap::from([("a".to_string(), 3), ("b".to_string(), 2)])
+        );
+        assert_eq!(
+            stats.daily,
+            BTreeMap::from([("2024-06-01".to_string(), 4), ("2024-06-02".to_string(), 1)])
+        );
+        assert_eq!(stats.countries.len(), 2);
+    }
+}
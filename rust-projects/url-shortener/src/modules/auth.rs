@@ -0,0 +1,158 @@
+use std::{collections::HashMap, fs, io, sync::Arc};
+
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+
+use super::{routes::json_error, storage::UrlRecord};
+
+/// Header carrying the API key, `Authorization: Bearer <key>` is accepted too.
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    /// Manages its own links.
+    User,
+    /// Manages every link.
+    Admin,
+}
+
+/// The user an API key belongs to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Principal {
+    pub user: String,
+    pub role: Role,
+}
+
+impl Principal {
+    pub fn user(name: &str) -> Self {
+        Principal {
+            user: name.to_string(),
+            role: Role::User,
+        }
+    }
+
+    pub fn admin(name: &str) -> Self {
+        Principal {
+            user: name.to_string(),
+            role: Role::Admin,
+        }
+    }
+
+    pub fn is_admin(&self) -> bool {
+        self.role == Role::Admin
+    }
+
+    /// Returns `true` if the principal may see, update or delete the link. Links created before
+    /// ownership existed have no owner and can only be managed by admins.
+    pub fn can_manage(&self, record: &UrlRecord) -> bool {
+        self.is_admin() || record.owner.as_deref() == Some(self.user.as_str())
+    }
+}
+
+/// API keys accepted by the server.
+#[derive(Debug, Clone, Default)]
+pub struct ApiKeys {
+    keys: HashMap<String, Principal>,
+}
+
+impl ApiKeys {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_key(mut self, key: &str, principal: Principal) -> Self {
+        self.keys.insert(key.to_string(), principal);
+        self
+    }
+
+    /// Parses keys given as `<key>:<user>[:admin]`, separated by commas or new lines. Empty
+    /// entries and lines starting with `#` are ignored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use url_shortener::modules::auth::{ApiKeys, Principal};
+    ///
+    /// let keys = ApiKeys::parse("k1:alice, k2:root:admin").unwrap();
+    ///
+    /// assert_eq!(keys.principal("k1"), Some(&Principal::user("alice")));
+    /// assert_eq!(keys.principal("k2"), Some(&Principal::admin("root")));
+    /// assert!(ApiKeys::parse("k3").is_err());
+    /// ```
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut keys = ApiKeys::new();
+
+        for entry in spec.split([',', '\n']).map(str::trim) {
+            if entry.is_empty() || entry.starts_with('#') {
+                continue;
+            }
+
+            let principal = match entry.split(':').collect::<Vec<_>>()[..] {
+                [key, user] if !key.is_empty() && !user.is_empty() => (key, Principal::user(user)),
+                [key, user, "admin"] if !key.is_empty() && !user.is_empty() => {
+                    (key, Principal::admin(user))
+                }
+                _ => return Err(format!("Invalid API key entry '{}'", entry)),
+            };
+            keys = keys.with_key(principal.0, principal.1);
+        }
+
+        Ok(keys)
+    }
+
+    /// Reads keys from a file, in the format of [`ApiKeys::parse`].
+    pub fn load(path: &str) -> io::Result<Self> {
+        let content = fs::read_to_string(path)?;
+        Self::parse(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn principal(&self, key: &str) -> Option<&Principal> {
+        self.keys.get(key)
+    }
+}
+
+/// Returns the API key of a request, from `X-API-Key` or `Authorization: Bearer`.
+pub fn request_key(headers: &HeaderMap) -> Option<&str> {
+    let value = |name| headers.get(name).and_then(|value| value.to_str().ok());
+
+    value(API_KEY_HEADER)
+        .or_else(|| {
+            value(header::AUTHORIZATION.as_str())
+                .and_then(|authorization| authorization.strip_prefix("Bearer "))
+        })
+        .map(str::trim)
+}
+
+/// Rejects requests without a valid API key with 401, and makes the [`Principal`] of the key
+/// available to the handlers as an `Extension`.
+pub async fn require_api_key(
+    State(keys): State<Arc<ApiKeys>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let principal = request_key(request.headers())
+        .and_then(|key| keys.principal(key))
+        .cloned();
+
+    match principal {
+        Some(principal) => {
+            request.extensions_mut().insert(principal);
+            next.run(request).await
+        }
+        None => {
+            let mut response = json_error(
+                StatusCode::UNAUTHORIZED,
+                "unauthorized",
+                "Missing or invalid API key",
+            );
+            response
+                .headers_mut()
+                .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+            response
+        }
+    }
+}
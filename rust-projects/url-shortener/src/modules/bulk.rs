@@ -0,0 +1,439 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use super::store::{ImportRow, LinkOptions, UrlMap};
+
+/// Columns of exported CSV files, named like the fields of [`UrlMap`].
+const CSV_COLUMNS: &[&str] = &[
+    "hash",
+    "original",
+    "short",
+    "expires_at",
+    "max_clicks",
+    "clicks",
+    "owner",
+    "interstitial",
+    "title",
+    "tags",
+    "group",
+];
+
+/// Format of bulk imports and exports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BulkFormat {
+    /// Comma separated values with a header line.
+    Csv,
+    /// One JSON object per line.
+    JsonLines,
+}
+
+impl BulkFormat {
+    /// Parses `csv` or `jsonl`.
+    pub fn parse(format: &str) -> Option<Self> {
+        match format {
+            "csv" => Some(BulkFormat::Csv),
+            "jsonl" | "ndjson" => Some(BulkFormat::JsonLines),
+            _ => None,
+        }
+    }
+
+    /// Reads the format from a `Content-Type` header, ignoring its parameters.
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let mime = content_type.split(';').next()?.trim().to_lowercase();
+
+        match mime.as_str() {
+            "text/csv" => Some(BulkFormat::Csv),
+            "application/x-ndjson" | "application/jsonl" | "application/json-lines" => {
+                Some(BulkFormat::JsonLines)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            BulkFormat::Csv => "text/csv; charset=utf-8",
+            BulkFormat::JsonLines => "application/x-ndjson",
+        }
+    }
+
+    /// Returns the first lines of an export, before the links.
+    pub fn header(&self) -> String {
+        match self {
+            BulkFormat::Csv => format!("{}\n", CSV_COLUMNS.join(",")),
+            BulkFormat::JsonLines => String::new(),
+        }
+    }
+
+    /// Appends a link to an export.
+    pub fn write(&self, link: &UrlMap, out: &mut String) {
+        match self {
+            BulkFormat::Csv => {
+                let fields = [
+                    link.hash.clone(),
+                    link.original.clone(),
+                    link.short.clone(),
+                    link.expires_at
+                        .map(|expires_at| expires_at.to_rfc3339())
+                        .unwrap_or_default(),
+                    link.max_clicks
+                        .map(|max| max.to_string())
+                        .unwrap_or_default(),
+                    link.clicks.to_string(),
+                    link.owner.clone().unwrap_or_default(),
+                    link.interstitial.to_string(),
+                    link.title.clone().unwrap_or_default(),
+                    link.tags.join(" "),
+                    link.group.clone().unwrap_or_default(),
+                ];
+                let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+                out.push_str(&fields.join(","));
+            }
+            BulkFormat::JsonLines => {
+                out.push_str(&serde_json::to_string(link).expect("links serialize to JSON"));
+            }
+        }
+        out.push('\n');
+    }
+
+    /// Reads the rows of an import. Blank lines are skipped.
+    ///
+    /// CSV imports start with a header naming their columns: `url` (or `original`), and
+    /// optionally `alias` (or `hash`), `expires_at`, `max_clicks`, `interstitial`, `title`,
+    /// `tags`, separated by spaces, and `group`, other columns are ignored. Quoted fields may
+    /// span several lines.
+    /// JSON lines have the same fields, with `tags` as an array. Exports can be imported back.
+    ///
+    /// # Returns
+    /// For each row, the number of its first line, from 1, and the row or why it could not be
+    /// read.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use url_shortener::modules::bulk::BulkFormat;
+    ///
+    /// let rows = BulkFormat::Csv.parse_rows(
+    ///     "url,alias\n\
+    ///      https://a.com,\n\
+    ///      \n\
+    ///      \"https://b.com/?q=1,2\",bee\n",
+    /// );
+    ///
+    /// assert_eq!(rows[0].0, 2);
+    /// assert_eq!(rows[0].1.as_ref().unwrap().alias, None);
+    /// assert_eq!(rows[1].0, 4);
+    /// assert_eq!(rows[1].1.as_ref().unwrap().url, "https://b.com/?q=1,2");
+    /// ```
+    pub fn parse_rows(&self, body: &str) -> Vec<(usize, Result<ImportRow, String>)> {
+        match self {
+            BulkFormat::Csv => {
+                let mut records = csv_records(body).into_iter();
+                let Some((line, header)) = records.next() else {
+                    return Vec::new();
+                };
+                let columns = match header.and_then(|header| CsvColumns::parse(&header)) {
+                    Ok(columns) => columns,
+                    Err(e) => return vec![(line, Err(e))],
+                };
+
+                records
+                    .map(|(line, fields)| (line, fields.and_then(|fields| columns.row(&fields))))
+                    .collect()
+            }
+            BulkFormat::JsonLines => body
+                .lines()
+                .enumerate()
+                .map(|(i, line)| (i + 1, line))
+                .filter(|(_, line)| !line.trim().is_empty())
+                .map(|(line, text)| {
+                    let row = serde_json::from_str::<JsonRow>(text)
+                        .map(JsonRow::into_row)
+                        .map_err(|e| e.to_string());
+                    (line, row)
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct JsonRow {
+    #[serde(alias = "original")]
+    url: String,
+    #[serde(alias = "hash")]
+    alias: Option<String>,
+    expires_at: Option<DateTime<Utc>>,
+    max_clicks: Option<u64>,
+    #[serde(default)]
+    interstitial: bool,
+    title: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    group: Option<String>,
+}
+
+impl JsonRow {
+    fn into_row(self) -> ImportRow {
+        ImportRow {
+            url: self.url,
+            alias: self.alias,
+            options: LinkOptions {
+                expires_at: self.expires_at,
+                max_clicks: self.max_clicks,
+                interstitial: self.interstitial,
+                title: self.title,
+                tags: self.tags,
+                group: self.group,
+            },
+        }
+    }
+}
+
+/// Positions of the columns of a CSV import.
+struct CsvColumns {
+    url: usize,
+    alias: Option<usize>,
+    expires_at: Option<usize>,
+    max_clicks: Option<usize>,
+    interstitial: Option<usize>,
+    title: Option<usize>,
+    tags: Option<usize>,
+    group: Option<usize>,
+}
+
+impl CsvColumns {
+    fn parse(names: &[String]) -> Result<Self, String> {
+        let position = |candidates: &[&str]| {
+            names
+                .iter()
+                .position(|name| candidates.contains(&name.trim().to_lowercase().as_str()))
+        };
+
+        Ok(CsvColumns {
+            url: position(&["url", "original"])
+                .ok_or_else(|| "Header has no url column".to_string())?,
+            alias: position(&["alias", "hash"]),
+            expires_at: position(&["expires_at"]),
+            max_clicks: position(&["max_clicks"]),
+            interstitial: position(&["interstitial"]),
+            title: position(&["title"]),
+            tags: position(&["tags"]),
+            group: position(&["group"]),
+        })
+    }
+
+    fn row(&self, fields: &[String]) -> Result<ImportRow, String> {
+        let field = |position: Option<usize>| {
+            position
+                .and_then(|position| fields.get(position))
+                .map(|field| field.trim())
+                .filter(|field| !field.is_empty())
+        };
+
+        Ok(ImportRow {
+            url: field(Some(self.url))
+                .ok_or_else(|| "Missing url".to_string())?
+                .to_string(),
+            alias: field(self.alias).map(str::to_string),
+            options: LinkOptions {
+                expires_at: field(self.expires_at)
+                    .map(|expires_at| {
+                        DateTime::parse_from_rfc3339(expires_at)
+                            .map(|expires_at| expires_at.with_timezone(&Utc))
+                            .map_err(|e| format!("Invalid expires_at '{}': {}", expires_at, e))
+                    })
+                    .transpose()?,
+                max_clicks: field(self.max_clicks)
+                    .map(|max| {
+                        max.parse()
+                            .map_err(|_| format!("Invalid max_clicks '{}'", max))
+                    })
+                    .transpose()?,
+                interstitial: match field(self.interstitial) {
+                    None | Some("false" | "0") => false,
+                    Some("true" | "1") => true,
+                    Some(other) => return Err(format!("Invalid interstitial '{}'", other)),
+                },
+                title: field(self.title).map(str::to_string),
+                tags: field(self.tags)
+                    .map(|tags| tags.split_whitespace().map(str::to_string).collect())
+                    .unwrap_or_default(),
+                group: field(self.group).map(str::to_string),
+            },
+        })
+    }
+}
+
+/// Splits a CSV body into records of fields, skipping blank lines. Fields in double quotes may
+/// contain commas, `""` and line breaks.
+///
+/// # Returns
+/// For each record, the number of its first line, from 1, and its fields, or an error for a
+/// quoted field left open at the end of the body.
+fn csv_records(body: &str) -> Vec<(usize, Result<Vec<String>, String>)> {
+    let mut records = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut chars = body.chars().peekable();
+    let mut quoted = false;
+    let mut blank = true;
+    let (mut line, mut start) = (1, 1);
+
+    while let Some(c) = chars.next() {
+        if c == '\n' {
+            line += 1;
+        }
+
+        match (c, quoted) {
+            ('"', false) if field.is_empty() => quoted = true,
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', true) => quoted = false,
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            ('\r', false) if chars.peek() == Some(&'\n') => {}
+            ('\n', false) => {
+                fields.push(std::mem::take(&mut field));
+                if !blank {
+                    records.push((start, Ok(std::mem::take(&mut fields))));
+                }
+                fields.clear();
+                blank = true;
+                start = line;
+            }
+            (c, _) => field.push(c),
+        }
+
+        blank &= c.is_whitespace();
+    }
+
+    if quoted {
+        records.push((start, Err("Unterminated quoted field".to_string())));
+    } else if !blank {
+        fields.push(field);
+        records.push((start, Ok(fields)));
+    }
+    records
+}
+
+/// Quotes a CSV field if needed.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn test_csv_records() {
+        let fields = |body: &str| -> Vec<Vec<String>> {
+            csv_records(body)
+                .into_iter()
+                .map(|(_, fields)| fields.unwrap())
+                .collect()
+        };
+
+        assert_eq!(fields("a,,c\r\n"), [["a", "", "c"]]);
+        assert_eq!(
+            fields(r#""a,b","say ""hi""",c"#),
+            [["a,b", r#"say "hi""#, "c"]]
+        );
+        assert_eq!(
+            csv_records("a,\"one\ntwo\"\n  \nb,c\n"),
+            [
+                (1, Ok(vec!["a".to_string(), "one\ntwo".to_string()])),
+                (4, Ok(vec!["b".to_string(), "c".to_string()])),
+            ]
+        );
+        assert_eq!(
+            csv_records("a\nb,\"c\nd\n"),
+            [
+                (1, Ok(vec!["a".to_string()])),
+                (2, Err("Unterminated quoted field".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_rows() {
+        let rows = BulkFormat::Csv.parse_rows(
+            "max_clicks,URL,expires_at\n\
+             5,https://a.com,2033-05-18T03:33:20Z\n\
+             x,https://b.com,\n\
+             ,,\n",
+        );
+
+        assert_eq!(
+            rows[0],
+            (
+                2,
+                Ok(ImportRow {
+                    url: "https://a.com".to_string(),
+                    alias: None,
+                    options: LinkOptions {
+                        expires_at: Utc.timestamp_opt(2_000_000_000, 0).single(),
+                        max_clicks: Some(5),
+                        ..LinkOptions::default()
+                    },
+                })
+            )
+        );
+        assert_eq!(rows[1], (3, Err("Invalid max_clicks 'x'".to_string())));
+        assert_eq!(rows[2], (4, Err("Missing url".to_string())));
+
+        assert!(BulkFormat::Csv.parse_rows("alias\nabc\n")[0].1.is_err());
+
+        let rows = BulkFormat::JsonLines
+            .parse_rows("{\"url\": \"https://a.com\", \"alias\": \"abc\"}\n{\"alias\": \"def\"}\n");
+        assert_eq!(rows[0].1.as_ref().unwrap().alias.as_deref(), Some("abc"));
+        assert!(rows[1].1.is_err());
+    }
+
+    #[test]
+    fn test_export_can_be_imported() {
+        let link = UrlMap {
+            hash: "abc".to_string(),
+            original: "https://a.com/?q=1,2".to_string(),
+            short: "http://localhost:3000/abc".to_string(),
+            expires_at: Utc.timestamp_opt(2_000_000_000, 0).single(),
+            max_clicks: Some(5),
+            clicks: 2,
+            owner: Some("alice".to_string()),
+            title: Some("A, \"quoted\"\ntitle".to_string()),
+            tags: vec!["launch".to_string(), "q3".to_string()],
+            group: Some("spring".to_string()),
+            interstitial: true,
+            preview: None,
+        };
+        let row = ImportRow {
+            url: link.original.clone(),
+            alias: Some(link.hash.clone()),
+            options: LinkOptions {
+                expires_at: link.expires_at,
+                max_clicks: link.max_clicks,
+                interstitial: link.interstitial,
+                title: link.title.clone(),
+                tags: link.tags.clone(),
+                group: link.group.clone(),
+            },
+        };
+
+        for format in [BulkFormat::Csv, BulkFormat::JsonLines] {
+            let mut export = format.header();
+            format.write(&link, &mut export);
+
+            let rows = format.parse_rows(&export);
+            assert_eq!(rows.len(), 1);
+            assert_eq!(rows[0].1, Ok(row.clone()));
+        }
+    }
+}
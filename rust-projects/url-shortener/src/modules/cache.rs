@@ -0,0 +1,334 @@
+pub mod redis_invalidations;
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+
+use super::{
+    config::Settings,
+    storage::{StoreError, UrlRecord},
+};
+
+const DEFAULT_CAPACITY: usize = 10_000;
+const DEFAULT_TTL_SECONDS: u64 = 60;
+const DEFAULT_NEGATIVE_TTL_SECONDS: u64 = 5;
+
+/// Size and lifetimes of the [`LinkCache`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheConfig {
+    /// Most links kept, from `CACHE_CAPACITY`. `0` disables the cache.
+    pub capacity: usize,
+    /// How long a link is kept, from `CACHE_TTL_SECONDS`. Bounds how stale a link can get
+    /// when an invalidation is lost.
+    pub ttl: Duration,
+    /// How long an unknown code is remembered, from `CACHE_NEGATIVE_TTL_SECONDS`.
+    pub negative_ttl: Duration,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            capacity: DEFAULT_CAPACITY,
+            ttl: Duration::from_secs(DEFAULT_TTL_SECONDS),
+            negative_ttl: Duration::from_secs(DEFAULT_NEGATIVE_TTL_SECONDS),
+        }
+    }
+}
+
+impl CacheConfig {
+    pub fn from_settings(settings: &Settings) -> Result<Self, String> {
+        let default = CacheConfig::default();
+
+        Ok(CacheConfig {
+            capacity: settings
+                .parsed("CACHE_CAPACITY")?
+                .unwrap_or(default.capacity),
+            ttl: settings
+                .parsed("CACHE_TTL_SECONDS")?
+                .map_or(default.ttl, Duration::from_secs),
+            negative_ttl: settings
+                .parsed("CACHE_NEGATIVE_TTL_SECONDS")?
+                .map_or(default.negative_ttl, Duration::from_secs),
+        })
+    }
+}
+
+/// Channel telling the other instances which links changed.
+#[async_trait]
+pub trait Invalidations: Send + Sync {
+    /// Tells every instance to drop its cached entries of `codes`.
+    async fn publish(&self, codes: &[String]) -> Result<(), StoreError>;
+}
+
+struct Entry {
+    /// `None` for a code that is not stored.
+    record: Option<UrlRecord>,
+    expires: Instant,
+    /// Time of the last use, on the clock of the [`Lru`].
+    used: u64,
+}
+
+/// Entries evicted least recently used first past their capacity.
+struct Lru {
+    capacity: usize,
+    entries: HashMap<String, Entry>,
+    /// Codes by time of last use.
+    recency: BTreeMap<u64, String>,
+    clock: u64,
+}
+
+impl Lru {
+    fn new(capacity: usize) -> Self {
+        Lru {
+            capacity,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            clock: 0,
+        }
+    }
+
+    fn get(&mut self, code: &str, now: Instant) -> Option<Option<UrlRecord>> {
+        if self.entries.get(code)?.expires <= now {
+            self.remove(code);
+            return None;
+        }
+
+        self.clock += 1;
+        let entry = self.entries.get_mut(code)?;
+        self.recency.remove(&entry.used);
+        self.recency.insert(self.clock, code.to_string());
+        entry.used = self.clock;
+
+        Some(entry.record.clone())
+    }
+
+    fn insert(&mut self, code: &str, record: Option<UrlRecord>, expires: Instant) {
+        self.remove(code);
+        self.clock += 1;
+        self.recency.insert(self.clock, code.to_string());
+        self.entries.insert(
+            code.to_string(),
+            Entry {
+                record,
+                expires,
+                used: self.clock,
+            },
+        );
+
+        while self.entries.len() > self.capacity {
+            let Some((_, oldest)) = self.recency.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+    }
+
+    fn remove(&mut self, code: &str) {
+        if let Some(entry) = self.entries.remove(code) {
+            self.recency.remove(&entry.used);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+    }
+}
+
+/// Links kept in the process memory, in front of the storage backend.
+///
+/// Unknown codes are cached too, apart from the links and for a shorter time, so that requests
+/// for codes that do not exist cannot evict the links. Clones share the same entries.
+#[derive(Clone)]
+pub struct LinkCache {
+    found: Arc<Mutex<Lru>>,
+    missing: Arc<Mutex<Lru>>,
+    ttl: Duration,
+    negative_ttl: Duration,
+    invalidations: Option<Arc<dyn Invalidations>>,
+}
+
+impl LinkCache {
+    /// Creates a cache of `config.capacity` links and a quarter as many unknown codes.
+    pub fn new(config: &CacheConfig) -> Self {
+        LinkCache {
+            found: Arc::new(Mutex::new(Lru::new(config.capacity))),
+            missing: Arc::new(Mutex::new(Lru::new((config.capacity / 4).max(1)))),
+            ttl: config.ttl,
+            negative_ttl: config.negative_ttl,
+            invalidations: None,
+        }
+    }
+
+    /// Sets the channel [`LinkCache::invalidate`] tells the other instances through.
+    pub fn with_invalidations(mut self, invalidations: Arc<dyn Invalidations>) -> Self {
+        self.invalidations = Some(invalidations);
+        self
+    }
+
+    /// Looks up a code.
+    ///
+    /// # Returns
+    /// * `Some(Some(record))` if the link is cached.
+    /// * `Some(None)` if the code is known not to be stored.
+    /// * `None` if the code is not cached.
+    pub fn get(&self, code: &str) -> Option<Option<UrlRecord>> {
+        self.get_at(code, Instant::now())
+    }
+
+    fn get_at(&self, code: &str, now: Instant) -> Option<Option<UrlRecord>> {
+        if let Some(record) = self.found.lock().unwrap().get(code, now) {
+            return Some(record);
+        }
+        self.missing.lock().unwrap().get(code, now)
+    }
+
+    /// Caches the record stored under `code`, `None` if there is none.
+    pub fn insert(&self, code: &str, record: Option<UrlRecord>) {
+        self.insert_at(code, record, Instant::now());
+    }
+
+    fn insert_at(&self, code: &str, record: Option<UrlRecord>, now: Instant) {
+        match record {
+            Some(record) => {
+                self.missing.lock().unwrap().remove(code);
+                self.found
+                    .lock()
+                    .unwrap()
+                    .insert(code, Some(record), now + self.ttl);
+            }
+            None => {
+                self.found.lock().unwrap().remove(code);
+                self.missing
+                    .lock()
+                    .unwrap()
+                    .insert(code, None, now + self.negative_ttl);
+            }
+        }
+    }
+
+    /// Drops the entries of `codes` from this instance only.
+    pub fn evict(&self, codes: &[String]) {
+        let (mut found, mut missing) = (self.found.lock().unwrap(), self.missing.lock().unwrap());
+        for code in codes {
+            found.remove(code);
+            missing.remove(code);
+        }
+    }
+
+    /// Drops every entry of this instance.
+    pub fn clear(&self) {
+        self.found.lock().unwrap().clear();
+        self.missing.lock().unwrap().clear();
+    }
+
+    /// Drops the entries of links that were added, changed or removed, here and on the other
+    /// instances. Failing to tell the other instances only delays the change there, until
+    /// their entries expire, so it is logged rather than returned.
+    pub async fn invalidate(&self, codes: &[String]) {
+        if codes.is_empty() {
+            return;
+        }
+
+        self.evict(codes);
+        if let Some(invalidations) = &self.invalidations {
+            if let Err(e) = invalidations.publish(codes).await {
+                tracing::warn!(error = %e, "could not publish cache invalidations");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(capacity: usize) -> LinkCache {
+        LinkCache::new(&CacheConfig {
+            capacity,
+            ..CacheConfig::default()
+        })
+    }
+
+    #[test]
+    fn test_least_recently_used_evicted() {
+        let cache = cache(2);
+        let now = Instant::now();
+
+        cache.insert_at("a", Some(UrlRecord::new("https://a.com")), now);
+        cache.insert_at("b", Some(UrlRecord::new("https://b.com")), now);
+        assert!(cache.get_at("a", now).is_some());
+        cache.insert_at("c", Some(UrlRecord::new("https://c.com")), now);
+
+        assert!(cache.get_at("a", now).is_some());
+        assert!(cache.get_at("b", now).is_none());
+        assert_eq!(
+            cache.get_at("c", now).flatten().unwrap().url,
+            "https://c.com"
+        );
+    }
+
+    #[test]
+    fn test_expiry() {
+        let cache = cache(10);
+        let now = Instant::now();
+
+        cache.insert_at("a", Some(UrlRecord::new("https://a.com")), now);
+        cache.insert_at("b", None, now);
+        assert_eq!(cache.get_at("b", now), Some(None));
+
+        let later = now + Duration::from_secs(DEFAULT_NEGATIVE_TTL_SECONDS);
+        assert!(cache.get_at("a", later).is_some());
+        assert_eq!(cache.get_at("b", later), None);
+
+        let later = now + Duration::from_secs(DEFAULT_TTL_SECONDS);
+        assert_eq!(cache.get_at("a", later), None);
+    }
+
+    #[test]
+    fn test_unknown_codes_kept_apart() {
+        let cache = cache(4);
+        let now = Instant::now();
+
+        cache.insert_at("a", Some(UrlRecord::new("https://a.com")), now);
+        for code in ["x", "y", "z"] {
+            cache.insert_at(code, None, now);
+        }
+
+        // A quarter of the capacity is left to unknown codes.
+        assert!(cache.get_at("a", now).is_some());
+        assert_eq!(cache.get_at("y", now), None);
+        assert_eq!(cache.get_at("z", now), Some(None));
+
+        cache.insert_at("z", Some(UrlRecord::new("https://z.com")), now);
+        assert!(cache.get_at("z", now).flatten().is_some());
+    }
+
+    struct Recorded(Mutex<Vec<String>>);
+
+    #[async_trait]
+    impl Invalidations for Recorded {
+        async fn publish(&self, codes: &[String]) -> Result<(), StoreError> {
+            self.0.lock().unwrap().extend_from_slice(codes);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_invalidate() {
+        let published = Arc::new(Recorded(Mutex::new(Vec::new())));
+        let cache = cache(10).with_invalidations(published.clone());
+
+        cache.insert("a", Some(UrlRecord::new("https://a.com")));
+        cache.insert("b", None);
+        cache.invalidate(&["a".to_string(), "b".to_string()]).await;
+
+        assert_eq!(cache.get("a"), None);
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(*published.0.lock().unwrap(), ["a", "b"]);
+    }
+}
@@ -0,0 +1,89 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use redis::aio::ConnectionManager;
+use tokio::task::JoinHandle;
+
+use super::{Invalidations, LinkCache};
+use crate::modules::storage::StoreError;
+
+/// Delay before subscribing again after the subscription was lost.
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(1);
+
+/// Invalidations published on a Redis channel, `{prefix}:cache:invalidate`, one message per
+/// code.
+pub struct RedisInvalidations {
+    client: redis::Client,
+    connection: ConnectionManager,
+    channel: String,
+}
+
+impl RedisInvalidations {
+    /// Connects to Redis, using the channel of the links stored under `prefix`.
+    pub async fn connect(url: &str, prefix: &str) -> Result<Self, StoreError> {
+        let client = redis::Client::open(url)?;
+        let connection = ConnectionManager::new(client.clone()).await?;
+
+        Ok(RedisInvalidations {
+            client,
+            connection,
+            channel: format!("{}:cache:invalidate", prefix),
+        })
+    }
+
+    /// Evicts the codes published by every instance, this one included, from `cache`.
+    ///
+    /// The subscription is renewed when lost, clearing `cache` as the invalidations published
+    /// in between were missed.
+    pub fn listen(&self, cache: LinkCache) -> JoinHandle<()> {
+        let (client, channel) = (self.client.clone(), self.channel.clone());
+
+        tokio::spawn(async move {
+            loop {
+                match subscribe(&client, &channel).await {
+                    Ok(mut pubsub) => {
+                        cache.clear();
+
+                        let mut messages = pubsub.on_message();
+                        while let Some(message) = messages.next().await {
+                            if let Ok(code) = message.get_payload::<String>() {
+                                cache.evict(&[code]);
+                            }
+                        }
+                        tracing::warn!("lost the cache invalidation subscription");
+                    }
+                    Err(e) => {
+                        tracing::warn!(error = %e, "could not subscribe to cache invalidations")
+                    }
+                }
+
+                tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+            }
+        })
+    }
+}
+
+async fn subscribe(
+    client: &redis::Client,
+    channel: &str,
+) -> redis::RedisResult<redis::aio::PubSub> {
+    let mut pubsub = client.get_async_pubsub().await?;
+    pubsub.subscribe(channel).await?;
+    Ok(pubsub)
+}
+
+#[async_trait]
+impl Invalidations for RedisInvalidations {
+    async fn publish(&self, codes: &[String]) -> Result<(), StoreError> {
+        let mut pipeline = redis::pipe();
+        for code in codes {
+            pipeline.publish(&self.channel, code).ignore();
+        }
+
+        pipeline
+            .query_async::<_, ()>(&mut self.connection.clone())
+            .await?;
+        Ok(())
+    }
+}
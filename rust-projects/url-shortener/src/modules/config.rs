@@ -0,0 +1,228 @@
+use std::{collections::HashMap, env, fs, net::SocketAddr, time::Duration};
+
+use super::{
+    cache::CacheConfig,
+    short_code::{DEFAULT_CODE_LENGTH, MAX_CODE_LENGTH},
+    storage::StorageConfig,
+};
+
+/// Environment variable with the path of the configuration file.
+pub const CONFIG_FILE_VAR: &str = "CONFIG_FILE";
+
+const DEFAULT_BIND_ADDRESS: &str = "127.0.0.1:3000";
+const DEFAULT_BASE_URL: &str = "http://localhost:3000";
+const DEFAULT_SHUTDOWN_TIMEOUT_SECONDS: u64 = 30;
+
+/// Settings given as environment variables or in a file of `KEY=value` lines, the environment
+/// taking precedence. Every setting of the service can be given either way.
+#[derive(Debug, Clone, Default)]
+pub struct Settings {
+    values: HashMap<String, String>,
+}
+
+impl Settings {
+    /// Parses `KEY=value` lines. Empty lines and lines starting with `#` are ignored, and values
+    /// may be quoted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use url_shortener::modules::config::Settings;
+    ///
+    /// let settings = Settings::parse(
+    ///     "# Server\n\
+    ///      BIND_ADDRESS = 0.0.0.0:8080\n\
+    ///      BASE_URL=\"https://sho.rt\"\n",
+    /// )
+    /// .unwrap();
+    ///
+    /// assert_eq!(settings.get("BIND_ADDRESS").as_deref(), Some("0.0.0.0:8080"));
+    /// assert_eq!(settings.get("BASE_URL").as_deref(), Some("https://sho.rt"));
+    /// assert!(Settings::parse("BIND_ADDRESS").is_err());
+    /// ```
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut values = HashMap::new();
+
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid configuration line '{}'", line))?;
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|value| value.strip_suffix('"'))
+                .unwrap_or(value);
+            values.insert(key.trim().to_string(), value.to_string());
+        }
+
+        Ok(Settings { values })
+    }
+
+    /// Reads the file named by `CONFIG_FILE`, if set, then the environment.
+    pub fn load() -> Result<Self, String> {
+        let mut settings = match env::var(CONFIG_FILE_VAR) {
+            Ok(path) => {
+                let content = fs::read_to_string(&path)
+                    .map_err(|e| format!("Could not read {}: {}", path, e))?;
+                Self::parse(&content)?
+            }
+            Err(_) => Settings::default(),
+        };
+
+        settings.values.extend(env::vars());
+        Ok(settings)
+    }
+
+    pub fn with(mut self, key: &str, value: &str) -> Self {
+        self.values.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.values.get(key).cloned()
+    }
+
+    /// Returns `true` if the setting is `1`, `true` or `on`.
+    pub fn flag(&self, key: &str) -> bool {
+        matches!(
+            self.values.get(key).map(String::as_str),
+            Some("1" | "true" | "on")
+        )
+    }
+
+    /// Parses a setting, `Ok(None)` if it is not set.
+    pub fn parsed<T: std::str::FromStr>(&self, key: &str) -> Result<Option<T>, String> {
+        self.values
+            .get(key)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| format!("Invalid {} '{}'", key, value))
+            })
+            .transpose()
+    }
+}
+
+/// Settings of the server itself.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// Address the server listens on, from `BIND_ADDRESS`.
+    pub bind_address: SocketAddr,
+    /// Public URL the short links start with, from `BASE_URL`.
+    pub base_url: String,
+    pub storage: StorageConfig,
+    pub cache: CacheConfig,
+    /// From `SHORT_CODE_LENGTH`.
+    pub code_length: usize,
+    /// How long open connections may take to finish once a shutdown started, from
+    /// `SHUTDOWN_TIMEOUT_SECONDS`.
+    pub shutdown_timeout: Duration,
+    /// How long fetching the preview of a new link may take, from `PREVIEW_TIMEOUT_SECONDS`.
+    /// `0`, the default, disables the previews, which fetch the pages of new links.
+    pub preview_timeout: Duration,
+}
+
+impl Config {
+    pub fn from_settings(settings: &Settings) -> Result<Self, String> {
+        let bind_address = settings
+            .parsed("BIND_ADDRESS")?
+            .unwrap_or_else(|| DEFAULT_BIND_ADDRESS.parse().unwrap());
+        let base_url = settings
+            .get("BASE_URL")
+            .unwrap_or_else(|| DEFAULT_BASE_URL.to_string())
+            .trim_end_matches('/')
+            .to_string();
+        let code_length = settings
+            .parsed("SHORT_CODE_LENGTH")?
+            .unwrap_or(DEFAULT_CODE_LENGTH);
+        if !(1..=MAX_CODE_LENGTH).contains(&code_length) {
+            return Err(format!(
+                "Invalid SHORT_CODE_LENGTH '{}', expected 1 to {}",
+                code_length, MAX_CODE_LENGTH
+            ));
+        }
+
+        Ok(Config {
+            bind_address,
+            base_url,
+            storage: StorageConfig::from_settings(settings).map_err(|e| e.to_string())?,
+            cache: CacheConfig::from_settings(settings)?,
+            code_length,
+            shutdown_timeout: Duration::from_secs(
+                settings
+                    .parsed("SHUTDOWN_TIMEOUT_SECONDS")?
+                    .unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT_SECONDS),
+            ),
+            preview_timeout: Duration::from_secs(
+                settings
+                    .parsed("PREVIEW_TIMEOUT_SECONDS")?
+                    .unwrap_or_default(),
+            ),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults() {
+        let config = Config::from_settings(&Settings::default()).unwrap();
+
+        assert_eq!(config.bind_address, "127.0.0.1:3000".parse().unwrap());
+        assert_eq!(config.base_url, "http://localhost:3000");
+        assert_eq!(
+            config.storage,
+            StorageConfig::Redis {
+                url: "redis://127.0.0.1/".to_string(),
+                prefix: "shortener".to_string(),
+                migrate_legacy_keys: false,
+            }
+        );
+        assert_eq!(config.code_length, DEFAULT_CODE_LENGTH);
+        assert_eq!(config.cache, CacheConfig::default());
+        assert_eq!(config.preview_timeout, Duration::ZERO);
+    }
+
+    #[test]
+    fn test_from_settings() {
+        let settings = Settings::parse(
+            "BIND_ADDRESS=0.0.0.0:8080\n\
+             BASE_URL=https://sho.rt/\n\
+             STORAGE_BACKEND=sqlite\n\
+             SQLITE_PATH=/var/lib/links.db\n\
+             SHORT_CODE_LENGTH=9\n\
+             CACHE_CAPACITY=0\n\
+             PREVIEW_TIMEOUT_SECONDS=3\n",
+        )
+        .unwrap()
+        .with("SHUTDOWN_TIMEOUT_SECONDS", "5");
+        let config = Config::from_settings(&settings).unwrap();
+
+        assert_eq!(config.bind_address, "0.0.0.0:8080".parse().unwrap());
+        assert_eq!(config.base_url, "https://sho.rt");
+        assert_eq!(
+            config.storage,
+            StorageConfig::Sqlite("/var/lib/links.db".to_string())
+        );
+        assert_eq!(config.code_length, 9);
+        assert_eq!(config.cache.capacity, 0);
+        assert_eq!(config.preview_timeout, Duration::from_secs(3));
+        assert_eq!(config.shutdown_timeout, Duration::from_secs(5));
+
+        let settings = settings.with("SHORT_CODE_LENGTH", "nine");
+        assert!(Config::from_settings(&settings).is_err());
+        for length in ["0", "11"] {
+            let settings = settings.clone().with("SHORT_CODE_LENGTH", length);
+            assert_eq!(
+                Config::from_settings(&settings).unwrap_err(),
+                format!("Invalid SHORT_CODE_LENGTH '{}', expected 1 to 10", length)
+            );
+        }
+    }
+}
@@ -0,0 +1,113 @@
+const MAX_TAGS: usize = 16;
+const MAX_TAG_LENGTH: usize = 32;
+const MAX_GROUP_LENGTH: usize = 64;
+const MAX_TITLE_CHARS: usize = 200;
+
+/// Returns `true` for the characters of tags and group names.
+fn is_label_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':' | '/')
+}
+
+/// Checks the tags of a link and returns them trimmed, lowercase, sorted and without
+/// duplicates.
+///
+/// # Example
+///
+/// ```
+/// use url_shortener::modules::labels::normalize_tags;
+///
+/// let tags = ["Launch", " q3:2024", "launch"].map(str::to_string);
+///
+/// assert_eq!(normalize_tags(&tags).unwrap(), ["launch", "q3:2024"]);
+/// assert!(normalize_tags(&["two words".to_string()]).is_err());
+/// ```
+pub fn normalize_tags(tags: &[String]) -> Result<Vec<String>, String> {
+    let mut ret = Vec::with_capacity(tags.len());
+
+    for tag in tags {
+        let tag = tag.trim().to_lowercase();
+        if tag.is_empty() || tag.len() > MAX_TAG_LENGTH {
+            return Err(format!(
+                "Tags must be between 1 and {} characters long",
+                MAX_TAG_LENGTH
+            ));
+        }
+        if !tag.chars().all(is_label_char) {
+            return Err(format!(
+                "Tag '{}' may only contain letters, digits, '-', '_', '.', ':' and '/'",
+                tag
+            ));
+        }
+        ret.push(tag);
+    }
+
+    ret.sort();
+    ret.dedup();
+    if ret.len() > MAX_TAGS {
+        return Err(format!("A link has at most {} tags", MAX_TAGS));
+    }
+    Ok(ret)
+}
+
+/// Checks the name of a group, which is case sensitive.
+pub fn validate_group(group: &str) -> Result<(), String> {
+    if group.is_empty() || group.len() > MAX_GROUP_LENGTH {
+        return Err(format!(
+            "Group must be between 1 and {} characters long",
+            MAX_GROUP_LENGTH
+        ));
+    }
+    // Groups are a path segment of `/groups/{name}/stats`.
+    if !group.chars().all(|c| is_label_char(c) && c != '/') {
+        return Err("Group may only contain letters, digits, '-', '_', '.' and ':'".to_string());
+    }
+    Ok(())
+}
+
+/// Checks the title of a link and returns it trimmed, `None` if it is blank.
+pub fn normalize_title(title: &str) -> Result<Option<String>, String> {
+    let title = title.trim();
+    if title.chars().count() > MAX_TITLE_CHARS {
+        return Err(format!(
+            "Title must be at most {} characters long",
+            MAX_TITLE_CHARS
+        ));
+    }
+    Ok((!title.is_empty()).then(|| title.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_tags() {
+        assert_eq!(normalize_tags(&[]).unwrap(), Vec::<String>::new());
+        assert!(normalize_tags(&[" ".to_string()]).is_err());
+        assert!(normalize_tags(&["a".repeat(33)]).is_err());
+        assert!(normalize_tags(&["caf\u{e9}".to_string()]).is_err());
+
+        let many: Vec<String> = (0..17).map(|i| format!("tag-{}", i)).collect();
+        assert!(normalize_tags(&many).is_err());
+        assert_eq!(normalize_tags(&many[..16]).unwrap().len(), 16);
+    }
+
+    #[test]
+    fn test_validate_group() {
+        assert!(validate_group("spring-sale:2024").is_ok());
+        assert!(validate_group("spring/2024").is_err());
+        assert!(validate_group("").is_err());
+        assert!(validate_group("with space").is_err());
+        assert!(validate_group(&"g".repeat(65)).is_err());
+    }
+
+    #[test]
+    fn test_normalize_title() {
+        assert_eq!(
+            normalize_title("  Déjà vu ").unwrap().as_deref(),
+            Some("Déjà vu")
+        );
+        assert_eq!(normalize_title(" ").unwrap(), None);
+        assert!(normalize_title(&"é".repeat(201)).is_err());
+    }
+}
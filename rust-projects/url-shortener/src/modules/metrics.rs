@@ -0,0 +1,358 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::header,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+/// Upper bounds, in seconds, of the buckets of the request latency histogram.
+const REQUEST_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Upper bounds, in seconds, of the buckets of the storage latency histogram.
+const BACKEND_BUCKETS: &[f64] = &[
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0,
+];
+
+/// Route label of the requests that matched no route, so that scanners cannot create a series
+/// per path.
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// Content type of the Prometheus text format.
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+struct Histogram {
+    bounds: &'static [f64],
+    /// Number of observations in each bucket, not cumulated.
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Histogram {
+            bounds,
+            counts: vec![0; bounds.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        if let Some(bucket) = self.bounds.iter().position(|bound| value <= *bound) {
+            self.counts[bucket] += 1;
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let separator = if labels.is_empty() { "" } else { "," };
+        let mut cumulated = 0;
+        for (bound, count) in self.bounds.iter().zip(&self.counts) {
+            cumulated += count;
+            let _ = writeln!(
+                out,
+                "{name}_bucket{{{labels}{separator}le=\"{bound}\"}} {cumulated}"
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{name}_bucket{{{labels}{separator}le=\"+Inf\"}} {}",
+            self.count
+        );
+        let _ = writeln!(out, "{name}_sum{{{labels}}} {}", self.sum);
+        let _ = writeln!(out, "{name}_count{{{labels}}} {}", self.count);
+    }
+}
+
+/// Method, route and status of a request.
+type RequestLabels = (String, String, u16);
+
+#[derive(Default)]
+struct Registry {
+    requests: Mutex<BTreeMap<RequestLabels, Histogram>>,
+    backend: Mutex<BTreeMap<&'static str, Histogram>>,
+    backend_errors: Mutex<BTreeMap<&'static str, u64>>,
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+    permanent_redirects: AtomicU64,
+    temporary_redirects: AtomicU64,
+}
+
+/// Counters and latency histograms of the service, rendered in the Prometheus text format by
+/// `GET /metrics`. Clones share the same values.
+#[derive(Clone, Default)]
+pub struct Metrics {
+    registry: Arc<Registry>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a request answered with `status` after `elapsed`.
+    pub fn record_request(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        self.registry
+            .requests
+            .lock()
+            .unwrap()
+            .entry((method.to_string(), route.to_string(), status))
+            .or_insert_with(|| Histogram::new(REQUEST_BUCKETS))
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Records a call to the storage backend, e.g. `get` or `insert`.
+    pub fn record_backend(&self, operation: &'static str, elapsed: Duration, failed: bool) {
+        self.registry
+            .backend
+            .lock()
+            .unwrap()
+            .entry(operation)
+            .or_insert_with(|| Histogram::new(BACKEND_BUCKETS))
+            .observe(elapsed.as_secs_f64());
+
+        if failed {
+            *self
+                .registry
+                .backend_errors
+                .lock()
+                .unwrap()
+                .entry(operation)
+                .or_default() += 1;
+        }
+    }
+
+    /// Records a lookup of the link cache.
+    pub fn record_cache_lookup(&self, hit: bool) {
+        let counter = if hit {
+            &self.registry.cache_hits
+        } else {
+            &self.registry.cache_misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records a redirect to the original URL of a link.
+    pub fn record_redirect(&self, permanent: bool) {
+        let counter = if permanent {
+            &self.registry.permanent_redirects
+        } else {
+            &self.registry.temporary_redirects
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Renders every metric in the Prometheus text format.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use url_shortener::modules::metrics::Metrics;
+    ///
+    /// let metrics = Metrics::new();
+    /// metrics.record_request("GET", "/:url_hash", 308, Duration::from_millis(3));
+    /// metrics.record_redirect(true);
+    ///
+    /// let text = metrics.render();
+    /// assert!(text.contains(
+    ///     "http_requests_total{method=\"GET\",route=\"/:url_hash\",status=\"308\"} 1"
+    /// ));
+    /// assert!(text.contains("redirects_total{kind=\"permanent\"} 1"));
+    /// ```
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let registry = &self.registry;
+
+        {
+            let requests = registry.requests.lock().unwrap();
+            let labels: Vec<(String, &Histogram)> = requests
+                .iter()
+                .map(|((method, route, status), histogram)| {
+                    let labels = format!(
+                        "method=\"{}\",route=\"{}\",status=\"{}\"",
+                        escape(method),
+                        escape(route),
+                        status
+                    );
+                    (labels, histogram)
+                })
+                .collect();
+
+            header(
+                &mut out,
+                "http_requests_total",
+                "counter",
+                "Requests answered, by method, route and status.",
+            );
+            for (labels, histogram) in &labels {
+                let _ = writeln!(out, "http_requests_total{{{labels}}} {}", histogram.count);
+            }
+
+            header(
+                &mut out,
+                "http_request_duration_seconds",
+                "histogram",
+                "Time taken to answer requests, by method, route and status.",
+            );
+            for (labels, histogram) in &labels {
+                histogram.render(&mut out, "http_request_duration_seconds", labels);
+            }
+        }
+
+        header(
+            &mut out,
+            "storage_operation_duration_seconds",
+            "histogram",
+            "Time taken by the storage backend, by operation.",
+        );
+        for (operation, histogram) in registry.backend.lock().unwrap().iter() {
+            let labels = format!("operation=\"{}\"", operation);
+            histogram.render(&mut out, "storage_operation_duration_seconds", &labels);
+        }
+
+        header(
+            &mut out,
+            "storage_errors_total",
+            "counter",
+            "Failed calls to the storage backend, by operation.",
+        );
+        for (operation, errors) in registry.backend_errors.lock().unwrap().iter() {
+            let _ = writeln!(
+                out,
+                "storage_errors_total{{operation=\"{}\"}} {}",
+                operation, errors
+            );
+        }
+
+        header(
+            &mut out,
+            "cache_lookups_total",
+            "counter",
+            "Lookups of the link cache, by result. The hit rate is hit / (hit + miss).",
+        );
+        for (result, counter) in [
+            ("hit", &registry.cache_hits),
+            ("miss", &registry.cache_misses),
+        ] {
+            let _ = writeln!(
+                out,
+                "cache_lookups_total{{result=\"{}\"}} {}",
+                result,
+                counter.load(Ordering::Relaxed)
+            );
+        }
+
+        header(
+            &mut out,
+            "redirects_total",
+            "counter",
+            "Redirects served, by kind.",
+        );
+        for (kind, counter) in [
+            ("permanent", &registry.permanent_redirects),
+            ("temporary", &registry.temporary_redirects),
+        ] {
+            let _ = writeln!(
+                out,
+                "redirects_total{{kind=\"{}\"}} {}",
+                kind,
+                counter.load(Ordering::Relaxed)
+            );
+        }
+
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// Escapes a label value.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Records the method, route, status and latency of every request.
+pub async fn track_requests(
+    State(metrics): State<Metrics>,
+    matched_path: Option<MatchedPath>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method().to_string();
+    let route = matched_path.map_or_else(
+        || UNMATCHED_ROUTE.to_string(),
+        |path| path.as_str().to_string(),
+    );
+
+    let start = Instant::now();
+    let response = next.run(request).await;
+    metrics.record_request(&method, &route, response.status().as_u16(), start.elapsed());
+
+    response
+}
+
+/// Serves the metrics to Prometheus.
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "health",
+    responses((status = 200, description = "Metrics in the Prometheus text format",
+        content_type = "text/plain", body = String)),
+    security(())
+)]
+pub async fn metrics(State(metrics): State<Metrics>) -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, CONTENT_TYPE)], metrics.render())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram() {
+        let metrics = Metrics::new();
+        metrics.record_backend("get", Duration::from_micros(200), false);
+        metrics.record_backend("get", Duration::from_millis(4), false);
+        metrics.record_backend("get", Duration::from_secs(2), true);
+
+        let text = metrics.render();
+        for line in [
+            "storage_operation_duration_seconds_bucket{operation=\"get\",le=\"0.0005\"} 1",
+            "storage_operation_duration_seconds_bucket{operation=\"get\",le=\"0.005\"} 2",
+            "storage_operation_duration_seconds_bucket{operation=\"get\",le=\"1\"} 2",
+            "storage_operation_duration_seconds_bucket{operation=\"get\",le=\"+Inf\"} 3",
+            "storage_operation_duration_seconds_count{operation=\"get\"} 3",
+            "storage_errors_total{operation=\"get\"} 1",
+            "cache_lookups_total{result=\"hit\"} 0",
+        ] {
+            assert!(text.lines().any(|l| l == line), "missing {}", line);
+        }
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
@@ -0,0 +1,68 @@
+use axum::Json;
+use utoipa::{
+    openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme},
+    Modify, OpenApi,
+};
+
+use super::{auth::API_KEY_HEADER, metrics, routes};
+
+/// OpenAPI document of the routes, served at `/openapi.json`.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "url-shortener",
+        description = "Shortens URLs and redirects to them, or shows a preview page first. Every \
+            route but the redirects, health checks and metrics requires an API key, and answers \
+            401 without one and 429 over its rate limit."
+    ),
+    paths(
+        routes::add_url,
+        routes::get_all,
+        routes::update_url,
+        routes::delete_url,
+        routes::list_urls,
+        routes::create_url,
+        routes::bulk_import,
+        routes::export,
+        routes::get_url,
+        routes::patch_url,
+        routes::delete_code,
+        routes::stats,
+        routes::list_groups,
+        routes::group_stats,
+        routes::redirect,
+        routes::healthz,
+        routes::readyz,
+        metrics::metrics,
+    ),
+    modifiers(&SecuritySchemes),
+    security(("api_key" = []), ("bearer" = [])),
+    tags(
+        (name = "links", description = "Links of the caller"),
+        (name = "redirects", description = "Public short links"),
+        (name = "health", description = "Probes and metrics"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Declares the two ways of passing an API key.
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new(API_KEY_HEADER))),
+        );
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+    }
+}
+
+/// Serves the OpenAPI document.
+pub async fn openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
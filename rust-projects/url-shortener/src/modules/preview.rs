@@ -0,0 +1,202 @@
+pub mod http_fetcher;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use url::Url;
+use utoipa::ToSchema;
+
+use super::store::UrlMap;
+
+/// `Content-Security-Policy` of the preview pages, which show text and images of other sites.
+pub const CONTENT_SECURITY_POLICY: &str =
+    "default-src 'none'; img-src http: https:; style-src 'unsafe-inline'";
+
+/// What a link leads to, as announced by the target page, e.g. in its Open Graph tags.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, ToSchema)]
+pub struct LinkPreview {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// Absolute URL of an image of the page.
+    pub image: Option<String>,
+}
+
+impl LinkPreview {
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none() && self.description.is_none() && self.image.is_none()
+    }
+}
+
+/// Reads the preview of a URL, from the network or from a stub in tests.
+#[async_trait]
+pub trait PreviewFetcher: Send + Sync {
+    /// Fetches the title, description and image of the page at `url`.
+    async fn fetch(&self, url: &str) -> Result<LinkPreview, String>;
+}
+
+/// Escapes text for HTML content and quoted attribute values.
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Renders the preview page of a link, with its preview if any and a button going to `target`.
+/// The page is titled with the title of the link, else of its preview, else with its host.
+///
+/// `target` is the original URL when showing the page counted the click, and the short link
+/// otherwise, so that following the link still counts.
+///
+/// # Example
+///
+/// ```
+/// use url_shortener::modules::{
+///     preview::{render_page, LinkPreview},
+///     store::UrlMap,
+/// };
+///
+/// let link = UrlMap {
+///     hash: "abc".to_string(),
+///     original: "https://example.com/".to_string(),
+///     short: "http://localhost:3000/abc".to_string(),
+///     expires_at: None,
+///     max_clicks: None,
+///     clicks: 0,
+///     owner: None,
+///     title: None,
+///     tags: Vec::new(),
+///     group: None,
+///     interstitial: true,
+///     preview: Some(LinkPreview {
+///         title: Some("Fish & Chips".to_string()),
+///         ..LinkPreview::default()
+///     }),
+/// };
+///
+/// let page = render_page(&link, &link.original);
+/// assert!(page.contains("<h1>Fish &amp; Chips</h1>"));
+/// assert!(page.contains("href=\"https://example.com/\""));
+/// ```
+pub fn render_page(link: &UrlMap, target: &str) -> String {
+    let preview = link.preview.clone().unwrap_or_default();
+    let title = link.title.clone().or(preview.title).unwrap_or_else(|| {
+        Url::parse(&link.original)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .unwrap_or_else(|| link.original.clone())
+    });
+
+    let (mut meta, mut card) = (String::new(), String::new());
+    if let Some(image) = &preview.image {
+        let image = escape_html(image);
+        meta.push_str(&format!(
+            "<meta property=\"og:image\" content=\"{}\">\n",
+            image
+        ));
+        card.push_str(&format!("<img src=\"{}\" alt=\"\">\n", image));
+    }
+    card.push_str(&format!("<h1>{}</h1>\n", escape_html(&title)));
+    if let Some(description) = &preview.description {
+        let description = escape_html(description);
+        meta.push_str(&format!(
+            "<meta property=\"og:description\" content=\"{}\">\n",
+            description
+        ));
+        card.push_str(&format!("<p>{}</p>\n", description));
+    }
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<meta name="robots" content="noindex">
+<title>{title}</title>
+<meta property="og:title" content="{title}">
+{meta}<style>
+body {{ font-family: sans-serif; margin: 0; background: #f4f4f5; color: #18181b; }}
+main {{ max-width: 36rem; margin: 4rem auto; padding: 1.5rem; background: #fff;
+    border-radius: 0.5rem; box-shadow: 0 1px 3px rgba(0, 0, 0, 0.2); }}
+img {{ max-width: 100%; border-radius: 0.25rem; }}
+.url {{ color: #52525b; word-break: break-all; }}
+.continue {{ display: inline-block; padding: 0.5rem 1rem; border-radius: 0.25rem;
+    background: #2563eb; color: #fff; text-decoration: none; }}
+</style>
+</head>
+<body>
+<main>
+{card}<p class="url">{original}</p>
+<a class="continue" href="{target}" rel="noreferrer nofollow">Continue</a>
+</main>
+</body>
+</html>
+"#,
+        title = escape_html(&title),
+        original = escape_html(&link.original),
+        target = escape_html(target),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(preview: Option<LinkPreview>) -> UrlMap {
+        UrlMap {
+            hash: "abc".to_string(),
+            original: "https://example.com/a?b=1&c=2".to_string(),
+            short: "http://localhost:3000/abc".to_string(),
+            expires_at: None,
+            max_clicks: None,
+            clicks: 0,
+            owner: None,
+            title: None,
+            tags: Vec::new(),
+            group: None,
+            interstitial: false,
+            preview,
+        }
+    }
+
+    #[test]
+    fn test_render_page_escapes() {
+        let link = link(Some(LinkPreview {
+            title: Some("<script>alert(1)</script>".to_string()),
+            description: Some("\"quoted\"".to_string()),
+            image: Some("https://example.com/a.png?x=\"><b>".to_string()),
+        }));
+        let page = render_page(&link, &link.short);
+
+        assert!(!page.contains("<script>"));
+        assert!(page.contains("<h1>&lt;script&gt;alert(1)&lt;/script&gt;</h1>"));
+        assert!(page.contains("<p>&quot;quoted&quot;</p>"));
+        assert!(page.contains("src=\"https://example.com/a.png?x=&quot;&gt;&lt;b&gt;\""));
+        assert!(page.contains("https://example.com/a?b=1&amp;c=2"));
+        assert!(page.contains("href=\"http://localhost:3000/abc\""));
+    }
+
+    #[test]
+    fn test_render_page_without_preview() {
+        let link = link(None);
+        let page = render_page(&link, &link.original);
+
+        assert!(page.contains("<title>example.com</title>"));
+        assert!(!page.contains("<img"));
+        assert!(!page.contains("og:description"));
+
+        let link = UrlMap {
+            title: Some("Our launch".to_string()),
+            ..link
+        };
+        assert!(render_page(&link, &link.original).contains("<h1>Our launch</h1>"));
+    }
+}
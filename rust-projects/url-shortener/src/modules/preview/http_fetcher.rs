@@ -0,0 +1,470 @@
+use std::{collections::HashMap, net::SocketAddr, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use reqwest::{
+    dns::{Addrs, Name, Resolve, Resolving},
+    header, redirect,
+};
+use url::Url;
+
+use super::{LinkPreview, PreviewFetcher};
+use crate::modules::validation::{is_private_ip, UrlPolicy};
+
+/// Redirects followed before giving up on a page.
+const MAX_REDIRECTS: usize = 5;
+
+/// Bytes of a page read at most, the tags of a preview are in its head.
+const MAX_BODY_BYTES: usize = 256 * 1024;
+
+const MAX_TITLE_CHARS: usize = 200;
+const MAX_DESCRIPTION_CHARS: usize = 500;
+
+const USER_AGENT: &str = concat!(
+    "url-shortener/",
+    env!("CARGO_PKG_VERSION"),
+    " (link preview)"
+);
+
+/// Fetches the pages over HTTP and reads their Open Graph and Twitter card tags, falling back
+/// to their `<title>` and description.
+pub struct HttpFetcher {
+    client: reqwest::Client,
+    policy: UrlPolicy,
+}
+
+impl HttpFetcher {
+    /// Creates a fetcher giving up on a page after `timeout`. Pages and redirects are only
+    /// fetched from URLs `policy` accepts.
+    ///
+    /// Private addresses are always blocked, whatever `policy` says, including host names
+    /// resolving to one: the previews are shown to anyone, they must not leak internal pages.
+    pub fn new(policy: UrlPolicy, timeout: Duration) -> Self {
+        HttpFetcher::build(policy, timeout, true)
+    }
+
+    /// Creates a fetcher reaching private addresses too, for the tests against local stubs.
+    #[cfg(test)]
+    pub(crate) fn allowing_private(policy: UrlPolicy, timeout: Duration) -> Self {
+        HttpFetcher::build(policy, timeout, false)
+    }
+
+    fn build(policy: UrlPolicy, timeout: Duration, block_private: bool) -> Self {
+        let mut builder = reqwest::Client::builder();
+        let policy = if block_private {
+            builder = builder.dns_resolver(Arc::new(PublicResolver));
+            policy.with_block_private(true)
+        } else {
+            policy
+        };
+
+        let redirect_policy = policy.clone();
+        let client = builder
+            .timeout(timeout)
+            .user_agent(USER_AGENT)
+            .redirect(redirect::Policy::custom(move |attempt| {
+                if attempt.previous().len() >= MAX_REDIRECTS {
+                    attempt.error("too many redirects")
+                } else if redirect_policy.normalize(attempt.url().as_str()).is_err() {
+                    attempt.stop()
+                } else {
+                    attempt.follow()
+                }
+            }))
+            .build()
+            .expect("the HTTP client configuration is valid");
+
+        HttpFetcher { client, policy }
+    }
+}
+
+/// Resolves host names with the system resolver, leaving out the private addresses.
+///
+/// URLs with an IP address are not resolved, the policy checks those.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addresses = tokio::net::lookup_host((name.as_str(), 0)).await?;
+            let addresses = public_addresses(addresses);
+            if addresses.is_empty() {
+                return Err(format!("{} has no public address", name.as_str()).into());
+            }
+
+            Ok(Box::new(addresses.into_iter()) as Addrs)
+        })
+    }
+}
+
+fn public_addresses(addresses: impl IntoIterator<Item = SocketAddr>) -> Vec<SocketAddr> {
+    addresses
+        .into_iter()
+        .filter(|address| !is_private_ip(&address.ip()))
+        .collect()
+}
+
+#[async_trait]
+impl PreviewFetcher for HttpFetcher {
+    async fn fetch(&self, url: &str) -> Result<LinkPreview, String> {
+        self.policy.normalize(url).map_err(|e| e.to_string())?;
+
+        let mut response = self
+            .client
+            .get(url)
+            .header(header::ACCEPT, "text/html,application/xhtml+xml,*/*;q=0.8")
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("{} answered {}", url, response.status()));
+        }
+
+        let content_type = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_lowercase();
+        let page_url = response.url().clone();
+
+        // Links to an image are previewed with the image itself.
+        if content_type.starts_with("image/") {
+            return Ok(LinkPreview {
+                image: Some(page_url.to_string()),
+                ..LinkPreview::default()
+            });
+        }
+        if !content_type.contains("html") {
+            return Ok(LinkPreview::default());
+        }
+
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+            body.extend_from_slice(&chunk);
+            if body.len() >= MAX_BODY_BYTES {
+                body.truncate(MAX_BODY_BYTES);
+                break;
+            }
+        }
+
+        Ok(parse_html(&String::from_utf8_lossy(&body), &page_url))
+    }
+}
+
+/// Reads the preview of an HTML page, resolving its image against `page_url`.
+fn parse_html(html: &str, page_url: &Url) -> LinkPreview {
+    // The first value of each property wins, as for the Open Graph parsers of social networks.
+    let mut properties: HashMap<String, String> = HashMap::new();
+    for tag in tags(html, "meta") {
+        let attributes = attributes(tag);
+        let name = attributes
+            .get("property")
+            .or_else(|| attributes.get("name"));
+        if let (Some(name), Some(content)) = (name, attributes.get("content")) {
+            properties
+                .entry(name.to_lowercase())
+                .or_insert_with(|| decode_entities(content));
+        }
+    }
+    let property = |names: &[&str]| names.iter().find_map(|name| properties.get(*name).cloned());
+
+    let title = property(&["og:title", "twitter:title"]).or_else(|| title_text(html));
+    let description = property(&["og:description", "twitter:description", "description"]);
+    let image = property(&["og:image", "og:image:url", "twitter:image"])
+        .and_then(|image| page_url.join(image.trim()).ok())
+        .filter(|image| matches!(image.scheme(), "http" | "https"));
+
+    LinkPreview {
+        title: title.and_then(|title| clean(&title, MAX_TITLE_CHARS)),
+        description: description.and_then(|description| clean(&description, MAX_DESCRIPTION_CHARS)),
+        image: image.map(String::from),
+    }
+}
+
+/// Returns the attributes part of each `<name ...>` tag of `html`.
+fn tags<'a>(html: &'a str, name: &str) -> Vec<&'a str> {
+    // ASCII lowercasing keeps the byte offsets of `html`.
+    let lower = html.to_ascii_lowercase();
+    let opening = format!("<{}", name);
+    let mut ret = Vec::new();
+    let mut start = 0;
+
+    while let Some(found) = lower[start..].find(&opening) {
+        let attributes_start = start + found + opening.len();
+        let Some(length) = lower[attributes_start..].find('>') else {
+            break;
+        };
+        let attributes_end = attributes_start + length;
+
+        // Skips longer tag names, e.g. `<metadata>` when looking for `<meta>`.
+        if lower[attributes_start..]
+            .starts_with(|c: char| c.is_ascii_whitespace() || c == '/' || c == '>')
+        {
+            ret.push(&html[attributes_start..attributes_end]);
+        }
+        start = attributes_end;
+    }
+    ret
+}
+
+/// Parses the attributes of a tag, with lowercase names. Values may be quoted or not.
+fn attributes(tag: &str) -> HashMap<String, String> {
+    let mut ret = HashMap::new();
+    let mut rest = tag;
+
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace() || c == '/');
+        if rest.is_empty() {
+            return ret;
+        }
+
+        let name_end = rest
+            .find(|c: char| c.is_ascii_whitespace() || c == '=' || c == '/')
+            .unwrap_or(rest.len());
+        let name = rest[..name_end].to_lowercase();
+        rest = rest[name_end..].trim_start();
+
+        let Some(value) = rest.strip_prefix('=') else {
+            ret.insert(name, String::new());
+            continue;
+        };
+        let value = value.trim_start();
+        let (value, remaining) = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let value = &value[1..];
+                let end = value.find(quote).unwrap_or(value.len());
+                (&value[..end], value.get(end + 1..).unwrap_or_default())
+            }
+            _ => {
+                let end = value
+                    .find(|c: char| c.is_ascii_whitespace())
+                    .unwrap_or(value.len());
+                value.split_at(end)
+            }
+        };
+        ret.insert(name, value.to_string());
+        rest = remaining;
+    }
+}
+
+/// Returns the text of the `<title>` of a page.
+fn title_text(html: &str) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+    let tag = lower.find("<title")?;
+    let start = tag + lower[tag..].find('>')? + 1;
+    let end = start + lower[start..].find("</title")?;
+
+    Some(decode_entities(&html[start..end]))
+}
+
+/// Decodes the named entities common in titles and the numeric ones.
+fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+
+        let entity = rest
+            .find(';')
+            .filter(|end| *end <= 10)
+            .map(|end| (&rest[1..end], end));
+        let decoded = entity.and_then(|(name, end)| {
+            let c = match name {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => name
+                    .strip_prefix("#x")
+                    .or_else(|| name.strip_prefix("#X"))
+                    .map(|hex| u32::from_str_radix(hex, 16))
+                    .or_else(|| name.strip_prefix('#').map(str::parse))
+                    .and_then(Result::ok)
+                    .and_then(char::from_u32),
+            };
+            c.map(|c| (c, end))
+        });
+
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Collapses the whitespace of a text and cuts it to `max_chars`, `None` if it is blank.
+fn clean(text: &str, max_chars: usize) -> Option<String> {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.is_empty() {
+        return None;
+    }
+
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => Some(format!("{}…", text[..end].trim_end())),
+        None => Some(text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use axum::{
+        http::header::{CONTENT_TYPE, LOCATION},
+        routing::get,
+        Router,
+    };
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_html() {
+        let page_url = Url::parse("https://example.com/articles/1").unwrap();
+        let html = r#"<!doctype html><html><head>
+            <TITLE>Fallback &amp; title</TITLE>
+            <metadata content="ignored">
+            <meta property="og:title" content="Fish &amp; Chips &#8211; &#x1F41F;">
+            <meta property='og:title' content='Second title'>
+            <meta name=description content=Crispy>
+            <meta property="og:image" content="/images/fish.png" />
+            </head><body></body></html>"#;
+
+        assert_eq!(
+            parse_html(html, &page_url),
+            LinkPreview {
+                title: Some("Fish & Chips – 🐟".to_string()),
+                description: Some("Crispy".to_string()),
+                image: Some("https://example.com/images/fish.png".to_string()),
+            }
+        );
+
+        let html = "<title>\n  Only   a\ttitle\n</title><meta property=\"og:image\" \
+                    content=\"javascript:alert(1)\">";
+        assert_eq!(
+            parse_html(html, &page_url),
+            LinkPreview {
+                title: Some("Only a title".to_string()),
+                ..LinkPreview::default()
+            }
+        );
+        assert_eq!(parse_html("", &page_url), LinkPreview::default());
+    }
+
+    #[test]
+    fn test_clean() {
+        assert_eq!(clean(" \n ", 10), None);
+        assert_eq!(clean("a  b", 10).as_deref(), Some("a b"));
+        assert_eq!(clean("déjà vu", 4).as_deref(), Some("déjà…"));
+        assert_eq!(decode_entities("a & b &bogus; &#0;"), "a & b &bogus; \0");
+    }
+
+    /// Serves a few pages on a free port and returns its address.
+    async fn stub() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let elsewhere = format!("http://localhost:{}/page", address.port());
+
+        let app = Router::new()
+            .route(
+                "/page",
+                get(|| async {
+                    (
+                        [(CONTENT_TYPE, "text/html; charset=utf-8")],
+                        "<html><head><title>Stub page</title>\
+                         <meta name=\"description\" content=\"A page\"></head></html>",
+                    )
+                }),
+            )
+            .route(
+                "/moved",
+                get(|| async { (axum::http::StatusCode::FOUND, [(LOCATION, "/page")]) }),
+            )
+            .route(
+                "/elsewhere",
+                get(|| async move { (axum::http::StatusCode::FOUND, [(LOCATION, elsewhere)]) }),
+            )
+            .route(
+                "/image",
+                get(|| async { ([(CONTENT_TYPE, "image/png")], vec![0u8; 8]) }),
+            );
+
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        address
+    }
+
+    #[tokio::test]
+    async fn test_fetch() {
+        let address = stub().await;
+        // The stub listens on a loopback address, which `new` blocks.
+        let fetcher = HttpFetcher::allowing_private(UrlPolicy::default(), Duration::from_secs(5));
+
+        let preview = fetcher
+            .fetch(&format!("http://{}/moved", address))
+            .await
+            .unwrap();
+        assert_eq!(preview.title.as_deref(), Some("Stub page"));
+        assert_eq!(preview.description.as_deref(), Some("A page"));
+
+        let preview = fetcher
+            .fetch(&format!("http://{}/image", address))
+            .await
+            .unwrap();
+        assert_eq!(preview.image, Some(format!("http://{}/image", address)));
+
+        assert!(fetcher
+            .fetch(&format!("http://{}/missing", address))
+            .await
+            .is_err());
+
+        // Redirects to hosts the policy rejects are not followed.
+        let fetcher = HttpFetcher::allowing_private(
+            UrlPolicy::default().with_blocklist(["localhost"]),
+            Duration::from_secs(5),
+        );
+        assert!(fetcher
+            .fetch(&format!("http://{}/elsewhere", address))
+            .await
+            .is_err());
+
+        let fetcher = HttpFetcher::new(UrlPolicy::default(), Duration::from_secs(5));
+        for url in [
+            format!("http://{}/page", address),
+            format!("http://localhost:{}/page", address.port()),
+        ] {
+            assert!(fetcher.fetch(&url).await.is_err(), "{} was fetched", url);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_public_resolver() {
+        // Names resolving to a private address are rejected, not only `localhost` itself.
+        assert!(PublicResolver
+            .resolve("localhost".parse().unwrap())
+            .await
+            .is_err());
+
+        let public: SocketAddr = "93.184.216.34:0".parse().unwrap();
+        assert_eq!(
+            public_addresses([
+                "127.0.0.1:0".parse().unwrap(),
+                public,
+                "[fd00::1]:0".parse().unwrap(),
+                "10.0.0.1:0".parse().unwrap(),
+            ]),
+            [public]
+        );
+    }
+}
@@ -0,0 +1,355 @@
+pub mod memory_buckets;
+pub mod redis_buckets;
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use axum::{
+    extract::{ConnectInfo, MatchedPath, Request, State},
+    http::{HeaderMap, HeaderName, HeaderValue, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use xxhash_rust::xxh3::xxh3_64;
+
+use super::{
+    auth::{request_key, ApiKeys},
+    routes::json_error,
+    storage::StoreError,
+};
+use memory_buckets::MemoryBuckets;
+
+/// A token bucket holding up to `capacity` requests, refilled at `capacity` tokens per `period`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub capacity: u32,
+    pub period: Duration,
+}
+
+impl RateLimit {
+    /// Parses limits such as `10/s`, `100/min` or `500/3600s`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use url_shortener::modules::rate_limit::RateLimit;
+    ///
+    /// assert_eq!(
+    ///     RateLimit::parse("100/min"),
+    ///     Ok(RateLimit { capacity: 100, period: Duration::from_secs(60) })
+    /// );
+    /// assert_eq!(RateLimit::parse("5/30s").unwrap().period, Duration::from_secs(30));
+    /// assert!(RateLimit::parse("0/s").is_err());
+    /// ```
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid rate limit '{}'", spec);
+
+        let (capacity, period) = spec.trim().split_once('/').ok_or_else(invalid)?;
+        let capacity: u32 = capacity.parse().map_err(|_| invalid())?;
+
+        let unit_start = period
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(period.len());
+        let (count, unit) = period.split_at(unit_start);
+        let count: u64 = if count.is_empty() {
+            1
+        } else {
+            count.parse().map_err(|_| invalid())?
+        };
+        let unit = match unit {
+            "s" | "sec" => 1,
+            "m" | "min" => 60,
+            "h" | "hour" => 3600,
+            _ => return Err(invalid()),
+        };
+
+        if capacity == 0 || count == 0 {
+            return Err(invalid());
+        }
+
+        Ok(RateLimit {
+            capacity,
+            period: Duration::from_secs(count * unit),
+        })
+    }
+
+    /// Tokens added to the bucket per second.
+    pub fn refill_rate(&self) -> f64 {
+        self.capacity as f64 / self.period.as_secs_f64()
+    }
+
+    /// Builds the outcome of taking a token, from the tokens left in the bucket.
+    pub fn decision(&self, allowed: bool, tokens: f64) -> Decision {
+        let rate = self.refill_rate();
+
+        Decision {
+            allowed,
+            limit: self.capacity,
+            remaining: tokens.max(0.0).floor() as u32,
+            retry_after: Duration::from_secs_f64(((1.0 - tokens) / rate).max(0.0)),
+            reset_after: Duration::from_secs_f64(((self.capacity as f64 - tokens) / rate).max(0.0)),
+        }
+    }
+}
+
+/// Outcome of a request against a bucket.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    /// Time until a token is available, zero if one is available now.
+    pub retry_after: Duration,
+    /// Time until the bucket is full again.
+    pub reset_after: Duration,
+}
+
+/// Token buckets, shared by every route and client.
+#[async_trait]
+pub trait TokenBuckets: Send + Sync {
+    /// Takes a token from the bucket `key`, created full if it does not exist.
+    async fn take(&self, key: &str, limit: &RateLimit) -> Result<Decision, StoreError>;
+}
+
+/// Limits per route, keyed by the route path such as `/add_url` or `/:url_hash`.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimits {
+    routes: HashMap<String, RateLimit>,
+    default: Option<RateLimit>,
+}
+
+impl RateLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_route(mut self, route: &str, limit: RateLimit) -> Self {
+        self.routes.insert(route.to_string(), limit);
+        self
+    }
+
+    /// Sets the limit of the routes without their own limit.
+    pub fn with_default(mut self, limit: RateLimit) -> Self {
+        self.default = Some(limit);
+        self
+    }
+
+    /// Parses limits given as `<route>=<limit>`, separated by commas, `*` being the default.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use url_shortener::modules::rate_limit::{RateLimit, RateLimits};
+    ///
+    /// let limits = RateLimits::parse("/add_url=10/min, *=100/s").unwrap();
+    ///
+    /// assert_eq!(limits.limit("/add_url"), RateLimit::parse("10/min").ok());
+    /// assert_eq!(limits.limit("/get_all"), RateLimit::parse("100/s").ok());
+    /// ```
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut limits = RateLimits::new();
+
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (route, limit) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid rate limit entry '{}'", entry))?;
+            let limit = RateLimit::parse(limit)?;
+
+            limits = match route.trim() {
+                "*" => limits.with_default(limit),
+                route => limits.with_route(route, limit),
+            };
+        }
+
+        Ok(limits)
+    }
+
+    pub fn limit(&self, route: &str) -> Option<RateLimit> {
+        self.routes.get(route).copied().or(self.default)
+    }
+}
+
+/// Rate limiting of the requests per client IP and per API key.
+#[derive(Clone)]
+pub struct RateLimiter {
+    buckets: Arc<dyn TokenBuckets>,
+    limits: RateLimits,
+    /// Reads the client IP from `X-Forwarded-For`, only safe behind a proxy that sets it.
+    trust_forwarded_for: bool,
+}
+
+impl Default for RateLimiter {
+    /// No limits.
+    fn default() -> Self {
+        RateLimiter::new(Arc::new(MemoryBuckets::new()), RateLimits::new())
+    }
+}
+
+impl RateLimiter {
+    pub fn new(buckets: Arc<dyn TokenBuckets>, limits: RateLimits) -> Self {
+        RateLimiter {
+            buckets,
+            limits,
+            trust_forwarded_for: false,
+        }
+    }
+
+    pub fn with_trust_forwarded_for(mut self, trust_forwarded_for: bool) -> Self {
+        self.trust_forwarded_for = trust_forwarded_for;
+        self
+    }
+
+    fn client_ip(&self, request: &Request) -> Option<IpAddr> {
+        let forwarded = self
+            .trust_forwarded_for
+            .then(|| request.headers().get("x-forwarded-for"))
+            .flatten()
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(',').next())
+            .and_then(|ip| ip.trim().parse().ok());
+
+        forwarded.or_else(|| {
+            request
+                .extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(address)| address.ip())
+        })
+    }
+
+    /// Names of the buckets of a request: one for the client IP and, if the request has a valid
+    /// one, one for its API key. Unknown keys get no bucket of their own, so sending made-up keys
+    /// cannot create buckets without end.
+    fn bucket_keys(&self, route: &str, request: &Request, api_keys: &ApiKeys) -> Vec<String> {
+        let ip = self
+            .client_ip(request)
+            .map_or_else(|| "unknown".to_string(), |ip| ip.to_string());
+        let mut keys = vec![format!("ip:{}:{}", ip, route)];
+
+        // Keys are hashed so they are never stored in the backend.
+        if let Some(key) = request_key(request.headers()) {
+            if api_keys.principal(key).is_some() {
+                keys.push(format!("key:{:016x}:{}", xxh3_64(key.as_bytes()), route));
+            }
+        }
+
+        keys
+    }
+
+    /// Takes a token from each bucket.
+    ///
+    /// # Returns
+    /// The most restrictive decision, or `None` if the route is not limited.
+    async fn check(&self, route: &str, keys: Vec<String>) -> Option<Decision> {
+        let limit = self.limits.limit(route)?;
+
+        let mut decision: Option<Decision> = None;
+        for key in keys {
+            match self.buckets.take(&key, &limit).await {
+                Ok(next) => {
+                    decision = Some(match decision {
+                        // Denied decisions sort first.
+                        Some(current)
+                            if (current.allowed, current.remaining)
+                                <= (next.allowed, next.remaining) =>
+                        {
+                            current
+                        }
+                        _ => next,
+                    });
+                }
+                // Failing open, an unavailable backend should not take the service down.
+                Err(e) => tracing::warn!(error = %e, "rate limit backend error"),
+            }
+        }
+
+        decision
+    }
+}
+
+fn insert_header(headers: &mut HeaderMap, name: &'static str, value: u64) {
+    headers.insert(HeaderName::from_static(name), HeaderValue::from(value));
+}
+
+fn limit_headers(headers: &mut HeaderMap, decision: &Decision) {
+    insert_header(headers, "x-ratelimit-limit", decision.limit as u64);
+    insert_header(headers, "x-ratelimit-remaining", decision.remaining as u64);
+    insert_header(
+        headers,
+        "x-ratelimit-reset",
+        decision.reset_after.as_secs_f64().ceil() as u64,
+    );
+}
+
+/// Answers 429 with `Retry-After` to clients over the limit of the route, and adds the
+/// `X-RateLimit-*` headers to every limited response.
+pub async fn rate_limit(
+    State(limiter): State<RateLimiter>,
+    State(api_keys): State<Arc<ApiKeys>>,
+    matched_path: Option<MatchedPath>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let route = matched_path.map_or_else(
+        || request.uri().path().to_string(),
+        |path| path.as_str().to_string(),
+    );
+
+    let keys = limiter.bucket_keys(&route, &request, &api_keys);
+    let Some(decision) = limiter.check(&route, keys).await else {
+        return next.run(request).await;
+    };
+
+    let mut response = if decision.allowed {
+        next.run(request).await
+    } else {
+        let mut response = json_error(
+            StatusCode::TOO_MANY_REQUESTS,
+            "rate_limited",
+            "Too many requests",
+        );
+        insert_header(
+            response.headers_mut(),
+            "retry-after",
+            decision.retry_after.as_secs_f64().ceil().max(1.0) as u64,
+        );
+        response
+    };
+
+    limit_headers(response.headers_mut(), &decision);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+
+    use super::*;
+    use crate::modules::auth::{Principal, API_KEY_HEADER};
+
+    #[test]
+    fn test_bucket_keys() {
+        let limiter = RateLimiter::default();
+        let api_keys = ApiKeys::new().with_key("alice-key", Principal::user("alice"));
+        let request = |key: &str| {
+            Request::builder()
+                .uri("/add_url")
+                .header(API_KEY_HEADER, key)
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let keys = limiter.bucket_keys("/add_url", &request("alice-key"), &api_keys);
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0], "ip:unknown:/add_url");
+        assert!(keys[1].starts_with("key:") && !keys[1].contains("alice-key"));
+
+        let keys = limiter.bucket_keys("/add_url", &request("made-up"), &api_keys);
+        assert_eq!(keys, ["ip:unknown:/add_url"]);
+    }
+}
@@ -0,0 +1,120 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+
+use super::{Decision, RateLimit, TokenBuckets};
+use crate::modules::storage::StoreError;
+
+/// Number of buckets above which full buckets are dropped, as they are the same as no bucket.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    /// When the bucket is full again, as the routes have different limits.
+    full_at: Instant,
+}
+
+/// Token buckets kept in the process memory, each instance limiting on its own.
+#[derive(Default)]
+pub struct MemoryBuckets {
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl MemoryBuckets {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn take_at(&self, key: &str, limit: &RateLimit, now: Instant) -> Decision {
+        let mut buckets = self.buckets.lock().unwrap();
+        let capacity = limit.capacity as f64;
+
+        if buckets.len() > PRUNE_THRESHOLD {
+            buckets.retain(|_, bucket| bucket.full_at > now);
+        }
+
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+            full_at: now,
+        });
+
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * limit.refill_rate()).min(capacity);
+        bucket.updated = now;
+
+        let allowed = bucket.tokens >= 1.0;
+        if allowed {
+            bucket.tokens -= 1.0;
+        }
+        bucket.full_at =
+            now + Duration::from_secs_f64((capacity - bucket.tokens) / limit.refill_rate());
+
+        limit.decision(allowed, bucket.tokens)
+    }
+}
+
+#[async_trait]
+impl TokenBuckets for MemoryBuckets {
+    async fn take(&self, key: &str, limit: &RateLimit) -> Result<Decision, StoreError> {
+        Ok(self.take_at(key, limit, Instant::now()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_and_refill() {
+        let buckets = MemoryBuckets::new();
+        let limit = RateLimit::parse("2/10s").unwrap();
+        let start = Instant::now();
+
+        let first = buckets.take_at("a", &limit, start);
+        assert!(first.allowed);
+        assert_eq!(first.remaining, 1);
+        assert!(buckets.take_at("a", &limit, start).allowed);
+
+        let denied = buckets.take_at("a", &limit, start);
+        assert!(!denied.allowed);
+        assert_eq!(denied.retry_after, Duration::from_secs(5));
+        assert_eq!(denied.reset_after, Duration::from_secs(10));
+
+        assert!(buckets.take_at("b", &limit, start).allowed);
+        assert!(
+            buckets
+                .take_at("a", &limit, start + Duration::from_secs(5))
+                .allowed
+        );
+        assert!(
+            !buckets
+                .take_at("a", &limit, start + Duration::from_secs(5))
+                .allowed
+        );
+    }
+
+    #[test]
+    fn test_prune_with_own_limits() {
+        let buckets = MemoryBuckets::new();
+        let slow = RateLimit::parse("1/h").unwrap();
+        let fast = RateLimit::parse("10/s").unwrap();
+        let start = Instant::now();
+
+        assert!(buckets.take_at("slow", &slow, start).allowed);
+        for i in 0..PRUNE_THRESHOLD {
+            buckets.take_at(&i.to_string(), &fast, start);
+        }
+
+        // The fast buckets are full again a second later, the slow one only after an hour.
+        let later = start + Duration::from_secs(1);
+        buckets.take_at("fast", &fast, later);
+        assert_eq!(buckets.buckets.lock().unwrap().len(), 2);
+        assert!(!buckets.take_at("slow", &slow, later).allowed);
+    }
+}
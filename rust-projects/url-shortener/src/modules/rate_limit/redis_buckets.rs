@@ -0,0 +1,71 @@
+use async_trait::async_trait;
+use redis::{aio::ConnectionManager, Script};
+
+use super::{Decision, RateLimit, TokenBuckets};
+use crate::modules::storage::StoreError;
+
+/// Refills the bucket `KEYS[1]` of capacity `ARGV[1]` at `ARGV[2]` tokens per second, using the
+/// Redis clock so every instance agrees, then takes a token if there is one.
+///
+/// Returns whether a token was taken and the tokens left, as a string to keep the fraction.
+const TAKE_SCRIPT: &str = r"
+local capacity = tonumber(ARGV[1])
+local rate = tonumber(ARGV[2])
+local time = redis.call('TIME')
+local now = tonumber(time[1]) + tonumber(time[2]) / 1000000
+
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'updated')
+local tokens = tonumber(bucket[1]) or capacity
+local updated = tonumber(bucket[2]) or now
+tokens = math.min(capacity, tokens + math.max(0, now - updated) * rate)
+
+local allowed = 0
+if tokens >= 1 then
+    tokens = tokens - 1
+    allowed = 1
+end
+
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'updated', tostring(now))
+redis.call('EXPIRE', KEYS[1], math.ceil(capacity / rate) + 1)
+return {allowed, tostring(tokens)}
+";
+
+/// Token buckets in Redis, `{prefix}:ratelimit:{key}`, shared by every instance of the service.
+pub struct RedisBuckets {
+    connection: ConnectionManager,
+    prefix: String,
+}
+
+impl RedisBuckets {
+    /// Connects to Redis, keeping the buckets next to the links stored under `prefix`.
+    pub async fn connect(url: &str, prefix: &str) -> Result<Self, StoreError> {
+        let client = redis::Client::open(url)?;
+        let connection = ConnectionManager::new(client).await?;
+
+        Ok(RedisBuckets {
+            connection,
+            prefix: prefix.to_string(),
+        })
+    }
+
+    fn bucket_key(&self, key: &str) -> String {
+        format!("{}:ratelimit:{}", self.prefix, key)
+    }
+}
+
+#[async_trait]
+impl TokenBuckets for RedisBuckets {
+    async fn take(&self, key: &str, limit: &RateLimit) -> Result<Decision, StoreError> {
+        let (allowed, tokens): (i64, String) = Script::new(TAKE_SCRIPT)
+            .key(self.bucket_key(key))
+            .arg(limit.capacity)
+            .arg(limit.refill_rate())
+            .invoke_async(&mut self.connection.clone())
+            .await?;
+
+        let tokens = tokens
+            .parse()
+            .map_err(|_| StoreError::Backend(format!("Invalid bucket '{}'", tokens)))?;
+        Ok(limit.decision(allowed == 1, tokens))
+    }
+}
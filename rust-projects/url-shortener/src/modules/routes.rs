@@ -1,53 +1,333 @@
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 
 use axum::{
-    extract::{Extension, Json, Path},
-    http::StatusCode,
-    response::{IntoResponse, Redirect},
+    body::Body,
+    extract::{DefaultBodyLimit, FromRef, Json, MatchedPath, Path, Query, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware,
+    response::{Html, IntoResponse, Redirect, Response},
+    Extension,
 };
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Duration, Utc};
+use futures_util::{future, stream, StreamExt, TryStreamExt};
+use serde::{Deserialize, Deserializer, Serialize};
+use tower::ServiceBuilder;
+use tower_http::{
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    trace::{DefaultOnResponse, TraceLayer},
+};
+use tracing::{Level, Span};
+use utoipa::{IntoParams, ToSchema};
 
-use super::store::Store;
+use super::{
+    analytics::{Click, GroupStats, LinkStats},
+    auth::{require_api_key, ApiKeys, Principal},
+    bulk::BulkFormat,
+    metrics::{self, track_requests, Metrics},
+    openapi::openapi,
+    preview,
+    rate_limit::{rate_limit, RateLimiter},
+    storage::{LinkFilter, SortOrder, StoreError},
+    store::{GroupSummary, LinkOptions, LinkUpdate, Store, UrlMap, UrlPage, DEFAULT_PAGE_SIZE},
+};
 
-#[derive(Deserialize)]
+/// Largest body accepted by `POST /urls/bulk`, enough for about half a million rows.
+const BULK_BODY_LIMIT: usize = 64 * 1024 * 1024;
+
+#[derive(Deserialize, ToSchema)]
 pub struct AddUrlRequest {
     url: String,
+    /// Custom short code, a generated one is used when missing.
+    alias: Option<String>,
+    /// Lifetime of the link in seconds, exclusive with `expires_at`.
+    ttl_seconds: Option<u64>,
+    expires_at: Option<DateTime<Utc>>,
+    /// Number of redirects after which the link is gone.
+    max_clicks: Option<u64>,
+    /// Shows a preview page of the URL instead of redirecting.
+    #[serde(default)]
+    interstitial: bool,
+    /// Free-form title, searched by `GET /urls?q=`.
+    title: Option<String>,
+    /// Tags, stored lowercase.
+    #[serde(default)]
+    tags: Vec<String>,
+    /// Group, or campaign, the link is part of.
+    group: Option<String>,
+}
+
+/// Turns a lifetime in seconds into an expiry.
+fn ttl_expiry(ttl_seconds: u64) -> Result<DateTime<Utc>, StoreError> {
+    i64::try_from(ttl_seconds)
+        .ok()
+        .and_then(Duration::try_seconds)
+        .and_then(|ttl| Utc::now().checked_add_signed(ttl))
+        .ok_or_else(|| StoreError::Invalid("ttl_seconds is too large".to_string()))
+}
+
+fn both_expiries() -> StoreError {
+    StoreError::Invalid("Only one of ttl_seconds and expires_at can be set".to_string())
+}
+
+impl AddUrlRequest {
+    fn options(&self) -> Result<LinkOptions, StoreError> {
+        let expires_at = match (self.ttl_seconds, self.expires_at) {
+            (Some(_), Some(_)) => return Err(both_expiries()),
+            (Some(ttl), None) => Some(ttl_expiry(ttl)?),
+            (None, expires_at) => expires_at,
+        };
+
+        Ok(LinkOptions {
+            expires_at,
+            max_clicks: self.max_clicks,
+            interstitial: self.interstitial,
+            title: self.title.clone(),
+            tags: self.tags.clone(),
+            group: self.group.clone(),
+        })
+    }
+}
+
+/// Deserializes a field that can be missing, `None`, or set to `null`, `Some(None)`.
+fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// Body of `PATCH /urls/:code`, missing fields are left unchanged and `null` removes a limit,
+/// the title or the group.
+#[derive(Deserialize, ToSchema)]
+pub struct PatchUrlRequest {
+    url: Option<String>,
+    /// Lifetime of the link in seconds from now, exclusive with `expires_at`.
+    ttl_seconds: Option<u64>,
+    #[serde(default, deserialize_with = "nullable")]
+    expires_at: Option<Option<DateTime<Utc>>>,
+    #[serde(default, deserialize_with = "nullable")]
+    max_clicks: Option<Option<u64>>,
+    interstitial: Option<bool>,
+    #[serde(default, deserialize_with = "nullable")]
+    title: Option<Option<String>>,
+    /// Replaces every tag of the link.
+    tags: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    group: Option<Option<String>>,
+}
+
+impl PatchUrlRequest {
+    fn update(self) -> Result<LinkUpdate, StoreError> {
+        let expires_at = match (self.ttl_seconds, self.expires_at) {
+            (Some(_), Some(_)) => return Err(both_expiries()),
+            (Some(ttl), None) => Some(Some(ttl_expiry(ttl)?)),
+            (None, expires_at) => expires_at,
+        };
+
+        Ok(LinkUpdate {
+            url: self.url,
+            expires_at,
+            max_clicks: self.max_clicks,
+            interstitial: self.interstitial,
+            title: self.title,
+            tags: self.tags,
+            group: self.group,
+        })
+    }
 }
 
 use axum::{
-    routing::{delete, get, post},
+    routing::{delete, get, post, put},
     Router,
 };
 
-pub async fn setup_router() -> Router {
-    let store = Arc::new(Mutex::new(Store::new()));
+#[derive(Clone)]
+pub struct AppState {
+    store: Store,
+    keys: Arc<ApiKeys>,
+    rate_limiter: RateLimiter,
+}
 
-    Router::new()
+impl FromRef<AppState> for Store {
+    fn from_ref(state: &AppState) -> Self {
+        state.store.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<ApiKeys> {
+    fn from_ref(state: &AppState) -> Self {
+        Arc::clone(&state.keys)
+    }
+}
+
+impl FromRef<AppState> for Metrics {
+    fn from_ref(state: &AppState) -> Self {
+        state.store.metrics().clone()
+    }
+}
+
+impl FromRef<AppState> for RateLimiter {
+    fn from_ref(state: &AppState) -> Self {
+        state.rate_limiter.clone()
+    }
+}
+
+/// Builds the routes, redirects, health checks, metrics and the OpenAPI document are public and
+/// every other route requires an API key. Every route but the health checks, metrics and
+/// OpenAPI document is rate limited, before the API key is checked. Every request is traced and
+/// measured, and answered with its `X-Request-Id`.
+pub async fn setup_router(store: Store, keys: ApiKeys, rate_limiter: RateLimiter) -> Router {
+    let state = AppState {
+        store,
+        keys: Arc::new(keys),
+        rate_limiter,
+    };
+
+    let api = Router::new()
         .route("/add_url", post(add_url))
         .route("/get_all", get(get_all))
-        .route("/:url_hash", get(redirect))
+        .route("/urls", get(list_urls).post(create_url))
+        .route(
+            "/urls/bulk",
+            post(bulk_import).layer(DefaultBodyLimit::max(BULK_BODY_LIMIT)),
+        )
+        .route("/urls/export", get(export))
+        .route(
+            "/urls/:code",
+            get(get_url).patch(patch_url).delete(delete_code),
+        )
+        .route("/update_url", put(update_url))
         .route("/delete_url", delete(delete_url))
-        .layer(Extension(store))
+        .route("/:url_hash/stats", get(stats))
+        .route("/groups", get(list_groups))
+        .route("/groups/:name/stats", get(group_stats))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            require_api_key,
+        ));
+
+    Router::new()
+        .route("/:url_hash", get(redirect))
+        .merge(api)
+        .layer(middleware::from_fn_with_state(state.clone(), rate_limit))
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .route("/metrics", get(metrics::metrics))
+        .route("/openapi.json", get(openapi))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            track_requests,
+        ))
+        .layer(
+            ServiceBuilder::new()
+                .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+                .layer(
+                    TraceLayer::new_for_http()
+                        .make_span_with(request_span)
+                        .on_response(DefaultOnResponse::new().level(Level::INFO)),
+                )
+                .layer(PropagateRequestIdLayer::x_request_id()),
+        )
+        .with_state(state)
 }
 
-#[derive(Serialize, Deserialize)]
+/// Span of a request, with the `X-Request-Id` given by the client or generated.
+fn request_span(request: &Request) -> Span {
+    let request_id = request
+        .headers()
+        .get("x-request-id")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(MatchedPath::as_str);
+
+    tracing::info_span!(
+        "request",
+        method = %request.method(),
+        uri = %request.uri(),
+        route,
+        request_id,
+    )
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
 struct AddUrlResponse {
     hashed_url: String,
 }
 
+/// Body of the error responses.
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ErrorResponse {
+    /// Machine readable reason, e.g. `unsupported_scheme` or `not_found`.
+    pub code: String,
+    pub message: String,
+}
+
+/// Builds an error response with a JSON [`ErrorResponse`] body.
+pub fn json_error(status: StatusCode, code: &str, message: &str) -> Response {
+    let response = ErrorResponse {
+        code: code.to_string(),
+        message: message.to_string(),
+    };
+
+    (status, Json(response)).into_response()
+}
+
+/// Returns the status code and the machine readable reason of a store error.
+fn error_status(e: &StoreError) -> (StatusCode, &'static str) {
+    match e {
+        StoreError::InvalidUrl(e) => (StatusCode::BAD_REQUEST, e.code()),
+        StoreError::Invalid(_) => (StatusCode::BAD_REQUEST, "invalid_request"),
+        StoreError::NotFound => (StatusCode::NOT_FOUND, "not_found"),
+        StoreError::Gone => (StatusCode::GONE, "gone"),
+        StoreError::Forbidden => (StatusCode::FORBIDDEN, "forbidden"),
+        StoreError::Conflict(_) => (StatusCode::CONFLICT, "conflict"),
+        StoreError::Backend(_) => (StatusCode::INTERNAL_SERVER_ERROR, "internal_error"),
+    }
+}
+
+/// Maps a store error to its status code and JSON body.
+fn error_response(e: StoreError) -> Response {
+    let (status, code) = error_status(&e);
+    if let StoreError::Backend(_) = e {
+        tracing::error!(error = %e, "storage backend failed");
+    }
+    json_error(status, code, &e.to_string())
+}
+
+#[utoipa::path(
+    post,
+    path = "/add_url",
+    tag = "links",
+    request_body = AddUrlRequest,
+    responses(
+        (status = 200, description = "The short code", body = AddUrlResponse),
+        (status = 400, description = "Invalid URL, alias or limits", body = ErrorResponse),
+        (status = 409, description = "The alias is taken", body = ErrorResponse),
+    )
+)]
 pub async fn add_url(
-    Extension(store): Extension<Arc<Mutex<Store>>>,
+    State(store): State<Store>,
+    Extension(principal): Extension<Principal>,
     Json(payload): Json<AddUrlRequest>,
 ) -> impl IntoResponse {
-    match store.lock() {
-        Ok(mut store) => match store.add(payload.url) {
-            Ok(hashed_url) => {
-                let response = AddUrlResponse { hashed_url };
-                (StatusCode::OK, Json(response)).into_response()
-            }
-            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
-        },
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    let options = match payload.options() {
+        Ok(options) => options,
+        Err(e) => return error_response(e),
+    };
+
+    match store
+        .add(payload.url, payload.alias, options, &principal)
+        .await
+    {
+        Ok(hashed_url) => {
+            let response = AddUrlResponse { hashed_url };
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Err(e) => error_response(e),
     }
 }
 
@@ -56,49 +336,620 @@ pub struct RedirectRequest {
     url_hash: String,
 }
 
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RedirectQuery {
+    /// Present, with any value or none, to show the preview page of the link without counting a
+    /// click.
+    preview: Option<String>,
+}
+
+/// Answers with the preview page of a link, its button going to `target`.
+fn preview_page(link: &UrlMap, target: &str) -> Response {
+    (
+        [
+            // The page of an interstitial link counts a click each time it is shown.
+            (header::CACHE_CONTROL, "no-store"),
+            (
+                header::CONTENT_SECURITY_POLICY,
+                preview::CONTENT_SECURITY_POLICY,
+            ),
+        ],
+        Html(preview::render_page(link, target)),
+    )
+        .into_response()
+}
+
+/// Follows a short link. Interstitial links, and any link with `?preview`, answer with a page
+/// showing the title, description and image of the URL and a button to continue.
+#[utoipa::path(
+    get,
+    path = "/{url_hash}",
+    tag = "redirects",
+    params(("url_hash" = String, Path, description = "Short code or alias"), RedirectQuery),
+    responses(
+        (status = 200, description = "Preview page of an interstitial link, or with `preview`",
+            content_type = "text/html", body = String),
+        (status = 307, description = "Redirect to the original URL",
+            headers(("location" = String, description = "Original URL"))),
+        (status = 404, description = "No link under the code", body = ErrorResponse),
+        (status = 410, description = "The link expired or reached its maximum number of clicks",
+            body = ErrorResponse),
+    ),
+    security(())
+)]
 pub async fn redirect(
-    Extension(store): Extension<Arc<Mutex<Store>>>,
+    State(store): State<Store>,
     Path(path): Path<RedirectRequest>,
+    Query(query): Query<RedirectQuery>,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
-    match store.lock() {
-        Ok(mut store) => match store.get(path.url_hash) {
-            Ok(url_map) => Redirect::permanent(&url_map.original).into_response(),
-            Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "URL not found").into_response(),
-        },
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    if query.preview.is_some() {
+        // Continuing goes through the short link, which counts the click.
+        return match store.preview(path.url_hash).await {
+            Ok(url_map) => preview_page(&url_map, &url_map.short),
+            Err(e) => error_response(e),
+        };
+    }
+
+    let click = Click::from_headers(&headers, Utc::now());
+
+    match store.visit(path.url_hash, &click).await {
+        Ok(url_map) if url_map.interstitial => preview_page(&url_map, &url_map.original),
+        // Browsers cache permanent redirects, which would bypass the counters, the limits and
+        // any later change of the URL through `PATCH /urls/{code}`.
+        Ok(url_map) => {
+            store.metrics().record_redirect(false);
+            Redirect::temporary(&url_map.original).into_response()
+        }
+        Err(e) => error_response(e),
     }
 }
 
-pub async fn get_all(Extension(store): Extension<Arc<Mutex<Store>>>) -> impl IntoResponse {
-    match store.lock() {
-        Ok(mut store) => match store.get_all() {
-            Ok(res) => (StatusCode::OK, Json(res)).into_response(),
-            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
-        },
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+#[utoipa::path(
+    get,
+    path = "/{url_hash}/stats",
+    tag = "links",
+    params(("url_hash" = String, Path, description = "Short code or alias")),
+    responses(
+        (status = 200, description = "Click counts of the link", body = LinkStats),
+        (status = 403, description = "The link belongs to another user", body = ErrorResponse),
+        (status = 404, description = "No link under the code", body = ErrorResponse),
+    )
+)]
+pub async fn stats(
+    State(store): State<Store>,
+    Extension(principal): Extension<Principal>,
+    Path(path): Path<RedirectRequest>,
+) -> impl IntoResponse {
+    match store.stats(path.url_hash, &principal).await {
+        Ok(stats) => (StatusCode::OK, Json(stats)).into_response(),
+        Err(e) => error_response(e),
     }
 }
 
-#[derive(Deserialize)]
+/// Lists the groups of the links of the caller, or of every link for admins, by name.
+#[utoipa::path(
+    get,
+    path = "/groups",
+    tag = "links",
+    responses((status = 200, description = "The groups", body = Vec<GroupSummary>))
+)]
+pub async fn list_groups(
+    State(store): State<Store>,
+    Extension(principal): Extension<Principal>,
+) -> impl IntoResponse {
+    match store.groups(&principal).await {
+        Ok(groups) => (StatusCode::OK, Json(groups)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Adds up the clicks of the links of a group, those of the caller or every one for admins.
+#[utoipa::path(
+    get,
+    path = "/groups/{name}/stats",
+    tag = "links",
+    params(("name" = String, Path, description = "Name of the group")),
+    responses(
+        (status = 200, description = "Click counts of the group", body = GroupStats),
+        (status = 404, description = "No link of the caller in the group", body = ErrorResponse),
+    )
+)]
+pub async fn group_stats(
+    State(store): State<Store>,
+    Extension(principal): Extension<Principal>,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    match store.group_stats(name, &principal).await {
+        Ok(stats) => (StatusCode::OK, Json(stats)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PageRequest {
+    /// `next_cursor` of the previous page.
+    cursor: Option<String>,
+    limit: Option<usize>,
+    /// `code`, `-code`, `created` or `-created`, by code by default.
+    sort: Option<String>,
+    /// Only the links with this tag.
+    tag: Option<String>,
+    /// Only the links whose URL or title contains this text, ignoring case.
+    q: Option<String>,
+    /// `substring` or `prefix`, where `q` is looked for, `substring` by default.
+    #[serde(rename = "match")]
+    match_mode: Option<String>,
+    /// Only the links of this group.
+    group: Option<String>,
+}
+
+impl PageRequest {
+    fn filter(&self) -> Result<LinkFilter, StoreError> {
+        let prefix = match self.match_mode.as_deref() {
+            None | Some("substring") => false,
+            Some("prefix") => true,
+            Some(other) => return Err(StoreError::Invalid(format!("Invalid match '{}'", other))),
+        };
+        let non_empty = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+
+        Ok(LinkFilter {
+            // Tags are stored lowercase.
+            tag: non_empty(&self.tag).map(|tag| tag.to_lowercase()),
+            group: non_empty(&self.group),
+            text: non_empty(&self.q),
+            prefix,
+        })
+    }
+}
+
+/// Lists the links of the caller, or every link for admins, a page at a time. The links can be
+/// filtered by tag, group and text in their URL or title.
+#[utoipa::path(
+    get,
+    path = "/urls",
+    tag = "links",
+    params(PageRequest),
+    responses(
+        (status = 200, description = "A page of links", body = UrlPage),
+        (status = 400, description = "Invalid sort, match or cursor", body = ErrorResponse),
+    )
+)]
+pub async fn list_urls(
+    State(store): State<Store>,
+    Extension(principal): Extension<Principal>,
+    Query(page): Query<PageRequest>,
+) -> Response {
+    let order = match page.sort.as_deref().map(SortOrder::parse) {
+        None => SortOrder::default(),
+        Some(Some(order)) => order,
+        Some(None) => {
+            return error_response(StoreError::Invalid(format!(
+                "Invalid sort '{}'",
+                page.sort.unwrap_or_default()
+            )))
+        }
+    };
+    let filter = match page.filter() {
+        Ok(filter) => filter,
+        Err(e) => return error_response(e),
+    };
+    let limit = page.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+
+    match store
+        .search(&principal, filter, order, page.cursor, limit)
+        .await
+    {
+        Ok(res) => (StatusCode::OK, Json(res)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Lists links like `GET /urls`.
+#[utoipa::path(
+    get,
+    path = "/get_all",
+    tag = "links",
+    params(PageRequest),
+    responses(
+        (status = 200, description = "A page of links", body = UrlPage),
+        (status = 400, description = "Invalid sort, match or cursor", body = ErrorResponse),
+    )
+)]
+pub async fn get_all(
+    state: State<Store>,
+    principal: Extension<Principal>,
+    page: Query<PageRequest>,
+) -> Response {
+    list_urls(state, principal, page).await
+}
+
+#[derive(Deserialize, ToSchema)]
 pub struct DeleteRequest {
     url: String,
 }
 
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateRequest {
+    hash: String,
+    url: String,
+}
+
+#[utoipa::path(
+    put,
+    path = "/update_url",
+    tag = "links",
+    request_body = UpdateRequest,
+    responses(
+        (status = 200, description = "The updated link", body = UrlMap),
+        (status = 400, description = "Invalid URL", body = ErrorResponse),
+        (status = 403, description = "The link belongs to another user", body = ErrorResponse),
+        (status = 404, description = "No link under the code", body = ErrorResponse),
+    )
+)]
+pub async fn update_url(
+    State(store): State<Store>,
+    Extension(principal): Extension<Principal>,
+    Json(payload): Json<UpdateRequest>,
+) -> impl IntoResponse {
+    let update = LinkUpdate {
+        url: Some(payload.url),
+        ..LinkUpdate::default()
+    };
+
+    match store.update(payload.hash, update, &principal).await {
+        Ok(url_map) => (StatusCode::OK, Json(url_map)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/delete_url",
+    tag = "links",
+    request_body = DeleteRequest,
+    responses(
+        (status = 200, description = "The URL", body = String),
+        (status = 403, description = "The URL is only shortened by other users",
+            body = ErrorResponse),
+    )
+)]
 pub async fn delete_url(
-    Extension(store): Extension<Arc<Mutex<Store>>>,
+    State(store): State<Store>,
+    Extension(principal): Extension<Principal>,
     Json(payload): Json<DeleteRequest>,
 ) -> impl IntoResponse {
-    match store.lock() {
-        Ok(mut store) => match store.delete(&payload.url) {
-            Ok(res) => (StatusCode::OK, Json(res)).into_response(),
-            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
-        },
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    match store.delete(&payload.url, &principal).await {
+        Ok(res) => (StatusCode::OK, Json(res)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Shortens a URL like `add_url`, answering 201 with the link and its location.
+#[utoipa::path(
+    post,
+    path = "/urls",
+    tag = "links",
+    request_body = AddUrlRequest,
+    responses(
+        (status = 201, description = "The link", body = UrlMap,
+            headers(("location" = String, description = "Path of the link"))),
+        (status = 400, description = "Invalid URL, alias or limits", body = ErrorResponse),
+        (status = 409, description = "The alias is taken", body = ErrorResponse),
+    )
+)]
+pub async fn create_url(
+    State(store): State<Store>,
+    Extension(principal): Extension<Principal>,
+    Json(payload): Json<AddUrlRequest>,
+) -> impl IntoResponse {
+    let options = match payload.options() {
+        Ok(options) => options,
+        Err(e) => return error_response(e),
+    };
+
+    let link = match store
+        .add(payload.url, payload.alias, options, &principal)
+        .await
+    {
+        Ok(code) => store.link(code, &principal).await,
+        Err(e) => Err(e),
+    };
+
+    match link {
+        Ok(link) => (
+            StatusCode::CREATED,
+            [(header::LOCATION, format!("/urls/{}", link.hash))],
+            Json(link),
+        )
+            .into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/urls/{code}",
+    tag = "links",
+    params(("code" = String, Path, description = "Short code or alias")),
+    responses(
+        (status = 200, description = "The link", body = UrlMap),
+        (status = 403, description = "The link belongs to another user", body = ErrorResponse),
+        (status = 404, description = "No link under the code", body = ErrorResponse),
+    )
+)]
+pub async fn get_url(
+    State(store): State<Store>,
+    Extension(principal): Extension<Principal>,
+    Path(code): Path<String>,
+) -> impl IntoResponse {
+    match store.link(code, &principal).await {
+        Ok(link) => (StatusCode::OK, Json(link)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    patch,
+    path = "/urls/{code}",
+    tag = "links",
+    params(("code" = String, Path, description = "Short code or alias")),
+    request_body = PatchUrlRequest,
+    responses(
+        (status = 200, description = "The updated link", body = UrlMap),
+        (status = 400, description = "Invalid URL or limits", body = ErrorResponse),
+        (status = 403, description = "The link belongs to another user", body = ErrorResponse),
+        (status = 404, description = "No link under the code", body = ErrorResponse),
+    )
+)]
+pub async fn patch_url(
+    State(store): State<Store>,
+    Extension(principal): Extension<Principal>,
+    Path(code): Path<String>,
+    Json(payload): Json<PatchUrlRequest>,
+) -> impl IntoResponse {
+    let update = match payload.update() {
+        Ok(update) => update,
+        Err(e) => return error_response(e),
+    };
+
+    match store.update(code, update, &principal).await {
+        Ok(link) => (StatusCode::OK, Json(link)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/urls/{code}",
+    tag = "links",
+    params(("code" = String, Path, description = "Short code or alias")),
+    responses(
+        (status = 204, description = "The link was removed"),
+        (status = 403, description = "The link belongs to another user", body = ErrorResponse),
+        (status = 404, description = "No link under the code", body = ErrorResponse),
+    )
+)]
+pub async fn delete_code(
+    State(store): State<Store>,
+    Extension(principal): Extension<Principal>,
+    Path(code): Path<String>,
+) -> impl IntoResponse {
+    match store.remove(&code, &principal).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Outcome of a row of a bulk import.
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct BulkResult {
+    /// Line of the row in the request body, from 1.
+    pub line: usize,
+    /// Short code of the link, if the row was added.
+    pub hash: Option<String>,
+    pub error: Option<ErrorResponse>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct BulkResponse {
+    pub succeeded: usize,
+    pub failed: usize,
+    pub results: Vec<BulkResult>,
+}
+
+/// Adds the links of a CSV or JSON lines body, see [`BulkFormat::parse_rows`]. Rows are added
+/// independently, the response tells which ones failed and why.
+#[utoipa::path(
+    post,
+    path = "/urls/bulk",
+    tag = "links",
+    request_body(
+        description = "Links to add, with a header line in CSV",
+        content((String = "text/csv"), (String = "application/x-ndjson"))
+    ),
+    responses(
+        (status = 200, description = "What became of each row", body = BulkResponse),
+        (status = 415, description = "Neither CSV nor JSON lines", body = ErrorResponse),
+    )
+)]
+pub async fn bulk_import(
+    State(store): State<Store>,
+    Extension(principal): Extension<Principal>,
+    headers: HeaderMap,
+    body: String,
+) -> impl IntoResponse {
+    let Some(format) = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(BulkFormat::from_content_type)
+    else {
+        return json_error(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "unsupported_media_type",
+            "Expected a text/csv or application/x-ndjson body",
+        );
+    };
+
+    let mut results = Vec::new();
+    let (mut lines, mut rows) = (Vec::new(), Vec::new());
+    for (line, row) in format.parse_rows(&body) {
+        match row {
+            Ok(row) => {
+                lines.push(line);
+                rows.push(row);
+            }
+            Err(message) => results.push(BulkResult {
+                line,
+                hash: None,
+                error: Some(ErrorResponse {
+                    code: "invalid_row".to_string(),
+                    message,
+                }),
+            }),
+        }
+    }
+
+    let added = match store.import(&rows, &principal).await {
+        Ok(added) => added,
+        Err(e) => return error_response(e),
+    };
+    for (line, result) in lines.into_iter().zip(added) {
+        results.push(match result {
+            Ok(hash) => BulkResult {
+                line,
+                hash: Some(hash),
+                error: None,
+            },
+            Err(e) => BulkResult {
+                line,
+                hash: None,
+                error: Some(ErrorResponse {
+                    code: error_status(&e).1.to_string(),
+                    message: e.to_string(),
+                }),
+            },
+        });
+    }
+    results.sort_by_key(|result| result.line);
+
+    let failed = results
+        .iter()
+        .filter(|result| result.error.is_some())
+        .count();
+    let response = BulkResponse {
+        succeeded: results.len() - failed,
+        failed,
+        results,
+    };
+    (StatusCode::OK, Json(response)).into_response()
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportRequest {
+    /// `csv` or `jsonl`, JSON lines by default.
+    format: Option<String>,
+}
+
+/// Streams the links of the caller, or every link for admins, as CSV or JSON lines.
+#[utoipa::path(
+    get,
+    path = "/urls/export",
+    tag = "links",
+    params(ExportRequest),
+    responses(
+        (status = 200, description = "The links, oldest first",
+            content((String = "text/csv"), (String = "application/x-ndjson"))),
+        (status = 400, description = "Unknown format", body = ErrorResponse),
+    )
+)]
+pub async fn export(
+    State(store): State<Store>,
+    Extension(principal): Extension<Principal>,
+    Query(request): Query<ExportRequest>,
+) -> impl IntoResponse {
+    let format = match request.format.as_deref().map(BulkFormat::parse) {
+        None => BulkFormat::JsonLines,
+        Some(Some(format)) => format,
+        Some(None) => {
+            return error_response(StoreError::Invalid(format!(
+                "Invalid format '{}'",
+                request.format.unwrap_or_default()
+            )))
+        }
+    };
+
+    let header = stream::once(future::ready(Ok(format.header())));
+    let links = store.export(principal).map_ok(move |links| {
+        let mut out = String::new();
+        for link in &links {
+            format.write(link, &mut out);
+        }
+        out
+    });
+
+    (
+        [(header::CONTENT_TYPE, format.content_type())],
+        Body::from_stream(header.chain(links)),
+    )
+        .into_response()
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+struct HealthResponse {
+    status: String,
+}
+
+/// Liveness probe, answers as long as the server runs.
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    tag = "health",
+    responses((status = 200, description = "The server runs", body = HealthResponse)),
+    security(())
+)]
+pub async fn healthz() -> impl IntoResponse {
+    let response = HealthResponse {
+        status: "ok".to_string(),
+    };
+    (StatusCode::OK, Json(response))
+}
+
+/// Readiness probe, answers 503 while the storage backend is unreachable.
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "health",
+    responses(
+        (status = 200, description = "The storage backend answers", body = HealthResponse),
+        (status = 503, description = "The storage backend is unreachable", body = ErrorResponse),
+    ),
+    security(())
+)]
+pub async fn readyz(State(store): State<Store>) -> impl IntoResponse {
+    match store.ping().await {
+        Ok(()) => healthz().await.into_response(),
+        Err(e) => json_error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "unavailable",
+            &e.to_string(),
+        ),
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::{collections::BTreeMap, net::SocketAddr};
+
     use axum::{
         body::{to_bytes, Body},
         extract::Request,
@@ -106,107 +957,877 @@ mod tests {
 
     use hyper::header::LOCATION;
     use serde_json::json;
+    use tokio::net::TcpListener;
     use tower::ServiceExt;
+    use url_shortener_client::{self as client, Client};
 
-    use crate::modules::store::UrlMap;
+    use crate::modules::{
+        auth::API_KEY_HEADER,
+        preview::http_fetcher::HttpFetcher,
+        rate_limit::{memory_buckets::MemoryBuckets, RateLimit, RateLimits},
+        store::UrlPage,
+        validation::UrlPolicy,
+    };
 
     use super::*;
 
+    const ALICE_KEY: &str = "alice-key";
+    const BOB_KEY: &str = "bob-key";
+
+    fn keys() -> ApiKeys {
+        ApiKeys::new()
+            .with_key(ALICE_KEY, Principal::user("alice"))
+            .with_key(BOB_KEY, Principal::user("bob"))
+    }
+
+    /// Serves `app` on a free port and returns a client of it, without an API key.
+    async fn serve(app: Router) -> Client {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await
+            .unwrap();
+        });
+
+        Client::new(&format!("http://{}", address))
+    }
+
+    fn new_link(url: &str, alias: Option<&str>) -> client::AddUrlRequest {
+        client::AddUrlRequest {
+            url: url.to_string(),
+            alias: alias.map(str::to_string),
+            ..client::AddUrlRequest::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_openapi() {
+        let client =
+            serve(setup_router(Store::memory(), keys(), RateLimiter::default()).await).await;
+
+        let document = client.openapi().await.unwrap();
+        let paths: Vec<&str> = document["paths"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                "/add_url",
+                "/delete_url",
+                "/get_all",
+                "/groups",
+                "/groups/{name}/stats",
+                "/healthz",
+                "/metrics",
+                "/readyz",
+                "/update_url",
+                "/urls",
+                "/urls/bulk",
+                "/urls/export",
+                "/urls/{code}",
+                "/{url_hash}",
+                "/{url_hash}/stats",
+            ]
+        );
+        assert_eq!(
+            document["components"]["securitySchemes"]["api_key"]["name"],
+            API_KEY_HEADER
+        );
+        assert_eq!(
+            document["paths"]["/{url_hash}"]["get"]["security"],
+            json!([{}])
+        );
+        assert!(document["components"]["schemas"]["LinkPreview"].is_object());
+        let parameters = document["paths"]["/urls"]["get"]["parameters"]
+            .as_array()
+            .unwrap();
+        assert!(parameters
+            .iter()
+            .any(|parameter| parameter["name"] == "match"));
+    }
+
     #[tokio::test]
     async fn test_add_and_redirect() {
-        let original_url = "https://example.com";
-        let app = setup_router().await;
+        let client =
+            serve(setup_router(Store::memory(), keys(), RateLimiter::default()).await).await;
+
+        let code = client
+            .clone()
+            .with_api_key(ALICE_KEY)
+            .add_url(&new_link("https://example.com", None))
+            .await
+            .unwrap();
+        assert_eq!(code, "mtLIlHP");
+
+        let redirect = client.visit(&code).await.unwrap();
+        assert_eq!(redirect.status, StatusCode::TEMPORARY_REDIRECT.as_u16());
+        assert_eq!(redirect.location, "https://example.com/");
+    }
+
+    #[tokio::test]
+    async fn test_health_checks() {
+        let client =
+            serve(setup_router(Store::memory(), ApiKeys::new(), RateLimiter::default()).await)
+                .await;
+
+        assert!(client.healthz().await.unwrap());
+        assert!(client.readyz().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_metrics() {
+        let app = setup_router(Store::memory(), keys(), RateLimiter::default()).await;
 
         let request = Request::builder()
-            .uri("/add_url")
+            .uri("/urls")
             .method("POST")
+            .header(API_KEY_HEADER, ALICE_KEY)
             .header("content-type", "application/json")
-            .body(Body::from(json!({ "url": original_url }).to_string()))
+            .header("x-request-id", "req-1")
+            .body(Body::from(
+                json!({ "url": "https://example.com", "alias": "example" }).to_string(),
+            ))
             .unwrap();
-
         let response = app.clone().oneshot(request).await.unwrap();
-        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.headers()["x-request-id"], "req-1");
 
-        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
-        let response_data: AddUrlResponse = serde_json::from_slice(&body).unwrap();
-        assert_eq!(response_data.hashed_url, "9398cc7c078760e6");
+        for uri in ["/example", "/example", "/missing", "/no/such/route"] {
+            let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert!(response.headers().contains_key("x-request-id"));
+        }
 
         let request = Request::builder()
-            .uri(format!("/{}", response_data.hashed_url))
+            .uri("/metrics")
             .body(Body::empty())
             .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], metrics::CONTENT_TYPE);
 
-        let response = app.clone().oneshot(request).await.unwrap();
-        assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
-        assert_eq!(response.headers().get(LOCATION).unwrap(), &original_url);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let text = String::from_utf8(body.to_vec()).unwrap();
+        for line in [
+            "http_requests_total{method=\"POST\",route=\"/urls\",status=\"201\"} 1",
+            "http_requests_total{method=\"GET\",route=\"/:url_hash\",status=\"307\"} 2",
+            "http_requests_total{method=\"GET\",route=\"/:url_hash\",status=\"404\"} 1",
+            "http_requests_total{method=\"GET\",route=\"unmatched\",status=\"404\"} 1",
+            "redirects_total{kind=\"temporary\"} 2",
+        ] {
+            assert!(text.lines().any(|l| l == line), "missing {}", line);
+        }
+        assert!(text.contains("storage_operation_duration_seconds_count{operation=\"get\"}"));
     }
 
     #[tokio::test]
-    async fn test_get_all_and_delete() {
-        let original_url = "https://example.com";
-        let app = setup_router().await;
+    async fn test_urls_resource() {
+        let app = setup_router(Store::memory(), keys(), RateLimiter::default()).await;
+        let client = serve(app.clone()).await;
+        let (alice, bob) = (
+            client.clone().with_api_key(ALICE_KEY),
+            client.with_api_key(BOB_KEY),
+        );
+
+        let link = alice
+            .create_url(&client::AddUrlRequest {
+                max_clicks: Some(5),
+                ..new_link("https://example.com", Some("example"))
+            })
+            .await
+            .unwrap();
+        assert_eq!(link.original, "https://example.com/");
+        assert_eq!(link.max_clicks, Some(5));
 
         let request = Request::builder()
-            .uri("/add_url")
+            .uri("/urls")
             .method("POST")
+            .header(API_KEY_HEADER, ALICE_KEY)
             .header("content-type", "application/json")
-            .body(Body::from(json!({"url": original_url}).to_string()))
+            .body(Body::from(
+                json!({ "url": "https://example.com", "alias": "second" }).to_string(),
+            ))
             .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.headers().get(LOCATION).unwrap(), "/urls/second");
 
-        let response = app.clone().oneshot(request).await.unwrap();
+        assert!(alice.get_url("example").await.is_ok());
+        let error = bob.get_url("example").await.unwrap_err();
+        assert_eq!(error.status(), Some(StatusCode::FORBIDDEN.as_u16()));
+
+        let patch = client::PatchUrlRequest {
+            url: Some("https://other.com".to_string()),
+            ttl_seconds: Some(60),
+            max_clicks: Some(None),
+            ..client::PatchUrlRequest::default()
+        };
+        let link = alice.patch_url("example", &patch).await.unwrap();
+        assert_eq!(link.original, "https://other.com/");
+        assert_eq!(link.max_clicks, None);
+        assert!(link.expires_at.is_some());
+
+        let patch = client::PatchUrlRequest {
+            ttl_seconds: Some(60),
+            expires_at: Some(None),
+            ..client::PatchUrlRequest::default()
+        };
+        let error = alice.patch_url("example", &patch).await.unwrap_err();
+        assert_eq!(error.status(), Some(StatusCode::BAD_REQUEST.as_u16()));
+
+        alice.delete_code("example").await.unwrap();
+
+        for error in [
+            alice.get_url("example").await.unwrap_err(),
+            alice.delete_code("example").await.unwrap_err(),
+            alice.visit("example").await.unwrap_err(),
+        ] {
+            assert_eq!(error.status(), Some(StatusCode::NOT_FOUND.as_u16()));
+            assert_eq!(error.code(), Some("not_found"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_urls_pages() {
+        let store = Store::memory();
+        for alias in ["ccc", "aaa", "bbb"] {
+            store
+                .add(
+                    format!("https://example.com/{}", alias),
+                    Some(alias.to_string()),
+                    LinkOptions::default(),
+                    &Principal::user("alice"),
+                )
+                .await
+                .unwrap();
+        }
+        let alice = serve(setup_router(store, keys(), RateLimiter::default()).await)
+            .await
+            .with_api_key(ALICE_KEY);
+
+        let hashes = |page: &client::UrlPage| -> Vec<String> {
+            page.links.iter().map(|link| link.hash.clone()).collect()
+        };
+        let request = |cursor: Option<String>, sort: &str| client::PageRequest {
+            cursor,
+            limit: Some(2),
+            sort: Some(sort.to_string()),
+            ..client::PageRequest::default()
+        };
+
+        let page = alice.list_urls(&request(None, "-created")).await.unwrap();
+        assert_eq!(hashes(&page), ["bbb", "aaa"]);
+
+        let page = alice
+            .list_urls(&request(page.next_cursor, "-created"))
+            .await
+            .unwrap();
+        assert_eq!(hashes(&page), ["ccc"]);
+        assert_eq!(page.next_cursor, None);
+
+        for request in [
+            request(None, "oldest"),
+            request(Some("abc".to_string()), "created"),
+        ] {
+            let error = alice.list_urls(&request).await.unwrap_err();
+            assert_eq!(error.status(), Some(StatusCode::BAD_REQUEST.as_u16()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_all_and_delete() {
+        let original_url = "https://example.com";
+        let alice = serve(setup_router(Store::memory(), keys(), RateLimiter::default()).await)
+            .await
+            .with_api_key(ALICE_KEY);
+        let link = client::UrlMap {
+            hash: "mtLIlHP".to_string(),
+            original: "https://example.com/".to_string(),
+            short: "http://localhost:3000/mtLIlHP".to_string(),
+            expires_at: None,
+            max_clicks: None,
+            clicks: 0,
+            owner: Some("alice".to_string()),
+            title: None,
+            tags: Vec::new(),
+            group: None,
+            interstitial: false,
+            preview: None,
+        };
+
+        alice.add_url(&new_link(original_url, None)).await.unwrap();
+
+        let links = alice
+            .get_all(&client::PageRequest::default())
+            .await
+            .unwrap()
+            .links;
+        assert!(links.contains(&link));
+
+        assert_eq!(alice.delete_url(original_url).await.unwrap(), original_url);
+
+        let links = alice
+            .get_all(&client::PageRequest::default())
+            .await
+            .unwrap()
+            .links;
+        assert!(!links.contains(&link));
+    }
+
+    #[tokio::test]
+    async fn test_bulk_import_and_export() {
+        let app = setup_router(Store::memory(), keys(), RateLimiter::default()).await;
+        let alice = serve(app.clone()).await.with_api_key(ALICE_KEY);
+
+        let report = alice
+            .bulk_import(
+                client::BulkFormat::Csv,
+                "url,alias,max_clicks\n\
+                 https://a.com,aaa,\n\
+                 https://b.com,,x\n\
+                 https://c.com,aaa,\n\
+                 https://d.com,,3\n",
+            )
+            .await
+            .unwrap();
+        assert_eq!((report.succeeded, report.failed), (2, 2));
+        let codes: Vec<Option<&str>> = report
+            .results
+            .iter()
+            .map(|result| result.error.as_ref().map(|error| error.code.as_str()))
+            .collect();
+        assert_eq!(codes, [None, Some("invalid_row"), Some("conflict"), None]);
+        assert_eq!(report.results[0].hash.as_deref(), Some("aaa"));
+        assert_eq!(report.results[2].line, 4);
+
+        let export = alice.export(client::BulkFormat::Csv).await.unwrap();
+        let lines: Vec<&str> = export.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[0],
+            "hash,original,short,expires_at,max_clicks,clicks,owner,interstitial,title,tags,group"
+        );
+        assert_eq!(
+            lines[1],
+            "aaa,https://a.com/,http://localhost:3000/aaa,,,0,alice,false,,,"
+        );
+
+        let export = alice.export(client::BulkFormat::JsonLines).await.unwrap();
+        let links: Vec<client::UrlMap> = export
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[1].original, "https://d.com/");
+        assert_eq!(links[1].max_clicks, Some(3));
+
+        // Content types and formats the client does not send.
+        let request = |method: &str, uri: &str, content_type: &str| {
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header(API_KEY_HEADER, ALICE_KEY)
+                .header("content-type", content_type)
+                .body(Body::from("{}"))
+                .unwrap()
+        };
+        let response = app
+            .clone()
+            .oneshot(request("POST", "/urls/bulk", "application/json"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let response = app
+            .clone()
+            .oneshot(request("GET", "/urls/export?format=xml", "text/plain"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        for (query, content_type) in [
+            ("?format=csv", "text/csv; charset=utf-8"),
+            ("", "application/x-ndjson"),
+        ] {
+            let uri = format!("/urls/export{}", query);
+            let response = app
+                .clone()
+                .oneshot(request("GET", &uri, "text/plain"))
+                .await
+                .unwrap();
+            assert_eq!(response.headers()["content-type"], content_type);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_requests() {
+        let alice = serve(setup_router(Store::memory(), keys(), RateLimiter::default()).await)
+            .await
+            .with_api_key(ALICE_KEY);
+
+        let requests = (0..50).map(|i| {
+            let alice = alice.clone();
+            tokio::spawn(async move {
+                alice
+                    .add_url(&new_link(&format!("https://example.com/{}", i), None))
+                    .await
+            })
+        });
+
+        for request in requests.collect::<Vec<_>>() {
+            assert!(request.await.unwrap().is_ok());
+        }
+
+        let links = alice
+            .get_all(&client::PageRequest::default())
+            .await
+            .unwrap()
+            .links;
+        assert_eq!(links.len(), 50);
+    }
+
+    #[tokio::test]
+    async fn test_add_alias() {
+        let client =
+            serve(setup_router(Store::memory(), keys(), RateLimiter::default()).await).await;
+        let alice = client.clone().with_api_key(ALICE_KEY);
+
+        alice
+            .add_url(&new_link("https://example.com", Some("example")))
+            .await
+            .unwrap();
+
+        let error = alice
+            .add_url(&new_link("https://other.com", Some("example")))
+            .await
+            .unwrap_err();
+        assert_eq!(error.status(), Some(StatusCode::CONFLICT.as_u16()));
+
+        let error = alice
+            .add_url(&new_link("https://other.com", Some("no way")))
+            .await
+            .unwrap_err();
+        assert_eq!(error.status(), Some(StatusCode::BAD_REQUEST.as_u16()));
+
+        let redirect = client.visit("example").await.unwrap();
+        assert_eq!(redirect.location, "https://example.com/");
+    }
+
+    #[tokio::test]
+    async fn test_link_limits() {
+        let client =
+            serve(setup_router(Store::memory(), keys(), RateLimiter::default()).await).await;
+        let alice = client.clone().with_api_key(ALICE_KEY);
+
+        alice
+            .add_url(&client::AddUrlRequest {
+                ttl_seconds: Some(3600),
+                max_clicks: Some(1),
+                ..new_link("https://example.com", Some("once"))
+            })
+            .await
+            .unwrap();
+
+        let redirect = client.visit("once").await.unwrap();
+        assert_eq!(redirect.status, StatusCode::TEMPORARY_REDIRECT.as_u16());
+        let error = client.visit("once").await.unwrap_err();
+        assert_eq!(error.status(), Some(StatusCode::GONE.as_u16()));
+
+        let links = alice
+            .get_all(&client::PageRequest::default())
+            .await
+            .unwrap()
+            .links;
+        assert_eq!(links[0].max_clicks, Some(1));
+        assert!(links[0].expires_at.is_some());
+
+        let past = "2000-01-01T00:00:00Z".parse().unwrap();
+        let future = "2100-01-01T00:00:00Z".parse().unwrap();
+        for request in [
+            client::AddUrlRequest {
+                max_clicks: Some(0),
+                ..new_link("https://example.com", None)
+            },
+            client::AddUrlRequest {
+                expires_at: Some(past),
+                ..new_link("https://example.com", None)
+            },
+            client::AddUrlRequest {
+                ttl_seconds: Some(60),
+                expires_at: Some(future),
+                ..new_link("https://example.com", None)
+            },
+        ] {
+            let error = alice.add_url(&request).await.unwrap_err();
+            assert_eq!(error.status(), Some(StatusCode::BAD_REQUEST.as_u16()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_preview() {
+        // The previews are fetched from a stub of the target site.
+        let site = serve(Router::new().route(
+            "/article",
+            get(|| async {
+                Html(
+                    "<html><head><meta property=\"og:title\" content=\"An article\">\
+                     <meta property=\"og:image\" content=\"/cover.png\"></head></html>",
+                )
+            }),
+        ))
+        .await;
+        let article = format!("{}/article", site.base_url());
+        let store = Store::memory().with_preview_fetcher(Arc::new(HttpFetcher::allowing_private(
+            UrlPolicy::default(),
+            std::time::Duration::from_secs(5),
+        )));
+        let client = serve(setup_router(store, keys(), RateLimiter::default()).await).await;
+        let alice = client.clone().with_api_key(ALICE_KEY);
+
+        let link = alice
+            .create_url(&client::AddUrlRequest {
+                interstitial: true,
+                ..new_link(&article, Some("article"))
+            })
+            .await
+            .unwrap();
+        let preview = link.preview.unwrap();
+        assert_eq!(preview.title.as_deref(), Some("An article"));
+        assert_eq!(
+            preview.image,
+            Some(format!("{}/cover.png", site.base_url()))
+        );
+
+        // Interstitial links answer with their page, counting the click.
+        let response = reqwest::get(format!("{}/article", client.base_url()))
+            .await
+            .unwrap();
         assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_SECURITY_POLICY],
+            preview::CONTENT_SECURITY_POLICY
+        );
+        let page = response.text().await.unwrap();
+        assert!(page.contains("<h1>An article</h1>"));
+        assert!(page.contains(&format!("href=\"{}\"", article)));
+        assert_eq!(alice.get_url("article").await.unwrap().clicks, 1);
+
+        // Any link can be previewed, without counting a click, continuing through the short link.
+        alice
+            .add_url(&new_link(&article, Some("plain")))
+            .await
+            .unwrap();
+        let page = client.preview("plain").await.unwrap();
+        assert!(page.contains("<h1>An article</h1>"));
+        assert!(page.contains("href=\"http://localhost:3000/plain\""));
+        assert_eq!(alice.get_url("plain").await.unwrap().clicks, 0);
+        assert_eq!(
+            client.visit("plain").await.unwrap().status,
+            StatusCode::TEMPORARY_REDIRECT.as_u16()
+        );
+
+        let error = client.preview("missing").await.unwrap_err();
+        assert_eq!(error.status(), Some(StatusCode::NOT_FOUND.as_u16()));
+    }
+
+    #[tokio::test]
+    async fn test_stats() {
+        let store = Store::memory().with_analytics(true);
+        let app = setup_router(store, keys(), RateLimiter::default()).await;
+        let alice = serve(app.clone()).await.with_api_key(ALICE_KEY);
+
+        alice
+            .add_url(&new_link("https://example.com", Some("example")))
+            .await
+            .unwrap();
 
         let request = Request::builder()
-            .uri("/get_all")
+            .uri("/example")
+            .header("referer", "https://news.ycombinator.com/item?id=1")
+            .header(
+                "user-agent",
+                "Mozilla/5.0 (X11; Linux x86_64; rv:126.0) Firefox/126.0",
+            )
+            .header("cf-ipcountry", "DE")
             .body(Body::empty())
             .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
 
-        let response = app.clone().oneshot(request).await.unwrap();
+        let stats = alice.stats("example").await.unwrap();
+        assert_eq!(stats.clicks, 1);
+        assert_eq!(stats.daily.values().sum::<u64>(), 1);
+        assert_eq!(stats.hourly.values().sum::<u64>(), 1);
+        assert_eq!(stats.referrers.get("news.ycombinator.com"), Some(&1));
+        assert_eq!(stats.user_agents.get("firefox"), Some(&1));
+        assert_eq!(stats.countries.get("DE"), Some(&1));
 
-        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
-        let response_data: Vec<UrlMap> = serde_json::from_slice(&body).unwrap();
+        let error = alice.stats("missing").await.unwrap_err();
+        assert_eq!(error.status(), Some(StatusCode::NOT_FOUND.as_u16()));
+    }
+
+    #[tokio::test]
+    async fn test_tags_search_and_groups() {
+        let store = Store::memory().with_analytics(true);
+        let client = serve(setup_router(store, keys(), RateLimiter::default()).await).await;
+        let (alice, bob) = (
+            client.clone().with_api_key(ALICE_KEY),
+            client.clone().with_api_key(BOB_KEY),
+        );
+
+        for (user, request) in [
+            (
+                &alice,
+                client::AddUrlRequest {
+                    title: Some("Launch post".to_string()),
+                    tags: vec!["Blog".to_string(), "launch".to_string()],
+                    group: Some("spring".to_string()),
+                    ..new_link("https://blog.example.com/launch", Some("launch"))
+                },
+            ),
+            (
+                &alice,
+                client::AddUrlRequest {
+                    tags: vec!["sale".to_string()],
+                    group: Some("spring".to_string()),
+                    ..new_link("https://shop.example.com/sale", Some("sale"))
+                },
+            ),
+            (&alice, new_link("https://example.org", Some("other"))),
+            (
+                &bob,
+                client::AddUrlRequest {
+                    group: Some("spring".to_string()),
+                    ..new_link("https://example.net", Some("bobs"))
+                },
+            ),
+        ] {
+            user.create_url(&request).await.unwrap();
+        }
+
+        let hashes = |page: client::UrlPage| -> Vec<String> {
+            page.links.into_iter().map(|link| link.hash).collect()
+        };
+        let search = |request: client::PageRequest| {
+            let alice = alice.clone();
+            async move { alice.list_urls(&request).await.map(hashes) }
+        };
+
+        let page = alice
+            .list_urls(&client::PageRequest {
+                tag: Some("BLOG".to_string()),
+                ..client::PageRequest::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(page.links.len(), 1);
+        assert_eq!(page.links[0].tags, ["blog", "launch"]);
+        assert_eq!(page.links[0].title.as_deref(), Some("Launch post"));
+
+        let q = |q: &str, match_mode: Option<&str>| client::PageRequest {
+            q: Some(q.to_string()),
+            match_mode: match_mode.map(str::to_string),
+            ..client::PageRequest::default()
+        };
+        assert_eq!(search(q("POST", None)).await.unwrap(), ["launch"]);
+        assert_eq!(
+            search(q("https://shop", Some("prefix"))).await.unwrap(),
+            ["sale"]
+        );
+        assert!(search(q("shop", Some("prefix"))).await.unwrap().is_empty());
+        let error = search(q("shop", Some("regex"))).await.unwrap_err();
+        assert_eq!(error.status(), Some(StatusCode::BAD_REQUEST.as_u16()));
+
+        let spring = client::PageRequest {
+            group: Some("spring".to_string()),
+            ..client::PageRequest::default()
+        };
+        assert_eq!(search(spring.clone()).await.unwrap(), ["launch", "sale"]);
+        assert_eq!(hashes(bob.get_all(&spring).await.unwrap()), ["bobs"]);
 
-        println!("{:?}", response_data);
+        for code in ["launch", "launch", "sale"] {
+            client.visit(code).await.unwrap();
+        }
+        assert_eq!(
+            alice.groups().await.unwrap(),
+            [client::GroupSummary {
+                name: "spring".to_string(),
+                links: 2,
+                clicks: 3,
+            }]
+        );
+        let stats = alice.group_stats("spring").await.unwrap();
+        assert_eq!(stats.clicks, 3);
+        assert_eq!(
+            stats.links,
+            BTreeMap::from([("launch".to_string(), 2), ("sale".to_string(), 1)])
+        );
+        assert_eq!(stats.daily.values().sum::<u64>(), 3);
+        assert_eq!(bob.group_stats("spring").await.unwrap().clicks, 0);
+        let error = alice.group_stats("winter").await.unwrap_err();
+        assert_eq!(error.status(), Some(StatusCode::NOT_FOUND.as_u16()));
 
-        assert!(response_data.len() > 0);
-        assert!(response_data.contains(&UrlMap {
-            hash: "9398cc7c078760e6".to_string(),
-            original: original_url.to_string(),
-            short: "http://localhost:3000/9398cc7c078760e6".to_string()
-        }));
+        let patch = client::PatchUrlRequest {
+            title: Some(None),
+            tags: Some(vec!["archived".to_string()]),
+            group: Some(None),
+            ..client::PatchUrlRequest::default()
+        };
+        let link = alice.patch_url("launch", &patch).await.unwrap();
+        assert_eq!(
+            (link.title, link.tags, link.group),
+            (None, vec!["archived".to_string()], None)
+        );
+        assert_eq!(alice.groups().await.unwrap()[0].links, 1);
+
+        let error = alice
+            .create_url(&client::AddUrlRequest {
+                tags: vec!["not a tag".to_string()],
+                ..new_link("https://example.com", None)
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), Some("invalid_request"));
+    }
+
+    #[tokio::test]
+    async fn test_add_invalid_url() {
+        let alice = serve(setup_router(Store::memory(), keys(), RateLimiter::default()).await)
+            .await
+            .with_api_key(ALICE_KEY);
+
+        for (url, code) in [
+            ("javascript:alert(1)", "unsupported_scheme"),
+            ("not a url", "invalid_url"),
+        ] {
+            let error = alice.add_url(&new_link(url, None)).await.unwrap_err();
+            assert_eq!(error.status(), Some(StatusCode::BAD_REQUEST.as_u16()));
+            assert_eq!(error.code(), Some(code));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_api_keys() {
+        let app = setup_router(Store::memory(), keys(), RateLimiter::default()).await;
+
+        let add = |key: Option<&str>| {
+            let mut request = Request::builder()
+                .uri("/add_url")
+                .method("POST")
+                .header("content-type", "application/json");
+            if let Some(key) = key {
+                request = request.header("authorization", format!("Bearer {}", key));
+            }
+            request
+                .body(Body::from(
+                    json!({ "url": "https://example.com", "alias": "example" }).to_string(),
+                ))
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(add(None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = app.clone().oneshot(add(Some("wrong"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = app.clone().oneshot(add(Some(ALICE_KEY))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Redirects stay public.
+        let request = Request::builder()
+            .uri("/example")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+
+        let update = |key: &str| {
+            Request::builder()
+                .uri("/update_url")
+                .method("PUT")
+                .header(API_KEY_HEADER, key)
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({ "hash": "example", "url": "https://other.com" }).to_string(),
+                ))
+                .unwrap()
+        };
+        let response = app.clone().oneshot(update(BOB_KEY)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = app.clone().oneshot(update(ALICE_KEY)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let request = Request::builder()
+            .uri("/get_all?limit=10")
+            .header(API_KEY_HEADER, BOB_KEY)
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let page: UrlPage = serde_json::from_slice(&body).unwrap();
+        assert!(page.links.is_empty());
+        assert_eq!(page.next_cursor, None);
+        assert_eq!(page.limit, 10);
 
         let request = Request::builder()
             .uri("/delete_url")
             .method("DELETE")
+            .header(API_KEY_HEADER, BOB_KEY)
             .header("content-type", "application/json")
-            .body(Body::from(json!({"url": original_url}).to_string()))
+            .body(Body::from(
+                json!({ "url": "https://other.com" }).to_string(),
+            ))
             .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
 
-        let response = app.clone().oneshot(request).await.unwrap();
+    #[tokio::test]
+    async fn test_rate_limit() {
+        let limits = RateLimits::new().with_route("/add_url", RateLimit::parse("2/min").unwrap());
+        let rate_limiter = RateLimiter::new(Arc::new(MemoryBuckets::new()), limits);
+        let app = setup_router(Store::memory(), keys(), rate_limiter).await;
+
+        let add = |key: &str| {
+            Request::builder()
+                .uri("/add_url")
+                .method("POST")
+                .header(API_KEY_HEADER, key)
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({ "url": "https://example.com" }).to_string(),
+                ))
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(add(ALICE_KEY)).await.unwrap();
         assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["x-ratelimit-limit"], "2");
+        assert_eq!(response.headers()["x-ratelimit-remaining"], "1");
+        app.clone().oneshot(add(ALICE_KEY)).await.unwrap();
 
-        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
-        let response_data: String = serde_json::from_slice(&body).unwrap();
+        let response = app.clone().oneshot(add(ALICE_KEY)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()["retry-after"], "30");
+        assert_eq!(response.headers()["x-ratelimit-remaining"], "0");
+        assert_eq!(response.headers()["x-ratelimit-reset"], "60");
 
-        assert_eq!(response_data, original_url);
+        // Every request of this test comes from the same unknown IP, which is limited too.
+        let response = app.clone().oneshot(add(BOB_KEY)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
 
         let request = Request::builder()
             .uri("/get_all")
+            .header(API_KEY_HEADER, ALICE_KEY)
             .body(Body::empty())
             .unwrap();
-
-        let response = app.clone().oneshot(request).await.unwrap();
-
-        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
-        let response_data: Vec<UrlMap> = serde_json::from_slice(&body).unwrap();
-
-        println!("{:?}", response_data);
-
-        assert!(response_data.len() > 0);
-        assert!(!response_data.contains(&UrlMap {
-            hash: "9398cc7c078760e6".to_string(),
-            original: original_url.to_string(),
-            short: "http://localhost:3000/9398cc7c078760e6".to_string()
-        }));
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get("x-ratelimit-limit").is_none());
     }
 }
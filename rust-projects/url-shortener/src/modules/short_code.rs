@@ -0,0 +1,126 @@
+use xxhash_rust::xxh3::xxh3_64_with_seed;
+
+const BASE62: &[u8; 62] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+pub const DEFAULT_CODE_LENGTH: usize = 7;
+/// Longest generated code, 62^10 being the largest power of 62 below 2^64.
+pub const MAX_CODE_LENGTH: usize = 10;
+
+const ALIAS_MIN_LENGTH: usize = 3;
+pub const ALIAS_MAX_LENGTH: usize = 32;
+
+/// Aliases that would shadow a route or that we keep for later use.
+const RESERVED_ALIASES: &[&str] = &[
+    "add_url",
+    "admin",
+    "api",
+    "bulk",
+    "delete_url",
+    "docs",
+    "export",
+    "get_all",
+    "groups",
+    "health",
+    "healthz",
+    "login",
+    "metrics",
+    "readyz",
+    "static",
+    "update_url",
+    "urls",
+];
+
+/// Encodes a number in base62, most significant digit first.
+///
+/// # Example
+///
+/// ```
+/// use url_shortener::modules::short_code::base62;
+///
+/// assert_eq!(base62(0), "0");
+/// assert_eq!(base62(61), "z");
+/// assert_eq!(base62(62), "10");
+/// ```
+pub fn base62(mut value: u64) -> String {
+    let mut digits = vec![];
+
+    loop {
+        digits.push(BASE62[(value % 62) as usize]);
+        value /= 62;
+
+        if value == 0 {
+            break;
+        }
+    }
+
+    digits.reverse();
+    String::from_utf8(digits).unwrap()
+}
+
+/// Generates the short code of `url` for a given attempt, retrying with the next attempt after
+/// a collision gives a different code.
+///
+/// # Arguments
+/// * `url` - The URL being shortened.
+/// * `attempt` - Number of collisions so far, used as the hash seed.
+/// * `length` - Number of base62 characters, at most [`MAX_CODE_LENGTH`].
+pub fn generate(url: &str, attempt: u64, length: usize) -> String {
+    // 62^10 < 2^64, so every character is uniformly taken from the hash.
+    let code =
+        base62(xxh3_64_with_seed(url.as_bytes(), attempt) % 62u64.pow(MAX_CODE_LENGTH as u32));
+
+    format!("{:0>10}", code)[..length.clamp(1, MAX_CODE_LENGTH)].to_string()
+}
+
+/// Checks that a custom alias can be used as a short code.
+///
+/// # Returns
+/// * `Ok(())` if the alias is valid.
+/// * `Err(String)` explaining why it is not.
+pub fn validate_alias(alias: &str) -> Result<(), String> {
+    if !(ALIAS_MIN_LENGTH..=ALIAS_MAX_LENGTH).contains(&alias.len()) {
+        return Err(format!(
+            "Alias must be between {} and {} characters long",
+            ALIAS_MIN_LENGTH, ALIAS_MAX_LENGTH
+        ));
+    }
+
+    if !alias
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err("Alias may only contain letters, digits, '-' and '_'".to_string());
+    }
+
+    if RESERVED_ALIASES.contains(&alias.to_lowercase().as_str()) {
+        return Err(format!("Alias '{}' is reserved", alias));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate() {
+        let code = generate("https://example.com", 0, 7);
+
+        assert_eq!(code.len(), 7);
+        assert!(code.chars().all(|c| c.is_ascii_alphanumeric()));
+        assert_eq!(code, generate("https://example.com", 0, 7));
+        assert_ne!(code, generate("https://example.com", 1, 7));
+        assert_eq!(generate("https://example.com", 0, 20).len(), 10);
+    }
+
+    #[test]
+    fn test_validate_alias() {
+        assert!(validate_alias("my-link_2024").is_ok());
+        assert!(validate_alias("ab").is_err());
+        assert!(validate_alias(&"a".repeat(33)).is_err());
+        assert!(validate_alias("with space").is_err());
+        assert!(validate_alias("caf\u{e9}").is_err());
+        assert!(validate_alias("GET_ALL").is_err());
+    }
+}
@@ -0,0 +1,719 @@
+pub mod memory_storage;
+pub mod metered_storage;
+pub mod redis_storage;
+pub mod sqlite_storage;
+
+use std::{fmt, sync::Arc};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+use super::{config::Settings, preview::LinkPreview, validation::UrlError};
+use memory_storage::MemoryStorage;
+use redis_storage::RedisStorage;
+use sqlite_storage::SqliteStorage;
+
+#[derive(Debug)]
+pub enum StoreError {
+    NotFound,
+    /// The short code or alias already maps to another URL.
+    Conflict(String),
+    /// The request is not valid, e.g. a reserved alias or an expiry in the past.
+    Invalid(String),
+    /// The URL to shorten was rejected by the [`UrlPolicy`](super::validation::UrlPolicy).
+    InvalidUrl(UrlError),
+    /// The link expired or reached its maximum number of clicks.
+    Gone,
+    /// The link belongs to another user.
+    Forbidden,
+    Backend(String),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::NotFound => write!(f, "URL not found"),
+            StoreError::Conflict(e) | StoreError::Invalid(e) => write!(f, "{}", e),
+            StoreError::InvalidUrl(e) => write!(f, "{}", e),
+            StoreError::Gone => write!(f, "URL expired"),
+            StoreError::Forbidden => write!(f, "URL belongs to another user"),
+            StoreError::Backend(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+impl From<redis::RedisError> for StoreError {
+    fn from(e: redis::RedisError) -> Self {
+        StoreError::Backend(e.to_string())
+    }
+}
+
+impl From<rusqlite::Error> for StoreError {
+    fn from(e: rusqlite::Error) -> Self {
+        StoreError::Backend(e.to_string())
+    }
+}
+
+/// A shortened URL as stored by the backends.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UrlRecord {
+    pub url: String,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub max_clicks: Option<u64>,
+    pub clicks: u64,
+    /// User who created the link, `None` for links created before API keys existed.
+    pub owner: Option<String>,
+    /// Insertion order, assigned by the backend on insert.
+    pub seq: u64,
+    /// Shows a preview page instead of redirecting.
+    pub interstitial: bool,
+    /// Preview of the URL, fetched when the link was added.
+    pub preview: Option<LinkPreview>,
+    pub title: Option<String>,
+    /// Lowercase, sorted and without duplicates.
+    pub tags: Vec<String>,
+    /// Group, or campaign, the link is part of.
+    pub group: Option<String>,
+}
+
+impl UrlRecord {
+    pub fn new(url: &str) -> Self {
+        UrlRecord {
+            url: url.to_string(),
+            expires_at: None,
+            max_clicks: None,
+            clicks: 0,
+            owner: None,
+            seq: 0,
+            interstitial: false,
+            preview: None,
+            title: None,
+            tags: Vec::new(),
+            group: None,
+        }
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+/// Order of the links in a page.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    #[default]
+    CodeAsc,
+    CodeDesc,
+    /// Oldest first.
+    CreatedAsc,
+    /// Newest first.
+    CreatedDesc,
+}
+
+impl SortOrder {
+    /// Parses `code`, `-code`, `created` or `-created`.
+    pub fn parse(sort: &str) -> Option<Self> {
+        match sort {
+            "code" => Some(SortOrder::CodeAsc),
+            "-code" => Some(SortOrder::CodeDesc),
+            "created" => Some(SortOrder::CreatedAsc),
+            "-created" => Some(SortOrder::CreatedDesc),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of the sort key of a link, as used in [`PageQuery::after`].
+    pub fn cursor(&self, code: &str, record: &UrlRecord) -> String {
+        match self {
+            SortOrder::CodeAsc | SortOrder::CodeDesc => code.to_string(),
+            SortOrder::CreatedAsc | SortOrder::CreatedDesc => record.seq.to_string(),
+        }
+    }
+}
+
+/// Links of a page to keep, every link by default.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LinkFilter {
+    /// Only the links with this tag.
+    pub tag: Option<String>,
+    /// Only the links of this group.
+    pub group: Option<String>,
+    /// Only the links whose URL or title contains this text, ignoring ASCII case.
+    pub text: Option<String>,
+    /// Matches `text` at the start of the URL or title rather than anywhere in them.
+    pub prefix: bool,
+}
+
+impl LinkFilter {
+    pub fn is_empty(&self) -> bool {
+        self.tag.is_none() && self.group.is_none() && self.text.is_none()
+    }
+
+    /// Returns `true` if `record` is kept by the filter.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use url_shortener::modules::storage::{LinkFilter, UrlRecord};
+    ///
+    /// let record = UrlRecord {
+    ///     title: Some("Spring sale".to_string()),
+    ///     ..UrlRecord::new("https://shop.example.com/sale")
+    /// };
+    /// let filter = LinkFilter {
+    ///     text: Some("SALE".to_string()),
+    ///     ..LinkFilter::default()
+    /// };
+    ///
+    /// assert!(filter.matches(&record));
+    /// assert!(!LinkFilter { prefix: true, ..filter }.matches(&record));
+    /// ```
+    pub fn matches(&self, record: &UrlRecord) -> bool {
+        if let Some(tag) = &self.tag {
+            if !record.tags.contains(tag) {
+                return false;
+            }
+        }
+        if self.group.is_some() && record.group != self.group {
+            return false;
+        }
+        let Some(text) = &self.text else {
+            return true;
+        };
+
+        let text = text.to_ascii_lowercase();
+        let found = |value: &str| {
+            let value = value.to_ascii_lowercase();
+            if self.prefix {
+                value.starts_with(&text)
+            } else {
+                value.contains(&text)
+            }
+        };
+        found(&record.url) || record.title.as_deref().is_some_and(found)
+    }
+}
+
+/// A page of links, in keyset pagination.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PageQuery {
+    /// Only the links of this user, every link if `None`.
+    pub owner: Option<String>,
+    pub filter: LinkFilter,
+    pub order: SortOrder,
+    /// Sort key of the last link of the previous page, see [`SortOrder::cursor`].
+    pub after: Option<String>,
+    pub limit: usize,
+}
+
+impl PageQuery {
+    /// Returns the sequence number a page sorted by creation starts after, `None` for the other
+    /// orders.
+    fn after_seq(&self) -> Result<Option<u64>, StoreError> {
+        match (self.order, self.after.as_deref()) {
+            (SortOrder::CreatedAsc | SortOrder::CreatedDesc, Some(after)) => after
+                .parse()
+                .map(Some)
+                .map_err(|_| StoreError::Invalid(format!("Invalid cursor '{}'", after))),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Storage of the shortened URLs, keyed by their short code.
+#[async_trait]
+pub trait Storage: Send + Sync {
+    /// Stores `record` under `code` unless the code is already taken, assigning its `seq`.
+    ///
+    /// # Returns
+    /// `true` if the record was stored, `false` if the code was taken.
+    async fn insert(&self, code: &str, record: &UrlRecord) -> Result<bool, StoreError>;
+
+    /// Stores many records at once, in order, as [`Storage::insert`] would.
+    ///
+    /// # Returns
+    /// For each record, `true` if it was stored, `false` if its code was taken.
+    async fn insert_many(&self, records: &[(String, UrlRecord)]) -> Result<Vec<bool>, StoreError> {
+        let mut ret = Vec::with_capacity(records.len());
+        for (code, record) in records {
+            ret.push(self.insert(code, record).await?);
+        }
+        Ok(ret)
+    }
+
+    /// Replaces the record stored under `code`, keeping its click count, counters, owner and
+    /// insertion order.
+    ///
+    /// # Returns
+    /// `true` if the record was replaced, `false` if there is no record under `code`.
+    async fn replace(&self, code: &str, record: &UrlRecord) -> Result<bool, StoreError>;
+
+    /// Returns the record stored under `code`, or `None` if there is none.
+    async fn get(&self, code: &str) -> Result<Option<UrlRecord>, StoreError>;
+
+    /// Returns the records stored under each of `codes`.
+    async fn get_many(&self, codes: &[String]) -> Result<Vec<Option<UrlRecord>>, StoreError> {
+        let mut ret = Vec::with_capacity(codes.len());
+        for code in codes {
+            ret.push(self.get(code).await?);
+        }
+        Ok(ret)
+    }
+
+    /// Returns up to `query.limit` `(code, record)` pairs kept by `query.filter`, in the order
+    /// of `query.order`.
+    async fn page(&self, query: &PageQuery) -> Result<Vec<(String, UrlRecord)>, StoreError>;
+
+    /// Returns every `(code, record)` pair, ordered by code.
+    async fn list(&self) -> Result<Vec<(String, UrlRecord)>, StoreError> {
+        let mut query = PageQuery {
+            limit: LIST_BATCH_SIZE,
+            ..PageQuery::default()
+        };
+        let mut ret = Vec::new();
+
+        loop {
+            let page = self.page(&query).await?;
+            let done = page.len() < query.limit;
+            query.after = page.last().map(|(code, _)| code.to_string());
+            ret.extend(page);
+
+            if done {
+                return Ok(ret);
+            }
+        }
+    }
+
+    /// Removes the record stored under `code` and its counters.
+    async fn remove(&self, code: &str) -> Result<(), StoreError>;
+
+    /// Atomically increments the click count of `code`.
+    ///
+    /// # Returns
+    /// The new click count, or `None` if there is no record under `code`.
+    async fn record_click(&self, code: &str) -> Result<Option<u64>, StoreError>;
+
+    /// Increments the named analytics counters of `code` by one, creating them if needed.
+    async fn increment_counters(&self, code: &str, counters: &[String]) -> Result<(), StoreError>;
+
+    /// Returns every `(counter, count)` pair of `code`.
+    async fn counters(&self, code: &str) -> Result<Vec<(String, u64)>, StoreError>;
+
+    /// Returns the `(counter, count)` pairs of each of `codes`.
+    async fn counters_many(&self, codes: &[String]) -> Result<Vec<Vec<(String, u64)>>, StoreError> {
+        let mut ret = Vec::with_capacity(codes.len());
+        for code in codes {
+            ret.push(self.counters(code).await?);
+        }
+        Ok(ret)
+    }
+
+    /// Returns the names of the groups of the links of `owner`, or of every link, in order.
+    /// Backends keeping an index may name a group whose links all expired.
+    async fn group_names(&self, owner: Option<&str>) -> Result<Vec<String>, StoreError>;
+
+    /// Returns the `(code, record)` pairs of the links of `group`, of `owner` or of anyone,
+    /// ordered by code.
+    async fn group_links(
+        &self,
+        group: &str,
+        owner: Option<&str>,
+    ) -> Result<Vec<(String, UrlRecord)>, StoreError>;
+
+    /// Returns the links of each of `groups`, as [`Storage::group_links`] would.
+    async fn group_links_many(
+        &self,
+        groups: &[String],
+        owner: Option<&str>,
+    ) -> Result<Vec<Vec<(String, UrlRecord)>>, StoreError> {
+        let mut ret = Vec::with_capacity(groups.len());
+        for group in groups {
+            ret.push(self.group_links(group, owner).await?);
+        }
+        Ok(ret)
+    }
+
+    /// Checks that the backend answers.
+    async fn ping(&self) -> Result<(), StoreError> {
+        Ok(())
+    }
+}
+
+/// Storage backend selected with the `STORAGE_BACKEND` setting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StorageConfig {
+    Memory,
+    /// Connection URL, from `REDIS_URL`, and prefix of the keys, from `REDIS_KEY_PREFIX`.
+    Redis {
+        url: String,
+        prefix: String,
+        /// Moves the links of previous versions under the prefix on start, from the
+        /// `REDIS_MIGRATE_LEGACY_KEYS` flag, see [`RedisStorage::migrate`].
+        migrate_legacy_keys: bool,
+    },
+    /// Database file, from `SQLITE_PATH`.
+    Sqlite(String),
+}
+
+/// Number of links read at a time by [`Storage::list`].
+const LIST_BATCH_SIZE: usize = 500;
+
+const DEFAULT_REDIS_URL: &str = "redis://127.0.0.1/";
+const DEFAULT_SQLITE_PATH: &str = "url-shortener.db";
+
+/// Returns the `REDIS_URL` setting, Redis on localhost by default.
+pub fn redis_url(settings: &Settings) -> String {
+    settings
+        .get("REDIS_URL")
+        .unwrap_or_else(|| DEFAULT_REDIS_URL.to_string())
+}
+
+/// Returns the `REDIS_KEY_PREFIX` setting, [`redis_storage::DEFAULT_PREFIX`] by default.
+pub fn redis_prefix(settings: &Settings) -> String {
+    settings
+        .get("REDIS_KEY_PREFIX")
+        .unwrap_or_else(|| redis_storage::DEFAULT_PREFIX.to_string())
+}
+
+impl StorageConfig {
+    /// Reads the configuration from the settings, defaulting to Redis on localhost.
+    pub fn from_settings(settings: &Settings) -> Result<Self, StoreError> {
+        let backend = settings
+            .get("STORAGE_BACKEND")
+            .unwrap_or_else(|| "redis".to_string());
+
+        match backend.to_lowercase().as_str() {
+            "memory" => Ok(StorageConfig::Memory),
+            "redis" => Ok(StorageConfig::Redis {
+                url: redis_url(settings),
+                prefix: redis_prefix(settings),
+                migrate_legacy_keys: settings.flag("REDIS_MIGRATE_LEGACY_KEYS"),
+            }),
+            "sqlite" => Ok(StorageConfig::Sqlite(
+                settings
+                    .get("SQLITE_PATH")
+                    .unwrap_or_else(|| DEFAULT_SQLITE_PATH.to_string()),
+            )),
+            other => Err(StoreError::Backend(format!(
+                "Unknown storage backend '{}'",
+                other
+            ))),
+        }
+    }
+
+    pub async fn connect(&self) -> Result<Arc<dyn Storage>, StoreError> {
+        Ok(match self {
+            StorageConfig::Memory => Arc::new(MemoryStorage::new()),
+            StorageConfig::Redis {
+                url,
+                prefix,
+                migrate_legacy_keys,
+            } => {
+                let storage = RedisStorage::connect(url).await?.with_prefix(prefix);
+                if *migrate_legacy_keys {
+                    storage.migrate().await?;
+                }
+                Arc::new(storage)
+            }
+            StorageConfig::Sqlite(path) => Arc::new(SqliteStorage::open(path)?),
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    fn record(url: &str, owner: Option<&str>) -> UrlRecord {
+        UrlRecord {
+            owner: owner.map(str::to_string),
+            ..UrlRecord::new(url)
+        }
+    }
+
+    async fn codes(storage: &dyn Storage, query: &PageQuery) -> Vec<String> {
+        storage
+            .page(query)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(code, _)| code)
+            .collect()
+    }
+
+    /// Checks the behavior shared by every backend, on an empty storage.
+    pub async fn check_storage(storage: &dyn Storage) {
+        assert!(storage
+            .insert("b", &record("https://b.com", Some("bob")))
+            .await
+            .unwrap());
+        assert!(storage
+            .insert("a", &record("https://a.com", Some("alice")))
+            .await
+            .unwrap());
+        assert!(!storage
+            .insert("a", &UrlRecord::new("https://a.org"))
+            .await
+            .unwrap());
+
+        let a = storage.get("a").await.unwrap().unwrap();
+        assert_eq!(a.url, "https://a.com");
+        assert_eq!(a.owner.as_deref(), Some("alice"));
+        let b = storage.get("b").await.unwrap().unwrap();
+        assert!(b.seq < a.seq);
+        assert_eq!(storage.get("missing").await.unwrap(), None);
+        assert_eq!(
+            storage.list().await.unwrap(),
+            vec![("a".to_string(), a.clone()), ("b".to_string(), b.clone())]
+        );
+
+        let replacement = UrlRecord {
+            expires_at: chrono::TimeZone::timestamp_opt(&Utc, 2_000_000_000, 0).single(),
+            max_clicks: Some(10),
+            interstitial: true,
+            preview: Some(LinkPreview {
+                title: Some("A".to_string()),
+                ..LinkPreview::default()
+            }),
+            title: Some("The A".to_string()),
+            tags: vec!["launch".to_string(), "q3".to_string()],
+            group: Some("spring".to_string()),
+            ..UrlRecord::new("https://a.org")
+        };
+        assert!(storage.replace("a", &replacement).await.unwrap());
+        assert!(!storage.replace("missing", &replacement).await.unwrap());
+        assert_eq!(
+            storage.get("a").await.unwrap(),
+            Some(UrlRecord {
+                owner: a.owner.clone(),
+                seq: a.seq,
+                ..replacement
+            })
+        );
+
+        assert_eq!(storage.record_click("a").await.unwrap(), Some(1));
+        assert_eq!(storage.record_click("a").await.unwrap(), Some(2));
+        assert_eq!(storage.record_click("missing").await.unwrap(), None);
+
+        let counters = ["day:2024-06-01".to_string(), "agent:curl".to_string()];
+        storage.increment_counters("a", &counters).await.unwrap();
+        storage
+            .increment_counters("a", &counters[..1])
+            .await
+            .unwrap();
+        let mut stored = storage.counters("a").await.unwrap();
+        stored.sort();
+        assert_eq!(
+            stored,
+            vec![
+                ("agent:curl".to_string(), 1),
+                ("day:2024-06-01".to_string(), 2)
+            ]
+        );
+        assert_eq!(
+            storage
+                .counters_many(&["missing".to_string(), "a".to_string()])
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|counters| counters.len())
+                .collect::<Vec<_>>(),
+            [0, 2]
+        );
+        assert_eq!(storage.group_names(None).await.unwrap(), ["spring"]);
+        assert_eq!(
+            storage.group_links("spring", Some("alice")).await.unwrap()[0].0,
+            "a"
+        );
+        assert!(storage
+            .group_links("spring", Some("bob"))
+            .await
+            .unwrap()
+            .is_empty());
+
+        let batch = [
+            ("c".to_string(), record("https://c.com", None)),
+            ("b".to_string(), record("https://b.org", None)),
+            (
+                "d".to_string(),
+                UrlRecord {
+                    interstitial: true,
+                    preview: Some(LinkPreview {
+                        image: Some("https://d.com/d.png".to_string()),
+                        ..LinkPreview::default()
+                    }),
+                    title: Some("D".to_string()),
+                    tags: vec!["d".to_string()],
+                    group: Some("letters".to_string()),
+                    ..record("https://d.com", None)
+                },
+            ),
+        ];
+        assert_eq!(
+            storage.insert_many(&batch).await.unwrap(),
+            [true, false, true]
+        );
+        let records = storage
+            .get_many(&["d".to_string(), "missing".to_string(), "b".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(records[0].as_ref().unwrap().url, "https://d.com");
+        assert!(records[0].as_ref().unwrap().seq > b.seq);
+        assert!(records[0].as_ref().unwrap().interstitial);
+        assert_eq!(records[0].as_ref().unwrap().preview, batch[2].1.preview);
+        assert_eq!(records[0].as_ref().unwrap().title.as_deref(), Some("D"));
+        assert_eq!(records[0].as_ref().unwrap().tags, ["d"]);
+        assert_eq!(
+            records[0].as_ref().unwrap().group.as_deref(),
+            Some("letters")
+        );
+        assert_eq!(records[1], None);
+        assert_eq!(records[2].as_ref().unwrap().url, "https://b.com");
+        storage.remove("c").await.unwrap();
+        storage.remove("d").await.unwrap();
+
+        storage.remove("a").await.unwrap();
+        storage.remove("missing").await.unwrap();
+        assert_eq!(storage.get("a").await.unwrap(), None);
+        assert!(storage.group_names(None).await.unwrap().is_empty());
+        assert!(storage.counters("a").await.unwrap().is_empty());
+        assert_eq!(storage.list().await.unwrap().len(), 1);
+    }
+
+    /// Checks the pagination of every backend, on an empty storage.
+    pub async fn check_pages(storage: &dyn Storage) {
+        for (code, owner) in [("c", "alice"), ("a", "bob"), ("e", "alice"), ("b", "alice")] {
+            storage
+                .insert(code, &record("https://example.com", Some(owner)))
+                .await
+                .unwrap();
+        }
+
+        let mut query = PageQuery {
+            limit: 2,
+            ..PageQuery::default()
+        };
+        assert_eq!(codes(storage, &query).await, ["a", "b"]);
+        query.after = Some("b".to_string());
+        assert_eq!(codes(storage, &query).await, ["c", "e"]);
+        query.after = Some("e".to_string());
+        assert!(codes(storage, &query).await.is_empty());
+
+        query.order = SortOrder::CodeDesc;
+        query.after = None;
+        assert_eq!(codes(storage, &query).await, ["e", "c"]);
+
+        query.order = SortOrder::CreatedAsc;
+        assert_eq!(codes(storage, &query).await, ["c", "a"]);
+        let a = storage.get("a").await.unwrap().unwrap();
+        query.after = Some(SortOrder::CreatedAsc.cursor("a", &a));
+        assert_eq!(codes(storage, &query).await, ["e", "b"]);
+
+        query.order = SortOrder::CreatedDesc;
+        query.after = None;
+        query.owner = Some("alice".to_string());
+        assert_eq!(codes(storage, &query).await, ["b", "e"]);
+        let e = storage.get("e").await.unwrap().unwrap();
+        query.after = Some(SortOrder::CreatedDesc.cursor("e", &e));
+        assert_eq!(codes(storage, &query).await, ["c"]);
+
+        query.after = Some("not a number".to_string());
+        assert!(matches!(
+            storage.page(&query).await,
+            Err(StoreError::Invalid(_))
+        ));
+
+        let labeled = [
+            (
+                "f",
+                UrlRecord {
+                    title: Some("Spring Sale".to_string()),
+                    tags: vec!["sale".to_string()],
+                    group: Some("spring".to_string()),
+                    ..record("https://shop.example.com/f", Some("alice"))
+                },
+            ),
+            (
+                "g",
+                UrlRecord {
+                    tags: vec!["q3".to_string(), "sale".to_string()],
+                    group: Some("spring".to_string()),
+                    ..record("https://blog.example.com/Sale", Some("bob"))
+                },
+            ),
+        ];
+        for (code, record) in &labeled {
+            storage.insert(code, record).await.unwrap();
+        }
+
+        assert_eq!(storage.group_names(None).await.unwrap(), ["spring"]);
+        assert!(storage.group_names(Some("carol")).await.unwrap().is_empty());
+        let group_codes = |links: Vec<(String, UrlRecord)>| {
+            links.into_iter().map(|(code, _)| code).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            group_codes(storage.group_links("spring", None).await.unwrap()),
+            ["f", "g"]
+        );
+        assert_eq!(
+            group_codes(storage.group_links("spring", Some("bob")).await.unwrap()),
+            ["g"]
+        );
+        assert!(storage
+            .group_links("autumn", None)
+            .await
+            .unwrap()
+            .is_empty());
+        let links = storage
+            .group_links_many(&["autumn".to_string(), "spring".to_string()], Some("alice"))
+            .await
+            .unwrap();
+        assert!(links[0].is_empty());
+        assert_eq!(group_codes(links[1].clone()), ["f"]);
+
+        let mut query = PageQuery {
+            limit: 2,
+            filter: LinkFilter {
+                tag: Some("sale".to_string()),
+                ..LinkFilter::default()
+            },
+            ..PageQuery::default()
+        };
+        assert_eq!(codes(storage, &query).await, ["f", "g"]);
+        query.filter.tag = Some("q3".to_string());
+        assert_eq!(codes(storage, &query).await, ["g"]);
+        query.owner = Some("alice".to_string());
+        assert!(codes(storage, &query).await.is_empty());
+
+        query.owner = None;
+        query.limit = 1;
+        query.filter = LinkFilter {
+            group: Some("spring".to_string()),
+            text: Some("SALE".to_string()),
+            ..LinkFilter::default()
+        };
+        assert_eq!(codes(storage, &query).await, ["f"]);
+        query.after = Some("f".to_string());
+        assert_eq!(codes(storage, &query).await, ["g"]);
+        query.after = Some("g".to_string());
+        assert!(codes(storage, &query).await.is_empty());
+
+        query.after = None;
+        query.limit = 10;
+        query.filter = LinkFilter {
+            text: Some("blog".to_string()),
+            ..LinkFilter::default()
+        };
+        assert_eq!(codes(storage, &query).await, ["g"]);
+        query.filter.prefix = true;
+        assert!(codes(storage, &query).await.is_empty());
+        query.filter.text = Some("HTTPS://blog.".to_string());
+        assert_eq!(codes(storage, &query).await, ["g"]);
+        query.filter.text = Some("spring".to_string());
+        assert_eq!(codes(storage, &query).await, ["f"]);
+        query.filter.text = Some("100%_".to_string());
+        assert!(codes(storage, &query).await.is_empty());
+    }
+}
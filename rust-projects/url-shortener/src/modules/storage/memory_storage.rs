@@ -0,0 +1,179 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use async_trait::async_trait;
+
+use super::{PageQuery, SortOrder, Storage, StoreError, UrlRecord};
+
+/// Storage kept in the process memory, lost on restart. Mostly useful for tests.
+#[derive(Default)]
+pub struct MemoryStorage {
+    map: Mutex<BTreeMap<String, UrlRecord>>,
+    counters: Mutex<BTreeMap<String, BTreeMap<String, u64>>>,
+    last_seq: AtomicU64,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl Storage for MemoryStorage {
+    async fn insert(&self, code: &str, record: &UrlRecord) -> Result<bool, StoreError> {
+        let mut map = self.map.lock().unwrap();
+        if map.contains_key(code) {
+            return Ok(false);
+        }
+
+        map.insert(
+            code.to_string(),
+            UrlRecord {
+                seq: self.last_seq.fetch_add(1, Ordering::Relaxed) + 1,
+                ..record.clone()
+            },
+        );
+        Ok(true)
+    }
+
+    async fn replace(&self, code: &str, record: &UrlRecord) -> Result<bool, StoreError> {
+        let mut map = self.map.lock().unwrap();
+        let Some(existing) = map.get_mut(code) else {
+            return Ok(false);
+        };
+
+        *existing = UrlRecord {
+            clicks: existing.clicks,
+            owner: existing.owner.clone(),
+            seq: existing.seq,
+            ..record.clone()
+        };
+        Ok(true)
+    }
+
+    async fn get(&self, code: &str) -> Result<Option<UrlRecord>, StoreError> {
+        Ok(self.map.lock().unwrap().get(code).cloned())
+    }
+
+    async fn page(&self, query: &PageQuery) -> Result<Vec<(String, UrlRecord)>, StoreError> {
+        let after_seq = query.after_seq()?;
+        let map = self.map.lock().unwrap();
+
+        let mut links: Vec<(&String, &UrlRecord)> = map
+            .iter()
+            .filter(|(_, record)| query.owner.is_none() || record.owner == query.owner)
+            .filter(|(_, record)| query.filter.matches(record))
+            .filter(
+                |(code, record)| match (query.order, query.after.as_deref()) {
+                    (_, None) => true,
+                    (SortOrder::CodeAsc, Some(after)) => code.as_str() > after,
+                    (SortOrder::CodeDesc, Some(after)) => code.as_str() < after,
+                    (SortOrder::CreatedAsc, _) => Some(record.seq) > after_seq,
+                    (SortOrder::CreatedDesc, _) => Some(record.seq) < after_seq,
+                },
+            )
+            .collect();
+
+        match query.order {
+            SortOrder::CodeAsc => {}
+            SortOrder::CodeDesc => links.reverse(),
+            SortOrder::CreatedAsc => links.sort_by_key(|(_, record)| record.seq),
+            SortOrder::CreatedDesc => links.sort_by_key(|(_, record)| Reverse(record.seq)),
+        }
+
+        Ok(links
+            .into_iter()
+            .take(query.limit)
+            .map(|(code, record)| (code.to_string(), record.clone()))
+            .collect())
+    }
+
+    async fn remove(&self, code: &str) -> Result<(), StoreError> {
+        self.map.lock().unwrap().remove(code);
+        self.counters.lock().unwrap().remove(code);
+        Ok(())
+    }
+
+    async fn record_click(&self, code: &str) -> Result<Option<u64>, StoreError> {
+        Ok(self.map.lock().unwrap().get_mut(code).map(|record| {
+            record.clicks += 1;
+            record.clicks
+        }))
+    }
+
+    async fn increment_counters(&self, code: &str, counters: &[String]) -> Result<(), StoreError> {
+        let mut map = self.counters.lock().unwrap();
+        let link_counters = map.entry(code.to_string()).or_default();
+
+        for counter in counters {
+            *link_counters.entry(counter.to_string()).or_default() += 1;
+        }
+        Ok(())
+    }
+
+    async fn counters(&self, code: &str) -> Result<Vec<(String, u64)>, StoreError> {
+        Ok(self
+            .counters
+            .lock()
+            .unwrap()
+            .get(code)
+            .map(|counters| {
+                counters
+                    .iter()
+                    .map(|(counter, count)| (counter.to_string(), *count))
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    async fn group_names(&self, owner: Option<&str>) -> Result<Vec<String>, StoreError> {
+        let names: BTreeSet<String> = self
+            .map
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|record| owner.is_none() || record.owner.as_deref() == owner)
+            .filter_map(|record| record.group.clone())
+            .collect();
+        Ok(names.into_iter().collect())
+    }
+
+    async fn group_links(
+        &self,
+        group: &str,
+        owner: Option<&str>,
+    ) -> Result<Vec<(String, UrlRecord)>, StoreError> {
+        Ok(self
+            .map
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, record)| owner.is_none() || record.owner.as_deref() == owner)
+            .filter(|(_, record)| record.group.as_deref() == Some(group))
+            .map(|(code, record)| (code.to_string(), record.clone()))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::storage::tests::{check_pages, check_storage};
+
+    #[tokio::test]
+    async fn test_storage() {
+        check_storage(&MemoryStorage::new()).await;
+    }
+
+    #[tokio::test]
+    async fn test_pages() {
+        check_pages(&MemoryStorage::new()).await;
+    }
+}
@@ -0,0 +1,137 @@
+use std::{future::Future, sync::Arc, time::Instant};
+
+use async_trait::async_trait;
+
+use super::{PageQuery, Storage, StoreError, UrlRecord};
+use crate::modules::metrics::Metrics;
+
+/// Storage recording the latency and the failures of each call to another storage.
+pub struct MeteredStorage {
+    inner: Arc<dyn Storage>,
+    metrics: Metrics,
+}
+
+impl MeteredStorage {
+    pub fn new(inner: Arc<dyn Storage>, metrics: Metrics) -> Self {
+        MeteredStorage { inner, metrics }
+    }
+
+    async fn timed<T>(
+        &self,
+        operation: &'static str,
+        call: impl Future<Output = Result<T, StoreError>>,
+    ) -> Result<T, StoreError> {
+        let start = Instant::now();
+        let result = call.await;
+        self.metrics
+            .record_backend(operation, start.elapsed(), result.is_err());
+        result
+    }
+}
+
+#[async_trait]
+impl Storage for MeteredStorage {
+    async fn insert(&self, code: &str, record: &UrlRecord) -> Result<bool, StoreError> {
+        self.timed("insert", self.inner.insert(code, record)).await
+    }
+
+    async fn insert_many(&self, records: &[(String, UrlRecord)]) -> Result<Vec<bool>, StoreError> {
+        self.timed("insert_many", self.inner.insert_many(records))
+            .await
+    }
+
+    async fn replace(&self, code: &str, record: &UrlRecord) -> Result<bool, StoreError> {
+        self.timed("replace", self.inner.replace(code, record))
+            .await
+    }
+
+    async fn get(&self, code: &str) -> Result<Option<UrlRecord>, StoreError> {
+        self.timed("get", self.inner.get(code)).await
+    }
+
+    async fn get_many(&self, codes: &[String]) -> Result<Vec<Option<UrlRecord>>, StoreError> {
+        self.timed("get_many", self.inner.get_many(codes)).await
+    }
+
+    async fn page(&self, query: &PageQuery) -> Result<Vec<(String, UrlRecord)>, StoreError> {
+        self.timed("page", self.inner.page(query)).await
+    }
+
+    async fn list(&self) -> Result<Vec<(String, UrlRecord)>, StoreError> {
+        self.timed("list", self.inner.list()).await
+    }
+
+    async fn remove(&self, code: &str) -> Result<(), StoreError> {
+        self.timed("remove", self.inner.remove(code)).await
+    }
+
+    async fn record_click(&self, code: &str) -> Result<Option<u64>, StoreError> {
+        self.timed("record_click", self.inner.record_click(code))
+            .await
+    }
+
+    async fn increment_counters(&self, code: &str, counters: &[String]) -> Result<(), StoreError> {
+        self.timed(
+            "increment_counters",
+            self.inner.increment_counters(code, counters),
+        )
+        .await
+    }
+
+    async fn counters(&self, code: &str) -> Result<Vec<(String, u64)>, StoreError> {
+        self.timed("counters", self.inner.counters(code)).await
+    }
+
+    async fn counters_many(&self, codes: &[String]) -> Result<Vec<Vec<(String, u64)>>, StoreError> {
+        self.timed("counters_many", self.inner.counters_many(codes))
+            .await
+    }
+
+    async fn group_names(&self, owner: Option<&str>) -> Result<Vec<String>, StoreError> {
+        self.timed("group_names", self.inner.group_names(owner))
+            .await
+    }
+
+    async fn group_links(
+        &self,
+        group: &str,
+        owner: Option<&str>,
+    ) -> Result<Vec<(String, UrlRecord)>, StoreError> {
+        self.timed("group_links", self.inner.group_links(group, owner))
+            .await
+    }
+
+    async fn group_links_many(
+        &self,
+        groups: &[String],
+        owner: Option<&str>,
+    ) -> Result<Vec<Vec<(String, UrlRecord)>>, StoreError> {
+        self.timed(
+            "group_links_many",
+            self.inner.group_links_many(groups, owner),
+        )
+        .await
+    }
+
+    async fn ping(&self) -> Result<(), StoreError> {
+        self.timed("ping", self.inner.ping()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::storage::{memory_storage::MemoryStorage, tests::check_storage};
+
+    #[tokio::test]
+    async fn test_metered_storage() {
+        let metrics = Metrics::new();
+        let storage = MeteredStorage::new(Arc::new(MemoryStorage::new()), metrics.clone());
+        check_storage(&storage).await;
+
+        let text = metrics.render();
+        assert!(text.contains("storage_operation_duration_seconds_count{operation=\"get\"}"));
+        assert!(text.contains("storage_operation_duration_seconds_count{operation=\"insert\"}"));
+        assert!(!text.contains("storage_errors_total{"));
+    }
+}
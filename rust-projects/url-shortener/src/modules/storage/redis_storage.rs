@@ -0,0 +1,907 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use chrono::{Duration, TimeZone, Utc};
+use redis::{aio::ConnectionManager, AsyncCommands, AsyncIter, Script};
+
+use super::{PageQuery, SortOrder, Storage, StoreError, UrlRecord};
+use crate::modules::short_code::ALIAS_MAX_LENGTH;
+
+/// How long Redis keeps a link after it expired, so it keeps answering 410 Gone instead of 404.
+const GONE_RETENTION_DAYS: i64 = 7;
+
+/// Codes read at a time by a filtered page, most of which the filter may drop.
+const FILTER_BATCH_SIZE: usize = 200;
+
+/// Prefix of the keys when none is configured.
+pub const DEFAULT_PREFIX: &str = "shortener";
+
+/// `HSET`s the record fields given as `ARGV[3..]` and a new sequence number, from `KEYS[2]`,
+/// unless the key exists, then adds the code `ARGV[2]` to the code and creation indexes given as
+/// pairs in `KEYS[4..]`. The key expires at the timestamp `ARGV[1]` when it is not empty.
+///
+/// The counters `KEYS[3]` outlive the link key, those of a previous link with the same code are
+/// deleted.
+const INSERT_SCRIPT: &str = r"
+if redis.call('EXISTS', KEYS[1]) == 1 then
+    return 0
+end
+redis.call('DEL', KEYS[3])
+local seq = redis.call('INCR', KEYS[2])
+redis.call('HSET', KEYS[1], 'seq', seq, unpack(ARGV, 3))
+if ARGV[1] ~= '' then
+    redis.call('EXPIREAT', KEYS[1], ARGV[1])
+end
+for i = 4, #KEYS, 2 do
+    redis.call('ZADD', KEYS[i], 0, ARGV[2])
+    redis.call('ZADD', KEYS[i + 1], seq, ARGV[2])
+end
+return 1
+";
+
+/// `HSET`s the record fields given as `ARGV[2..]` if the key exists, dropping the optional fields
+/// missing from `ARGV` and the expiry when `ARGV[1]` is empty.
+const REPLACE_SCRIPT: &str = r"
+if redis.call('EXISTS', KEYS[1]) == 0 then
+    return 0
+end
+redis.call('HDEL', KEYS[1], 'expires_at', 'max_clicks', 'interstitial', 'preview', 'title',
+    'tags', 'group')
+redis.call('HSET', KEYS[1], unpack(ARGV, 2))
+if ARGV[1] ~= '' then
+    redis.call('EXPIREAT', KEYS[1], ARGV[1])
+else
+    redis.call('PERSIST', KEYS[1])
+end
+return 1
+";
+
+/// Drops the codes `ARGV[2..]` from the group indexes given in `KEYS` each followed by the index
+/// of the names of their groups, and the group `ARGV[1]` from the names once it has no code left.
+const UNGROUP_SCRIPT: &str = r"
+for i = 1, #KEYS, 2 do
+    if #ARGV > 1 then
+        redis.call('ZREM', KEYS[i], unpack(ARGV, 2))
+    end
+    if redis.call('EXISTS', KEYS[i]) == 0 then
+        redis.call('ZREM', KEYS[i + 1], ARGV[1])
+    end
+end
+return 1
+";
+
+/// Increments the click count of an existing key, returns `-1` if the key does not exist.
+const CLICK_SCRIPT: &str = r"
+if redis.call('EXISTS', KEYS[1]) == 0 then
+    return -1
+end
+return redis.call('HINCRBY', KEYS[1], 'clicks', 1)
+";
+
+/// Storage in Redis through a reconnecting connection manager, with every key under a prefix:
+///
+/// - `{prefix}:link:{code}`, a hash per link,
+/// - `{prefix}:counters:{code}`, the analytics counters of a link,
+/// - `{prefix}:seq`, the last sequence number given to a link,
+/// - `{prefix}:index:code` and `{prefix}:index:created`, sorted sets of every code by code and
+///   by sequence number, and `{prefix}:index:code:{owner}` and `{prefix}:index:created:{owner}`
+///   for the links of each user,
+/// - `{prefix}:index:groups` and `{prefix}:index:groups:{owner}`, sorted sets of the names of the
+///   groups of every link and of the links of each user,
+/// - `{prefix}:index:group/{group}` and `{prefix}:index:group:{owner}/{group}`, sorted sets of
+///   the codes of the links of a group, of anyone and of each user.
+///
+/// Links expire from Redis on their own, their codes are dropped from an index when a page of it
+/// reads them.
+pub struct RedisStorage {
+    connection: ConnectionManager,
+    prefix: String,
+}
+
+fn read_record(fields: HashMap<String, String>) -> Option<UrlRecord> {
+    let number = |name| {
+        fields
+            .get(name)
+            .and_then(|value: &String| value.parse().ok())
+    };
+
+    Some(UrlRecord {
+        url: fields.get("url")?.to_string(),
+        expires_at: number("expires_at")
+            .and_then(|timestamp| Utc.timestamp_opt(timestamp as i64, 0).single()),
+        max_clicks: number("max_clicks"),
+        clicks: number("clicks").unwrap_or(0),
+        owner: fields.get("owner").cloned(),
+        seq: number("seq").unwrap_or(0),
+        interstitial: fields.get("interstitial").is_some_and(|value| value == "1"),
+        preview: fields
+            .get("preview")
+            .and_then(|preview| serde_json::from_str(preview).ok()),
+        title: fields.get("title").cloned(),
+        tags: fields
+            .get("tags")
+            .and_then(|tags| serde_json::from_str(tags).ok())
+            .unwrap_or_default(),
+        group: fields.get("group").cloned(),
+    })
+}
+
+/// Returns the key expiry argument of the scripts, the expiry of the link plus the retention.
+/// Empty, so the key never expires, if the link expires too far in the future to add it.
+fn expiry_arg(record: &UrlRecord) -> String {
+    record
+        .expires_at
+        .and_then(|expires_at| expires_at.checked_add_signed(Duration::days(GONE_RETENTION_DAYS)))
+        .map_or_else(String::new, |expires_at| expires_at.timestamp().to_string())
+}
+
+/// Appends the fields of `record` a replacement may change to script arguments.
+fn field_args(args: &mut Vec<String>, record: &UrlRecord) {
+    args.extend(["url".to_string(), record.url.clone()]);
+    if let Some(expires_at) = record.expires_at {
+        args.extend(["expires_at".to_string(), expires_at.timestamp().to_string()]);
+    }
+    if let Some(max_clicks) = record.max_clicks {
+        args.extend(["max_clicks".to_string(), max_clicks.to_string()]);
+    }
+    if record.interstitial {
+        args.extend(["interstitial".to_string(), "1".to_string()]);
+    }
+    if let Some(preview) = &record.preview {
+        let preview = serde_json::to_string(preview).expect("previews serialize to JSON");
+        args.extend(["preview".to_string(), preview]);
+    }
+    if let Some(title) = &record.title {
+        args.extend(["title".to_string(), title.clone()]);
+    }
+    if !record.tags.is_empty() {
+        let tags = serde_json::to_string(&record.tags).expect("tags serialize to JSON");
+        args.extend(["tags".to_string(), tags]);
+    }
+    if let Some(group) = &record.group {
+        args.extend(["group".to_string(), group.clone()]);
+    }
+}
+
+impl RedisStorage {
+    pub async fn connect(url: &str) -> Result<Self, StoreError> {
+        let client = redis::Client::open(url)?;
+        let connection = ConnectionManager::new(client).await?;
+
+        Ok(RedisStorage {
+            connection,
+            prefix: DEFAULT_PREFIX.to_string(),
+        })
+    }
+
+    /// Replaces the prefix of the keys, `shortener` by default.
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    fn link_key(&self, code: &str) -> String {
+        format!("{}:link:{}", self.prefix, code)
+    }
+
+    fn counters_key(&self, code: &str) -> String {
+        format!("{}:counters:{}", self.prefix, code)
+    }
+
+    fn seq_key(&self) -> String {
+        format!("{}:seq", self.prefix)
+    }
+
+    /// Key of the sorted set of the codes of every link, or of the links of `owner`, in `order`.
+    fn index_key(&self, order: SortOrder, owner: Option<&str>) -> String {
+        let index = match order {
+            SortOrder::CodeAsc | SortOrder::CodeDesc => "code",
+            SortOrder::CreatedAsc | SortOrder::CreatedDesc => "created",
+        };
+
+        match owner {
+            Some(owner) => format!("{}:index:{}:{}", self.prefix, index, owner),
+            None => format!("{}:index:{}", self.prefix, index),
+        }
+    }
+
+    /// Key of the sorted set of the names of the groups of every link, or of the links of `owner`.
+    fn groups_key(&self, owner: Option<&str>) -> String {
+        match owner {
+            Some(owner) => format!("{}:index:groups:{}", self.prefix, owner),
+            None => format!("{}:index:groups", self.prefix),
+        }
+    }
+
+    /// Key of the sorted set of the codes of the links of `group`, of anyone or of `owner`.
+    /// Groups have no `/`, so an owner and a group cannot run into each other.
+    fn group_key(&self, group: &str, owner: Option<&str>) -> String {
+        match owner {
+            Some(owner) => format!("{}:index:group:{}/{}", self.prefix, owner, group),
+            None => format!("{}:index:group/{}", self.prefix, group),
+        }
+    }
+
+    /// Keys of the indexes holding the code of `record`, code index first.
+    fn index_keys(&self, record: &UrlRecord) -> Vec<String> {
+        index_owners(record)
+            .into_iter()
+            .flat_map(|owner| {
+                [
+                    self.index_key(SortOrder::CodeAsc, owner),
+                    self.index_key(SortOrder::CreatedAsc, owner),
+                ]
+            })
+            .collect()
+    }
+
+    /// Adds to `pipe` the commands adding `code` to the indexes of the group of `record`.
+    fn group(&self, pipe: &mut redis::Pipeline, code: &str, record: &UrlRecord) {
+        let Some(group) = &record.group else {
+            return;
+        };
+
+        for owner in index_owners(record) {
+            pipe.zadd(self.groups_key(owner), group, 0)
+                .ignore()
+                .zadd(self.group_key(group, owner), code, 0)
+                .ignore();
+        }
+    }
+
+    /// Drops `codes` from the indexes of `group` of each of `owners`, and the group from their
+    /// group names once it has no link left.
+    async fn ungroup(
+        &self,
+        group: &str,
+        owners: &[Option<&str>],
+        codes: &[String],
+    ) -> Result<(), StoreError> {
+        let keys: Vec<String> = owners
+            .iter()
+            .flat_map(|owner| [self.group_key(group, *owner), self.groups_key(*owner)])
+            .collect();
+
+        Script::new(UNGROUP_SCRIPT)
+            .key(keys)
+            .arg(group)
+            .arg(codes)
+            .invoke_async::<_, i64>(&mut self.connection.clone())
+            .await?;
+        Ok(())
+    }
+
+    /// Returns the keys and arguments of `INSERT_SCRIPT` storing `record` under `code`.
+    fn insert_args(&self, code: &str, record: &UrlRecord) -> (Vec<String>, Vec<String>) {
+        let mut keys = vec![self.link_key(code), self.seq_key(), self.counters_key(code)];
+        keys.extend(self.index_keys(record));
+
+        let mut args = vec![expiry_arg(record), code.to_string()];
+        field_args(&mut args, record);
+        args.extend(["clicks".to_string(), record.clicks.to_string()]);
+        if let Some(owner) = &record.owner {
+            args.extend(["owner".to_string(), owner.clone()]);
+        }
+
+        (keys, args)
+    }
+
+    /// Reads up to `count` codes of an index after `after`, with the sort key of each code.
+    async fn range(
+        &self,
+        index: &str,
+        order: SortOrder,
+        after: Option<&str>,
+        count: usize,
+    ) -> Result<Vec<(String, String)>, StoreError> {
+        let mut connection = self.connection.clone();
+        let count = count as isize;
+
+        Ok(match order {
+            SortOrder::CodeAsc | SortOrder::CodeDesc => {
+                let codes: Vec<String> = match (order, after) {
+                    (SortOrder::CodeAsc, None) => {
+                        connection.zrangebylex_limit(index, "-", "+", 0, count)
+                    }
+                    (SortOrder::CodeAsc, Some(after)) => {
+                        connection.zrangebylex_limit(index, format!("({}", after), "+", 0, count)
+                    }
+                    (_, None) => connection.zrevrangebylex_limit(index, "+", "-", 0, count),
+                    (_, Some(after)) => {
+                        connection.zrevrangebylex_limit(index, format!("({}", after), "-", 0, count)
+                    }
+                }
+                .await?;
+                codes.into_iter().map(|code| (code.clone(), code)).collect()
+            }
+            SortOrder::CreatedAsc | SortOrder::CreatedDesc => {
+                let codes: Vec<(String, u64)> = match (order, after) {
+                    (SortOrder::CreatedAsc, None) => {
+                        connection.zrangebyscore_limit_withscores(index, "-inf", "+inf", 0, count)
+                    }
+                    (SortOrder::CreatedAsc, Some(after)) => connection
+                        .zrangebyscore_limit_withscores(
+                            index,
+                            format!("({}", after),
+                            "+inf",
+                            0,
+                            count,
+                        ),
+                    (_, None) => connection
+                        .zrevrangebyscore_limit_withscores(index, "+inf", "-inf", 0, count),
+                    (_, Some(after)) => connection.zrevrangebyscore_limit_withscores(
+                        index,
+                        format!("({}", after),
+                        "-inf",
+                        0,
+                        count,
+                    ),
+                }
+                .await?;
+                codes
+                    .into_iter()
+                    .map(|(code, seq)| (code, seq.to_string()))
+                    .collect()
+            }
+        })
+    }
+
+    /// Moves the links written by previous versions of the service, stored under their bare code
+    /// as a string holding the URL or as a hash, with their counters under `counters:{code}`,
+    /// under the prefix. Run on demand, see `REDIS_MIGRATE_LEGACY_KEYS`, as it scans every key.
+    ///
+    /// Only keys shaped like a short code holding a link are touched, other keys sharing the
+    /// database are left alone. A legacy key is deleted once its link is under the prefix.
+    pub async fn migrate(&self) -> Result<(), StoreError> {
+        let mut connection = self.connection.clone();
+
+        let mut codes = Vec::new();
+        {
+            let mut iter: AsyncIter<String> = connection.scan().await?;
+            while let Some(key) = iter.next_item().await {
+                if is_legacy_code(&key) {
+                    codes.push(key);
+                }
+            }
+        }
+
+        for code in codes {
+            let key_type: String = redis::cmd("TYPE")
+                .arg(&code)
+                .query_async(&mut connection)
+                .await?;
+            let record = match key_type.as_str() {
+                "string" => {
+                    let url: Option<String> = connection.get(&code).await?;
+                    url.filter(|url| is_legacy_url(url))
+                        .map(|url| UrlRecord::new(&url))
+                }
+                "hash" => read_record(connection.hgetall(&code).await?)
+                    .filter(|record| is_legacy_url(&record.url)),
+                _ => None,
+            };
+            let Some(record) = record else {
+                continue;
+            };
+
+            let legacy_counters = format!("counters:{}", code);
+            if self.insert(&code, &record).await? {
+                let counters: Vec<(String, u64)> = connection.hgetall(&legacy_counters).await?;
+                let mut pipe = redis::pipe();
+                for (counter, count) in counters {
+                    pipe.hincr(self.counters_key(&code), counter, count)
+                        .ignore();
+                }
+                pipe.query_async::<_, ()>(&mut connection).await?;
+            } else if self
+                .get(&code)
+                .await?
+                .is_none_or(|existing| existing.url != record.url)
+            {
+                tracing::warn!(code, "legacy link not migrated, its code is taken");
+                continue;
+            }
+
+            connection
+                .del::<_, ()>(&[code.as_str(), legacy_counters.as_str()])
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the owners whose indexes hold the code of `record`: `None`, for the indexes of every
+/// link, then its owner.
+fn index_owners(record: &UrlRecord) -> Vec<Option<&str>> {
+    let mut owners = vec![None];
+    owners.extend(record.owner.as_deref().map(Some));
+    owners
+}
+
+/// Returns `true` if `key` could be a link of a previous version, stored under its bare code.
+/// Codes are made of letters, digits, `-` and `_`, unlike the prefixed keys.
+fn is_legacy_code(key: &str) -> bool {
+    (1..=ALIAS_MAX_LENGTH).contains(&key.len())
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Returns `true` if a legacy value holds a URL, as the links stored by previous versions did.
+fn is_legacy_url(url: &str) -> bool {
+    url::Url::parse(url).is_ok_and(|url| url.has_host())
+}
+
+#[async_trait]
+impl Storage for RedisStorage {
+    async fn insert(&self, code: &str, record: &UrlRecord) -> Result<bool, StoreError> {
+        let (keys, args) = self.insert_args(code, record);
+
+        let inserted: i64 = Script::new(INSERT_SCRIPT)
+            .key(keys)
+            .arg(args)
+            .invoke_async(&mut self.connection.clone())
+            .await?;
+        if inserted == 1 && record.group.is_some() {
+            let mut pipe = redis::pipe();
+            self.group(&mut pipe, code, record);
+            pipe.query_async::<_, ()>(&mut self.connection.clone())
+                .await?;
+        }
+        Ok(inserted == 1)
+    }
+
+    async fn insert_many(&self, records: &[(String, UrlRecord)]) -> Result<Vec<bool>, StoreError> {
+        let script = Script::new(INSERT_SCRIPT);
+
+        // The script is loaded first so the `EVALSHA`s of the same pipeline find it.
+        let mut pipe = redis::pipe();
+        pipe.cmd("SCRIPT").arg("LOAD").arg(INSERT_SCRIPT).ignore();
+        for (code, record) in records {
+            let (keys, args) = self.insert_args(code, record);
+            pipe.cmd("EVALSHA")
+                .arg(script.get_hash())
+                .arg(keys.len())
+                .arg(keys)
+                .arg(args);
+        }
+
+        let inserted: Vec<bool> = pipe
+            .query_async::<_, Vec<i64>>(&mut self.connection.clone())
+            .await?
+            .into_iter()
+            .map(|inserted| inserted == 1)
+            .collect();
+
+        let mut pipe = redis::pipe();
+        for ((code, record), inserted) in records.iter().zip(&inserted) {
+            if *inserted {
+                self.group(&mut pipe, code, record);
+            }
+        }
+        pipe.query_async::<_, ()>(&mut self.connection.clone())
+            .await?;
+        Ok(inserted)
+    }
+
+    async fn replace(&self, code: &str, record: &UrlRecord) -> Result<bool, StoreError> {
+        // The group indexes of the previous group are only known from the record.
+        let Some(previous) = self.get(code).await? else {
+            return Ok(false);
+        };
+        let mut args = vec![expiry_arg(record)];
+        field_args(&mut args, record);
+
+        let replaced: i64 = Script::new(REPLACE_SCRIPT)
+            .key(self.link_key(code))
+            .arg(args)
+            .invoke_async(&mut self.connection.clone())
+            .await?;
+        if replaced == 1 && previous.group != record.group {
+            if let Some(group) = &previous.group {
+                self.ungroup(group, &index_owners(&previous), &[code.to_string()])
+                    .await?;
+            }
+            let mut pipe = redis::pipe();
+            self.group(
+                &mut pipe,
+                code,
+                &UrlRecord {
+                    owner: previous.owner.clone(),
+                    ..record.clone()
+                },
+            );
+            pipe.query_async::<_, ()>(&mut self.connection.clone())
+                .await?;
+        }
+        Ok(replaced == 1)
+    }
+
+    async fn get(&self, code: &str) -> Result<Option<UrlRecord>, StoreError> {
+        let fields: HashMap<String, String> =
+            self.connection.clone().hgetall(self.link_key(code)).await?;
+        Ok(read_record(fields))
+    }
+
+    async fn get_many(&self, codes: &[String]) -> Result<Vec<Option<UrlRecord>>, StoreError> {
+        let mut pipe = redis::pipe();
+        for code in codes {
+            pipe.hgetall(self.link_key(code));
+        }
+
+        let records: Vec<HashMap<String, String>> =
+            pipe.query_async(&mut self.connection.clone()).await?;
+        Ok(records.into_iter().map(read_record).collect())
+    }
+
+    async fn page(&self, query: &PageQuery) -> Result<Vec<(String, UrlRecord)>, StoreError> {
+        // Rejects cursors that are not a sequence number before reading the index.
+        query.after_seq()?;
+
+        let owner = query.owner.as_deref();
+        let index = self.index_key(query.order, owner);
+        let mut connection = self.connection.clone();
+        let mut after = query.after.clone();
+        let mut ret = Vec::new();
+
+        // Expired links, and the links the filter drops, leave holes in the page, filled from the
+        // next codes of the index.
+        while ret.len() < query.limit {
+            let mut count = query.limit - ret.len();
+            if !query.filter.is_empty() {
+                count = count.max(FILTER_BATCH_SIZE);
+            }
+            let codes = self
+                .range(&index, query.order, after.as_deref(), count)
+                .await?;
+            let exhausted = codes.len() < count;
+            let Some((_, last)) = codes.last() else {
+                break;
+            };
+            after = Some(last.clone());
+
+            let mut pipe = redis::pipe();
+            for (code, _) in &codes {
+                pipe.hgetall(self.link_key(code));
+            }
+            let records: Vec<HashMap<String, String>> = pipe.query_async(&mut connection).await?;
+
+            // Codes of expired links, and of links an owner index still holds after their code
+            // expired and was claimed again by someone else.
+            let mut stale = Vec::new();
+            for ((code, _), fields) in codes.into_iter().zip(records) {
+                match read_record(fields) {
+                    Some(record) if owner.is_some() && record.owner.as_deref() != owner => {
+                        stale.push(code)
+                    }
+                    Some(record) if query.filter.matches(&record) => ret.push((code, record)),
+                    Some(_) => {}
+                    None => stale.push(code),
+                }
+            }
+
+            if !stale.is_empty() {
+                redis::pipe()
+                    .zrem(self.index_key(SortOrder::CodeAsc, owner), &stale)
+                    .ignore()
+                    .zrem(self.index_key(SortOrder::CreatedAsc, owner), &stale)
+                    .ignore()
+                    .query_async::<_, ()>(&mut connection)
+                    .await?;
+            }
+            if exhausted {
+                break;
+            }
+        }
+
+        // The next page starts after the last link returned, not the last one read.
+        ret.truncate(query.limit);
+        Ok(ret)
+    }
+
+    async fn remove(&self, code: &str) -> Result<(), StoreError> {
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .del(&[self.link_key(code), self.counters_key(code)])
+            .ignore();
+
+        // The owner and group indexes are only known from the record, an expired link leaves its
+        // code in them until a page or its group reads it.
+        let record = self.get(code).await?;
+        let keys = match &record {
+            Some(record) => self.index_keys(record),
+            None => self.index_keys(&UrlRecord::new("")),
+        };
+        for key in keys {
+            pipe.zrem(key, code).ignore();
+        }
+
+        pipe.query_async::<_, ()>(&mut self.connection.clone())
+            .await?;
+        if let Some(record) = record {
+            if let Some(group) = &record.group {
+                self.ungroup(group, &index_owners(&record), &[code.to_string()])
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn record_click(&self, code: &str) -> Result<Option<u64>, StoreError> {
+        let clicks: i64 = Script::new(CLICK_SCRIPT)
+            .key(self.link_key(code))
+            .invoke_async(&mut self.connection.clone())
+            .await?;
+
+        Ok((clicks >= 0).then_some(clicks as u64))
+    }
+
+    async fn increment_counters(&self, code: &str, counters: &[String]) -> Result<(), StoreError> {
+        let key = self.counters_key(code);
+        let mut pipe = redis::pipe();
+        for counter in counters {
+            pipe.hincr(&key, counter, 1).ignore();
+        }
+
+        pipe.query_async::<_, ()>(&mut self.connection.clone())
+            .await?;
+        Ok(())
+    }
+
+    async fn counters(&self, code: &str) -> Result<Vec<(String, u64)>, StoreError> {
+        let counters: Vec<(String, u64)> = self
+            .connection
+            .clone()
+            .hgetall(self.counters_key(code))
+            .await?;
+        Ok(counters)
+    }
+
+    async fn counters_many(&self, codes: &[String]) -> Result<Vec<Vec<(String, u64)>>, StoreError> {
+        let mut pipe = redis::pipe();
+        for code in codes {
+            pipe.hgetall(self.counters_key(code));
+        }
+        Ok(pipe.query_async(&mut self.connection.clone()).await?)
+    }
+
+    async fn group_names(&self, owner: Option<&str>) -> Result<Vec<String>, StoreError> {
+        Ok(self
+            .connection
+            .clone()
+            .zrange(self.groups_key(owner), 0, -1)
+            .await?)
+    }
+
+    async fn group_links(
+        &self,
+        group: &str,
+        owner: Option<&str>,
+    ) -> Result<Vec<(String, UrlRecord)>, StoreError> {
+        let mut links = self.group_links_many(&[group.to_string()], owner).await?;
+        Ok(links.pop().unwrap_or_default())
+    }
+
+    async fn group_links_many(
+        &self,
+        groups: &[String],
+        owner: Option<&str>,
+    ) -> Result<Vec<Vec<(String, UrlRecord)>>, StoreError> {
+        let mut pipe = redis::pipe();
+        for group in groups {
+            pipe.zrange(self.group_key(group, owner), 0, -1);
+        }
+        let codes: Vec<Vec<String>> = pipe.query_async(&mut self.connection.clone()).await?;
+        let mut records = self.get_many(&codes.concat()).await?.into_iter();
+
+        let mut ret = Vec::with_capacity(groups.len());
+        for (group, codes) in groups.iter().zip(codes) {
+            // Expired links, the links moved to another group and, in an owner index, the links
+            // whose code was claimed again by someone else are dropped from the index.
+            let mut links = Vec::new();
+            let mut stale = Vec::new();
+            for (code, record) in codes.into_iter().zip(records.by_ref()) {
+                match record {
+                    Some(record)
+                        if record.group.as_ref() == Some(group)
+                            && (owner.is_none() || record.owner.as_deref() == owner) =>
+                    {
+                        links.push((code, record))
+                    }
+                    _ => stale.push(code),
+                }
+            }
+
+            if links.is_empty() || !stale.is_empty() {
+                self.ungroup(group, &[owner], &stale).await?;
+            }
+            ret.push(links);
+        }
+        Ok(ret)
+    }
+
+    async fn ping(&self) -> Result<(), StoreError> {
+        redis::cmd("PING")
+            .query_async::<_, ()>(&mut self.connection.clone())
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::{
+        preview::LinkPreview,
+        storage::tests::{check_pages, check_storage},
+    };
+
+    /// Connects to the Redis of `REDIS_URL`, under a prefix of its own so a run starts from an
+    /// empty storage.
+    async fn live_storage() -> RedisStorage {
+        let url = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".to_string());
+        let prefix = format!(
+            "shortener-test:{}:{}",
+            std::process::id(),
+            Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        );
+
+        RedisStorage::connect(&url)
+            .await
+            .unwrap()
+            .with_prefix(&prefix)
+    }
+
+    /// Deletes every key under the prefix of `storage`.
+    async fn clear(storage: &RedisStorage) {
+        let mut connection = storage.connection.clone();
+        let keys: Vec<String> = {
+            let mut iter: AsyncIter<String> = connection
+                .scan_match(format!("{}:*", storage.prefix))
+                .await
+                .unwrap();
+            let mut keys = Vec::new();
+            while let Some(key) = iter.next_item().await {
+                keys.push(key);
+            }
+            keys
+        };
+        if !keys.is_empty() {
+            connection.del::<_, ()>(keys).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server, at REDIS_URL or on localhost"]
+    async fn test_storage() {
+        let storage = live_storage().await;
+        check_storage(&storage).await;
+        clear(&storage).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server, at REDIS_URL or on localhost"]
+    async fn test_pages() {
+        let storage = live_storage().await;
+        check_pages(&storage).await;
+        clear(&storage).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server, at REDIS_URL or on localhost"]
+    async fn test_reclaimed_code() {
+        let storage = live_storage().await;
+        let alice = UrlRecord {
+            owner: Some("alice".to_string()),
+            group: Some("spring".to_string()),
+            ..UrlRecord::new("https://a.com")
+        };
+        assert!(storage.insert("x", &alice).await.unwrap());
+        storage
+            .increment_counters("x", &["day:2024-06-01".to_string()])
+            .await
+            .unwrap();
+
+        // The link key expires on its own, the indexes and counters stay behind.
+        let mut connection = storage.connection.clone();
+        connection
+            .del::<_, ()>(storage.link_key("x"))
+            .await
+            .unwrap();
+        let bob = UrlRecord {
+            owner: Some("bob".to_string()),
+            group: Some("spring".to_string()),
+            ..UrlRecord::new("https://b.com")
+        };
+        assert!(storage.insert("x", &bob).await.unwrap());
+        assert!(storage.counters("x").await.unwrap().is_empty());
+
+        let query = PageQuery {
+            owner: Some("alice".to_string()),
+            limit: 10,
+            ..PageQuery::default()
+        };
+        assert!(storage.page(&query).await.unwrap().is_empty());
+        assert!(storage
+            .group_links("spring", Some("alice"))
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            storage.group_links("spring", Some("bob")).await.unwrap()[0]
+                .1
+                .url,
+            "https://b.com"
+        );
+        clear(&storage).await;
+    }
+
+    #[test]
+    fn test_read_record() {
+        let fields = HashMap::from([
+            ("url".to_string(), "https://a.com".to_string()),
+            ("expires_at".to_string(), "2000000000".to_string()),
+            ("max_clicks".to_string(), "10".to_string()),
+            ("clicks".to_string(), "3".to_string()),
+            ("owner".to_string(), "alice".to_string()),
+            ("seq".to_string(), "42".to_string()),
+            ("interstitial".to_string(), "1".to_string()),
+            ("preview".to_string(), r#"{"title":"A"}"#.to_string()),
+            ("title".to_string(), "The A".to_string()),
+            ("tags".to_string(), r#"["launch","q3"]"#.to_string()),
+            ("group".to_string(), "spring".to_string()),
+        ]);
+
+        assert_eq!(
+            read_record(fields),
+            Some(UrlRecord {
+                url: "https://a.com".to_string(),
+                expires_at: Utc.timestamp_opt(2_000_000_000, 0).single(),
+                max_clicks: Some(10),
+                clicks: 3,
+                owner: Some("alice".to_string()),
+                seq: 42,
+                interstitial: true,
+                preview: Some(LinkPreview {
+                    title: Some("A".to_string()),
+                    ..LinkPreview::default()
+                }),
+                title: Some("The A".to_string()),
+                tags: vec!["launch".to_string(), "q3".to_string()],
+                group: Some("spring".to_string()),
+            })
+        );
+        assert_eq!(read_record(HashMap::new()), None);
+    }
+
+    #[test]
+    fn test_expiry_arg() {
+        let record = UrlRecord {
+            expires_at: Utc.timestamp_opt(2_000_000_000, 0).single(),
+            ..UrlRecord::new("https://a.com")
+        };
+        assert_eq!(expiry_arg(&record), "2000604800");
+
+        let record = UrlRecord {
+            expires_at: Some(chrono::DateTime::<Utc>::MAX_UTC),
+            ..UrlRecord::new("https://a.com")
+        };
+        assert_eq!(expiry_arg(&record), "");
+        assert_eq!(expiry_arg(&UrlRecord::new("https://a.com")), "");
+    }
+
+    #[test]
+    fn test_is_legacy() {
+        assert!(is_legacy_code("ZyIxL8K"));
+        assert!(is_legacy_code("my-link_2024"));
+        assert!(!is_legacy_code("shortener:seq"));
+        assert!(!is_legacy_code("counters:abc"));
+        assert!(!is_legacy_code("session.42"));
+        assert!(!is_legacy_code(&"a".repeat(33)));
+
+        assert!(is_legacy_url("https://example.com/page"));
+        assert!(!is_legacy_url("42"));
+        assert!(!is_legacy_url("{\"cart\":[]}"));
+    }
+}
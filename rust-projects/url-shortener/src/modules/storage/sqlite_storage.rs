@@ -0,0 +1,505 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use chrono::{TimeZone, Utc};
+use rusqlite::{params, types::Value, Connection, OptionalExtension, Row};
+
+use super::{PageQuery, SortOrder, Storage, StoreError, UrlRecord};
+
+/// Storage in a SQLite database file. Queries run on the blocking thread pool.
+pub struct SqliteStorage {
+    connection: Arc<Mutex<Connection>>,
+}
+
+/// Columns added after the first version of the `urls` table, created on older databases.
+const MIGRATIONS: &[(&str, &str)] = &[
+    (
+        "expires_at",
+        "ALTER TABLE urls ADD COLUMN expires_at INTEGER",
+    ),
+    (
+        "max_clicks",
+        "ALTER TABLE urls ADD COLUMN max_clicks INTEGER",
+    ),
+    (
+        "clicks",
+        "ALTER TABLE urls ADD COLUMN clicks INTEGER NOT NULL DEFAULT 0",
+    ),
+    ("owner", "ALTER TABLE urls ADD COLUMN owner TEXT"),
+    // Existing rows keep their insertion order.
+    (
+        "seq",
+        "ALTER TABLE urls ADD COLUMN seq INTEGER;
+         UPDATE urls SET seq = rowid;",
+    ),
+    (
+        "interstitial",
+        "ALTER TABLE urls ADD COLUMN interstitial INTEGER NOT NULL DEFAULT 0",
+    ),
+    // The preview as a JSON object.
+    ("preview", "ALTER TABLE urls ADD COLUMN preview TEXT"),
+    ("title", "ALTER TABLE urls ADD COLUMN title TEXT"),
+    // The tags as a JSON array.
+    (
+        "tags",
+        "ALTER TABLE urls ADD COLUMN tags TEXT NOT NULL DEFAULT '[]'",
+    ),
+    // `GROUP` is a keyword.
+    ("group_name", "ALTER TABLE urls ADD COLUMN group_name TEXT"),
+];
+
+fn migrate(connection: &Connection) -> rusqlite::Result<()> {
+    connection.execute(
+        "CREATE TABLE IF NOT EXISTS urls (hash TEXT PRIMARY KEY, url TEXT NOT NULL)",
+        [],
+    )?;
+    connection.execute(
+        "CREATE TABLE IF NOT EXISTS counters (
+             hash TEXT NOT NULL,
+             counter TEXT NOT NULL,
+             count INTEGER NOT NULL,
+             PRIMARY KEY (hash, counter)
+         )",
+        [],
+    )?;
+
+    let columns: Vec<String> = connection
+        .prepare("SELECT name FROM pragma_table_info('urls')")?
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    for (column, statement) in MIGRATIONS {
+        if !columns.iter().any(|name| name == column) {
+            connection.execute_batch(statement)?;
+        }
+    }
+    connection.execute(
+        "CREATE INDEX IF NOT EXISTS urls_owner_seq ON urls (owner, seq)",
+        [],
+    )?;
+    connection.execute("CREATE INDEX IF NOT EXISTS urls_seq ON urls (seq)", [])?;
+    // The last sequence number given, so numbers are never reused when the newest link is
+    // removed. Starts from the links of databases created before it.
+    connection.execute(
+        "CREATE TABLE IF NOT EXISTS sequences (name TEXT PRIMARY KEY, last INTEGER NOT NULL)",
+        [],
+    )?;
+    connection.execute(
+        "INSERT OR IGNORE INTO sequences (name, last)
+         VALUES ('urls', (SELECT COALESCE(MAX(seq), 0) FROM urls))",
+        [],
+    )?;
+    connection.execute(
+        "CREATE INDEX IF NOT EXISTS urls_group_name ON urls (group_name)",
+        [],
+    )?;
+    connection.execute(
+        "CREATE INDEX IF NOT EXISTS urls_owner_group_name ON urls (owner, group_name)",
+        [],
+    )?;
+
+    Ok(())
+}
+
+fn read_record(row: &Row) -> rusqlite::Result<UrlRecord> {
+    Ok(UrlRecord {
+        url: row.get("url")?,
+        expires_at: row
+            .get::<_, Option<i64>>("expires_at")?
+            .and_then(|timestamp| Utc.timestamp_opt(timestamp, 0).single()),
+        max_clicks: row
+            .get::<_, Option<i64>>("max_clicks")?
+            .map(|max| max as u64),
+        clicks: row.get::<_, i64>("clicks")? as u64,
+        owner: row.get("owner")?,
+        seq: row.get::<_, i64>("seq")? as u64,
+        interstitial: row.get("interstitial")?,
+        preview: row
+            .get::<_, Option<String>>("preview")?
+            .and_then(|preview| serde_json::from_str(&preview).ok()),
+        title: row.get("title")?,
+        tags: serde_json::from_str(&row.get::<_, String>("tags")?).unwrap_or_default(),
+        group: row.get("group_name")?,
+    })
+}
+
+fn preview_column(record: &UrlRecord) -> Option<String> {
+    record
+        .preview
+        .as_ref()
+        .map(|preview| serde_json::to_string(preview).expect("previews serialize to JSON"))
+}
+
+fn tags_column(record: &UrlRecord) -> String {
+    serde_json::to_string(&record.tags).expect("tags serialize to JSON")
+}
+
+fn insert(connection: &Connection, code: &str, record: &UrlRecord) -> rusqlite::Result<bool> {
+    // Checked first so a taken code does not use up a sequence number.
+    if connection
+        .prepare_cached("SELECT 1 FROM urls WHERE hash = ?1")?
+        .exists([code])?
+    {
+        return Ok(false);
+    }
+    let seq: i64 = connection
+        .prepare_cached("UPDATE sequences SET last = last + 1 WHERE name = 'urls' RETURNING last")?
+        .query_row([], |row| row.get(0))?;
+
+    let inserted = connection
+        .prepare_cached(
+            "INSERT OR IGNORE INTO urls
+                 (hash, url, expires_at, max_clicks, clicks, owner, interstitial, preview, title,
+                  tags, group_name, seq)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        )?
+        .execute(params![
+            code,
+            record.url,
+            record.expires_at.map(|expires_at| expires_at.timestamp()),
+            record.max_clicks.map(|max| max as i64),
+            record.clicks as i64,
+            record.owner,
+            record.interstitial,
+            preview_column(record),
+            record.title,
+            tags_column(record),
+            record.group,
+            seq
+        ])?;
+    Ok(inserted == 1)
+}
+
+impl SqliteStorage {
+    /// Opens the database at `path`, creating or upgrading the schema if needed. `:memory:`
+    /// opens a private in-memory database.
+    pub fn open(path: &str) -> Result<Self, StoreError> {
+        let connection = Connection::open(path)?;
+        migrate(&connection)?;
+
+        Ok(SqliteStorage {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    async fn run<T, F>(&self, f: F) -> Result<T, StoreError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> rusqlite::Result<T> + Send + 'static,
+    {
+        let connection = Arc::clone(&self.connection);
+
+        tokio::task::spawn_blocking(move || f(&connection.lock().unwrap()))
+            .await
+            .map_err(|e| StoreError::Backend(e.to_string()))?
+            .map_err(StoreError::from)
+    }
+}
+
+#[async_trait]
+impl Storage for SqliteStorage {
+    async fn insert(&self, code: &str, record: &UrlRecord) -> Result<bool, StoreError> {
+        let (code, record) = (code.to_string(), record.clone());
+        self.run(move |connection| insert(connection, &code, &record))
+            .await
+    }
+
+    async fn insert_many(&self, records: &[(String, UrlRecord)]) -> Result<Vec<bool>, StoreError> {
+        let records = records.to_vec();
+        self.run(move |connection| {
+            let transaction = connection.unchecked_transaction()?;
+            let inserted = records
+                .iter()
+                .map(|(code, record)| insert(&transaction, code, record))
+                .collect::<rusqlite::Result<_>>()?;
+            transaction.commit()?;
+            Ok(inserted)
+        })
+        .await
+    }
+
+    async fn replace(&self, code: &str, record: &UrlRecord) -> Result<bool, StoreError> {
+        let (code, record) = (code.to_string(), record.clone());
+        let replaced = self
+            .run(move |connection| {
+                connection.execute(
+                    "UPDATE urls SET url = ?2, expires_at = ?3, max_clicks = ?4, interstitial = ?5,
+                         preview = ?6, title = ?7, tags = ?8, group_name = ?9
+                     WHERE hash = ?1",
+                    params![
+                        code,
+                        record.url,
+                        record.expires_at.map(|expires_at| expires_at.timestamp()),
+                        record.max_clicks.map(|max| max as i64),
+                        record.interstitial,
+                        preview_column(&record),
+                        record.title,
+                        tags_column(&record),
+                        record.group
+                    ],
+                )
+            })
+            .await?;
+        Ok(replaced == 1)
+    }
+
+    async fn get(&self, code: &str) -> Result<Option<UrlRecord>, StoreError> {
+        let code = code.to_string();
+        self.run(move |connection| {
+            connection
+                .query_row("SELECT * FROM urls WHERE hash = ?1", [code], read_record)
+                .optional()
+        })
+        .await
+    }
+
+    async fn get_many(&self, codes: &[String]) -> Result<Vec<Option<UrlRecord>>, StoreError> {
+        let codes = codes.to_vec();
+        self.run(move |connection| {
+            let mut statement = connection.prepare_cached("SELECT * FROM urls WHERE hash = ?1")?;
+            codes
+                .iter()
+                .map(|code| statement.query_row([code], read_record).optional())
+                .collect()
+        })
+        .await
+    }
+
+    async fn page(&self, query: &PageQuery) -> Result<Vec<(String, UrlRecord)>, StoreError> {
+        let after = match query.order {
+            SortOrder::CodeAsc | SortOrder::CodeDesc => query.after.clone().map(Value::Text),
+            SortOrder::CreatedAsc | SortOrder::CreatedDesc => {
+                query.after_seq()?.map(|seq| Value::Integer(seq as i64))
+            }
+        };
+        let (column, comparison, direction) = match query.order {
+            SortOrder::CodeAsc => ("hash", ">", "ASC"),
+            SortOrder::CodeDesc => ("hash", "<", "DESC"),
+            SortOrder::CreatedAsc => ("seq", ">", "ASC"),
+            SortOrder::CreatedDesc => ("seq", "<", "DESC"),
+        };
+
+        // The text is matched against the ASCII lowercase URL and title, as `lower` folds.
+        let text_match = if query.filter.prefix {
+            "substr(lower({}), 1, length(?6)) = ?6"
+        } else {
+            "instr(lower({}), ?6) > 0"
+        };
+        let sql = format!(
+            "SELECT * FROM urls
+             WHERE (?1 IS NULL OR owner = ?1) AND (?2 IS NULL OR {column} {comparison} ?2)
+                 AND (?4 IS NULL OR EXISTS (SELECT 1 FROM json_each(urls.tags) WHERE value = ?4))
+                 AND (?5 IS NULL OR group_name = ?5)
+                 AND (?6 IS NULL OR {} OR {})
+             ORDER BY {column} {direction} LIMIT ?3",
+            text_match.replace("{}", "url"),
+            text_match.replace("{}", "title"),
+        );
+        let (owner, limit) = (query.owner.clone(), query.limit as i64);
+        let filter = query.filter.clone();
+        let text = filter.text.map(|text| text.to_ascii_lowercase());
+
+        self.run(move |connection| {
+            let mut statement = connection.prepare_cached(&sql)?;
+            let rows = statement.query_map(
+                params![owner, after, limit, filter.tag, filter.group, text],
+                |row| Ok((row.get("hash")?, read_record(row)?)),
+            )?;
+            rows.collect()
+        })
+        .await
+    }
+
+    async fn remove(&self, code: &str) -> Result<(), StoreError> {
+        let code = code.to_string();
+        self.run(move |connection| {
+            let transaction = connection.unchecked_transaction()?;
+            transaction.execute("DELETE FROM urls WHERE hash = ?1", [&code])?;
+            transaction.execute("DELETE FROM counters WHERE hash = ?1", [&code])?;
+            transaction.commit()
+        })
+        .await
+    }
+
+    async fn record_click(&self, code: &str) -> Result<Option<u64>, StoreError> {
+        let code = code.to_string();
+        self.run(move |connection| {
+            connection
+                .query_row(
+                    "UPDATE urls SET clicks = clicks + 1 WHERE hash = ?1 RETURNING clicks",
+                    [code],
+                    |row| row.get::<_, i64>(0),
+                )
+                .optional()
+        })
+        .await
+        .map(|clicks| clicks.map(|clicks| clicks as u64))
+    }
+
+    async fn increment_counters(&self, code: &str, counters: &[String]) -> Result<(), StoreError> {
+        let (code, counters) = (code.to_string(), counters.to_vec());
+        self.run(move |connection| {
+            let transaction = connection.unchecked_transaction()?;
+            {
+                let mut statement = transaction.prepare_cached(
+                    "INSERT INTO counters (hash, counter, count) VALUES (?1, ?2, 1)
+                     ON CONFLICT (hash, counter) DO UPDATE SET count = count + 1",
+                )?;
+                for counter in counters {
+                    statement.execute(params![code, counter])?;
+                }
+            }
+            transaction.commit()
+        })
+        .await
+    }
+
+    async fn counters(&self, code: &str) -> Result<Vec<(String, u64)>, StoreError> {
+        let code = code.to_string();
+        self.run(move |connection| {
+            let mut statement = connection
+                .prepare("SELECT counter, count FROM counters WHERE hash = ?1 ORDER BY counter")?;
+            let rows = statement.query_map([code], |row| {
+                Ok((row.get(0)?, row.get::<_, i64>(1)? as u64))
+            })?;
+            rows.collect()
+        })
+        .await
+    }
+
+    async fn counters_many(&self, codes: &[String]) -> Result<Vec<Vec<(String, u64)>>, StoreError> {
+        let codes = codes.to_vec();
+        self.run(move |connection| {
+            let mut statement = connection.prepare_cached(
+                "SELECT counter, count FROM counters WHERE hash = ?1 ORDER BY counter",
+            )?;
+            codes
+                .iter()
+                .map(|code| {
+                    statement
+                        .query_map([code], |row| {
+                            Ok((row.get(0)?, row.get::<_, i64>(1)? as u64))
+                        })?
+                        .collect()
+                })
+                .collect()
+        })
+        .await
+    }
+
+    async fn group_names(&self, owner: Option<&str>) -> Result<Vec<String>, StoreError> {
+        let owner = owner.map(str::to_string);
+        self.run(move |connection| {
+            let mut statement = connection.prepare_cached(
+                "SELECT DISTINCT group_name FROM urls
+                 WHERE group_name IS NOT NULL AND (?1 IS NULL OR owner = ?1)
+                 ORDER BY group_name",
+            )?;
+            let rows = statement.query_map([owner], |row| row.get(0))?;
+            rows.collect()
+        })
+        .await
+    }
+
+    async fn group_links(
+        &self,
+        group: &str,
+        owner: Option<&str>,
+    ) -> Result<Vec<(String, UrlRecord)>, StoreError> {
+        let (group, owner) = (group.to_string(), owner.map(str::to_string));
+        self.run(move |connection| {
+            let mut statement = connection.prepare_cached(
+                "SELECT * FROM urls WHERE group_name = ?1 AND (?2 IS NULL OR owner = ?2)
+                 ORDER BY hash",
+            )?;
+            let rows = statement.query_map(params![group, owner], |row| {
+                Ok((row.get("hash")?, read_record(row)?))
+            })?;
+            rows.collect()
+        })
+        .await
+    }
+
+    async fn ping(&self) -> Result<(), StoreError> {
+        self.run(|connection| connection.query_row("SELECT 1", [], |_| Ok(())))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::storage::tests::{check_pages, check_storage};
+
+    #[tokio::test]
+    async fn test_storage() {
+        check_storage(&SqliteStorage::open(":memory:").unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn test_pages() {
+        check_pages(&SqliteStorage::open(":memory:").unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn test_seq_not_reused() {
+        let storage = SqliteStorage::open(":memory:").unwrap();
+        storage
+            .insert("a", &UrlRecord::new("https://a.com"))
+            .await
+            .unwrap();
+        storage
+            .insert("b", &UrlRecord::new("https://b.com"))
+            .await
+            .unwrap();
+        storage.remove("b").await.unwrap();
+        assert!(!storage
+            .insert("a", &UrlRecord::new("https://a.org"))
+            .await
+            .unwrap());
+
+        storage
+            .insert("c", &UrlRecord::new("https://c.com"))
+            .await
+            .unwrap();
+        assert_eq!(storage.get("c").await.unwrap().unwrap().seq, 3);
+    }
+
+    #[test]
+    fn test_migrate_first_version_schema() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute(
+                "CREATE TABLE urls (hash TEXT PRIMARY KEY, url TEXT NOT NULL)",
+                [],
+            )
+            .unwrap();
+        connection
+            .execute(
+                "INSERT INTO urls VALUES ('b', 'https://b.com'), ('a', 'https://a.com')",
+                [],
+            )
+            .unwrap();
+
+        migrate(&connection).unwrap();
+
+        let record = connection
+            .query_row("SELECT * FROM urls WHERE hash = 'a'", [], read_record)
+            .unwrap();
+        assert_eq!(
+            record,
+            UrlRecord {
+                seq: 2,
+                ..UrlRecord::new("https://a.com")
+            }
+        );
+        let last: i64 = connection
+            .query_row(
+                "SELECT last FROM sequences WHERE name = 'urls'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(last, 2);
+    }
+}
@@ -1,60 +1,1712 @@
-use redis::{Commands, Connection, Iter, RedisError};
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Utc};
+use futures_util::{stream, Stream};
 use serde::{Deserialize, Serialize};
-use xxhash_rust::xxh3::xxh3_64;
+use utoipa::ToSchema;
+
+use super::{
+    analytics::{Click, GroupStats, LinkStats},
+    auth::Principal,
+    cache::LinkCache,
+    labels,
+    metrics::Metrics,
+    preview::{LinkPreview, PreviewFetcher},
+    short_code::{self, DEFAULT_CODE_LENGTH},
+    storage::{
+        memory_storage::MemoryStorage, metered_storage::MeteredStorage, LinkFilter, PageQuery,
+        SortOrder, Storage, StoreError, UrlRecord,
+    },
+    validation::UrlPolicy,
+};
+
+const DEFAULT_BASE_URL: &str = "http://localhost:3000";
+
+/// Number of codes tried for a URL before giving up, each collision moving to the next one.
+const MAX_CODE_ATTEMPTS: u64 = 8;
 
-const SERVER_URL: &str = "http://localhost:3000";
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+pub const MAX_PAGE_SIZE: usize = 1000;
 
+/// Rows added at a time by [`Store::import`].
+const IMPORT_BATCH_SIZE: usize = 500;
+
+/// Furthest a link can expire from now, further dates overflow the storage backends.
+const MAX_EXPIRY_DAYS: i64 = 100 * 365;
+
+/// Shortened URLs, on top of a pluggable storage backend.
+#[derive(Clone)]
 pub struct Store {
-    map: Connection,
+    map: Arc<dyn Storage>,
+    code_length: usize,
+    analytics: bool,
+    policy: Arc<UrlPolicy>,
+    /// Public URL the short links start with, without a trailing `/`.
+    base_url: Arc<str>,
+    metrics: Metrics,
+    cache: Option<LinkCache>,
+    previews: Option<Arc<dyn PreviewFetcher>>,
+}
+
+/// Limits on how long and how often a short link can be followed, how, and its labels.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LinkOptions {
+    pub expires_at: Option<DateTime<Utc>>,
+    pub max_clicks: Option<u64>,
+    /// Shows a preview page instead of redirecting.
+    pub interstitial: bool,
+    pub title: Option<String>,
+    pub tags: Vec<String>,
+    /// Group, or campaign, of the link.
+    pub group: Option<String>,
+}
+
+impl LinkOptions {
+    /// Rejects expiries in the past or more than [`MAX_EXPIRY_DAYS`] away, and a maximum of zero
+    /// clicks.
+    fn validate(&self) -> Result<(), StoreError> {
+        let now = Utc::now();
+        if self.expires_at.is_some_and(|expires_at| expires_at <= now) {
+            return Err(StoreError::Invalid(
+                "Expiry must be in the future".to_string(),
+            ));
+        }
+        if self
+            .expires_at
+            .is_some_and(|expires_at| expires_at > now + Duration::days(MAX_EXPIRY_DAYS))
+        {
+            return Err(StoreError::Invalid(format!(
+                "Expiry must be within {} days",
+                MAX_EXPIRY_DAYS
+            )));
+        }
+        if self.max_clicks == Some(0) {
+            return Err(StoreError::Invalid(
+                "Maximum number of clicks must be positive".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A link to add with [`Store::import`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportRow {
+    pub url: String,
+    pub alias: Option<String>,
+    pub options: LinkOptions,
 }
 
-#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+/// Changes to a link, the fields left to `None` are kept.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LinkUpdate {
+    pub url: Option<String>,
+    /// `Some(None)` removes the expiry.
+    pub expires_at: Option<Option<DateTime<Utc>>>,
+    /// `Some(None)` removes the limit.
+    pub max_clicks: Option<Option<u64>>,
+    pub interstitial: Option<bool>,
+    /// `Some(None)` removes the title.
+    pub title: Option<Option<String>>,
+    /// Replaces every tag.
+    pub tags: Option<Vec<String>>,
+    /// `Some(None)` takes the link out of its group.
+    pub group: Option<Option<String>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, ToSchema)]
 pub struct UrlMap {
     pub hash: String,
     pub original: String,
     pub short: String,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub max_clicks: Option<u64>,
+    pub clicks: u64,
+    pub owner: Option<String>,
+    pub title: Option<String>,
+    pub tags: Vec<String>,
+    pub group: Option<String>,
+    /// Shows a preview page instead of redirecting.
+    pub interstitial: bool,
+    /// Title, description and image of the URL, if they could be fetched.
+    pub preview: Option<LinkPreview>,
+}
+
+/// A page of links.
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, ToSchema)]
+pub struct UrlPage {
+    pub links: Vec<UrlMap>,
+    /// Cursor of the next page, `None` on the last page.
+    pub next_cursor: Option<String>,
+    pub limit: usize,
+}
+
+/// A group of links, with their clicks added up.
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, ToSchema)]
+pub struct GroupSummary {
+    pub name: String,
+    /// Number of links in the group.
+    pub links: usize,
+    pub clicks: u64,
+}
+
+/// Returns `true` if two records are the same link of the same owner, clicks and preview aside.
+fn same_link(existing: &UrlRecord, record: &UrlRecord) -> bool {
+    existing.url == record.url
+        && existing.owner == record.owner
+        && existing.expires_at == record.expires_at
+        && existing.max_clicks == record.max_clicks
+        && existing.interstitial == record.interstitial
+        && existing.title == record.title
+        && existing.tags == record.tags
+        && existing.group == record.group
+}
+
+fn alias_taken(alias: &str) -> StoreError {
+    StoreError::Conflict(format!("Alias '{}' is already taken", alias))
+}
+
+fn no_free_code() -> StoreError {
+    StoreError::Conflict("Could not allocate a free short code".to_string())
+}
+
+/// Input the generated codes of a link are derived from.
+///
+/// Every field [`same_link`] compares goes in, so adding the same link again finds its code, while
+/// other users, or other limits and labels, for the same URL get codes of their own instead of
+/// using up the attempts on codes they can never claim.
+fn code_input(record: &UrlRecord) -> String {
+    format!(
+        "{} owner:{:?} {:?} {:?} interstitial:{} title:{:?} tags:{:?} group:{:?}",
+        record.url,
+        record.owner,
+        record.expires_at.map(|expires_at| expires_at.timestamp()),
+        record.max_clicks,
+        record.interstitial,
+        record.title,
+        record.tags,
+        record.group
+    )
+}
+
+/// Checks a title, see [`labels::normalize_title`].
+fn title(title: Option<&str>) -> Result<Option<String>, StoreError> {
+    Ok(title
+        .map(labels::normalize_title)
+        .transpose()
+        .map_err(StoreError::Invalid)?
+        .flatten())
+}
+
+/// Checks a group, see [`labels::validate_group`].
+fn group(group: Option<&str>) -> Result<(), StoreError> {
+    match group {
+        Some(group) => labels::validate_group(group).map_err(StoreError::Invalid),
+        None => Ok(()),
+    }
+}
+
+fn url_map(base_url: &str, url_hash: String, record: UrlRecord) -> UrlMap {
+    UrlMap {
+        short: format!("{}/{}", base_url, url_hash),
+        hash: url_hash,
+        original: record.url,
+        expires_at: record.expires_at,
+        max_clicks: record.max_clicks,
+        clicks: record.clicks,
+        owner: record.owner,
+        title: record.title,
+        tags: record.tags,
+        group: record.group,
+        interstitial: record.interstitial,
+        preview: record.preview,
+    }
+}
+
+/// Returns `true` if the link expired or reached its maximum number of clicks.
+fn is_gone(record: &UrlRecord) -> bool {
+    record.is_expired(Utc::now()) || record.max_clicks.is_some_and(|max| record.clicks >= max)
 }
 
 impl Store {
-    pub fn new() -> Self {
-        let client = redis::Client::open("redis://127.0.0.1/").unwrap();
-        let con = client.get_connection().unwrap();
+    /// Creates a store on top of `map`, timing each of its calls, see [`Store::metrics`].
+    pub fn new(map: Arc<dyn Storage>) -> Self {
+        let metrics = Metrics::new();
+
+        Store {
+            map: Arc::new(MeteredStorage::new(map, metrics.clone())),
+            code_length: DEFAULT_CODE_LENGTH,
+            analytics: false,
+            policy: Arc::new(UrlPolicy::default()),
+            base_url: Arc::from(DEFAULT_BASE_URL),
+            metrics,
+            cache: None,
+            previews: None,
+        }
+    }
+
+    /// Sets the public URL the short links start with, `http://localhost:3000` by default.
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = Arc::from(base_url.trim_end_matches('/'));
+        self
+    }
+
+    /// Sets the number of characters of the generated short codes.
+    pub fn with_code_length(mut self, code_length: usize) -> Self {
+        self.code_length = code_length;
+        self
+    }
 
-        Store { map: con }
+    /// Records the referrer, user agent and country of each click, see [`Store::stats`].
+    pub fn with_analytics(mut self, analytics: bool) -> Self {
+        self.analytics = analytics;
+        self
     }
 
-    pub fn add(&mut self, url: String) -> Result<String, RedisError> {
-        let url_hash = format!("{:x}", xxh3_64(url.as_bytes()));
-        self.map
-            .set::<String, String, String>(url_hash.to_string(), url)?;
-        Ok(url_hash)
+    /// Sets the rules the URLs must follow to be shortened.
+    pub fn with_url_policy(mut self, policy: UrlPolicy) -> Self {
+        self.policy = Arc::new(policy);
+        self
+    }
+
+    /// Keeps the links followed in `cache`, in front of the storage backend. Links are looked up
+    /// in the backend again when added, changed or removed, on every instance sharing the
+    /// cache invalidations.
+    pub fn with_cache(mut self, cache: LinkCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Fetches the preview of the URLs of the links added one at a time, or given a new URL.
+    /// Bulk imports are not previewed.
+    pub fn with_preview_fetcher(mut self, previews: Arc<dyn PreviewFetcher>) -> Self {
+        self.previews = Some(previews);
+        self
+    }
+
+    pub fn analytics(&self) -> bool {
+        self.analytics
+    }
+
+    /// Metrics of the service, shared with the routes.
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// Checks that the storage backend answers.
+    pub async fn ping(&self) -> Result<(), StoreError> {
+        self.map.ping().await
+    }
+
+    /// Store kept in memory, used by the tests.
+    pub fn memory() -> Self {
+        Store::new(Arc::new(MemoryStorage::new()))
+    }
+
+    /// Shortens a URL, under a generated code or under a custom alias.
+    ///
+    /// The URL is normalized first, see [`UrlPolicy::normalize`]. Adding a URL that is already
+    /// stored under its code, or under the requested alias, with the same options returns the
+    /// existing code. Existing mappings are never overwritten.
+    ///
+    /// # Returns
+    /// * `Ok(String)` with the short code.
+    /// * `Err(StoreError::InvalidUrl)` if the URL is rejected by the policy.
+    /// * `Err(StoreError::Invalid)` if the alias is not allowed, the expiry is in the past, the
+    ///   maximum number of clicks is zero or a label is not valid.
+    /// * `Err(StoreError::Conflict)` if the alias maps to another URL or belongs to another user,
+    ///   or if no free code was found.
+    pub async fn add(
+        &self,
+        url: String,
+        alias: Option<String>,
+        options: LinkOptions,
+        owner: &Principal,
+    ) -> Result<String, StoreError> {
+        let mut record = self.new_record(&url, alias.as_deref(), &options, owner)?;
+        record.preview = self.fetch_preview(&record.url).await;
+
+        match alias {
+            Some(alias) => {
+                if self.claim(&alias, &record).await? {
+                    Ok(alias)
+                } else {
+                    Err(alias_taken(&alias))
+                }
+            }
+            None => {
+                let input = code_input(&record);
+
+                for attempt in 0..MAX_CODE_ATTEMPTS {
+                    let code = short_code::generate(&input, attempt, self.code_length);
+
+                    if self.claim(&code, &record).await? {
+                        return Ok(code);
+                    }
+                }
+
+                Err(no_free_code())
+            }
+        }
     }
 
-    pub fn get(&mut self, url_hash: String) -> Result<UrlMap, RedisError> {
-        let url = self.map.get::<String, String>(url_hash.clone())?;
-        Ok(UrlMap {
-            hash: url_hash.to_string(),
-            original: url.to_string(),
-            short: format!("{}/{}", SERVER_URL, url_hash),
+    /// Checks a link to add against the policy and builds its record.
+    fn new_record(
+        &self,
+        url: &str,
+        alias: Option<&str>,
+        options: &LinkOptions,
+        owner: &Principal,
+    ) -> Result<UrlRecord, StoreError> {
+        let url = self.policy.normalize(url).map_err(StoreError::InvalidUrl)?;
+        options.validate()?;
+        if let Some(alias) = alias {
+            short_code::validate_alias(alias).map_err(StoreError::Invalid)?;
+        }
+        group(options.group.as_deref())?;
+
+        Ok(UrlRecord {
+            expires_at: options.expires_at,
+            max_clicks: options.max_clicks,
+            interstitial: options.interstitial,
+            title: title(options.title.as_deref())?,
+            tags: labels::normalize_tags(&options.tags).map_err(StoreError::Invalid)?,
+            group: options.group.clone(),
+            owner: Some(owner.user.to_string()),
+            ..UrlRecord::new(&url)
         })
     }
 
-    pub fn get_all(&mut self) -> Result<Vec<UrlMap>, RedisError> {
-        let iter: Iter<String> = self.map.scan()?;
-        let mut keys = Vec::new();
-        iter.for_each(|key| keys.push(key));
+    /// Fetches the preview of a URL. A page that cannot be read only goes without a preview.
+    async fn fetch_preview(&self, url: &str) -> Option<LinkPreview> {
+        let previews = self.previews.as_ref()?;
+
+        match previews.fetch(url).await {
+            Ok(preview) if !preview.is_empty() => Some(preview),
+            Ok(_) => None,
+            Err(e) => {
+                tracing::warn!(url, error = %e, "could not fetch the link preview");
+                None
+            }
+        }
+    }
 
-        let mut ret = Vec::new();
-        for url_hash in keys {
-            let value = self.get(url_hash.to_string())?;
-            ret.push(value)
+    /// Shortens many URLs, as [`Store::add`] would one at a time, with a few storage round trips
+    /// per batch of rows.
+    ///
+    /// # Returns
+    /// * `Ok(Vec)` with, for each row, its short code or why it was rejected.
+    /// * `Err(StoreError::Backend)` if the storage failed, rows of the previous batches may have
+    ///   been added.
+    pub async fn import(
+        &self,
+        rows: &[ImportRow],
+        owner: &Principal,
+    ) -> Result<Vec<Result<String, StoreError>>, StoreError> {
+        let mut ret = Vec::with_capacity(rows.len());
+        for batch in rows.chunks(IMPORT_BATCH_SIZE) {
+            ret.extend(self.import_batch(batch, owner).await?);
         }
         Ok(ret)
     }
 
-    pub fn delete(&mut self, url: &str) -> Result<String, RedisError> {
-        let url_hash = format!("{:x}", xxh3_64(url.as_bytes()));
-        self.map.del(url_hash)?;
+    async fn import_batch(
+        &self,
+        rows: &[ImportRow],
+        owner: &Principal,
+    ) -> Result<Vec<Result<String, StoreError>>, StoreError> {
+        let mut results: Vec<Option<Result<String, StoreError>>> = Vec::new();
+        // Rows still looking for a code: their index, record, alias and generated code input.
+        let mut pending = Vec::new();
+
+        for (i, row) in rows.iter().enumerate() {
+            match self.new_record(&row.url, row.alias.as_deref(), &row.options, owner) {
+                Ok(record) => {
+                    let input = code_input(&record);
+                    pending.push((i, record, row.alias.clone(), input));
+                    results.push(None);
+                }
+                Err(e) => results.push(Some(Err(e))),
+            }
+        }
+
+        for attempt in 0..MAX_CODE_ATTEMPTS {
+            if pending.is_empty() {
+                break;
+            }
+
+            let batch: Vec<(String, UrlRecord)> = pending
+                .iter()
+                .map(|(_, record, alias, input)| {
+                    let code = alias
+                        .clone()
+                        .unwrap_or_else(|| short_code::generate(input, attempt, self.code_length));
+                    (code, record.clone())
+                })
+                .collect();
+            let inserted = self.map.insert_many(&batch).await?;
+            let added: Vec<String> = batch
+                .iter()
+                .zip(&inserted)
+                .filter(|(_, inserted)| **inserted)
+                .map(|((code, _), _)| code.clone())
+                .collect();
+            self.invalidate(&added).await;
+
+            let taken: Vec<String> = batch
+                .iter()
+                .zip(&inserted)
+                .filter(|(_, inserted)| !**inserted)
+                .map(|((code, _), _)| code.clone())
+                .collect();
+            let mut existing = self.map.get_many(&taken).await?.into_iter();
+
+            let mut retry = Vec::new();
+            for ((row, (code, _)), inserted) in pending.into_iter().zip(batch).zip(inserted) {
+                let claimed = inserted
+                    || existing
+                        .next()
+                        .flatten()
+                        .is_some_and(|existing| same_link(&existing, &row.1));
+
+                if claimed {
+                    results[row.0] = Some(Ok(code));
+                } else if row.2.is_some() {
+                    results[row.0] = Some(Err(alias_taken(&code)));
+                } else {
+                    retry.push(row);
+                }
+            }
+            pending = retry;
+        }
+
+        for (i, ..) in pending {
+            results[i] = Some(Err(no_free_code()));
+        }
+        Ok(results.into_iter().flatten().collect())
+    }
+
+    /// Streams the links of `principal`, or every link for admins, oldest first, a page at a
+    /// time.
+    pub fn export(
+        &self,
+        principal: Principal,
+    ) -> impl Stream<Item = Result<Vec<UrlMap>, StoreError>> {
+        let store = self.clone();
+
+        // The state is the cursor of the next page, `None` once the last page was read.
+        stream::try_unfold(Some(None), move |cursor: Option<Option<String>>| {
+            let (store, principal) = (store.clone(), principal.clone());
+            async move {
+                let Some(cursor) = cursor else {
+                    return Ok(None);
+                };
+
+                let page = store
+                    .get_all(&principal, SortOrder::CreatedAsc, cursor, MAX_PAGE_SIZE)
+                    .await?;
+                if page.links.is_empty() {
+                    return Ok(None);
+                }
+                Ok(Some((page.links, page.next_cursor.map(Some))))
+            }
+        })
+    }
+
+    /// Stores `record` under `code` if the code is free or already maps to the same link of the
+    /// same owner. Users shortening the same URL get different codes.
+    async fn claim(&self, code: &str, record: &UrlRecord) -> Result<bool, StoreError> {
+        if self.map.insert(code, record).await? {
+            self.invalidate(&[code.to_string()]).await;
+            return Ok(true);
+        }
+
+        Ok(self
+            .map
+            .get(code)
+            .await?
+            .is_some_and(|existing| same_link(&existing, record)))
+    }
+
+    /// Returns the record stored under `code`, from the cache if possible.
+    async fn lookup(&self, code: &str) -> Result<Option<UrlRecord>, StoreError> {
+        let Some(cache) = &self.cache else {
+            return self.map.get(code).await;
+        };
+
+        let cached = cache.get(code);
+        self.metrics.record_cache_lookup(cached.is_some());
+        if let Some(record) = cached {
+            return Ok(record);
+        }
+
+        let record = self.map.get(code).await?;
+        cache.insert(code, record.clone());
+        Ok(record)
+    }
+
+    /// Drops the cached entries of links that were added, changed or removed.
+    async fn invalidate(&self, codes: &[String]) {
+        if let Some(cache) = &self.cache {
+            cache.invalidate(codes).await;
+        }
+    }
+
+    pub async fn get(&self, url_hash: String) -> Result<UrlMap, StoreError> {
+        let record = self.lookup(&url_hash).await?.ok_or(StoreError::NotFound)?;
+        Ok(url_map(&self.base_url, url_hash, record))
+    }
+
+    /// Follows a short link, counting the click and, with analytics on, recording its details.
+    ///
+    /// # Returns
+    /// * `Ok(UrlMap)` with the link, its click count including this visit.
+    /// * `Err(StoreError::NotFound)` if there is no link under `url_hash`.
+    /// * `Err(StoreError::Gone)` if the link expired or reached its maximum number of clicks.
+    pub async fn visit(&self, url_hash: String, click: &Click) -> Result<UrlMap, StoreError> {
+        let mut record = self.lookup(&url_hash).await?.ok_or(StoreError::NotFound)?;
+        if is_gone(&record) {
+            return Err(StoreError::Gone);
+        }
+
+        // Concurrent visits may both pass the check above, the count decides which one is last.
+        record.clicks = self
+            .map
+            .record_click(&url_hash)
+            .await?
+            .ok_or(StoreError::NotFound)?;
+        if record.max_clicks.is_some_and(|max| record.clicks > max) {
+            return Err(StoreError::Gone);
+        }
+
+        if self.analytics {
+            self.map
+                .increment_counters(&url_hash, &click.counters())
+                .await?;
+        }
+
+        Ok(url_map(&self.base_url, url_hash, record))
+    }
+
+    /// Returns a link to preview, without counting a click.
+    ///
+    /// # Returns
+    /// * `Ok(UrlMap)` with the link.
+    /// * `Err(StoreError::NotFound)` if there is no link under `url_hash`.
+    /// * `Err(StoreError::Gone)` if the link expired or reached its maximum number of clicks.
+    pub async fn preview(&self, url_hash: String) -> Result<UrlMap, StoreError> {
+        let record = self.lookup(&url_hash).await?.ok_or(StoreError::NotFound)?;
+        if is_gone(&record) {
+            return Err(StoreError::Gone);
+        }
+        Ok(url_map(&self.base_url, url_hash, record))
+    }
+
+    /// Returns the record of a link `principal` is allowed to manage.
+    async fn owned(&self, url_hash: &str, principal: &Principal) -> Result<UrlRecord, StoreError> {
+        let record = self.map.get(url_hash).await?.ok_or(StoreError::NotFound)?;
+        if !principal.can_manage(&record) {
+            return Err(StoreError::Forbidden);
+        }
+        Ok(record)
+    }
+
+    /// Returns the click breakdown of a link, empty unless analytics are on.
+    pub async fn stats(
+        &self,
+        url_hash: String,
+        principal: &Principal,
+    ) -> Result<LinkStats, StoreError> {
+        let record = self.owned(&url_hash, principal).await?;
+        let counters = self.map.counters(&url_hash).await?;
+
+        Ok(LinkStats::from_counters(url_hash, record.clicks, counters))
+    }
+
+    /// Lists the groups of the links of `principal`, or of every link for admins, by name.
+    pub async fn groups(&self, principal: &Principal) -> Result<Vec<GroupSummary>, StoreError> {
+        let owner = (!principal.is_admin()).then_some(principal.user.as_str());
+        let names = self.map.group_names(owner).await?;
+        let links = self.map.group_links_many(&names, owner).await?;
+
+        Ok(names
+            .into_iter()
+            .zip(links)
+            .filter(|(_, links)| !links.is_empty())
+            .map(|(name, links)| GroupSummary {
+                name,
+                links: links.len(),
+                clicks: links.iter().map(|(_, record)| record.clicks).sum(),
+            })
+            .collect())
+    }
+
+    /// Adds up the clicks of the links of a group owned by `principal`, or by anyone for admins.
+    ///
+    /// # Returns
+    /// * `Ok(GroupStats)` with the clicks, broken down as in [`Store::stats`].
+    /// * `Err(StoreError::NotFound)` if `principal` has no link in the group.
+    pub async fn group_stats(
+        &self,
+        name: String,
+        principal: &Principal,
+    ) -> Result<GroupStats, StoreError> {
+        let owner = (!principal.is_admin()).then_some(principal.user.as_str());
+        let links = self.map.group_links(&name, owner).await?;
+        if links.is_empty() {
+            return Err(StoreError::NotFound);
+        }
+
+        let codes: Vec<String> = links.iter().map(|(url_hash, _)| url_hash.clone()).collect();
+        let counters = self.map.counters_many(&codes).await?;
+
+        let mut stats = GroupStats::new(name);
+        for ((url_hash, record), counters) in links.into_iter().zip(counters) {
+            stats.add(LinkStats::from_counters(url_hash, record.clicks, counters));
+        }
+        Ok(stats)
+    }
+
+    /// Lists the links of `principal`, or every link for admins, one page at a time.
+    pub async fn get_all(
+        &self,
+        principal: &Principal,
+        order: SortOrder,
+        cursor: Option<String>,
+        limit: usize,
+    ) -> Result<UrlPage, StoreError> {
+        self.search(principal, LinkFilter::default(), order, cursor, limit)
+            .await
+    }
+
+    /// Lists the links of `principal`, or every link for admins, kept by `filter`, one page at a
+    /// time.
+    ///
+    /// # Arguments
+    /// * `cursor` - The `next_cursor` of the previous page, `None` for the first page.
+    /// * `limit` - Number of links in the page, clamped to [`MAX_PAGE_SIZE`].
+    ///
+    /// # Returns
+    /// * `Ok(UrlPage)` with a `next_cursor` unless it is the last page.
+    /// * `Err(StoreError::Invalid)` if the cursor does not belong to `order`.
+    pub async fn search(
+        &self,
+        principal: &Principal,
+        filter: LinkFilter,
+        order: SortOrder,
+        cursor: Option<String>,
+        limit: usize,
+    ) -> Result<UrlPage, StoreError> {
+        let query = PageQuery {
+            owner: (!principal.is_admin()).then(|| principal.user.clone()),
+            filter,
+            order,
+            after: cursor,
+            limit: limit.clamp(1, MAX_PAGE_SIZE),
+        };
+        let links = self.map.page(&query).await?;
+
+        let next_cursor = match links.last() {
+            Some((url_hash, record)) if links.len() == query.limit => {
+                Some(order.cursor(url_hash, record))
+            }
+            _ => None,
+        };
+
+        Ok(UrlPage {
+            links: links
+                .into_iter()
+                .map(|(url_hash, record)| url_map(&self.base_url, url_hash, record))
+                .collect(),
+            next_cursor,
+            limit: query.limit,
+        })
+    }
+
+    /// Returns a link `principal` is allowed to manage.
+    ///
+    /// # Returns
+    /// * `Ok(UrlMap)` with the link, expired or not.
+    /// * `Err(StoreError::NotFound)` if there is no link under `url_hash`.
+    /// * `Err(StoreError::Forbidden)` if the link belongs to another user.
+    pub async fn link(
+        &self,
+        url_hash: String,
+        principal: &Principal,
+    ) -> Result<UrlMap, StoreError> {
+        let record = self.owned(&url_hash, principal).await?;
+        Ok(url_map(&self.base_url, url_hash, record))
+    }
+
+    /// Changes the URL, the limits, the mode or the labels of a link, keeping its code, owner and
+    /// clicks. A new URL is previewed again.
+    ///
+    /// # Returns
+    /// * `Ok(UrlMap)` with the updated link.
+    /// * `Err(StoreError::InvalidUrl)` if the URL is rejected by the policy.
+    /// * `Err(StoreError::Invalid)` if the new expiry is in the past, the new maximum number of
+    ///   clicks is zero or a new label is not valid.
+    /// * `Err(StoreError::NotFound)` if there is no link under `url_hash`.
+    /// * `Err(StoreError::Forbidden)` if the link belongs to another user.
+    pub async fn update(
+        &self,
+        url_hash: String,
+        update: LinkUpdate,
+        principal: &Principal,
+    ) -> Result<UrlMap, StoreError> {
+        let url = update
+            .url
+            .map(|url| self.policy.normalize(&url).map_err(StoreError::InvalidUrl))
+            .transpose()?;
+        let existing = self.owned(&url_hash, principal).await?;
+
+        // Only the new limits are checked, an expired link can be given a new expiry.
+        LinkOptions {
+            expires_at: update.expires_at.flatten(),
+            max_clicks: update.max_clicks.flatten(),
+            ..LinkOptions::default()
+        }
+        .validate()?;
+        let new_title = update
+            .title
+            .map(|new_title| title(new_title.as_deref()))
+            .transpose()?;
+        let tags = update
+            .tags
+            .map(|tags| labels::normalize_tags(&tags).map_err(StoreError::Invalid))
+            .transpose()?;
+        group(update.group.clone().flatten().as_deref())?;
+
+        let preview = match &url {
+            Some(url) if *url != existing.url => self.fetch_preview(url).await,
+            _ => existing.preview.clone(),
+        };
+        let record = UrlRecord {
+            url: url.unwrap_or(existing.url.clone()),
+            expires_at: update.expires_at.unwrap_or(existing.expires_at),
+            max_clicks: update.max_clicks.unwrap_or(existing.max_clicks),
+            interstitial: update.interstitial.unwrap_or(existing.interstitial),
+            preview,
+            title: new_title.unwrap_or(existing.title.clone()),
+            tags: tags.unwrap_or(existing.tags.clone()),
+            group: update.group.unwrap_or(existing.group.clone()),
+            ..existing
+        };
+
+        let replaced = self.map.replace(&url_hash, &record).await?;
+        self.invalidate(std::slice::from_ref(&url_hash)).await;
+        if !replaced {
+            return Err(StoreError::NotFound);
+        }
+        Ok(url_map(&self.base_url, url_hash, record))
+    }
+
+    /// Removes a link and its counters.
+    ///
+    /// # Returns
+    /// * `Err(StoreError::NotFound)` if there is no link under `url_hash`.
+    /// * `Err(StoreError::Forbidden)` if the link belongs to another user.
+    pub async fn remove(&self, url_hash: &str, principal: &Principal) -> Result<(), StoreError> {
+        self.owned(url_hash, principal).await?;
+        self.map.remove(url_hash).await?;
+        self.invalidate(&[url_hash.to_string()]).await;
+        Ok(())
+    }
+
+    /// Removes every short code and alias of a URL owned by `principal`, or by anyone for admins.
+    ///
+    /// # Returns
+    /// * `Ok(String)` with the URL.
+    /// * `Err(StoreError::Forbidden)` if the URL is only shortened by other users.
+    pub async fn delete(&self, url: &str, principal: &Principal) -> Result<String, StoreError> {
+        // Links are stored normalized, URLs added before validation existed may not be.
+        let normalized = self.policy.normalize(url).ok();
+        let (mut removed, mut skipped) = (0, 0);
+
+        for (code, record) in self.map.list().await? {
+            if record.url != url && Some(&record.url) != normalized.as_ref() {
+                continue;
+            }
+
+            if principal.can_manage(&record) {
+                self.map.remove(&code).await?;
+                self.invalidate(&[code]).await;
+                removed += 1;
+            } else {
+                skipped += 1;
+            }
+        }
+
+        if removed == 0 && skipped > 0 {
+            return Err(StoreError::Forbidden);
+        }
         Ok(url.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use futures_util::TryStreamExt;
+
+    use super::*;
+    use crate::modules::cache::CacheConfig;
+
+    fn alice() -> Principal {
+        Principal::user("alice")
+    }
+
+    /// Code of a plain link of alice's, four characters long.
+    fn alice_code(url: &str, attempt: u64) -> String {
+        let record = UrlRecord {
+            owner: Some("alice".to_string()),
+            ..UrlRecord::new(url)
+        };
+        short_code::generate(&code_input(&record), attempt, 4)
+    }
+
+    fn url_update(url: &str) -> LinkUpdate {
+        LinkUpdate {
+            url: Some(url.to_string()),
+            ..LinkUpdate::default()
+        }
+    }
+
+    fn click() -> Click {
+        Click {
+            at: Utc.with_ymd_and_hms(2024, 6, 1, 13, 45, 0).unwrap(),
+            referrer: None,
+            user_agent: Some("curl/8.5.0".to_string()),
+            country: Some("FR".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_is_idempotent() {
+        let store = Store::memory();
+
+        let code = store
+            .add(
+                "https://example.com".to_string(),
+                None,
+                LinkOptions::default(),
+                &alice(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(code.len(), DEFAULT_CODE_LENGTH);
+        assert_eq!(
+            store
+                .add(
+                    "https://example.com".to_string(),
+                    None,
+                    LinkOptions::default(),
+                    &alice(),
+                )
+                .await
+                .unwrap(),
+            code
+        );
+    }
+
+    #[tokio::test]
+    async fn test_add_retries_on_collision() {
+        let store = Store::memory().with_code_length(4);
+        let taken = alice_code("https://example.com/", 0);
+        store
+            .map
+            .insert(&taken, &UrlRecord::new("https://other.com"))
+            .await
+            .unwrap();
+
+        let code = store
+            .add(
+                "https://example.com".to_string(),
+                None,
+                LinkOptions::default(),
+                &alice(),
+            )
+            .await
+            .unwrap();
+
+        assert_ne!(code, taken);
+        assert_eq!(code, alice_code("https://example.com/", 1));
+        assert_eq!(
+            store.get(taken).await.unwrap().original,
+            "https://other.com"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_add_same_url_for_many_users() {
+        let store = Store::memory();
+        let url = "https://example.com".to_string();
+
+        let mut codes = Vec::new();
+        for user in 0..=MAX_CODE_ATTEMPTS {
+            let owner = Principal::user(&format!("user{}", user));
+            let code = store
+                .add(url.clone(), None, LinkOptions::default(), &owner)
+                .await
+                .unwrap();
+            codes.push(code);
+        }
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes.len() as u64, MAX_CODE_ATTEMPTS + 1);
+
+        // The same user with other labels gets another code, and the first one back when
+        // adding the first link again.
+        let plain = store
+            .add(url.clone(), None, LinkOptions::default(), &alice())
+            .await
+            .unwrap();
+        let titled = LinkOptions {
+            title: Some("Example".to_string()),
+            ..LinkOptions::default()
+        };
+        let code = store
+            .add(url.clone(), None, titled, &alice())
+            .await
+            .unwrap();
+        assert_ne!(code, plain);
+        assert_eq!(
+            store
+                .add(url, None, LinkOptions::default(), &alice())
+                .await
+                .unwrap(),
+            plain
+        );
+    }
+
+    #[tokio::test]
+    async fn test_add_alias() {
+        let store = Store::memory();
+
+        let url = "https://example.com".to_string();
+        assert_eq!(
+            store
+                .add(
+                    url.clone(),
+                    Some("example".to_string()),
+                    LinkOptions::default(),
+                    &alice(),
+                )
+                .await
+                .unwrap(),
+            "example"
+        );
+        assert_eq!(
+            store
+                .add(
+                    url.clone(),
+                    Some("example".to_string()),
+                    LinkOptions::default(),
+                    &alice(),
+                )
+                .await
+                .unwrap(),
+            "example"
+        );
+        assert!(matches!(
+            store
+                .add(
+                    "https://other.com".to_string(),
+                    Some("example".to_string()),
+                    LinkOptions::default(),
+                    &alice(),
+                )
+                .await,
+            Err(StoreError::Conflict(_))
+        ));
+        assert!(matches!(
+            store
+                .add(
+                    url.clone(),
+                    Some("get_all".to_string()),
+                    LinkOptions::default(),
+                    &alice(),
+                )
+                .await,
+            Err(StoreError::Invalid(_))
+        ));
+
+        store
+            .add(url.clone(), None, LinkOptions::default(), &alice())
+            .await
+            .unwrap();
+        store.delete(&url, &alice()).await.unwrap();
+        assert!(store
+            .get_all(&alice(), SortOrder::default(), None, DEFAULT_PAGE_SIZE)
+            .await
+            .unwrap()
+            .links
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_visit_max_clicks() {
+        let store = Store::memory();
+        let options = LinkOptions {
+            max_clicks: Some(2),
+            ..LinkOptions::default()
+        };
+
+        let code = store
+            .add("https://example.com".to_string(), None, options, &alice())
+            .await
+            .unwrap();
+        let plain = store
+            .add(
+                "https://example.com".to_string(),
+                None,
+                LinkOptions::default(),
+                &alice(),
+            )
+            .await
+            .unwrap();
+        assert_ne!(code, plain);
+
+        assert_eq!(store.visit(code.clone(), &click()).await.unwrap().clicks, 1);
+        assert_eq!(store.visit(code.clone(), &click()).await.unwrap().clicks, 2);
+        assert!(matches!(
+            store.visit(code.clone(), &click()).await,
+            Err(StoreError::Gone)
+        ));
+        assert!(matches!(
+            store.visit("missing".to_string(), &click()).await,
+            Err(StoreError::NotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_visit_expired() {
+        let store = Store::memory();
+        store
+            .map
+            .insert(
+                "old",
+                &UrlRecord {
+                    expires_at: Some(Utc::now() - chrono::Duration::seconds(1)),
+                    ..UrlRecord::new("https://example.com")
+                },
+            )
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            store.visit("old".to_string(), &click()).await,
+            Err(StoreError::Gone)
+        ));
+        assert_eq!(store.get("old".to_string()).await.unwrap().clicks, 0);
+
+        let past = LinkOptions {
+            expires_at: Some(Utc::now() - chrono::Duration::seconds(1)),
+            ..LinkOptions::default()
+        };
+        assert!(matches!(
+            store
+                .add("https://example.com".to_string(), None, past, &alice())
+                .await,
+            Err(StoreError::Invalid(_))
+        ));
+
+        let far = LinkOptions {
+            expires_at: Some(DateTime::<Utc>::MAX_UTC),
+            ..LinkOptions::default()
+        };
+        assert!(matches!(
+            store
+                .add("https://example.com".to_string(), None, far, &alice())
+                .await,
+            Err(StoreError::Invalid(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_stats() {
+        let store = Store::memory().with_analytics(true);
+        let code = store
+            .add(
+                "https://example.com".to_string(),
+                None,
+                LinkOptions::default(),
+                &alice(),
+            )
+            .await
+            .unwrap();
+
+        store.visit(code.clone(), &click()).await.unwrap();
+        store
+            .visit(
+                code.clone(),
+                &Click {
+                    at: Utc.with_ymd_and_hms(2024, 6, 2, 8, 0, 0).unwrap(),
+                    ..click()
+                },
+            )
+            .await
+            .unwrap();
+
+        let stats = store.stats(code.clone(), &alice()).await.unwrap();
+        assert_eq!(stats.clicks, 2);
+        assert_eq!(stats.daily.len(), 2);
+        assert_eq!(stats.hourly.get("2024-06-01T13"), Some(&1));
+        assert_eq!(stats.user_agents.get("curl"), Some(&2));
+        assert_eq!(stats.countries.get("FR"), Some(&2));
+        assert_eq!(stats.referrers.get("direct"), Some(&2));
+
+        assert!(matches!(
+            store.stats("missing".to_string(), &alice()).await,
+            Err(StoreError::NotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_add_normalizes_url() {
+        let store =
+            Store::memory().with_url_policy(UrlPolicy::default().with_blocklist(["evil.com"]));
+
+        let code = store
+            .add(
+                "HTTPS://Example.com:443".to_string(),
+                None,
+                LinkOptions::default(),
+                &alice(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            store.get(code).await.unwrap().original,
+            "https://example.com/"
+        );
+
+        assert!(matches!(
+            store
+                .add(
+                    "javascript:alert(1)".to_string(),
+                    None,
+                    LinkOptions::default(),
+                    &alice(),
+                )
+                .await,
+            Err(StoreError::InvalidUrl(_))
+        ));
+        assert!(matches!(
+            store
+                .add(
+                    "https://evil.com/".to_string(),
+                    None,
+                    LinkOptions::default(),
+                    &alice(),
+                )
+                .await,
+            Err(StoreError::InvalidUrl(_))
+        ));
+
+        store.delete("https://EXAMPLE.com", &alice()).await.unwrap();
+        assert!(store
+            .get_all(&alice(), SortOrder::default(), None, DEFAULT_PAGE_SIZE)
+            .await
+            .unwrap()
+            .links
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_update_limits_and_remove() {
+        let store = Store::memory();
+        let code = store
+            .add(
+                "https://example.com".to_string(),
+                None,
+                LinkOptions {
+                    max_clicks: Some(1),
+                    ..LinkOptions::default()
+                },
+                &alice(),
+            )
+            .await
+            .unwrap();
+        store.visit(code.clone(), &click()).await.unwrap();
+        assert!(matches!(
+            store.visit(code.clone(), &click()).await,
+            Err(StoreError::Gone)
+        ));
+
+        assert!(matches!(
+            store
+                .update(
+                    code.clone(),
+                    LinkUpdate {
+                        max_clicks: Some(Some(0)),
+                        ..LinkUpdate::default()
+                    },
+                    &alice()
+                )
+                .await,
+            Err(StoreError::Invalid(_))
+        ));
+        let expires_at = Utc::now() + chrono::Duration::hours(1);
+        let updated = store
+            .update(
+                code.clone(),
+                LinkUpdate {
+                    expires_at: Some(Some(expires_at)),
+                    max_clicks: Some(None),
+                    ..LinkUpdate::default()
+                },
+                &alice(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(updated.original, "https://example.com/");
+        assert_eq!(updated.expires_at, Some(expires_at));
+        assert_eq!(updated.max_clicks, None);
+        assert_eq!(updated.clicks, 1);
+        store.visit(code.clone(), &click()).await.unwrap();
+
+        assert!(matches!(
+            store.remove(&code, &Principal::user("bob")).await,
+            Err(StoreError::Forbidden)
+        ));
+        store.remove(&code, &alice()).await.unwrap();
+        assert!(matches!(
+            store.link(code.clone(), &alice()).await,
+            Err(StoreError::NotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_cache() {
+        let store = Store::memory().with_cache(LinkCache::new(&CacheConfig::default()));
+        let click = click();
+
+        assert!(matches!(
+            store.visit("abc".to_string(), &click).await,
+            Err(StoreError::NotFound)
+        ));
+        let code = store
+            .add(
+                "https://a.com".to_string(),
+                Some("abc".to_string()),
+                LinkOptions::default(),
+                &alice(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(store.visit(code.clone(), &click).await.unwrap().clicks, 1);
+
+        // Changes made around the store are not seen until the link is invalidated.
+        store
+            .map
+            .replace(&code, &UrlRecord::new("https://b.com/"))
+            .await
+            .unwrap();
+        assert_eq!(
+            store.get(code.clone()).await.unwrap().original,
+            "https://a.com/"
+        );
+
+        let update = LinkUpdate {
+            url: Some("https://c.com".to_string()),
+            ..LinkUpdate::default()
+        };
+        store.update(code.clone(), update, &alice()).await.unwrap();
+        assert_eq!(
+            store.get(code.clone()).await.unwrap().original,
+            "https://c.com/"
+        );
+
+        store.remove(&code, &alice()).await.unwrap();
+        assert!(matches!(
+            store.visit(code, &click).await,
+            Err(StoreError::NotFound)
+        ));
+
+        let text = store.metrics().render();
+        assert!(text.contains("cache_lookups_total{result=\"hit\"} 1"));
+        assert!(text.contains("cache_lookups_total{result=\"miss\"} 4"));
+    }
+
+    /// Previews every page as titled with its URL, failing on `https://down.com/`.
+    struct StubFetcher;
+
+    #[async_trait::async_trait]
+    impl PreviewFetcher for StubFetcher {
+        async fn fetch(&self, url: &str) -> Result<LinkPreview, String> {
+            if url == "https://down.com/" {
+                return Err("connection refused".to_string());
+            }
+            Ok(LinkPreview {
+                title: Some(url.to_string()),
+                ..LinkPreview::default()
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_previews() {
+        let store = Store::memory().with_preview_fetcher(Arc::new(StubFetcher));
+        let interstitial = LinkOptions {
+            interstitial: true,
+            ..LinkOptions::default()
+        };
+
+        let plain = store
+            .add(
+                "https://a.com".to_string(),
+                None,
+                LinkOptions::default(),
+                &alice(),
+            )
+            .await
+            .unwrap();
+        let code = store
+            .add("https://a.com".to_string(), None, interstitial, &alice())
+            .await
+            .unwrap();
+        assert_ne!(code, plain);
+
+        let link = store.preview(code.clone()).await.unwrap();
+        assert!(link.interstitial);
+        assert_eq!(
+            link.preview.and_then(|preview| preview.title).as_deref(),
+            Some("https://a.com/")
+        );
+        // Previews do not count as clicks, visits of interstitial links do.
+        assert_eq!(link.clicks, 0);
+        assert_eq!(store.visit(code.clone(), &click()).await.unwrap().clicks, 1);
+
+        let update = LinkUpdate {
+            url: Some("https://down.com".to_string()),
+            interstitial: Some(false),
+            ..LinkUpdate::default()
+        };
+        let link = store.update(code.clone(), update, &alice()).await.unwrap();
+        assert!(!link.interstitial);
+        assert_eq!(link.preview, None);
+
+        let update = LinkUpdate {
+            max_clicks: Some(Some(1)),
+            ..LinkUpdate::default()
+        };
+        store.update(code.clone(), update, &alice()).await.unwrap();
+        assert!(matches!(store.preview(code).await, Err(StoreError::Gone)));
+    }
+
+    #[tokio::test]
+    async fn test_labels() {
+        let store = Store::memory();
+        let labeled = LinkOptions {
+            title: Some(" Spring sale ".to_string()),
+            tags: vec!["Sale".to_string(), "q3".to_string(), "sale".to_string()],
+            group: Some("spring".to_string()),
+            ..LinkOptions::default()
+        };
+
+        let plain = store
+            .add(
+                "https://a.com".to_string(),
+                None,
+                LinkOptions::default(),
+                &alice(),
+            )
+            .await
+            .unwrap();
+        let code = store
+            .add("https://a.com".to_string(), None, labeled.clone(), &alice())
+            .await
+            .unwrap();
+        assert_ne!(code, plain);
+        assert_eq!(
+            store
+                .add("https://a.com".to_string(), None, labeled.clone(), &alice())
+                .await
+                .unwrap(),
+            code
+        );
+
+        let link = store.get(code.clone()).await.unwrap();
+        assert_eq!(link.title.as_deref(), Some("Spring sale"));
+        assert_eq!(link.tags, ["q3", "sale"]);
+        assert_eq!(link.group.as_deref(), Some("spring"));
+
+        for options in [
+            LinkOptions {
+                tags: vec!["two words".to_string()],
+                ..LinkOptions::default()
+            },
+            LinkOptions {
+                group: Some("".to_string()),
+                ..LinkOptions::default()
+            },
+            LinkOptions {
+                title: Some("t".repeat(201)),
+                ..LinkOptions::default()
+            },
+        ] {
+            assert!(matches!(
+                store
+                    .add("https://b.com".to_string(), None, options, &alice())
+                    .await,
+                Err(StoreError::Invalid(_))
+            ));
+        }
+
+        let filter = LinkFilter {
+            text: Some("SALE".to_string()),
+            ..LinkFilter::default()
+        };
+        let page = store
+            .search(&alice(), filter, SortOrder::default(), None, 10)
+            .await
+            .unwrap();
+        assert_eq!(page.links.len(), 1);
+        assert_eq!(page.links[0].hash, code);
+
+        let update = LinkUpdate {
+            title: Some(None),
+            tags: Some(vec!["Done".to_string()]),
+            group: Some(Some("autumn".to_string())),
+            ..LinkUpdate::default()
+        };
+        let link = store.update(code.clone(), update, &alice()).await.unwrap();
+        assert_eq!(link.title, None);
+        assert_eq!(link.tags, ["done"]);
+        assert_eq!(link.group.as_deref(), Some("autumn"));
+        let update = LinkUpdate {
+            group: Some(Some("no/slash".to_string())),
+            ..LinkUpdate::default()
+        };
+        assert!(matches!(
+            store.update(code, update, &alice()).await,
+            Err(StoreError::Invalid(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_groups() {
+        let store = Store::memory().with_analytics(true);
+        let bob = Principal::user("bob");
+        let in_group = |group: &str| LinkOptions {
+            group: Some(group.to_string()),
+            ..LinkOptions::default()
+        };
+
+        let a = store
+            .add(
+                "https://a.com".to_string(),
+                None,
+                in_group("spring"),
+                &alice(),
+            )
+            .await
+            .unwrap();
+        let b = store
+            .add(
+                "https://b.com".to_string(),
+                None,
+                in_group("spring"),
+                &alice(),
+            )
+            .await
+            .unwrap();
+        store
+            .add(
+                "https://c.com".to_string(),
+                None,
+                in_group("autumn"),
+                &alice(),
+            )
+            .await
+            .unwrap();
+        store
+            .add(
+                "https://d.com".to_string(),
+                None,
+                LinkOptions::default(),
+                &alice(),
+            )
+            .await
+            .unwrap();
+        store
+            .add("https://e.com".to_string(), None, in_group("spring"), &bob)
+            .await
+            .unwrap();
+
+        for code in [&a, &a, &b] {
+            store.visit(code.clone(), &click()).await.unwrap();
+        }
+
+        assert_eq!(
+            store.groups(&alice()).await.unwrap(),
+            [
+                GroupSummary {
+                    name: "autumn".to_string(),
+                    links: 1,
+                    clicks: 0,
+                },
+                GroupSummary {
+                    name: "spring".to_string(),
+                    links: 2,
+                    clicks: 3,
+                },
+            ]
+        );
+        assert_eq!(
+            store.groups(&Principal::admin("root")).await.unwrap()[1].links,
+            3
+        );
+
+        let stats = store
+            .group_stats("spring".to_string(), &alice())
+            .await
+            .unwrap();
+        assert_eq!(stats.clicks, 3);
+        assert_eq!(stats.links.get(&a), Some(&2));
+        assert_eq!(stats.links.get(&b), Some(&1));
+        assert_eq!(stats.countries.get("FR"), Some(&3));
+        assert!(matches!(
+            store.group_stats("winter".to_string(), &alice()).await,
+            Err(StoreError::NotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_import() {
+        let store = Store::memory().with_code_length(4);
+        let taken = alice_code("https://b.com/", 0);
+        store
+            .map
+            .insert(&taken, &UrlRecord::new("https://other.com"))
+            .await
+            .unwrap();
+        store
+            .add(
+                "https://other.com".to_string(),
+                Some("used".to_string()),
+                LinkOptions::default(),
+                &Principal::user("bob"),
+            )
+            .await
+            .unwrap();
+
+        let row = |url: &str, alias: Option<&str>| ImportRow {
+            url: url.to_string(),
+            alias: alias.map(str::to_string),
+            options: LinkOptions::default(),
+        };
+        let results = store
+            .import(
+                &[
+                    row("https://a.com", None),
+                    row("https://b.com", None),
+                    row("https://a.com", None),
+                    row("https://c.com", Some("used")),
+                    row("https://c.com", Some("mine")),
+                    row("not a url", None),
+                ],
+                &alice(),
+            )
+            .await
+            .unwrap();
+
+        let a = alice_code("https://a.com/", 0);
+        let b = alice_code("https://b.com/", 1);
+        assert_eq!(results[0].as_deref().ok(), Some(a.as_str()));
+        assert_eq!(results[1].as_deref().ok(), Some(b.as_str()));
+        assert_eq!(results[2].as_deref().ok(), Some(a.as_str()));
+        assert!(matches!(results[3], Err(StoreError::Conflict(_))));
+        assert_eq!(results[4].as_deref().ok(), Some("mine"));
+        assert!(matches!(results[5], Err(StoreError::InvalidUrl(_))));
+
+        let exported: Vec<UrlMap> = store.export(alice()).try_concat().await.unwrap();
+        let mut urls: Vec<&str> = exported.iter().map(|link| link.original.as_str()).collect();
+        urls.sort();
+        assert_eq!(urls, ["https://a.com/", "https://b.com/", "https://c.com/"]);
+    }
+
+    #[tokio::test]
+    async fn test_ownership() {
+        let store = Store::memory();
+        let bob = Principal::user("bob");
+        let admin = Principal::admin("root");
+        let url = "https://example.com/".to_string();
+
+        let alice_code = store
+            .add(url.clone(), None, LinkOptions::default(), &alice())
+            .await
+            .unwrap();
+        let bob_code = store
+            .add(url.clone(), None, LinkOptions::default(), &bob)
+            .await
+            .unwrap();
+        assert_ne!(alice_code, bob_code);
+        assert!(matches!(
+            store
+                .add(
+                    url.clone(),
+                    Some(alice_code.clone()),
+                    LinkOptions::default(),
+                    &bob
+                )
+                .await,
+            Err(StoreError::Conflict(_))
+        ));
+
+        let page = store
+            .get_all(&alice(), SortOrder::default(), None, DEFAULT_PAGE_SIZE)
+            .await
+            .unwrap();
+        assert_eq!(page.links.len(), 1);
+        assert_eq!(page.links[0].hash, alice_code);
+        assert_eq!(page.links[0].owner.as_deref(), Some("alice"));
+
+        let page = store
+            .get_all(&admin, SortOrder::CreatedDesc, None, 1)
+            .await
+            .unwrap();
+        assert_eq!(page.links[0].hash, bob_code);
+        let page = store
+            .get_all(&admin, SortOrder::CreatedDesc, page.next_cursor, 1)
+            .await
+            .unwrap();
+        assert_eq!(page.links[0].hash, alice_code);
+        assert!(store
+            .get_all(&admin, SortOrder::CreatedDesc, page.next_cursor, 1)
+            .await
+            .unwrap()
+            .links
+            .is_empty());
+
+        assert!(matches!(
+            store
+                .update(alice_code.clone(), url_update("https://other.com"), &bob)
+                .await,
+            Err(StoreError::Forbidden)
+        ));
+        let updated = store
+            .update(
+                alice_code.clone(),
+                url_update("https://other.com"),
+                &alice(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(updated.original, "https://other.com/");
+        assert_eq!(updated.owner.as_deref(), Some("alice"));
+
+        assert!(matches!(
+            store.delete("https://other.com", &bob).await,
+            Err(StoreError::Forbidden)
+        ));
+        store.delete(&url, &admin).await.unwrap();
+        assert!(matches!(
+            store.get(bob_code).await,
+            Err(StoreError::NotFound)
+        ));
+        assert!(store.get(alice_code).await.is_ok());
+    }
+}
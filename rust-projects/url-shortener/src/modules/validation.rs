@@ -0,0 +1,300 @@
+use std::{
+    collections::BTreeSet,
+    fmt, fs, io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+};
+
+use url::{Host, Url};
+
+/// Why a redirect target was rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UrlError {
+    /// The target is not an absolute URL.
+    Invalid(String),
+    UnsupportedScheme(String),
+    MissingHost,
+    /// The host is a loopback, private or link-local address.
+    PrivateAddress(String),
+    BlockedDomain(String),
+}
+
+impl UrlError {
+    /// Stable identifier of the error, returned to API clients.
+    pub fn code(&self) -> &'static str {
+        match self {
+            UrlError::Invalid(_) => "invalid_url",
+            UrlError::UnsupportedScheme(_) => "unsupported_scheme",
+            UrlError::MissingHost => "missing_host",
+            UrlError::PrivateAddress(_) => "private_address",
+            UrlError::BlockedDomain(_) => "blocked_domain",
+        }
+    }
+}
+
+impl fmt::Display for UrlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UrlError::Invalid(e) => write!(f, "Invalid URL: {}", e),
+            UrlError::UnsupportedScheme(scheme) => write!(f, "Scheme '{}' is not allowed", scheme),
+            UrlError::MissingHost => write!(f, "URL has no host"),
+            UrlError::PrivateAddress(host) => write!(f, "Host '{}' is a private address", host),
+            UrlError::BlockedDomain(host) => write!(f, "Domain '{}' is blocked", host),
+        }
+    }
+}
+
+impl std::error::Error for UrlError {}
+
+/// Rules applied to the URLs before they are shortened.
+#[derive(Debug, Clone)]
+pub struct UrlPolicy {
+    schemes: BTreeSet<String>,
+    block_private: bool,
+    /// Blocked domains, each one also blocking its subdomains.
+    blocklist: BTreeSet<String>,
+}
+
+impl Default for UrlPolicy {
+    fn default() -> Self {
+        UrlPolicy {
+            schemes: ["http", "https"].map(str::to_string).into(),
+            block_private: false,
+            blocklist: BTreeSet::new(),
+        }
+    }
+}
+
+/// Whether `ip` is a loopback, private, link-local or otherwise internal address.
+pub fn is_private_ip(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_private_ipv4(ip),
+        IpAddr::V6(ip) => is_private_ipv6(ip),
+    }
+}
+
+fn is_private_ipv4(ip: &Ipv4Addr) -> bool {
+    ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        // Carrier-grade NAT, 100.64.0.0/10.
+        || (ip.octets()[0] == 100 && ip.octets()[1] & 0xc0 == 64)
+}
+
+fn is_private_ipv6(ip: &Ipv6Addr) -> bool {
+    if let Some(ip) = ip.to_ipv4_mapped() {
+        return is_private_ipv4(&ip);
+    }
+
+    ip.is_loopback()
+        || ip.is_unspecified()
+        // Unique local, fc00::/7.
+        || (ip.segments()[0] & 0xfe00) == 0xfc00
+        // Link-local, fe80::/10.
+        || (ip.segments()[0] & 0xffc0) == 0xfe80
+}
+
+impl UrlPolicy {
+    /// Replaces the allowed schemes, `http` and `https` by default.
+    pub fn with_schemes<I, S>(mut self, schemes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.schemes = schemes
+            .into_iter()
+            .map(|scheme| scheme.as_ref().trim().to_lowercase())
+            .filter(|scheme| !scheme.is_empty())
+            .collect();
+        self
+    }
+
+    /// Rejects loopback, private and link-local IP addresses and `localhost`.
+    ///
+    /// Only literal addresses are checked, domains are not resolved.
+    pub fn with_block_private(mut self, block_private: bool) -> Self {
+        self.block_private = block_private;
+        self
+    }
+
+    pub fn with_blocklist<I, S>(mut self, domains: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.blocklist.extend(
+            domains
+                .into_iter()
+                .map(|domain| domain.as_ref().trim().trim_end_matches('.').to_lowercase())
+                .filter(|domain| !domain.is_empty() && !domain.starts_with('#')),
+        );
+        self
+    }
+
+    /// Adds the domains listed in a file, one per line. Lines starting with `#` are ignored.
+    pub fn load_blocklist(self, path: &str) -> io::Result<Self> {
+        let content = fs::read_to_string(path)?;
+        Ok(self.with_blocklist(content.lines()))
+    }
+
+    fn is_blocked(&self, host: &str) -> bool {
+        let host = host.trim_end_matches('.');
+
+        // `a.b.example.com` is blocked by `a.b.example.com`, `b.example.com`, `example.com`...
+        std::iter::successors(Some(host), |domain| {
+            domain.split_once('.').map(|(_, parent)| parent)
+        })
+        .any(|domain| self.blocklist.contains(domain))
+    }
+
+    /// Checks a URL against the policy and returns its normalized form, with a lowercase scheme
+    /// and host and without the default port of the scheme.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use url_shortener::modules::validation::{UrlError, UrlPolicy};
+    ///
+    /// let policy = UrlPolicy::default().with_blocklist(["evil.com"]);
+    ///
+    /// assert_eq!(
+    ///     policy.normalize("HTTPS://Example.COM:443/Path?q=1"),
+    ///     Ok("https://example.com/Path?q=1".to_string())
+    /// );
+    /// assert_eq!(
+    ///     policy.normalize("javascript:alert(1)"),
+    ///     Err(UrlError::UnsupportedScheme("javascript".to_string()))
+    /// );
+    /// assert_eq!(
+    ///     policy.normalize("https://www.evil.com/"),
+    ///     Err(UrlError::BlockedDomain("www.evil.com".to_string()))
+    /// );
+    /// ```
+    pub fn normalize(&self, input: &str) -> Result<String, UrlError> {
+        let url = Url::parse(input.trim()).map_err(|e| UrlError::Invalid(e.to_string()))?;
+
+        if !self.schemes.contains(url.scheme()) {
+            return Err(UrlError::UnsupportedScheme(url.scheme().to_string()));
+        }
+
+        let host = url.host().ok_or(UrlError::MissingHost)?;
+        let host_name = host.to_string().to_lowercase();
+
+        if self.block_private {
+            let private = match &host {
+                Host::Domain(domain) => {
+                    let domain = domain.trim_end_matches('.').to_lowercase();
+                    domain == "localhost" || domain.ends_with(".localhost")
+                }
+                Host::Ipv4(ip) => is_private_ipv4(ip),
+                Host::Ipv6(ip) => is_private_ipv6(ip),
+            };
+
+            if private {
+                return Err(UrlError::PrivateAddress(host_name));
+            }
+        }
+
+        if let Host::Domain(_) = host {
+            if self.is_blocked(&host_name) {
+                return Err(UrlError::BlockedDomain(host_name));
+            }
+        }
+
+        // The parser lowercases the host and drops the default port of special schemes such as
+        // http, other schemes keep their host as typed.
+        let mut url = url.clone();
+        if matches!(host, Host::Domain(domain) if domain != host_name) {
+            url.set_host(Some(&host_name))
+                .map_err(|e| UrlError::Invalid(e.to_string()))?;
+        }
+
+        Ok(url.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_private() {
+        let policy = UrlPolicy::default().with_block_private(true);
+
+        for url in [
+            "http://localhost:8080/",
+            "http://api.localhost/",
+            "http://127.0.0.1/",
+            "http://10.1.2.3/",
+            "http://192.168.0.1/",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://[::1]/",
+            "http://[fd00::1]/",
+            "http://[::ffff:127.0.0.1]/",
+            "http://0x7f000001/",
+        ] {
+            assert!(
+                matches!(policy.normalize(url), Err(UrlError::PrivateAddress(_))),
+                "{} was accepted",
+                url
+            );
+        }
+
+        assert!(policy.normalize("http://93.184.216.34/").is_ok());
+        assert!(UrlPolicy::default().normalize("http://127.0.0.1/").is_ok());
+    }
+
+    #[test]
+    fn test_normalize() {
+        let policy = UrlPolicy::default().with_schemes(["https", "ftp", "foo"]);
+
+        assert_eq!(
+            policy.normalize("  https://EXAMPLE.com:443  "),
+            Ok("https://example.com/".to_string())
+        );
+        assert_eq!(
+            policy.normalize("https://example.com:8443/"),
+            Ok("https://example.com:8443/".to_string())
+        );
+        assert_eq!(
+            policy.normalize("ftp://Files.Example.com:21/a"),
+            Ok("ftp://files.example.com/a".to_string())
+        );
+        assert_eq!(
+            policy.normalize("foo://Example.com/a"),
+            Ok("foo://example.com/a".to_string())
+        );
+        assert_eq!(
+            policy.normalize("http://example.com/"),
+            Err(UrlError::UnsupportedScheme("http".to_string()))
+        );
+        assert!(matches!(
+            policy.normalize("not a url"),
+            Err(UrlError::Invalid(_))
+        ));
+        assert_eq!(policy.normalize("foo:bar"), Err(UrlError::MissingHost));
+    }
+
+    #[test]
+    fn test_blocklist_file() {
+        let path = std::env::temp_dir().join(format!("blocklist-{}.txt", std::process::id()));
+        fs::write(&path, "# phishing\nEvil.com\n\nbad.example.org.\n").unwrap();
+
+        let policy = UrlPolicy::default()
+            .load_blocklist(path.to_str().unwrap())
+            .unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            policy.normalize("https://login.evil.com/"),
+            Err(UrlError::BlockedDomain(_))
+        ));
+        assert!(matches!(
+            policy.normalize("https://BAD.example.org/"),
+            Err(UrlError::BlockedDomain(_))
+        ));
+        assert!(policy.normalize("https://example.org/").is_ok());
+        assert!(policy.normalize("https://notevil.com/").is_ok());
+    }
+}